@@ -0,0 +1,112 @@
+use std::{
+    io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use serde::Serialize;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+
+use crate::{ChaosEvents, RecordStatus, RunMetadata};
+
+/// Attempt at processing a record in a stage, as written to the
+/// `--audit-log` file.
+#[derive(Debug, Serialize)]
+pub struct AuditEntry<'a> {
+    /// ID of the record.
+    pub record_id: usize,
+    /// Title number of the record.
+    pub title_number: String,
+    /// Name of the stage, e.g. `retrieve`.
+    pub stage: &'static str,
+    /// Attempt at the stage, starting from 1, which is above 1 when the stage
+    /// is retried.
+    pub attempt: u32,
+    /// When the attempt started.
+    #[serde(skip)]
+    pub started: SystemTime,
+    /// Time the attempt took.
+    #[serde(skip)]
+    pub duration: Duration,
+    /// Why the attempt failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a str>,
+    /// Outcome of looking up the record's information, on the stage that
+    /// looked it up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookup: Option<AuditLookup>,
+}
+
+/// Outcome of looking up a record's information, in an [`AuditEntry`].
+#[derive(Debug, Serialize)]
+pub struct AuditLookup {
+    /// Whether all, some, or none of the information was retrieved.
+    pub status: RecordStatus,
+    /// Why the information could not be retrieved, if it failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Number of requests made to retrieve the information, including
+    /// retries.
+    pub attempts: u32,
+    /// Chaos faults encountered across the requests.
+    pub chaos_events: ChaosEvents,
+}
+
+/// An audit entry, with the run it belongs to.
+#[derive(Debug, Serialize)]
+struct AuditLine<'a> {
+    run_id: &'a str,
+    timestamp: String,
+    #[serde(flatten)]
+    entry: &'a AuditEntry<'a>,
+    duration_ms: f64,
+}
+
+/// Append-only log of every attempt at processing each record, for
+/// debugging specific records after a run.
+#[derive(Debug)]
+pub struct AuditLog {
+    run_id: String,
+    file: Mutex<File>,
+}
+
+impl AuditLog {
+    /// Opens the audit log, appending to it when `resume` is true, and
+    /// truncating it otherwise.
+    pub async fn open(path: &Path, resume: bool, run_metadata: &RunMetadata) -> io::Result<Self> {
+        let file = if resume {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?
+        } else {
+            File::create(path).await?
+        };
+
+        Ok(Self {
+            run_id: run_metadata.run_id.clone(),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Appends an entry.
+    pub async fn write(&self, entry: &AuditEntry<'_>) -> io::Result<()> {
+        let audit_line = AuditLine {
+            run_id: &self.run_id,
+            timestamp: humantime::format_rfc3339_millis(entry.started).to_string(),
+            entry,
+            duration_ms: entry.duration.as_secs_f64() * 1000.0,
+        };
+        let mut line = serde_json::to_string(&audit_line)?;
+        line.push('\n');
+
+        let mut file = self.file.lock().await;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await
+    }
+}
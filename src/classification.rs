@@ -0,0 +1,72 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ClassificationConfig, ClassificationRule};
+
+/// How a record's error is counted in the report, set by the rules in the
+/// `[classification]` section of the config file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// The record may succeed if processed again, e.g. after a timeout.
+    Retryable,
+    /// The record won't succeed if processed again.
+    #[default]
+    Fatal,
+    /// Some of the record's information was retrieved, so it is counted as
+    /// processed with missing info rather than failed.
+    Partial,
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Retryable => f.pad("retryable"),
+            Self::Fatal => f.pad("fatal"),
+            Self::Partial => f.pad("partial"),
+        }
+    }
+}
+
+/// Classifies errors by the first rule whose text they contain, ignoring
+/// case.
+#[derive(Clone, Debug, Default)]
+pub struct Classifier {
+    rules: Vec<ClassificationRule>,
+    /// Class of errors that no rule matches.
+    default: ErrorClass,
+}
+
+impl Classifier {
+    /// Returns a classifier for the rules in the config file.
+    pub fn new(classification_config: &ClassificationConfig) -> Self {
+        let rules = classification_config
+            .rules
+            .iter()
+            .map(|rule| ClassificationRule {
+                contains: rule.contains.to_lowercase(),
+                class: rule.class,
+            })
+            .collect();
+
+        Self {
+            rules,
+            default: classification_config.default.unwrap_or_default(),
+        }
+    }
+
+    /// Returns whether any rules were configured.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns the class of an error message.
+    pub fn classify(&self, error: &str) -> ErrorClass {
+        let error = error.to_lowercase();
+        self.rules
+            .iter()
+            .find(|rule| error.contains(&rule.contains))
+            .map_or(self.default, |rule| rule.class)
+    }
+}
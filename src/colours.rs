@@ -1,79 +1,30 @@
-use crossterm::style::{Attribute, Attributes, Color, ContentStyle};
-use once_cell::sync::Lazy;
+use once_cell::sync::OnceCell;
+
+use crate::{ColorDepth, Theme, ThemeName};
+
+/// Theme used for UI output, set once at startup.
+static THEME: OnceCell<Theme> = OnceCell::new();
 
 /// Colours for UI output on terminal
 pub struct Colours;
 
 impl Colours {
-    /// Logo left color.
-    pub const LOGO_LEFT: Lazy<ContentStyle> = Lazy::new(|| ContentStyle {
-        foreground_color: Some(Color::Blue),
-        background_color: None,
-        attributes: Attributes::from(Attribute::Bold),
-    });
-    /// Logo left color.
-    pub const LOGO_RIGHT: Lazy<ContentStyle> = Lazy::new(|| ContentStyle {
-        foreground_color: Some(Color::Green),
-        background_color: None,
-        attributes: Attributes::from(Attribute::Bold),
-    });
+    /// Sets the theme used for UI output.
+    ///
+    /// When colours are disabled, no styles are applied. Colours outside the
+    /// terminal's palette fall back to the nearest supported colour.
+    pub fn init(theme: Theme, enabled: bool, color_depth: ColorDepth) {
+        let theme = if enabled {
+            theme.fit(color_depth)
+        } else {
+            Theme::plain()
+        };
+        // Only the first call takes effect.
+        let _ = THEME.set(theme);
+    }
 
-    /// Styling for a report border.
-    pub const REPORT_BORDER: Lazy<ContentStyle> = Lazy::new(|| ContentStyle {
-        foreground_color: Some(Color::Blue),
-        background_color: None,
-        attributes: Attributes::from(Attribute::Bold),
-    });
-    /// Styling for a report section title.
-    pub const REPORT_TITLE: Lazy<ContentStyle> = Lazy::new(|| ContentStyle {
-        foreground_color: Some(Color::Cyan),
-        background_color: None,
-        attributes: Attributes::from(Attribute::Bold),
-    });
-    /// Styling for a report error section title.
-    pub const REPORT_TITLE_ERROR: Lazy<ContentStyle> = Lazy::new(|| ContentStyle {
-        foreground_color: Some(Color::Red),
-        background_color: None,
-        attributes: Attributes::from(Attribute::Bold),
-    });
-    /// Styling for a report label.
-    pub const REPORT_LABEL: Lazy<ContentStyle> = Lazy::new(|| ContentStyle {
-        foreground_color: None,
-        background_color: None,
-        attributes: Attributes::from(Attribute::Bold),
-    });
-    /// Styling for a report item success.
-    pub const REPORT_ITEM_SUCCESS: Lazy<ContentStyle> = Lazy::new(|| ContentStyle {
-        foreground_color: Some(Color::Green),
-        background_color: None,
-        attributes: Attributes::from(Attribute::Bold),
-    });
-    /// Styling for a report item partial success.
-    pub const REPORT_ITEM_PARTIAL_SUCCESS: Lazy<ContentStyle> = Lazy::new(|| ContentStyle {
-        foreground_color: Some(Color::Rgb {
-            r: 216,
-            g: 216,
-            b: 0,
-        }),
-        background_color: None,
-        attributes: Attributes::from(Attribute::Bold),
-    });
-    /// Styling for a report item failure.
-    pub const REPORT_ITEM_FAILURE: Lazy<ContentStyle> = Lazy::new(|| ContentStyle {
-        foreground_color: Some(Color::Red),
-        background_color: None,
-        attributes: Attributes::from(Attribute::Bold),
-    });
-    /// Styling for a report error item.
-    pub const REPORT_ERROR_ITEM: Lazy<ContentStyle> = Lazy::new(|| ContentStyle {
-        foreground_color: None,
-        background_color: None,
-        attributes: Attributes::default(),
-    });
-    /// Styling for a report error item.
-    pub const REPORT_ERROR_MESSAGE: Lazy<ContentStyle> = Lazy::new(|| ContentStyle {
-        foreground_color: Some(Color::Yellow),
-        background_color: None,
-        attributes: Attributes::default(),
-    });
+    /// Returns the theme used for UI output.
+    pub fn theme() -> &'static Theme {
+        THEME.get_or_init(|| Theme::named(ThemeName::Default))
+    }
 }
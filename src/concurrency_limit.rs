@@ -0,0 +1,81 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use indicatif::ProgressBar;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Limit on how many records are written concurrently, which can be changed
+/// while running.
+///
+/// The current limit is shown as the progress bar's prefix.
+#[derive(Debug)]
+pub struct ConcurrencyLimit {
+    semaphore: Arc<Semaphore>,
+    limit: AtomicUsize,
+    progress_bar: ProgressBar,
+}
+
+impl ConcurrencyLimit {
+    /// Returns a concurrency limit, which is at least 1.
+    pub fn new(limit: usize, progress_bar: ProgressBar) -> Self {
+        let limit = limit.max(1);
+        progress_bar.set_prefix(limit.to_string());
+
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit: AtomicUsize::new(limit),
+            progress_bar,
+        }
+    }
+
+    /// Waits until there is capacity, and returns a permit that holds it.
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.semaphore
+            .acquire()
+            .await
+            .expect("Concurrency limit semaphore closed.")
+    }
+
+    /// Returns the current limit.
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::SeqCst)
+    }
+
+    /// Raises the limit by one.
+    pub fn increase(&self) {
+        let limit = self.limit.fetch_add(1, Ordering::SeqCst) + 1;
+        self.semaphore.add_permits(1);
+        self.progress_bar.set_prefix(limit.to_string());
+        tracing::info!(limit, "Concurrency limit raised.");
+    }
+
+    /// Lowers the limit by one, down to a minimum of 1.
+    ///
+    /// Records already being written are not cancelled, so the lower limit
+    /// takes effect once one of them finishes.
+    pub fn decrease(&self) {
+        let decreased = self
+            .limit
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |limit| {
+                if limit > 1 {
+                    Some(limit - 1)
+                } else {
+                    None
+                }
+            });
+        if let Ok(limit) = decreased {
+            let limit = limit - 1;
+            self.progress_bar.set_prefix(limit.to_string());
+            tracing::info!(limit, "Concurrency limit lowered.");
+
+            let semaphore = Arc::clone(&self.semaphore);
+            tokio::spawn(async move {
+                if let Ok(permit) = semaphore.acquire().await {
+                    permit.forget();
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{ErrorClass, ThemeName};
+
+/// Settings read from the config file.
+///
+/// Command line flags take precedence over these.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// `[progress]` section.
+    pub progress: ProgressConfig,
+    /// `[theme]` section.
+    pub theme: ThemeConfig,
+    /// `[logo]` section.
+    pub logo: LogoConfig,
+    /// `[classification]` section.
+    pub classification: ClassificationConfig,
+}
+
+/// `[progress]` section of the config file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProgressConfig {
+    /// Template for the overall progress bar, see `--progress-template`.
+    pub template: Option<String>,
+    /// Characters for the progress bar, see `--progress-chars`.
+    pub chars: Option<String>,
+}
+
+/// `[logo]` section of the config file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogoConfig {
+    /// Whether to skip printing the logo, see `--no-logo`.
+    pub hidden: bool,
+    /// Path to an ASCII-art logo file, see `--logo`.
+    pub path: Option<PathBuf>,
+    /// Text to render as the logo, see `--logo-text`.
+    pub text: Option<String>,
+}
+
+/// `[classification]` section of the config file, which sets how failed
+/// records are counted in the report by their error message.
+///
+/// ```toml
+/// [classification]
+/// default = "fatal"
+///
+/// [[classification.rules]]
+/// contains = "connection reset"
+/// class = "retryable"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClassificationConfig {
+    /// Class of errors that no rule matches, `fatal` if not given.
+    pub default: Option<ErrorClass>,
+    /// Rules in the order they are tried.
+    pub rules: Vec<ClassificationRule>,
+}
+
+/// Rule in the `[[classification.rules]]` array of the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClassificationRule {
+    /// Text the error message contains, ignoring case.
+    pub contains: String,
+    /// Class of the errors that match.
+    pub class: ErrorClass,
+}
+
+/// `[theme]` section of the config file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Built in theme to start from, see `--theme`.
+    pub name: Option<ThemeName>,
+    /// Styles that replace the theme's, keyed by name, e.g. `report_title`.
+    pub styles: BTreeMap<String, StyleConfig>,
+}
+
+/// Style in the `[theme.styles]` section of the config file.
+///
+/// Colours are names such as `dark_yellow`, ANSI colour numbers, or hex
+/// colours such as `#d8d800`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleConfig {
+    /// Text colour.
+    pub foreground: Option<String>,
+    /// Background colour.
+    pub background: Option<String>,
+    /// Whether the text is bold.
+    pub bold: bool,
+    /// Whether the text is dim.
+    pub dim: bool,
+    /// Whether the text is italic.
+    pub italic: bool,
+    /// Whether the text is underlined.
+    pub underlined: bool,
+}
+
+impl Config {
+    /// Returns the path to the default config file, if the config directory is known.
+    ///
+    /// On Linux this is `~/.config/cli_async/config.toml`.
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|config_dir| config_dir.join("cli_async").join("config.toml"))
+    }
+
+    /// Reads the config file.
+    ///
+    /// When no path is given, the default config file is read if it exists.
+    pub fn load(path: Option<&Path>) -> io::Result<Self> {
+        let contents = match path {
+            Some(path) => fs::read_to_string(path)?,
+            None => match Self::path() {
+                Some(path) if path.exists() => fs::read_to_string(path)?,
+                _ => return Ok(Self::default()),
+            },
+        };
+
+        toml::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
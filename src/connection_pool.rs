@@ -0,0 +1,149 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+/// How connections to each host are reused, for the simulated server and
+/// outgoing HTTP requests.
+#[derive(Clone, Copy, Debug)]
+pub struct PoolOptions {
+    /// Maximum number of idle connections kept open to each host, or `None`
+    /// for no limit.
+    pub max_idle_per_host: Option<usize>,
+    /// Time an idle connection is kept open for reuse.
+    pub keep_alive: Duration,
+    /// Whether requests are sent over HTTP/2, so concurrent requests to a
+    /// host share one connection.
+    pub http2: bool,
+}
+
+impl PoolOptions {
+    /// Returns whether connections are kept open after a request at all.
+    pub fn reuses_connections(&self) -> bool {
+        !self.keep_alive.is_zero() && self.max_idle_per_host != Some(0)
+    }
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: None,
+            keep_alive: Duration::from_secs(90),
+            http2: false,
+        }
+    }
+}
+
+/// Number of connections opened and reused to retrieve information.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct ConnectionStats {
+    /// Connections opened, each needing a handshake.
+    pub opened_count: usize,
+    /// Requests sent over a connection that was already open.
+    pub reused_count: usize,
+}
+
+/// A connection to the simulated server, checked out of the
+/// [`ConnectionPool`] for one request.
+#[derive(Debug)]
+pub struct Connection {
+    _private: (),
+}
+
+/// Connections to the simulated server, so requests reuse open connections
+/// instead of handshaking each time.
+#[derive(Debug)]
+pub struct ConnectionPool {
+    options: PoolOptions,
+    /// Time a handshake takes to open a connection.
+    delay_handshake: Duration,
+    /// When each idle connection was last used.
+    ///
+    /// With HTTP/2 this holds the shared connection, which stays here while
+    /// it is in use.
+    idle: Mutex<Vec<Instant>>,
+    opened_count: AtomicUsize,
+    reused_count: AtomicUsize,
+}
+
+impl ConnectionPool {
+    /// Returns a pool without any open connections.
+    pub fn new(options: PoolOptions, delay_handshake: Duration) -> Self {
+        Self {
+            options,
+            delay_handshake,
+            idle: Mutex::new(Vec::new()),
+            opened_count: AtomicUsize::new(0),
+            reused_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns an open connection, opening a new one if none are idle.
+    pub async fn checkout(&self) -> Connection {
+        let reused = {
+            let mut idle = self.idle.lock().expect("Connection pool lock poisoned.");
+            let now = Instant::now();
+            idle.retain(|last_used| now.duration_since(*last_used) < self.options.keep_alive);
+            if self.options.http2 {
+                !idle.is_empty()
+            } else {
+                idle.pop().is_some()
+            }
+        };
+
+        if reused {
+            self.reused_count.fetch_add(1, Ordering::Relaxed);
+        } else {
+            sleep(self.delay_handshake).await;
+            self.opened_count.fetch_add(1, Ordering::Relaxed);
+        }
+        Connection { _private: () }
+    }
+
+    /// Returns a connection to the pool after its request, keeping it open
+    /// if there is room.
+    pub fn checkin(&self, _connection: Connection) {
+        if !self.options.reuses_connections() {
+            return;
+        }
+
+        let mut idle = self.idle.lock().expect("Connection pool lock poisoned.");
+        if self.options.http2 {
+            // Requests that opened a connection at the same time share the
+            // first one afterwards.
+            idle.clear();
+            idle.push(Instant::now());
+        } else if self
+            .options
+            .max_idle_per_host
+            .is_none_or(|max_idle| idle.len() < max_idle)
+        {
+            idle.push(Instant::now());
+        }
+    }
+
+    /// Drops a connection that the server closed, e.g. with a connection
+    /// reset, so it isn't reused.
+    pub fn close(&self, _connection: Connection) {
+        if self.options.http2 {
+            self.idle
+                .lock()
+                .expect("Connection pool lock poisoned.")
+                .clear();
+        }
+    }
+
+    /// Returns the number of connections opened and reused so far.
+    pub fn stats(&self) -> ConnectionStats {
+        ConnectionStats {
+            opened_count: self.opened_count.load(Ordering::Relaxed),
+            reused_count: self.reused_count.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,454 @@
+use std::{
+    fmt,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use clap::Args;
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::Notify,
+};
+
+use crate::{
+    EventReceiver, LookupResult, Metrics, ProgressMessage, RecordStatus, RunEvent, Status,
+};
+
+/// Sends a command to a running instance started with `--control`.
+#[derive(Debug, Args)]
+pub struct CtlOpt {
+    /// Path to the control socket, or the pipe name on Windows.
+    ///
+    /// Defaults to the same path as `--control-socket`.
+    #[arg(long)]
+    socket: Option<PathBuf>,
+    /// Command to send: pause, resume, status, or stop.
+    command: ControlCommand,
+}
+
+/// Command accepted on the control socket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// Stops taking in new records.
+    Pause,
+    /// Continues taking in new records.
+    Resume,
+    /// Returns the run's progress as JSON.
+    Status,
+    /// Shuts down the same way as Ctrl-C.
+    Stop,
+}
+
+impl fmt::Display for ControlCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let command = match self {
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+            Self::Status => "status",
+            Self::Stop => "stop",
+        };
+        f.pad(command)
+    }
+}
+
+impl FromStr for ControlCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pause" => Ok(Self::Pause),
+            "resume" => Ok(Self::Resume),
+            "status" => Ok(Self::Status),
+            "stop" => Ok(Self::Stop),
+            _ => Err(format!(
+                "`{}` is not one of `pause`, `resume`, `status`, `stop`.",
+                s
+            )),
+        }
+    }
+}
+
+/// Why a run was stopped before all records were processed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterruptReason {
+    /// Ctrl-C was pressed.
+    CtrlC,
+    /// The process received `SIGTERM`, e.g. from `kill` or a container
+    /// runtime.
+    Sigterm,
+    /// Ctrl-Break was pressed, on Windows.
+    CtrlBreak,
+    /// The console window was closed, on Windows.
+    ConsoleClose,
+    /// The `--deadline` passed.
+    Deadline,
+    /// `--fail-fast` records failed.
+    FailFast,
+    /// `q` was pressed, or `cli_async ctl stop` was sent.
+    Stop,
+}
+
+impl fmt::Display for InterruptReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Self::CtrlC => "Ctrl-C",
+            Self::Sigterm => "SIGTERM",
+            Self::CtrlBreak => "Ctrl-Break",
+            Self::ConsoleClose => "console closed",
+            Self::Deadline => "deadline",
+            Self::FailFast => "fail-fast threshold",
+            Self::Stop => "stop command",
+        };
+        f.pad(reason)
+    }
+}
+
+/// Pauses, resumes, and stops the processing of records.
+#[derive(Debug, Default)]
+pub struct RunControl {
+    paused: AtomicBool,
+    resumed: Notify,
+    stopped: AtomicBool,
+    /// Why the run was first stopped.
+    stop_reason: Mutex<Option<InterruptReason>>,
+    stop: Notify,
+}
+
+impl RunControl {
+    /// Stops taking in new records, and shows "PAUSED" on the progress bar.
+    ///
+    /// Records already in flight still complete.
+    pub fn pause(&self, progress_message: &ProgressMessage) {
+        self.paused.store(true, Ordering::SeqCst);
+        progress_message.set_paused(true);
+    }
+
+    /// Continues taking in new records.
+    pub fn resume(&self, progress_message: &ProgressMessage) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.resumed.notify_waiters();
+        progress_message.set_paused(false);
+    }
+
+    /// Returns whether taking in new records is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Waits until the run is not paused.
+    pub async fn wait_while_paused(&self) {
+        loop {
+            // Registered before checking, so a `resume` in between is not missed.
+            let resumed = self.resumed.notified();
+            if !self.is_paused() {
+                return;
+            }
+            resumed.await;
+        }
+    }
+
+    /// Requests the run to shut down, the same way as Ctrl-C.
+    ///
+    /// Only the first reason is kept, if the run is stopped more than once.
+    pub fn stop(&self, reason: InterruptReason) {
+        self.stop_reason
+            .lock()
+            .expect("Stop reason lock poisoned.")
+            .get_or_insert(reason);
+        self.stopped.store(true, Ordering::SeqCst);
+        self.stop.notify_waiters();
+    }
+
+    /// Returns why the run was stopped, if it has been.
+    pub fn stop_reason(&self) -> Option<InterruptReason> {
+        *self.stop_reason.lock().expect("Stop reason lock poisoned.")
+    }
+
+    /// Stops the run once `threshold` records have failed, counting the
+    /// failures published on the [`EventBus`].
+    ///
+    /// [`EventBus`]: crate::EventBus
+    pub async fn stop_after_failures<R, I>(
+        self: Arc<Self>,
+        threshold: usize,
+        mut events: EventReceiver<R, I>,
+    ) where
+        I: LookupResult,
+    {
+        let mut failed_count = 0;
+        while let Some(event) = events.recv().await {
+            match event {
+                RunEvent::RecordRetrieved(record_progress)
+                    if record_progress.info.status() == RecordStatus::Error =>
+                {
+                    failed_count += 1
+                }
+                // Records that panicked after being looked up were already
+                // counted when they were retrieved.
+                RunEvent::RecordPanicked { looked_up, .. } if !looked_up => failed_count += 1,
+                RunEvent::RecordFailed { .. } => failed_count += 1,
+                RunEvent::RunFinished(_) => break,
+                RunEvent::RunStarted { .. }
+                | RunEvent::RecordsDiscovered { .. }
+                | RunEvent::RecordDuplicate { .. }
+                | RunEvent::RecordRetrieved(_)
+                | RunEvent::RecordWritten { .. }
+                | RunEvent::Throttled { .. }
+                | RunEvent::CircuitChanged(_)
+                | RunEvent::RecordPanicked { .. }
+                | RunEvent::Interrupted(_)
+                | RunEvent::RecordsAbandoned { .. }
+                | RunEvent::ProcessingFinished => {}
+            }
+
+            if failed_count >= threshold && self.stop_reason().is_none() {
+                tracing::warn!("{} records failed, stopping.", failed_count);
+                self.stop(InterruptReason::FailFast);
+            }
+        }
+    }
+
+    /// Waits until the run is requested to shut down.
+    pub async fn stopped(&self) {
+        loop {
+            let stop = self.stop.notified();
+            if self.stopped.load(Ordering::SeqCst) {
+                return;
+            }
+            stop.await;
+        }
+    }
+}
+
+/// Accepts commands from `cli_async ctl` while the run is in progress.
+///
+/// Each connection sends one command as a line, and receives one line in
+/// response: `ok`, the status JSON, or `error: <reason>`.
+#[derive(Debug)]
+pub struct ControlServer {
+    run_control: Arc<RunControl>,
+    metrics: Arc<Metrics>,
+    progress_bar: ProgressBar,
+    progress_message: ProgressMessage,
+}
+
+impl ControlServer {
+    /// Returns a control server that acts on the given run.
+    pub fn new(
+        run_control: Arc<RunControl>,
+        metrics: Arc<Metrics>,
+        progress_bar: ProgressBar,
+        progress_message: ProgressMessage,
+    ) -> Self {
+        Self {
+            run_control,
+            metrics,
+            progress_bar,
+            progress_message,
+        }
+    }
+
+    /// Returns the default control socket path.
+    ///
+    /// This is `cli_async.sock` in the runtime directory, or the temporary
+    /// directory if there is none. On Windows, this is the `cli_async` pipe.
+    pub fn default_path() -> PathBuf {
+        if cfg!(windows) {
+            PathBuf::from(r"\\.\pipe\cli_async")
+        } else {
+            dirs::runtime_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("cli_async.sock")
+        }
+    }
+
+    /// Listens on the control socket.
+    ///
+    /// The socket is bound before this returns, so errors are reported
+    /// immediately. The returned future accepts connections until the process
+    /// exits, and the returned guard removes the socket file when dropped.
+    #[cfg(unix)]
+    pub fn serve(self, path: &Path) -> io::Result<(ControlSocketGuard, impl Future<Output = ()>)> {
+        use tokio::net::{UnixListener, UnixStream};
+
+        // Only remove a socket left behind by an instance that is no longer running.
+        if path.exists() && std::os::unix::net::UnixStream::connect(path).is_err() {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        let guard = ControlSocketGuard {
+            path: path.to_path_buf(),
+        };
+
+        let control_server = Arc::new(self);
+        let accept_future = async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _addr)) => {
+                        let control_server = Arc::clone(&control_server);
+                        tokio::spawn(async move {
+                            control_server.handle::<UnixStream>(stream).await;
+                        });
+                    }
+                    Err(e) => tracing::warn!("Failed to accept control connection: {}", e),
+                }
+            }
+        };
+
+        Ok((guard, accept_future))
+    }
+
+    /// Listens on the control pipe.
+    ///
+    /// The pipe is created before this returns, so errors are reported
+    /// immediately. The returned future accepts connections until the process
+    /// exits.
+    #[cfg(windows)]
+    pub fn serve(self, path: &Path) -> io::Result<(ControlSocketGuard, impl Future<Output = ()>)> {
+        use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+
+        let pipe_name = path.as_os_str().to_owned();
+        let mut server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(&pipe_name)?;
+        let guard = ControlSocketGuard {
+            path: path.to_path_buf(),
+        };
+
+        let control_server = Arc::new(self);
+        let accept_future = async move {
+            loop {
+                if let Err(e) = server.connect().await {
+                    tracing::warn!("Failed to accept control connection: {}", e);
+                    continue;
+                }
+                let connected = server;
+                server = match ServerOptions::new().create(&pipe_name) {
+                    Ok(server) => server,
+                    Err(e) => {
+                        tracing::error!("Failed to create control pipe: {}", e);
+                        return;
+                    }
+                };
+
+                let control_server = Arc::clone(&control_server);
+                tokio::spawn(async move {
+                    control_server.handle::<NamedPipeServer>(connected).await;
+                });
+            }
+        };
+
+        Ok((guard, accept_future))
+    }
+
+    /// Reads one command from the connection, and writes the response.
+    async fn handle<S>(&self, stream: S)
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut stream = BufReader::new(stream);
+        let mut line = String::new();
+        if let Err(e) = stream.read_line(&mut line).await {
+            tracing::debug!("Failed to read control command: {}", e);
+            return;
+        }
+
+        let response = match line.trim().parse::<ControlCommand>() {
+            Ok(command) => {
+                tracing::info!(%command, "Received control command.");
+                self.execute(command)
+            }
+            Err(e) => format!("error: {}", e),
+        };
+
+        let stream = stream.get_mut();
+        if let Err(e) = stream.write_all(format!("{}\n", response).as_bytes()).await {
+            tracing::debug!("Failed to write control response: {}", e);
+        }
+        let _ = stream.shutdown().await;
+    }
+
+    /// Executes a command, returning the response.
+    fn execute(&self, command: ControlCommand) -> String {
+        match command {
+            ControlCommand::Pause => self.run_control.pause(&self.progress_message),
+            ControlCommand::Resume => self.run_control.resume(&self.progress_message),
+            ControlCommand::Status => {
+                let status = Status::new(&self.metrics, &self.progress_bar, &self.run_control);
+                return serde_json::to_string(&status).expect("Failed to serialize status.");
+            }
+            ControlCommand::Stop => self.run_control.stop(InterruptReason::Stop),
+        }
+        String::from("ok")
+    }
+}
+
+/// Removes the control socket file when dropped.
+#[derive(Debug)]
+pub struct ControlSocketGuard {
+    path: PathBuf,
+}
+
+impl Drop for ControlSocketGuard {
+    fn drop(&mut self) {
+        // Named pipes are removed by Windows when the last handle is closed.
+        if cfg!(unix) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Sends commands to a running instance.
+pub struct ControlClient;
+
+impl ControlClient {
+    /// Sends the command, and prints the response to stdout.
+    pub async fn run(ctl_opt: &CtlOpt) -> io::Result<()> {
+        let path = ctl_opt
+            .socket
+            .clone()
+            .unwrap_or_else(ControlServer::default_path);
+        let response = Self::send(&path, ctl_opt.command).await?;
+        let response = response.trim_end();
+        println!("{}", response);
+
+        if let Some(error) = response.strip_prefix("error: ") {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, error));
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    async fn send(path: &Path, command: ControlCommand) -> io::Result<String> {
+        let stream = tokio::net::UnixStream::connect(path).await?;
+        Self::exchange(stream, command).await
+    }
+
+    #[cfg(windows)]
+    async fn send(path: &Path, command: ControlCommand) -> io::Result<String> {
+        let client = tokio::net::windows::named_pipe::ClientOptions::new().open(path)?;
+        Self::exchange(client, command).await
+    }
+
+    async fn exchange<S>(mut stream: S, command: ControlCommand) -> io::Result<String>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        stream
+            .write_all(format!("{}\n", command).as_bytes())
+            .await?;
+
+        let mut response = String::new();
+        BufReader::new(stream).read_line(&mut response).await?;
+        Ok(response)
+    }
+}
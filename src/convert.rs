@@ -0,0 +1,345 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use tokio::{
+    fs::File,
+    io::{AsyncWrite, AsyncWriteExt},
+};
+
+use crate::{
+    output::{Compression, OutputLine, OutputLineReader, OutputRecord},
+    output_parquet::{ParquetReader, ParquetWriter},
+    RunMetadata,
+};
+
+/// Columns of a CSV file before the `source:<name>` columns.
+const CSV_COLUMNS: [&str; 9] = [
+    "run_id",
+    "run_version",
+    "run_args",
+    "run_started_at",
+    "record_id",
+    "title_number",
+    "status",
+    "error",
+    "missing_fields",
+];
+/// Prefix of the columns holding whether each `--enrich` source succeeded.
+const CSV_SOURCE_PREFIX: &str = "source:";
+/// Separates the fields in the `missing_fields` column.
+const CSV_LIST_SEPARATOR: char = ';';
+/// Number of records in each row group of Parquet files.
+const PARQUET_ROW_GROUP_SIZE: usize = 8192;
+
+/// Converts an output file between formats.
+#[derive(Debug, Args)]
+pub struct ConvertOpt {
+    /// File to convert, written with `--output` or by `convert`.
+    ///
+    /// JSON lines files ending in `.gz` or `.zst` are decompressed.
+    input: PathBuf,
+    /// File to write the converted records to.
+    ///
+    /// The format is chosen by the extension, `.jsonl`, `.csv`, or
+    /// `.parquet`. JSON lines files are compressed if they end in `.gz` or
+    /// `.zst`.
+    output: PathBuf,
+}
+
+/// Format of a file for `convert`, chosen by its extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    /// JSON lines, as written to `--output`, with a header line per run.
+    Jsonl,
+    /// Comma separated values, with a row per record, for spreadsheets.
+    ///
+    /// Each row repeats the metadata of the run that wrote it.
+    Csv,
+    /// Parquet, as written with `--output-format parquet`, for analytics
+    /// systems.
+    Parquet,
+}
+
+impl FileFormat {
+    /// Returns the format for a path's extension, ignoring a compression
+    /// extension after it for JSON lines files.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let compression = Compression::from_path(path);
+        let path = match compression {
+            Some(_) => Path::new(path.file_stem()?),
+            None => path,
+        };
+        match (path.extension()?.to_str()?, compression) {
+            ("jsonl", _) => Some(Self::Jsonl),
+            ("csv", None) => Some(Self::Csv),
+            ("parquet", None) => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jsonl => write!(f, "JSON lines"),
+            Self::Csv => write!(f, "CSV"),
+            Self::Parquet => write!(f, "Parquet"),
+        }
+    }
+}
+
+/// Records of a run, in the order they were read.
+type Run = (RunMetadata, Vec<OutputRecord>);
+
+/// Converts output files between JSON lines, CSV, and Parquet.
+pub struct OutputConvert;
+
+impl OutputConvert {
+    /// Converts the input file to the output file's format, and writes a
+    /// summary to stderr.
+    ///
+    /// Runs and records keep their order, so converting a file and back
+    /// gives the same records.
+    pub async fn run(convert_opt: &ConvertOpt) -> io::Result<()> {
+        let (input_format, output_format) = match (
+            FileFormat::from_path(&convert_opt.input),
+            FileFormat::from_path(&convert_opt.output),
+        ) {
+            (Some(input_format), Some(output_format)) => (input_format, output_format),
+            (None, _) => return Err(Self::format_unknown(&convert_opt.input)),
+            (_, None) => return Err(Self::format_unknown(&convert_opt.output)),
+        };
+
+        let runs = match input_format {
+            FileFormat::Jsonl => Self::read_jsonl(&convert_opt.input).await?,
+            FileFormat::Csv => Self::read_csv(&convert_opt.input)?,
+            FileFormat::Parquet => ParquetReader::read(&convert_opt.input)?,
+        };
+        let record_count = runs.iter().map(|(_, records)| records.len()).sum::<usize>();
+        match output_format {
+            FileFormat::Jsonl => Self::write_jsonl(&convert_opt.output, &runs).await?,
+            FileFormat::Csv => Self::write_csv(&convert_opt.output, &runs)?,
+            FileFormat::Parquet => Self::write_parquet(&convert_opt.output, runs)?,
+        }
+
+        eprintln!(
+            "Converted {} records from {} to {} in `{}`.",
+            record_count,
+            input_format,
+            output_format,
+            convert_opt.output.display()
+        );
+
+        Ok(())
+    }
+
+    fn format_unknown(path: &Path) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "`{}` is not a `.jsonl` file, which may be compressed, or a `.csv` or \
+                 `.parquet` file.",
+                path.display()
+            ),
+        )
+    }
+
+    async fn read_jsonl(path: &Path) -> io::Result<Vec<Run>> {
+        let mut runs = Vec::<Run>::new();
+        let mut output_lines = OutputLineReader::open(path).await?;
+        while let Some(output_line) = output_lines.next_line().await? {
+            match output_line {
+                OutputLine::Header(run_metadata) => runs.push((run_metadata, Vec::new())),
+                OutputLine::Record(output_record) => match runs.last_mut() {
+                    Some((_, records)) => records.push(output_record),
+                    None => {
+                        return Err(output_lines.invalid_data("Record found before a run header."))
+                    }
+                },
+            }
+        }
+
+        Ok(runs)
+    }
+
+    fn read_csv(path: &Path) -> io::Result<Vec<Run>> {
+        let mut reader = csv::Reader::from_path(path)?;
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("`{}` has no `{}` column.", path.display(), name),
+                    )
+                })
+        };
+        let columns = CSV_COLUMNS
+            .iter()
+            .map(|name| column(name))
+            .collect::<io::Result<Vec<_>>>()?;
+        let source_columns = headers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, header)| {
+                header
+                    .strip_prefix(CSV_SOURCE_PREFIX)
+                    .map(|source| (index, source.to_string()))
+            })
+            .collect::<Vec<_>>();
+
+        let mut runs = Vec::<Run>::new();
+        for row in reader.records() {
+            let row = row?;
+            let invalid_data = |message: String| {
+                let line = row.position().map_or(0, |position| position.line());
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: {}", path.display(), line, message),
+                )
+            };
+            let field = |column: usize| row.get(columns[column]).unwrap_or_default();
+
+            let run_id = field(0);
+            if runs
+                .last()
+                .map(|(run_metadata, _)| run_metadata.run_id.as_str())
+                != Some(run_id)
+            {
+                let args = serde_json::from_str(field(2))
+                    .map_err(|e| invalid_data(format!("Invalid `run_args`: {}", e)))?;
+                runs.push((
+                    RunMetadata {
+                        run_id: run_id.to_string(),
+                        version: field(1).to_string(),
+                        args,
+                        started_at: field(3).to_string(),
+                    },
+                    Vec::new(),
+                ));
+            }
+
+            let record_id = field(4)
+                .parse()
+                .map_err(|e| invalid_data(format!("Invalid `record_id`: {}", e)))?;
+            let status = field(6).parse().map_err(invalid_data)?;
+            let error = Some(field(7))
+                .filter(|error| !error.is_empty())
+                .map(str::to_string);
+            let missing_fields = field(8)
+                .split(CSV_LIST_SEPARATOR)
+                .filter(|missing_field| !missing_field.is_empty())
+                .map(str::to_string)
+                .collect();
+            let sources = source_columns
+                .iter()
+                .filter_map(|(index, source)| {
+                    let succeeded = row.get(*index).filter(|succeeded| !succeeded.is_empty())?;
+                    Some(
+                        succeeded
+                            .parse()
+                            .map(|succeeded| (source.clone(), succeeded))
+                            .map_err(|e| invalid_data(format!("Invalid `{}`: {}", source, e))),
+                    )
+                })
+                .collect::<io::Result<BTreeMap<_, _>>>()?;
+
+            let (_, records) = runs.last_mut().expect("Run was pushed for the row.");
+            records.push(OutputRecord {
+                record_id,
+                title_number: field(5).to_string(),
+                status,
+                error,
+                missing_fields,
+                sources,
+            });
+        }
+
+        Ok(runs)
+    }
+
+    async fn write_jsonl(path: &Path, runs: &[Run]) -> io::Result<()> {
+        let file = File::create(path).await?;
+        let mut output: Box<dyn AsyncWrite + Send + Unpin> = match Compression::from_path(path) {
+            Some(compression) => compression.encoder(file),
+            None => Box::new(tokio::io::BufWriter::new(file)),
+        };
+        for (run_metadata, records) in runs {
+            output
+                .write_all(&OutputLine::Header(run_metadata.clone()).to_line()?)
+                .await?;
+            for record in records {
+                output
+                    .write_all(&OutputLine::Record(record.clone()).to_line()?)
+                    .await?;
+            }
+        }
+        output.shutdown().await
+    }
+
+    fn write_parquet(path: &Path, runs: Vec<Run>) -> io::Result<()> {
+        let run_metadatas = runs
+            .iter()
+            .map(|(run_metadata, _)| run_metadata.clone())
+            .collect::<Vec<_>>();
+        let mut parquet_writer =
+            ParquetWriter::create(path, &run_metadatas, PARQUET_ROW_GROUP_SIZE)?;
+        for (run_index, (_, records)) in runs.into_iter().enumerate() {
+            for record in records {
+                parquet_writer.write(run_index, record)?;
+            }
+        }
+        parquet_writer.finish().map(|_| ())
+    }
+
+    fn write_csv(path: &Path, runs: &[Run]) -> io::Result<()> {
+        let sources = runs
+            .iter()
+            .flat_map(|(_, records)| records.iter())
+            .flat_map(|record| record.sources.keys())
+            .collect::<BTreeSet<_>>();
+
+        let mut writer = csv::Writer::from_path(path)?;
+        let headers = CSV_COLUMNS
+            .iter()
+            .map(|column| column.to_string())
+            .chain(
+                sources
+                    .iter()
+                    .map(|source| format!("{}{}", CSV_SOURCE_PREFIX, source)),
+            )
+            .collect::<Vec<_>>();
+        writer.write_record(&headers)?;
+        for (run_metadata, records) in runs {
+            let run_args = serde_json::to_string(&run_metadata.args)?;
+            for record in records {
+                let mut row = vec![
+                    run_metadata.run_id.clone(),
+                    run_metadata.version.clone(),
+                    run_args.clone(),
+                    run_metadata.started_at.clone(),
+                    record.record_id.to_string(),
+                    record.title_number.clone(),
+                    record.status.to_string(),
+                    record.error.clone().unwrap_or_default(),
+                    record.missing_fields.join(&CSV_LIST_SEPARATOR.to_string()),
+                ];
+                row.extend(sources.iter().map(|source| {
+                    record
+                        .sources
+                        .get(*source)
+                        .map(bool::to_string)
+                        .unwrap_or_default()
+                }));
+                writer.write_record(&row)?;
+            }
+        }
+        writer.flush()
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::Path,
+    str::FromStr,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// How requests are spread across the keys in `--credentials-file`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CredentialRotation {
+    /// Each request uses the next key in turn.
+    RoundRobin,
+    /// Requests use the same key until the server rejects it or rate limits
+    /// it, then switch to the next key.
+    OnError,
+}
+
+impl fmt::Display for CredentialRotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RoundRobin => write!(f, "round-robin"),
+            Self::OnError => write!(f, "on-error"),
+        }
+    }
+}
+
+impl FromStr for CredentialRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(Self::RoundRobin),
+            "on-error" => Ok(Self::OnError),
+            _ => Err(format!("`{}` is not one of `round-robin`, `on-error`.", s)),
+        }
+    }
+}
+
+/// An API key to authenticate with the server.
+#[derive(Clone, Debug)]
+pub struct Credential {
+    /// Position of the key in the credentials file.
+    index: usize,
+    /// Name given to the key in the credentials file, if any.
+    name: Option<String>,
+    key: String,
+}
+
+impl Credential {
+    /// Returns how the key is shown in logs and the report, without revealing
+    /// it: its name, or its last 4 characters.
+    pub fn label(&self) -> String {
+        match self.name.as_deref() {
+            Some(name) => String::from(name),
+            None => {
+                let suffix_start = self
+                    .key
+                    .char_indices()
+                    .rev()
+                    .nth(3)
+                    .map_or(0, |(index, _)| index);
+                format!("…{}", &self.key[suffix_start..])
+            }
+        }
+    }
+}
+
+/// Keys to authenticate with the server, and which one to use for each
+/// request.
+#[derive(Debug)]
+pub struct Credentials {
+    credentials: Vec<Credential>,
+    rotation: CredentialRotation,
+    /// Number of requests made so far with round-robin rotation, or the index
+    /// of the key in use with on-error rotation.
+    next: AtomicUsize,
+    /// Whether each key has been authenticated.
+    authenticated: Vec<AtomicBool>,
+    /// Number of requests made with each key.
+    request_counts: Vec<AtomicUsize>,
+    /// Whether the keys were read from a credentials file, so their usage is
+    /// worth reporting.
+    from_file: bool,
+}
+
+impl Credentials {
+    /// Reads the keys from a credentials file, or returns a single default
+    /// key if there is no file.
+    ///
+    /// Each line is a key, or `name=key`. Blank lines and `#` comments are
+    /// ignored.
+    pub fn read(path: Option<&Path>, rotation: CredentialRotation) -> io::Result<Self> {
+        let credentials = match path {
+            Some(path) => Self::parse(&fs::read_to_string(path)?)?,
+            None => vec![Credential {
+                index: 0,
+                name: Some(String::from("default")),
+                key: String::new(),
+            }],
+        };
+
+        Ok(Self {
+            authenticated: credentials.iter().map(|_| AtomicBool::new(false)).collect(),
+            request_counts: credentials.iter().map(|_| AtomicUsize::new(0)).collect(),
+            credentials,
+            rotation,
+            next: AtomicUsize::new(0),
+            from_file: path.is_some(),
+        })
+    }
+
+    fn parse(contents: &str) -> io::Result<Vec<Credential>> {
+        let credentials = contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(index, line)| {
+                let (name, key) = match line.split_once('=') {
+                    Some((name, key)) => (Some(String::from(name.trim())), key.trim()),
+                    None => (None, line),
+                };
+                Credential {
+                    index,
+                    name,
+                    key: String::from(key),
+                }
+            })
+            .collect::<Vec<_>>();
+        if credentials.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no keys found in credentials file",
+            ));
+        }
+
+        Ok(credentials)
+    }
+
+    /// Returns the key that the next request would use, without counting a
+    /// request.
+    pub fn current(&self) -> &Credential {
+        &self.credentials[self.next.load(Ordering::SeqCst) % self.credentials.len()]
+    }
+
+    /// Returns the key to make a request with, and counts the request.
+    pub fn acquire(&self) -> &Credential {
+        let next = match self.rotation {
+            CredentialRotation::RoundRobin => self.next.fetch_add(1, Ordering::SeqCst),
+            CredentialRotation::OnError => self.next.load(Ordering::SeqCst),
+        };
+        let credential = &self.credentials[next % self.credentials.len()];
+        self.request_counts[credential.index].fetch_add(1, Ordering::Relaxed);
+        credential
+    }
+
+    /// Returns whether the key needs to be authenticated before it is used,
+    /// and marks it as authenticated.
+    pub fn authenticate(&self, credential: &Credential) -> bool {
+        !self.authenticated[credential.index].swap(true, Ordering::SeqCst)
+    }
+
+    /// Records that the server rejected the key's authentication, so it needs
+    /// to be authenticated again.
+    pub fn expired(&self, credential: &Credential) {
+        self.authenticated[credential.index].store(false, Ordering::SeqCst);
+    }
+
+    /// Switches to the next key with on-error rotation, after the server
+    /// rejected or rate limited `credential`.
+    ///
+    /// Returns whether later requests use a different key.
+    pub fn rotate(&self, credential: &Credential) -> bool {
+        if self.rotation != CredentialRotation::OnError || self.credentials.len() < 2 {
+            return false;
+        }
+
+        // Other requests may have rotated away from the key already.
+        let _ = self.next.compare_exchange(
+            credential.index,
+            (credential.index + 1) % self.credentials.len(),
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+        tracing::info!(credential = %credential.label(), "Rotating to the next key.");
+        true
+    }
+
+    /// Returns the number of requests made with each key, by label, if the
+    /// keys were read from a credentials file.
+    pub fn usage(&self) -> Option<BTreeMap<String, usize>> {
+        self.from_file.then(|| {
+            self.credentials
+                .iter()
+                .map(|credential| {
+                    (
+                        credential.label(),
+                        self.request_counts[credential.index].load(Ordering::Relaxed),
+                    )
+                })
+                .collect()
+        })
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use futures::{stream, Stream};
+use tokio::{sync::mpsc, time::sleep};
+
+use crate::PropertyRecord;
+
+/// A page of record IDs returned by the listing endpoint.
+#[derive(Clone, Debug)]
+pub struct ListingPage {
+    /// Records on this page.
+    pub records: Vec<PropertyRecord>,
+    /// Token to fetch the next page with, or `None` if this is the last page.
+    pub continuation_token: Option<String>,
+}
+
+/// Lists records from the simulated paginated listing endpoint, for
+/// `--discover`.
+///
+/// Pages are fetched one ahead of processing, so records are processed while
+/// later pages are still being listed.
+#[derive(Clone, Copy, Debug)]
+pub struct Discovery {
+    /// Total number of records the endpoint lists.
+    record_count: usize,
+    /// Maximum number of records on each page.
+    page_size: usize,
+    /// Time each page takes to fetch.
+    delay: Duration,
+}
+
+impl Discovery {
+    /// Returns a discovery for the listing endpoint.
+    pub fn new(record_count: usize, page_size: usize, delay: Duration) -> Self {
+        Self {
+            record_count,
+            page_size,
+            delay,
+        }
+    }
+
+    /// Returns the records on each page, in listing order.
+    ///
+    /// The stream ends after the last page, or if a page fails to fetch.
+    pub fn pages(self) -> impl Stream<Item = Vec<PropertyRecord>> {
+        let (pages_tx, pages_rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            let mut continuation_token = None;
+            loop {
+                let page = match self.fetch_page(continuation_token.as_deref()).await {
+                    Ok(page) => page,
+                    Err(e) => {
+                        tracing::error!("Failed to list records: {}", e);
+                        break;
+                    }
+                };
+                tracing::debug!(
+                    continuation_token = ?page.continuation_token,
+                    "Listed {} records.",
+                    page.records.len()
+                );
+                // Processing has stopped, e.g. because the run was interrupted.
+                if pages_tx.send(page.records).await.is_err() {
+                    break;
+                }
+                match page.continuation_token {
+                    Some(token) => continuation_token = Some(token),
+                    None => break,
+                }
+            }
+        });
+
+        stream::unfold(pages_rx, |mut pages_rx| async move {
+            pages_rx.recv().await.map(|page| (page, pages_rx))
+        })
+    }
+
+    /// Fetches the page after the given continuation token, or the first page
+    /// if there is no token.
+    async fn fetch_page(&self, continuation_token: Option<&str>) -> Result<ListingPage, String> {
+        let start = match continuation_token {
+            Some(token) => usize::from_str_radix(token, 16)
+                .map_err(|_| format!("`{}` is not a valid continuation token.", token))?,
+            None => 0,
+        };
+        sleep(self.delay).await;
+
+        let end = start.saturating_add(self.page_size).min(self.record_count);
+        Ok(ListingPage {
+            records: (start..end).map(PropertyRecord).collect(),
+            continuation_token: (end < self.record_count).then(|| format!("{:08x}", end)),
+        })
+    }
+}
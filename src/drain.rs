@@ -0,0 +1,138 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    task::JoinHandle,
+    time::{self, Instant},
+};
+
+use crate::{InterruptReason, ProgressMessage};
+
+/// Number of records that have started passing through a [`Pipeline`]'s
+/// stages, and haven't finished or failed yet.
+///
+/// [`Pipeline`]: crate::Pipeline
+#[derive(Debug, Default)]
+pub struct InFlight {
+    count: AtomicUsize,
+}
+
+impl InFlight {
+    /// Counts a record as in flight, until the returned guard is dropped.
+    pub fn start(&self) -> InFlightRecord<'_> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        InFlightRecord { in_flight: self }
+    }
+
+    /// Returns the number of records in flight.
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+}
+
+/// A record counted in [`InFlight`] while this is held.
+#[derive(Debug)]
+pub struct InFlightRecord<'a> {
+    in_flight: &'a InFlight,
+}
+
+impl Drop for InFlightRecord<'_> {
+    fn drop(&mut self) {
+        self.in_flight.count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Lets records already in flight finish after the run is interrupted, up to
+/// `--drain-timeout`, before abandoning them.
+#[derive(Debug)]
+pub struct Drain {
+    in_flight: Arc<InFlight>,
+    timeout: Duration,
+    progress_message: ProgressMessage,
+}
+
+impl Drain {
+    /// How often the countdown on the progress bar is updated.
+    const TICK_INTERVAL: Duration = Duration::from_millis(200);
+    /// Longest time to drain for after the console window is closed.
+    ///
+    /// Windows terminates the process 5 seconds after the console is closed,
+    /// so this leaves time to flush the output and journal.
+    const CONSOLE_CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// Returns a drain that waits up to `timeout` for the records in flight.
+    pub fn new(
+        in_flight: Arc<InFlight>,
+        timeout: Duration,
+        progress_message: ProgressMessage,
+    ) -> Self {
+        Self {
+            in_flight,
+            timeout,
+            progress_message,
+        }
+    }
+
+    /// Waits for processing to finish, showing "finishing N in-flight
+    /// records…" with the time remaining, then aborts it once the timeout
+    /// passes.
+    ///
+    /// The timeout is shortened if the console window was closed.
+    ///
+    /// Returns the number of records abandoned mid-flight.
+    pub async fn drain(&self, mut processing: JoinHandle<()>, reason: InterruptReason) -> usize {
+        let timeout = match reason {
+            InterruptReason::ConsoleClose => self.timeout.min(Self::CONSOLE_CLOSE_TIMEOUT),
+            InterruptReason::CtrlC
+            | InterruptReason::Sigterm
+            | InterruptReason::CtrlBreak
+            | InterruptReason::Deadline
+            | InterruptReason::FailFast
+            | InterruptReason::Stop => self.timeout,
+        };
+        let in_flight_count = self.in_flight.count();
+        if in_flight_count > 0 {
+            tracing::warn!(
+                "Interrupted, finishing {} in-flight records within {}.",
+                in_flight_count,
+                humantime::format_duration(timeout)
+            );
+        }
+
+        let deadline = Instant::now() + timeout;
+        let mut tick = time::interval(Self::TICK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = &mut processing => {
+                    self.progress_message.set_draining(None);
+                    return 0;
+                }
+                () = time::sleep_until(deadline) => break,
+                _ = tick.tick() => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    self.progress_message
+                        .set_draining(Some((self.in_flight.count(), remaining)));
+                }
+            }
+        }
+
+        // Read before aborting, as aborting drops the records' guards.
+        let abandoned_count = self.in_flight.count();
+        processing.abort();
+        if abandoned_count > 0 {
+            tracing::warn!(
+                "Drain timeout of {} reached, abandoning {} in-flight records.",
+                humantime::format_duration(timeout),
+                abandoned_count
+            );
+        }
+        self.progress_message.set_draining(None);
+
+        abandoned_count
+    }
+}
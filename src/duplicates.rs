@@ -0,0 +1,85 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write as _,
+    fs, io,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use futures::{Stream, StreamExt};
+
+use crate::{EventBus, PropertyRecord, RunEvent};
+
+/// Records whose ID appears more than once in the input.
+#[derive(Debug, Default)]
+pub struct Duplicates {
+    /// IDs of the records seen in the input so far.
+    seen: HashSet<usize>,
+    /// Number of times each duplicated record ID appears in the input.
+    occurrences: BTreeMap<usize, usize>,
+}
+
+impl Duplicates {
+    /// Removes records whose ID appeared earlier in `records`, publishing a
+    /// [`RunEvent::RecordDuplicate`] for each one.
+    ///
+    /// The first occurrence of each record is kept, so the processing order is
+    /// unchanged. Only the record IDs are held, so records from `--stdin`,
+    /// `--watch`, and `--discover` are checked as they arrive.
+    pub fn remove_from(
+        duplicates: Arc<Mutex<Self>>,
+        records: impl Stream<Item = (usize, PropertyRecord)>,
+        event_bus: EventBus,
+    ) -> impl Stream<Item = (usize, PropertyRecord)> {
+        records.filter_map(move |(n, record)| {
+            let first_seen = duplicates
+                .lock()
+                .expect("Duplicates lock poisoned.")
+                .first_seen(record);
+            let event_bus = event_bus.clone();
+            async move {
+                if first_seen {
+                    Some((n, record))
+                } else {
+                    event_bus
+                        .publish(RunEvent::RecordDuplicate { record })
+                        .await;
+                    None
+                }
+            }
+        })
+    }
+
+    /// Returns whether this is the first time the record's ID is seen, and
+    /// counts it as a duplicate otherwise.
+    fn first_seen(&mut self, record: PropertyRecord) -> bool {
+        if self.seen.insert(record.0) {
+            true
+        } else {
+            *self.occurrences.entry(record.0).or_insert(1) += 1;
+            false
+        }
+    }
+
+    /// Returns the number of records removed.
+    pub fn count(&self) -> usize {
+        self.occurrences
+            .values()
+            .map(|occurrences| occurrences - 1)
+            .sum()
+    }
+
+    /// Writes the duplicated record IDs to a file, one per line.
+    ///
+    /// Each line is commented with the number of occurrences, so the file can
+    /// be passed to `--only-ids` or `--exclude-ids`.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut contents = String::with_capacity(self.occurrences.len() * 24);
+        self.occurrences.iter().for_each(|(id, occurrences)| {
+            // Writing to a `String` doesn't fail.
+            let _ = writeln!(&mut contents, "{} # {} occurrences", id, occurrences);
+        });
+
+        fs::write(path, contents)
+    }
+}
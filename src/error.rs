@@ -0,0 +1,217 @@
+use std::{fmt, sync::Arc};
+
+use miette::Diagnostic;
+
+/// Source of an [`Error`], shared so that errors can be cloned into events.
+type Source = Arc<dyn std::error::Error + Send + Sync>;
+
+/// Kind of failure, so that errors are reported and handled by what went
+/// wrong rather than by their message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// The server didn't accept our credentials.
+    Auth,
+    /// Talking to the server or another service failed.
+    Network,
+    /// The server doesn't have the record's information.
+    Lookup,
+    /// Reading or writing a local file or stream failed.
+    Io,
+    /// The options or config are invalid.
+    Config,
+    /// The run was interrupted before it finished.
+    Interrupted,
+    /// A bug in `cli_async`, e.g. a task panicked.
+    Internal,
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auth => write!(f, "auth"),
+            Self::Network => write!(f, "network"),
+            Self::Lookup => write!(f, "lookup"),
+            Self::Io => write!(f, "io"),
+            Self::Config => write!(f, "config"),
+            Self::Interrupted => write!(f, "interrupted"),
+            Self::Internal => write!(f, "internal"),
+        }
+    }
+}
+
+/// Errors that fail a record, or stop the run.
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum Error {
+    /// The authentication token expired, and retries were exhausted.
+    #[error("Authentication expired.")]
+    AuthExpired,
+    /// The server closed the connection, and retries were exhausted.
+    #[error("Connection reset by server.")]
+    ConnectionReset,
+    /// The server rate limited the request, and retries were exhausted.
+    #[error("Rate limited by server (429 Too Many Requests).")]
+    RateLimited,
+    /// The server doesn't have the record's information.
+    #[error("Could not find record information online.")]
+    NotFound,
+    /// Failed to prepare to authenticate with the server.
+    #[error("Failed to {action}.")]
+    Auth {
+        action: &'static str,
+        #[source]
+        source: Source,
+    },
+    /// Failed to talk to another service, e.g. the store or a broker.
+    #[error("Failed to {action}.")]
+    Network {
+        action: &'static str,
+        #[source]
+        source: Source,
+    },
+    /// Failed to read or write a local file or stream.
+    #[error("Failed to {action}.")]
+    Io {
+        action: &'static str,
+        #[source]
+        source: Source,
+    },
+    /// Failed to read the options or config.
+    #[error("Failed to {action}.")]
+    Config {
+        action: &'static str,
+        #[source]
+        source: Source,
+    },
+    /// `validate` found problems, which it has already printed.
+    #[error("Validation failed.")]
+    ValidationFailed,
+    /// `verify` found records missing from or repeated in the output, which
+    /// it has already printed.
+    #[error("Output does not match the input.")]
+    VerificationFailed,
+    /// The run was interrupted, by Ctrl-C or because it was stopped.
+    #[error("Run was interrupted.")]
+    Interrupted,
+    /// A task panicked, so the run's results are incomplete. The panic
+    /// message has already been printed.
+    #[error("The {task} panicked.")]
+    Panicked { task: &'static str },
+}
+
+impl Error {
+    /// Returns a function that wraps an error from authenticating, for
+    /// `map_err`.
+    pub fn auth<E>(action: &'static str) -> impl FnOnce(E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        move |source| Self::Auth {
+            action,
+            source: Arc::new(source),
+        }
+    }
+
+    /// Returns a function that wraps an error from talking to another
+    /// service, for `map_err`.
+    pub fn network<E>(action: &'static str) -> impl FnOnce(E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        move |source| Self::Network {
+            action,
+            source: Arc::new(source),
+        }
+    }
+
+    /// Returns a function that wraps an error from reading or writing, for
+    /// `map_err`.
+    pub fn io<E>(action: &'static str) -> impl FnOnce(E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        move |source| Self::Io {
+            action,
+            source: Arc::new(source),
+        }
+    }
+
+    /// Returns a function that wraps an error from reading the options or
+    /// config, for `map_err`.
+    pub fn config<E>(action: &'static str) -> impl FnOnce(E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        move |source| Self::Config {
+            action,
+            source: Arc::new(source),
+        }
+    }
+
+    /// Returns what kind of failure this is.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::AuthExpired | Self::Auth { .. } => ErrorCategory::Auth,
+            Self::ConnectionReset | Self::RateLimited | Self::Network { .. } => {
+                ErrorCategory::Network
+            }
+            Self::NotFound => ErrorCategory::Lookup,
+            Self::Io { .. } | Self::VerificationFailed => ErrorCategory::Io,
+            Self::Config { .. } | Self::ValidationFailed => ErrorCategory::Config,
+            Self::Interrupted => ErrorCategory::Interrupted,
+            Self::Panicked { .. } => ErrorCategory::Internal,
+        }
+    }
+
+    /// Returns the process exit code for a run that stopped with this error.
+    ///
+    /// Interrupted runs exit with 130, as shells do for Ctrl-C.
+    pub fn exit_code(&self) -> u8 {
+        match self.category() {
+            ErrorCategory::Interrupted => 130,
+            _ => 1,
+        }
+    }
+}
+
+/// Shown as a diagnostic when the run stops with an error, with the error's
+/// category as its code, the errors that caused it, and a hint to fix it.
+impl Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(format!("cli_async::{}", self.category())))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let help = match self {
+            Self::AuthExpired | Self::ConnectionReset | Self::RateLimited => {
+                "Try again later, or with more `--retries`."
+            }
+            Self::NotFound => return None,
+            Self::Auth { .. } => {
+                "`--credentials-file` should list one key per line, as `key` or `name=key`."
+            }
+            Self::Network { .. } => "Check that the address is correct and the service is running.",
+            Self::Io { .. } => "Check that the path exists and that you have permission to use it.",
+            Self::Config { .. } => "Run `cli_async validate` to check the options and config.",
+            Self::ValidationFailed => "Fix the problems listed above, then validate again.",
+            Self::VerificationFailed => {
+                "Process the missing records again, e.g. with `--only-ids`, then verify again."
+            }
+            Self::Interrupted => {
+                "Run again with `--resume` and the same `--journal` or `--store` to continue."
+            }
+            Self::Panicked { .. } => {
+                "This is a bug in `cli_async`. The panic message above shows where it happened."
+            }
+        };
+        Some(Box::new(help))
+    }
+}
+
+impl From<fmt::Error> for Error {
+    fn from(error: fmt::Error) -> Self {
+        Self::Io {
+            action: "format output",
+            source: Arc::new(error),
+        }
+    }
+}
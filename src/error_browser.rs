@@ -0,0 +1,316 @@
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+use ratatui::{
+    crossterm::{
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        terminal,
+        tty::IsTty,
+    },
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    Frame,
+};
+
+use crate::{report::RecordFailure, PropertyRecord, Record, Report, Tui};
+
+/// Order of the rows in the [`ErrorBrowser`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorSort {
+    /// By record ID.
+    RecordId,
+    /// By error message, then record ID, so records with the same error are
+    /// together.
+    Message,
+}
+
+impl fmt::Display for ErrorSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RecordId => f.pad("record id"),
+            Self::Message => f.pad("message"),
+        }
+    }
+}
+
+/// Full-screen pager for the failed records, offered after the report instead
+/// of listing every failure.
+///
+/// * `↑` / `↓`, `PgUp` / `PgDn`, `Home` / `End`: scroll.
+/// * `/`: search the record IDs, title numbers, and errors.
+/// * `s`: sort by record ID or error message.
+/// * `q` or `Esc`: quit.
+#[derive(Debug)]
+pub struct ErrorBrowser<'report, R = PropertyRecord> {
+    /// Failed and panicked records, in the current sort order.
+    record_failures: Vec<&'report RecordFailure<R>>,
+    sort: ErrorSort,
+    /// Text the rows are filtered by.
+    query: String,
+    /// Whether the query is being typed.
+    searching: bool,
+    /// Indices into `record_failures` of the rows that match the query.
+    rows: Vec<usize>,
+    table_state: TableState,
+}
+
+impl<'report, R> ErrorBrowser<'report, R>
+where
+    R: Record,
+{
+    /// Number of rows `PgUp` and `PgDn` scroll by.
+    const PAGE_ROWS: usize = 20;
+
+    /// Returns a browser for the report's failed records.
+    ///
+    /// Returns `None` if no records failed, or stdin or stderr is not a
+    /// terminal.
+    pub fn new(report: &'report Report<R>) -> Option<Self> {
+        if !io::stdin().is_tty() || !io::stderr().is_tty() {
+            return None;
+        }
+        let record_failures = report
+            .records_processed_failed
+            .iter()
+            .chain(report.records_panicked.iter())
+            .collect::<Vec<_>>();
+        if record_failures.is_empty() {
+            return None;
+        }
+
+        let mut error_browser = Self {
+            record_failures,
+            sort: ErrorSort::RecordId,
+            query: String::new(),
+            searching: false,
+            rows: Vec::new(),
+            table_state: TableState::default(),
+        };
+        error_browser.sort();
+
+        Some(error_browser)
+    }
+
+    /// Asks whether to browse the errors, and shows the browser if `e` is
+    /// pressed.
+    pub fn offer(mut self) -> io::Result<()> {
+        let mut stderr = io::stderr();
+        write!(
+            stderr,
+            "Press `e` to browse the {} failed records, or any other key to exit.",
+            self.record_failures.len()
+        )?;
+        stderr.flush()?;
+
+        terminal::enable_raw_mode()?;
+        let browse = loop {
+            match event::read() {
+                Ok(Event::Key(KeyEvent {
+                    kind: KeyEventKind::Press,
+                    code,
+                    ..
+                })) => break code == KeyCode::Char('e'),
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::warn!("Failed to read key press: {}", e);
+                    break false;
+                }
+            }
+        };
+        terminal::disable_raw_mode()?;
+        writeln!(stderr)?;
+
+        if browse {
+            self.browse()?;
+        }
+        Ok(())
+    }
+
+    /// Shows the browser until it is quit.
+    fn browse(&mut self) -> io::Result<()> {
+        let (mut terminal, _terminal_guard) = Tui::enter()?;
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            match event::read()? {
+                Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                    let quit = if self.searching {
+                        self.search_key(key_event);
+                        false
+                    } else {
+                        self.key(key_event)
+                    };
+                    if quit {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles a key press while browsing, returning whether to quit.
+    fn key(&mut self, key_event: KeyEvent) -> bool {
+        match key_event.code {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                return true
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.scroll_down(1),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_up(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll_down(Self::PAGE_ROWS),
+            KeyCode::PageUp => self.scroll_up(Self::PAGE_ROWS),
+            KeyCode::Home | KeyCode::Char('g') => self.table_state.select_first(),
+            KeyCode::End | KeyCode::Char('G') => self.table_state.select_last(),
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Char('s') => {
+                self.sort = match self.sort {
+                    ErrorSort::RecordId => ErrorSort::Message,
+                    ErrorSort::Message => ErrorSort::RecordId,
+                };
+                self.sort();
+            }
+            _ => {}
+        }
+
+        false
+    }
+
+    /// Handles a key press while the query is being typed.
+    fn search_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Enter => self.searching = false,
+            KeyCode::Esc => {
+                self.searching = false;
+                self.query.clear();
+                self.filter();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.filter();
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.filter();
+            }
+            _ => {}
+        }
+    }
+
+    fn scroll_down(&mut self, row_count: usize) {
+        let selected = self.table_state.selected().unwrap_or(0);
+        let last = self.rows.len().saturating_sub(1);
+        self.table_state
+            .select(Some(selected.saturating_add(row_count).min(last)));
+    }
+
+    fn scroll_up(&mut self, row_count: usize) {
+        let selected = self.table_state.selected().unwrap_or(0);
+        self.table_state
+            .select(Some(selected.saturating_sub(row_count)));
+    }
+
+    /// Sorts the records in the current order, and filters them again.
+    fn sort(&mut self) {
+        match self.sort {
+            ErrorSort::RecordId => self
+                .record_failures
+                .sort_by_key(|record_failure| record_failure.record.id()),
+            ErrorSort::Message => self.record_failures.sort_by(|a, b| {
+                a.error
+                    .cmp(&b.error)
+                    .then_with(|| a.record.id().cmp(&b.record.id()))
+            }),
+        }
+        self.filter();
+    }
+
+    /// Keeps the rows that contain the query, ignoring case, and selects the
+    /// first.
+    fn filter(&mut self) {
+        let query = self.query.to_lowercase();
+        self.rows = self
+            .record_failures
+            .iter()
+            .enumerate()
+            .filter(|(_, record_failure)| {
+                query.is_empty()
+                    || record_failure.record.id().to_string().contains(&query)
+                    || record_failure
+                        .record
+                        .label()
+                        .to_lowercase()
+                        .contains(&query)
+                    || record_failure.error.to_lowercase().contains(&query)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        self.table_state
+            .select(if self.rows.is_empty() { None } else { Some(0) });
+    }
+
+    fn draw(&mut self, frame: &mut Frame<'_>) {
+        let [table_area, status_area, keys_area] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let label_style = Style::default().add_modifier(Modifier::BOLD);
+        let header = Row::new(["#", "title_number", "attempts", "error"]).style(label_style);
+        let rows = self.rows.iter().map(|index| {
+            let record_failure = self.record_failures[*index];
+            Row::new([
+                Cell::from(record_failure.record.id().to_string()),
+                Cell::from(record_failure.record.label())
+                    .style(Style::default().fg(Color::Magenta)),
+                Cell::from(record_failure.attempts.to_string()),
+                Cell::from(record_failure.error.as_str()).style(Style::default().fg(Color::Red)),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(6),
+                Constraint::Length(13),
+                Constraint::Length(8),
+                Constraint::Min(20),
+            ],
+        )
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(" Errors "))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.table_state);
+
+        let mut status = vec![Span::raw(format!(
+            "{}/{} failed records · sorted by {}",
+            self.rows.len(),
+            self.record_failures.len(),
+            self.sort
+        ))];
+        if self.searching || !self.query.is_empty() {
+            status.push(Span::raw(" · search: "));
+            status.push(Span::styled(
+                format!("{}{}", self.query, if self.searching { "▏" } else { "" }),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        frame.render_widget(Paragraph::new(Line::from(status)), status_area);
+
+        let keys = if self.searching {
+            "Enter finish search · Esc clear search"
+        } else {
+            "↑ / ↓ scroll · / search · s sort · q quit"
+        };
+        frame.render_widget(
+            Paragraph::new(keys).style(Style::default().fg(Color::DarkGray)),
+            keys_area,
+        );
+    }
+}
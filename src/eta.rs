@@ -0,0 +1,119 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use indicatif::{HumanDuration, ProgressState, ProgressStyle};
+
+/// Estimates the time remaining from how fast records finished over the last
+/// [`Eta::WINDOW`].
+///
+/// Unlike `indicatif`'s estimate, records skipped when resuming aren't counted
+/// as finished, and the estimate follows changes in rate, e.g. after being
+/// throttled, within the window.
+#[derive(Clone, Debug, Default)]
+pub struct Eta {
+    /// When each record in the window finished, oldest first.
+    completions: Arc<Mutex<VecDeque<Instant>>>,
+}
+
+impl Eta {
+    /// How far back finished records are counted towards the rate.
+    pub const WINDOW: Duration = Duration::from_secs(30);
+    /// Fewest finished records to estimate the rate from, which are kept even
+    /// if they are older than the window.
+    const COMPLETION_COUNT_MIN: usize = 3;
+
+    /// Records that a record finished processing.
+    pub fn record_completed(&self) {
+        let now = Instant::now();
+        let mut completions = self.completions.lock().expect("ETA lock poisoned.");
+        completions.push_back(now);
+        while completions.len() > Self::COMPLETION_COUNT_MIN
+            && completions
+                .front()
+                .is_some_and(|completion| now.duration_since(*completion) > Self::WINDOW)
+        {
+            completions.pop_front();
+        }
+    }
+
+    /// Returns the number of records finishing per second, or `None` if too
+    /// few have finished to tell.
+    ///
+    /// The rate falls while no records finish, so the estimate grows during
+    /// stalls.
+    pub fn rate(&self) -> Option<f64> {
+        let completions = self.completions.lock().expect("ETA lock poisoned.");
+        if completions.len() < Self::COMPLETION_COUNT_MIN {
+            return None;
+        }
+        let elapsed = completions.front()?.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            Some((completions.len() - 1) as f64 / elapsed)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the time until `record_remaining_count` more records finish.
+    pub fn remaining(&self, record_remaining_count: u64) -> Option<Duration> {
+        if record_remaining_count == 0 {
+            return Some(Duration::ZERO);
+        }
+        self.rate()
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| Duration::from_secs_f64(record_remaining_count as f64 / rate))
+    }
+
+    /// Returns the local time `remaining` from now, e.g. `14:32:05`, with the
+    /// date if it is a day or more away.
+    pub fn finish_at(remaining: Duration) -> String {
+        let now = chrono::Local::now();
+        let finish_at = chrono::Duration::from_std(remaining)
+            .ok()
+            .and_then(|remaining| now.checked_add_signed(remaining))
+            .unwrap_or(now);
+        if remaining < Duration::from_secs(24 * 60 * 60) {
+            finish_at.format("%H:%M:%S").to_string()
+        } else {
+            finish_at.format("%Y-%m-%d %H:%M").to_string()
+        }
+    }
+
+    /// Replaces the `{eta}` progress bar key with this estimate, and adds the
+    /// `{finish_at}` key for the local time the run is estimated to finish.
+    ///
+    /// Both show `-` until the estimate is known.
+    pub fn style(&self, style: ProgressStyle) -> ProgressStyle {
+        let eta = self.clone();
+        let finish_at = self.clone();
+        style
+            .with_key(
+                "eta",
+                move |state: &ProgressState, w: &mut dyn fmt::Write| {
+                    let _ = match eta.state_remaining(state) {
+                        Some(remaining) => write!(w, "{:#}", HumanDuration(remaining)),
+                        None => write!(w, "-"),
+                    };
+                },
+            )
+            .with_key(
+                "finish_at",
+                move |state: &ProgressState, w: &mut dyn fmt::Write| {
+                    let _ = match finish_at.state_remaining(state) {
+                        Some(remaining) => write!(w, "{}", Self::finish_at(remaining)),
+                        None => write!(w, "-"),
+                    };
+                },
+            )
+    }
+
+    /// Returns the time until the records left on the progress bar finish.
+    fn state_remaining(&self, state: &ProgressState) -> Option<Duration> {
+        let record_remaining_count = state.len()?.saturating_sub(state.pos());
+        self.remaining(record_remaining_count)
+    }
+}
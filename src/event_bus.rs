@@ -0,0 +1,228 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, error::TrySendError, Receiver, Sender};
+
+use crate::{
+    CircuitState, InterruptReason, PropertyInfoResult, PropertyRecord, RecordProgress, Report,
+    StageKind,
+};
+
+/// Something that happened during a run, published on the [`EventBus`].
+///
+/// Records are [`PropertyRecord`]s with [`PropertyInfoResult`]s, unless the
+/// run is for another kind of [`Pipeline`].
+///
+/// [`Pipeline`]: crate::Pipeline
+#[derive(Clone, Debug)]
+pub enum RunEvent<R = PropertyRecord, I = PropertyInfoResult> {
+    /// Processing has started.
+    RunStarted {
+        /// Total number of records, or `None` if it isn't known.
+        record_count: Option<usize>,
+        /// Number of records already processed.
+        record_skipped_count: usize,
+    },
+    /// The record source found more records to process after the run
+    /// started, e.g. a new input file with `--watch`.
+    RecordsDiscovered {
+        /// Number of records added to the total.
+        record_count: usize,
+    },
+    /// A record was left out because its ID appeared earlier in the input.
+    RecordDuplicate { record: R },
+    /// Information was looked up for a record, whether or not it was found.
+    RecordRetrieved(RecordProgress<R, I>),
+    /// A record passed through every stage, and was written.
+    RecordWritten { record: R },
+    /// The server is throttling requests, so taking in new records is paused.
+    Throttled {
+        /// Time until the server allows requests again.
+        retry_after: Duration,
+    },
+    /// The circuit breaker around retrieving information changed state.
+    CircuitChanged(CircuitState),
+    /// A stage failed to process a record, so it skipped the remaining stages.
+    RecordFailed {
+        record: R,
+        /// Kind of stage that failed.
+        stage: StageKind,
+        /// Why the stage failed.
+        error: String,
+    },
+    /// A stage panicked while processing a record, so it skipped the
+    /// remaining stages.
+    RecordPanicked {
+        record: R,
+        /// Kind of stage that panicked.
+        stage: StageKind,
+        /// Message the stage panicked with.
+        message: String,
+        /// Whether the record's information had been looked up, and published
+        /// in `RecordRetrieved`, before the stage panicked.
+        looked_up: bool,
+    },
+    /// The run was interrupted, by Ctrl-C or because it was stopped.
+    ///
+    /// Records in flight continue until they finish or the drain timeout
+    /// passes.
+    Interrupted(InterruptReason),
+    /// Records still in flight when the drain timeout passed were aborted.
+    RecordsAbandoned {
+        /// Number of records aborted mid-flight.
+        record_count: usize,
+    },
+    /// Every record has passed through the pipeline, or stopped at a stage.
+    ProcessingFinished,
+    /// The run has finished, and its report is complete.
+    ///
+    /// This is the last event, so subscribers stop after it.
+    RunFinished(Arc<Report<R>>),
+}
+
+/// Publishes [`RunEvent`]s to every subscriber, e.g. the [`Reporter`], the
+/// metrics, and the event writer.
+///
+/// Each subscriber receives every event published after it subscribed, in
+/// the order they were published. Each subscriber's queue holds up to
+/// `capacity` events, so publishing waits while a subscriber lags behind,
+/// instead of queuing events without bound.
+///
+/// [`Reporter`]: crate::Reporter
+#[derive(Debug)]
+pub struct EventBus<R = PropertyRecord, I = PropertyInfoResult> {
+    subscribers: Subscribers<R, I>,
+    /// Number of events each subscriber's queue holds.
+    capacity: usize,
+    backpressure: Arc<Mutex<Backpressure>>,
+}
+
+/// Senders to each subscriber of an [`EventBus`].
+type Subscribers<R, I> = Arc<Mutex<Vec<Sender<RunEvent<R, I>>>>>;
+
+/// How often publishing an event waited for a subscriber to make room in its
+/// queue.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct Backpressure {
+    /// Number of events whose publishing waited.
+    pub wait_count: usize,
+    /// Total time spent waiting.
+    pub wait_duration: Duration,
+}
+
+/// Receives the events published on an [`EventBus`].
+#[derive(Debug)]
+pub struct EventReceiver<R = PropertyRecord, I = PropertyInfoResult> {
+    rx: Receiver<RunEvent<R, I>>,
+}
+
+impl<R, I> EventReceiver<R, I> {
+    /// Receives the next event, or `None` once the event bus is dropped.
+    pub async fn recv(&mut self) -> Option<RunEvent<R, I>> {
+        self.rx.recv().await
+    }
+
+    /// Stops receiving events, so publishing doesn't wait for this receiver.
+    pub fn close(&mut self) {
+        self.rx.close();
+    }
+}
+
+impl<R, I> EventBus<R, I>
+where
+    R: Clone,
+    I: Clone,
+{
+    /// Returns an event bus without any subscribers, whose subscribers each
+    /// queue up to `capacity` events.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            capacity: capacity.max(1),
+            backpressure: Arc::new(Mutex::new(Backpressure::default())),
+        }
+    }
+
+    /// Returns a receiver for the events published from now on.
+    pub fn subscribe(&self) -> EventReceiver<R, I> {
+        let (tx, rx) = mpsc::channel(self.capacity);
+        self.subscribers
+            .lock()
+            .expect("Event bus subscribers lock poisoned.")
+            .push(tx);
+        EventReceiver { rx }
+    }
+
+    /// Sends an event to every subscriber, waiting for room in each
+    /// subscriber's queue.
+    ///
+    /// Subscribers that have stopped receiving are removed.
+    pub async fn publish(&self, event: RunEvent<R, I>) {
+        // Cloned, so the lock isn't held while waiting.
+        let subscribers = self
+            .subscribers
+            .lock()
+            .expect("Event bus subscribers lock poisoned.")
+            .clone();
+        let mut closed = false;
+        for subscriber in subscribers {
+            let sent = match subscriber.try_send(event.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(event)) => {
+                    let wait_start = Instant::now();
+                    let sent = subscriber.send(event).await.is_ok();
+                    let mut backpressure = self
+                        .backpressure
+                        .lock()
+                        .expect("Event bus backpressure lock poisoned.");
+                    backpressure.wait_count += 1;
+                    backpressure.wait_duration += wait_start.elapsed();
+                    sent
+                }
+                Err(TrySendError::Closed(_)) => false,
+            };
+            closed |= !sent;
+        }
+
+        if closed {
+            self.subscribers
+                .lock()
+                .expect("Event bus subscribers lock poisoned.")
+                .retain(|subscriber| !subscriber.is_closed());
+        }
+    }
+
+    /// Returns the largest number of events waiting to be received by any
+    /// one subscriber.
+    pub fn queue_depth(&self) -> usize {
+        self.subscribers
+            .lock()
+            .expect("Event bus subscribers lock poisoned.")
+            .iter()
+            .map(|subscriber| subscriber.max_capacity() - subscriber.capacity())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns how often publishing waited for a subscriber.
+    pub fn backpressure(&self) -> Backpressure {
+        *self
+            .backpressure
+            .lock()
+            .expect("Event bus backpressure lock poisoned.")
+    }
+}
+
+// Manual impl, as the derive would require `R: Clone` and `I: Clone`.
+impl<R, I> Clone for EventBus<R, I> {
+    fn clone(&self) -> Self {
+        Self {
+            subscribers: Arc::clone(&self.subscribers),
+            capacity: self.capacity,
+            backpressure: Arc::clone(&self.backpressure),
+        }
+    }
+}
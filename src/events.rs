@@ -0,0 +1,232 @@
+use std::{
+    io::{self, Write as _},
+    time::{Duration, SystemTime},
+};
+
+use serde::Serialize;
+
+use crate::{
+    history::RunStatus, EventReceiver, LookupResult, ProgressBroadcast, Record, RecordProgress,
+    RecordStatus, Report, RunEvent, RunMetadata,
+};
+
+/// Lifecycle event emitted on stdout with `--events`, and to WebSocket clients
+/// with `--ws-port`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// Processing has started.
+    RunStarted {
+        /// Command line arguments the run was started with.
+        args: &'a [String],
+        /// Total number of records, or `null` if it isn't known, e.g. when
+        /// they are read from stdin.
+        record_count: Option<usize>,
+        /// Number of records already processed.
+        record_skipped_count: usize,
+    },
+    /// Information was retrieved for a record.
+    RecordSucceeded {
+        /// ID of the record.
+        record_id: usize,
+        /// Title number of the record.
+        title_number: String,
+        /// Whether some information was missing.
+        partial: bool,
+        /// Number of attempts made to retrieve the record's information.
+        attempts: u32,
+        /// Time taken to retrieve the record's information in milliseconds.
+        duration_ms: u64,
+    },
+    /// Information could not be retrieved for a record.
+    RecordFailed {
+        /// ID of the record.
+        record_id: usize,
+        /// Title number of the record.
+        title_number: String,
+        /// Why the record failed.
+        error: &'a str,
+        /// Number of attempts made to retrieve the record's information.
+        attempts: u32,
+        /// Time taken to retrieve the record's information in milliseconds.
+        duration_ms: u64,
+    },
+    /// A record was skipped because its ID appeared earlier in the input.
+    RecordDuplicate {
+        /// ID of the record.
+        record_id: usize,
+        /// Title number of the record.
+        title_number: String,
+    },
+    /// The run was interrupted before all records were processed.
+    Interrupted,
+    /// The run has finished.
+    RunFinished {
+        /// How the run finished.
+        status: RunStatus,
+        /// Number of records processed successfully.
+        processed_count: usize,
+        /// Number of records processed with some information missing.
+        partial_count: usize,
+        /// Number of records that failed to process.
+        failed_count: usize,
+        /// Number of records skipped because they were already processed.
+        skipped_count: usize,
+        /// Wall-clock duration of the run in milliseconds.
+        duration_ms: u64,
+    },
+}
+
+/// An event, with the run it belongs to and when it happened.
+#[derive(Debug, Serialize)]
+struct EventLine<'a> {
+    run_id: &'a str,
+    timestamp: String,
+    #[serde(flatten)]
+    event: Event<'a>,
+}
+
+/// Writes lifecycle events as JSON lines to stdout and / or WebSocket clients.
+#[derive(Clone, Debug)]
+pub struct EventWriter {
+    run_id: String,
+    /// Command line arguments the run was started with.
+    args: Vec<String>,
+    /// Whether to write events to stdout.
+    stdout: bool,
+    /// Broadcasts events to WebSocket clients.
+    progress_broadcast: Option<ProgressBroadcast>,
+}
+
+impl EventWriter {
+    /// Returns an event writer for the run.
+    pub fn new(
+        run_metadata: &RunMetadata,
+        stdout: bool,
+        progress_broadcast: Option<ProgressBroadcast>,
+    ) -> Self {
+        Self {
+            run_id: run_metadata.run_id.clone(),
+            args: run_metadata.args.clone(),
+            stdout,
+            progress_broadcast,
+        }
+    }
+
+    /// Writes an event.
+    pub fn write(&self, event: Event<'_>) {
+        let event_line = EventLine {
+            run_id: &self.run_id,
+            timestamp: humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            event,
+        };
+        let line = serde_json::to_string(&event_line).expect("Failed to serialize event.");
+
+        if self.stdout {
+            let mut stdout = io::stdout();
+            if let Err(e) = writeln!(stdout, "{}", line).and_then(|()| stdout.flush()) {
+                tracing::error!("Failed to write event to stdout: {}", e);
+            }
+        }
+        if let Some(progress_broadcast) = self.progress_broadcast.as_ref() {
+            progress_broadcast.send(line);
+        }
+    }
+
+    /// Writes events as they are published on the [`EventBus`], until the
+    /// run finishes.
+    ///
+    /// [`EventBus`]: crate::EventBus
+    pub async fn write_events(self, mut events: EventReceiver) {
+        while let Some(event) = events.recv().await {
+            match event {
+                RunEvent::RunStarted {
+                    record_count,
+                    record_skipped_count,
+                } => self.run_started(record_count, record_skipped_count),
+                RunEvent::RecordRetrieved(RecordProgress {
+                    record,
+                    info,
+                    attempts,
+                    duration,
+                    ..
+                }) => self.record_processed(record, &info, attempts, duration),
+                RunEvent::RecordDuplicate { record } => self.record_duplicate(record),
+                RunEvent::RunFinished(report) => {
+                    self.run_finished(&report);
+                    break;
+                }
+                RunEvent::RecordsDiscovered { .. }
+                | RunEvent::Throttled { .. }
+                | RunEvent::CircuitChanged(_)
+                | RunEvent::RecordWritten { .. }
+                | RunEvent::RecordFailed { .. }
+                | RunEvent::RecordPanicked { .. }
+                | RunEvent::Interrupted(_)
+                | RunEvent::RecordsAbandoned { .. }
+                | RunEvent::ProcessingFinished => {}
+            }
+        }
+    }
+
+    /// Writes the `run_started` event.
+    pub fn run_started(&self, record_count: Option<usize>, record_skipped_count: usize) {
+        self.write(Event::RunStarted {
+            args: &self.args,
+            record_count,
+            record_skipped_count,
+        });
+    }
+
+    /// Writes the `record_succeeded` or `record_failed` event for a processed record.
+    pub fn record_processed(
+        &self,
+        record: impl Record,
+        info: &impl LookupResult,
+        attempts: u32,
+        duration: Duration,
+    ) {
+        let duration_ms = duration.as_millis() as u64;
+        let error = info.error().unwrap_or_default();
+        let event = match info.status() {
+            RecordStatus::Success | RecordStatus::SuccessPartial => Event::RecordSucceeded {
+                record_id: record.id(),
+                title_number: record.label(),
+                partial: info.status() == RecordStatus::SuccessPartial,
+                attempts,
+                duration_ms,
+            },
+            RecordStatus::Error => Event::RecordFailed {
+                record_id: record.id(),
+                title_number: record.label(),
+                error: &error,
+                attempts,
+                duration_ms,
+            },
+        };
+        self.write(event);
+    }
+
+    /// Writes the `record_duplicate` event.
+    pub fn record_duplicate(&self, record: impl Record) {
+        self.write(Event::RecordDuplicate {
+            record_id: record.id(),
+            title_number: record.label(),
+        });
+    }
+
+    /// Writes the `interrupted` event if the run was interrupted, then the `run_finished` event.
+    pub fn run_finished(&self, report: &Report) {
+        if report.interrupted {
+            self.write(Event::Interrupted);
+        }
+        self.write(Event::RunFinished {
+            status: RunStatus::from_report(report),
+            processed_count: report.record_processed_successful_count,
+            partial_count: report.record_processed_info_missing_count,
+            failed_count: report.records_processed_failed.len(),
+            skipped_count: report.record_skipped_count,
+            duration_ms: report.duration.as_millis() as u64,
+        });
+    }
+}
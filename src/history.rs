@@ -0,0 +1,215 @@
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write as _},
+    path::PathBuf,
+    str::FromStr,
+    time::SystemTime,
+};
+
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+use crate::{Colours, Report};
+
+/// Lists past runs recorded in the history file.
+#[derive(Debug, Args)]
+pub struct HistoryOpt {
+    /// Only list runs with this status: completed, completed_with_errors, or interrupted.
+    #[arg(long)]
+    status: Option<RunStatus>,
+    /// Maximum number of most recent runs to list.
+    #[arg(short, long, default_value = "20")]
+    limit: usize,
+}
+
+/// How a run finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    /// All records were processed successfully.
+    Completed,
+    /// All records were processed, but some failed.
+    CompletedWithErrors,
+    /// The run was interrupted before all records were processed.
+    Interrupted,
+}
+
+impl RunStatus {
+    /// Returns the status of the run described by the report.
+    pub fn from_report<R>(report: &Report<R>) -> Self {
+        if report.interrupted {
+            Self::Interrupted
+        } else if !report.records_processed_failed.is_empty() || !report.records_panicked.is_empty()
+        {
+            Self::CompletedWithErrors
+        } else {
+            Self::Completed
+        }
+    }
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            Self::Completed => "completed",
+            Self::CompletedWithErrors => "completed_with_errors",
+            Self::Interrupted => "interrupted",
+        };
+        f.pad(status)
+    }
+}
+
+impl FromStr for RunStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "completed" => Ok(Self::Completed),
+            "completed_with_errors" => Ok(Self::CompletedWithErrors),
+            "interrupted" => Ok(Self::Interrupted),
+            _ => Err(format!(
+                "`{}` is not one of `completed`, `completed_with_errors`, `interrupted`.",
+                s
+            )),
+        }
+    }
+}
+
+/// One-line summary of a run, stored in the history file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    /// Unique ID of the run.
+    #[serde(default)]
+    pub run_id: String,
+    /// When the run finished, in RFC 3339 format.
+    pub timestamp: String,
+    /// Command line arguments the run was started with.
+    pub args: Vec<String>,
+    /// How the run finished.
+    pub status: RunStatus,
+    /// Number of records processed successfully.
+    pub processed_count: usize,
+    /// Number of records processed with some information missing.
+    pub partial_count: usize,
+    /// Number of records that failed to process.
+    pub failed_count: usize,
+    /// Number of records skipped because they were already processed.
+    pub skipped_count: usize,
+    /// Wall-clock duration of the run in milliseconds.
+    pub duration_ms: u64,
+}
+
+impl HistoryEntry {
+    /// Returns the history entry for a run.
+    pub fn new(report: &Report) -> Self {
+        Self {
+            run_id: report.run.run_id.clone(),
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            args: report.run.args.clone(),
+            status: RunStatus::from_report(report),
+            processed_count: report.record_processed_successful_count,
+            partial_count: report.record_processed_info_missing_count,
+            failed_count: report.records_processed_failed.len(),
+            skipped_count: report.record_skipped_count,
+            duration_ms: report.duration.as_millis() as u64,
+        }
+    }
+}
+
+/// Run history, stored as JSON lines in the user's data directory.
+pub struct History;
+
+impl History {
+    /// Returns the path to the history file, if the data directory is known.
+    ///
+    /// On Linux this is `~/.local/share/cli_async/history.jsonl`.
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|data_dir| data_dir.join("cli_async").join("history.jsonl"))
+    }
+
+    /// Appends an entry to the history file.
+    pub fn append(entry: &HistoryEntry) -> io::Result<()> {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(line.as_bytes())
+    }
+
+    /// Reads all entries from the history file, oldest first.
+    ///
+    /// Lines that cannot be parsed are skipped.
+    pub fn read() -> io::Result<Vec<HistoryEntry>> {
+        let path = match Self::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Vec::new()),
+        };
+
+        BufReader::new(File::open(path)?)
+            .lines()
+            .filter_map(|line| match line {
+                Ok(line) => serde_json::from_str(&line).ok().map(Ok),
+                Err(e) => Some(Err(e)),
+            })
+            .collect()
+    }
+
+    /// Writes the runs matching the options to stdout.
+    pub fn print(history_opt: &HistoryOpt) -> io::Result<()> {
+        let entries = Self::read()?;
+        let entries = entries
+            .iter()
+            .filter(|entry| {
+                history_opt
+                    .status
+                    .map(|status| entry.status == status)
+                    .unwrap_or(true)
+            })
+            .collect::<Vec<_>>();
+        let entries = &entries[entries.len().saturating_sub(history_opt.limit)..];
+
+        let mut stdout = io::stdout();
+        writeln!(
+            stdout,
+            "{timestamp:<20} | {status:<21} | {processed:>9} | {partial:>7} | {failed:>6} | {skipped:>7} | {duration:>10} | {args}",
+            timestamp = Colours::theme().report_label.apply("timestamp"),
+            status = Colours::theme().report_label.apply("status"),
+            processed = Colours::theme().report_label.apply("processed"),
+            partial = Colours::theme().report_label.apply("partial"),
+            failed = Colours::theme().report_label.apply("failed"),
+            skipped = Colours::theme().report_label.apply("skipped"),
+            duration = Colours::theme().report_label.apply("duration"),
+            args = Colours::theme().report_label.apply("args"),
+        )?;
+        writeln!(
+            stdout,
+            "-------------------- | --------------------- | --------- | ------- | ------ | ------- | ---------- | ----"
+        )?;
+        entries.iter().try_for_each(|entry| {
+            writeln!(
+                stdout,
+                "{timestamp:<20} | {status:<21} | {processed:>9} | {partial:>7} | {failed:>6} | {skipped:>7} | {duration:>10} | {args}",
+                timestamp = entry.timestamp,
+                status = entry.status,
+                processed = entry.processed_count,
+                partial = entry.partial_count,
+                failed = entry.failed_count,
+                skipped = entry.skipped_count,
+                duration = format!("{:.1} s", entry.duration_ms as f64 / 1000.0),
+                args = entry.args.join(" "),
+            )
+        })?;
+        stdout.flush()
+    }
+}
@@ -0,0 +1,211 @@
+use std::process::Stdio;
+
+use tokio::{process::Command, sync::mpsc};
+
+use crate::{
+    history::RunStatus, EventReceiver, LookupResult, Record, RecordProgress, RecordStatus, Report,
+    RunEvent, RunMetadata, StageKind,
+};
+
+/// Shell commands run when a record fails and when the run finishes, given
+/// the details in `CLI_ASYNC_*` environment variables.
+///
+/// Commands run one at a time, in the order of the events. They are queued
+/// while an earlier command runs, so a slow command delays the commands after
+/// it but not the run.
+#[derive(Clone, Debug)]
+pub struct Hooks {
+    run_id: String,
+    /// Command run for each record that fails, see `--on-error`.
+    on_error: Option<String>,
+    /// Command run when the run finishes, see `--on-complete`.
+    on_complete: Option<String>,
+}
+
+impl Hooks {
+    /// Returns the hooks for the run, or `None` if there are no commands to
+    /// run.
+    pub fn new(
+        run_metadata: &RunMetadata,
+        on_error: Option<String>,
+        on_complete: Option<String>,
+    ) -> Option<Self> {
+        if on_error.is_none() && on_complete.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            run_id: run_metadata.run_id.clone(),
+            on_error,
+            on_complete,
+        })
+    }
+
+    /// Runs the commands for events published on the [`EventBus`], until the
+    /// run finishes.
+    ///
+    /// [`EventBus`]: crate::EventBus
+    pub async fn run_hooks(self, mut events: EventReceiver) {
+        let (invocation_tx, mut invocation_rx) = mpsc::unbounded_channel::<Invocation>();
+        // Events keep being received while a command runs, so the event bus
+        // doesn't fill up and hold up the records behind it.
+        let receive = async move {
+            while let Some(event) = events.recv().await {
+                let (invocation, finished) = self.invocation(event);
+                if let Some(invocation) = invocation {
+                    // The receiver is only dropped once this sender is.
+                    let _ = invocation_tx.send(invocation);
+                }
+                if finished {
+                    break;
+                }
+            }
+        };
+        let run = async move {
+            while let Some(invocation) = invocation_rx.recv().await {
+                Self::run(&invocation.command, &invocation.env).await;
+            }
+        };
+
+        tokio::join!(receive, run);
+    }
+
+    /// Returns the command to run for an event, if any, and whether it is the
+    /// last event of the run.
+    fn invocation(&self, event: RunEvent) -> (Option<Invocation>, bool) {
+        match event {
+            RunEvent::RecordRetrieved(RecordProgress { record, info, .. })
+                if info.status() == RecordStatus::Error =>
+            {
+                let error = info.error().unwrap_or_default();
+                (
+                    self.record_failed(record, StageKind::Retrieve, &error),
+                    false,
+                )
+            }
+            RunEvent::RecordFailed {
+                record,
+                stage,
+                error,
+            } => (self.record_failed(record, stage, &error), false),
+            RunEvent::RecordPanicked {
+                record,
+                stage,
+                message,
+                ..
+            } => (self.record_failed(record, stage, &message), false),
+            RunEvent::RunFinished(report) => (self.run_finished(&report), true),
+            RunEvent::RunStarted { .. }
+            | RunEvent::RecordsDiscovered { .. }
+            | RunEvent::RecordDuplicate { .. }
+            | RunEvent::Throttled { .. }
+            | RunEvent::CircuitChanged(_)
+            | RunEvent::RecordRetrieved(_)
+            | RunEvent::RecordWritten { .. }
+            | RunEvent::Interrupted(_)
+            | RunEvent::RecordsAbandoned { .. }
+            | RunEvent::ProcessingFinished => (None, false),
+        }
+    }
+
+    /// Returns the `--on-error` command for a record that failed at a stage.
+    fn record_failed(
+        &self,
+        record: impl Record,
+        stage: StageKind,
+        error: &str,
+    ) -> Option<Invocation> {
+        self.on_error.clone().map(|command| Invocation {
+            command,
+            env: vec![
+                ("CLI_ASYNC_EVENT", String::from("record_failed")),
+                ("CLI_ASYNC_RUN_ID", self.run_id.clone()),
+                ("CLI_ASYNC_RECORD_ID", record.id().to_string()),
+                ("CLI_ASYNC_TITLE_NUMBER", record.label()),
+                ("CLI_ASYNC_STAGE", String::from(stage.name())),
+                ("CLI_ASYNC_ERROR", String::from(error)),
+            ],
+        })
+    }
+
+    /// Returns the `--on-complete` command with the counts from the report.
+    fn run_finished(&self, report: &Report) -> Option<Invocation> {
+        self.on_complete.clone().map(|command| Invocation {
+            command,
+            env: vec![
+                ("CLI_ASYNC_EVENT", String::from("run_finished")),
+                ("CLI_ASYNC_RUN_ID", self.run_id.clone()),
+                (
+                    "CLI_ASYNC_STATUS",
+                    RunStatus::from_report(report).to_string(),
+                ),
+                (
+                    "CLI_ASYNC_PROCESSED_COUNT",
+                    report.record_processed_successful_count.to_string(),
+                ),
+                (
+                    "CLI_ASYNC_PARTIAL_COUNT",
+                    report.record_processed_info_missing_count.to_string(),
+                ),
+                (
+                    "CLI_ASYNC_FAILED_COUNT",
+                    report.records_processed_failed.len().to_string(),
+                ),
+                (
+                    "CLI_ASYNC_SKIPPED_COUNT",
+                    report.record_skipped_count.to_string(),
+                ),
+                (
+                    "CLI_ASYNC_DURATION_MS",
+                    report.duration.as_millis().to_string(),
+                ),
+            ],
+        })
+    }
+
+    /// Runs a command in the shell, logging if it fails.
+    ///
+    /// The command's output is logged rather than inherited, so that it
+    /// doesn't mix with events written to stdout.
+    async fn run(command: &str, env: &[(&str, String)]) {
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        let output = shell
+            .arg(command)
+            .envs(env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::null())
+            .output()
+            .await;
+
+        match output {
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                tracing::debug!(command, %stdout, %stderr, "Hook finished.");
+                if !output.status.success() {
+                    tracing::warn!(
+                        command,
+                        stderr = stderr.trim(),
+                        "Hook exited with {}.",
+                        output.status
+                    );
+                }
+            }
+            Err(e) => tracing::error!(command, "Failed to run hook: {}", e),
+        }
+    }
+}
+
+/// A hook command to run, with the details of its event.
+#[derive(Debug)]
+struct Invocation {
+    command: String,
+    env: Vec<(&'static str, String)>,
+}
@@ -0,0 +1,101 @@
+use std::{fs, io, path::Path};
+
+use object_store::ClientOptions;
+use reqwest::{Certificate, ClientBuilder, Proxy};
+
+use crate::PoolOptions;
+
+/// Options for outgoing HTTP requests, i.e. to the `--notify-webhook` URL and
+/// `s3://` outputs, so they work behind a proxy or with a self-signed
+/// certificate, and reuse connections.
+#[derive(Clone, Debug, Default)]
+pub struct HttpOptions {
+    /// URL of the proxy to send requests through.
+    proxy: Option<String>,
+    /// PEM encoded certificate to trust in addition to the system's.
+    ca_cert: Option<String>,
+    /// Whether to accept certificates that fail verification.
+    insecure: bool,
+    /// How connections to each host are reused.
+    pool: PoolOptions,
+}
+
+impl HttpOptions {
+    /// Returns the options, reading the certificate from `ca_cert` if given.
+    pub fn new(
+        proxy: Option<String>,
+        ca_cert: Option<&Path>,
+        insecure: bool,
+        pool: PoolOptions,
+    ) -> io::Result<Self> {
+        let ca_cert = ca_cert
+            .map(|ca_cert| {
+                let ca_cert = fs::read_to_string(ca_cert)?;
+                // Files without certificates would otherwise be ignored, so the
+                // certificate would silently not be trusted.
+                if !ca_cert.contains("-----BEGIN CERTIFICATE-----") {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "no PEM encoded certificates found",
+                    ));
+                }
+                Ok(ca_cert)
+            })
+            .transpose()?;
+
+        Ok(Self {
+            proxy,
+            ca_cert,
+            insecure,
+            pool,
+        })
+    }
+
+    /// Applies the options to a `reqwest` client.
+    pub fn apply(&self, mut builder: ClientBuilder) -> Result<ClientBuilder, reqwest::Error> {
+        if let Some(proxy) = self.proxy.as_deref() {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+        if let Some(ca_cert) = self.ca_cert.as_deref() {
+            builder = builder.add_root_certificate(Certificate::from_pem(ca_cert.as_bytes())?);
+        }
+
+        builder = builder
+            .pool_idle_timeout(self.pool.keep_alive)
+            .pool_max_idle_per_host(self.pool.max_idle_per_host.unwrap_or(usize::MAX));
+        if self.pool.http2 {
+            builder = builder.http2_prior_knowledge();
+        }
+
+        Ok(builder.danger_accept_invalid_certs(self.insecure))
+    }
+
+    /// Returns whether the certificate is given but isn't trusted by object
+    /// stores, as they only trust it for requests sent through the proxy.
+    pub fn object_store_ignores_ca_cert(&self) -> bool {
+        self.ca_cert.is_some() && self.proxy.is_none()
+    }
+
+    /// Returns the client options for an object store.
+    ///
+    /// The object store only trusts the certificate for requests sent through
+    /// the proxy.
+    pub fn client_options(&self) -> ClientOptions {
+        let mut client_options = ClientOptions::new()
+            .with_allow_invalid_certificates(self.insecure)
+            .with_pool_idle_timeout(self.pool.keep_alive);
+        if let Some(max_idle_per_host) = self.pool.max_idle_per_host {
+            client_options = client_options.with_pool_max_idle_per_host(max_idle_per_host);
+        }
+        if self.pool.http2 {
+            client_options = client_options.with_http2_only();
+        }
+        if let Some(proxy) = self.proxy.as_deref() {
+            client_options = client_options.with_proxy_url(proxy);
+            if let Some(ca_cert) = self.ca_cert.as_deref() {
+                client_options = client_options.with_proxy_ca_certificate(ca_cert);
+            }
+        }
+        client_options
+    }
+}
@@ -0,0 +1,33 @@
+use std::{convert::Infallible, future::Future, net::SocketAddr, sync::Arc};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server,
+};
+
+/// Minimal HTTP server for local monitoring endpoints.
+pub struct HttpServer;
+
+impl HttpServer {
+    /// Serves requests on `127.0.0.1` at the given port until the process exits.
+    ///
+    /// The port is bound before this returns, so errors are reported immediately.
+    pub fn serve<F>(port: u16, respond: F) -> hyper::Result<impl Future<Output = hyper::Result<()>>>
+    where
+        F: Fn(&Request<Body>) -> Response<Body> + Send + Sync + 'static,
+    {
+        let respond = Arc::new(respond);
+        let make_service = make_service_fn(move |_connection| {
+            let respond = Arc::clone(&respond);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let response = respond(&request);
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        Ok(Server::try_bind(&addr)?.serve(make_service))
+    }
+}
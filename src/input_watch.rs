@@ -0,0 +1,132 @@
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use futures::{stream, Stream};
+use notify_debouncer_mini::{
+    new_debouncer,
+    notify::{self, RecommendedWatcher, RecursiveMode},
+    DebounceEventResult, Debouncer,
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::{PropertyRecord, RecordFilter};
+
+/// Watches a directory for new input files, for `--watch`.
+///
+/// Each file lists record IDs, one per line, in the same format as
+/// `--only-ids`. Files already in the directory when watching starts, and
+/// files whose names start with `.`, are ignored, so a file can be written
+/// under a hidden name and renamed once it is complete.
+pub struct InputWatch {
+    dir: PathBuf,
+    /// Stops watching when dropped.
+    _debouncer: Debouncer<RecommendedWatcher>,
+    events_rx: UnboundedReceiver<DebounceEventResult>,
+    /// Files that have been read, or were in the directory before watching.
+    seen: HashSet<PathBuf>,
+}
+
+impl InputWatch {
+    /// Time a file must go without changes before it is read, so that it
+    /// isn't read while it is still being written.
+    const SETTLE: Duration = Duration::from_millis(500);
+
+    /// Starts watching the directory.
+    pub fn new(dir: &Path) -> notify::Result<Self> {
+        // Changes are reported with the path that is watched, so this makes
+        // them match the paths of the files already in the directory.
+        let dir = dir.canonicalize()?;
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        let mut debouncer = new_debouncer(Self::SETTLE, move |result| {
+            // The receiver is only dropped when watching stops.
+            let _ = events_tx.send(result);
+        })?;
+        debouncer
+            .watcher()
+            .watch(&dir, RecursiveMode::NonRecursive)?;
+        let seen = fs::read_dir(&dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<HashSet<_>, _>>()?;
+
+        Ok(Self {
+            dir,
+            _debouncer: debouncer,
+            events_rx,
+            seen,
+        })
+    }
+
+    /// Returns the records in each new file, in the order the files appear.
+    ///
+    /// The stream only ends if the watcher stops.
+    pub fn batches(self) -> impl Stream<Item = Vec<PropertyRecord>> {
+        stream::unfold(self, |mut input_watch| async move {
+            loop {
+                let events = match input_watch.events_rx.recv().await? {
+                    Ok(events) => events,
+                    Err(e) => {
+                        tracing::error!(
+                            dir = %input_watch.dir.display(),
+                            "Failed to watch input directory: {}",
+                            e
+                        );
+                        continue;
+                    }
+                };
+                let mut paths = events
+                    .into_iter()
+                    .map(|event| event.path)
+                    .filter(|path| path.is_file() && !Self::is_hidden(path))
+                    .filter(|path| input_watch.seen.insert(path.clone()))
+                    .collect::<Vec<_>>();
+                paths.sort();
+
+                let batch = paths
+                    .iter()
+                    .filter_map(|path| match RecordFilter::read_id_list(path) {
+                        Ok(ids) => {
+                            tracing::info!(
+                                path = %path.display(),
+                                "Found {} records in new input file.",
+                                ids.len()
+                            );
+                            Some(ids)
+                        }
+                        Err(e) => {
+                            tracing::error!(
+                                path = %path.display(),
+                                "Failed to read input file: {}",
+                                e
+                            );
+                            None
+                        }
+                    })
+                    .flatten()
+                    .map(PropertyRecord)
+                    .collect::<Vec<_>>();
+                if !batch.is_empty() {
+                    return Some((batch, input_watch));
+                }
+            }
+        })
+    }
+
+    fn is_hidden(path: &Path) -> bool {
+        path.file_name()
+            .and_then(|file_name| file_name.to_str())
+            .is_some_and(|file_name| file_name.starts_with('.'))
+    }
+}
+
+impl fmt::Debug for InputWatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InputWatch")
+            .field("dir", &self.dir)
+            .field("seen", &self.seen)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,103 @@
+use std::{fmt, time::Instant};
+
+use async_trait::async_trait;
+use clap::Args;
+
+use crate::{Colours, Layer, LookupResult, Record, Reporter, Stage, StageKind, Work};
+
+/// Runs every stage for one record, printing what goes in and out of each.
+#[derive(Debug, Args)]
+pub struct InspectOpt {
+    /// ID of the record to process, which is its position in the input.
+    pub record_id: usize,
+}
+
+/// Prints each record's work before and after each stage, and how long the
+/// stage took, for `inspect`.
+#[derive(Clone, Copy, Debug)]
+pub struct InspectLayer;
+
+impl<S> Layer<S> for InspectLayer {
+    type Stage = Inspected<S>;
+
+    fn layer(&self, stage: S) -> Self::Stage {
+        Inspected { inner: stage }
+    }
+}
+
+/// Stage wrapped by an [`InspectLayer`].
+#[derive(Debug)]
+pub struct Inspected<S> {
+    inner: S,
+}
+
+#[async_trait]
+impl<R, I, O, S> Stage<R, I, O> for Inspected<S>
+where
+    R: Record,
+    I: LookupResult,
+    O: fmt::Debug + Send + 'static,
+    S: Stage<R, I, O>,
+{
+    fn kind(&self) -> StageKind {
+        self.inner.kind()
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        self.inner.depends_on()
+    }
+
+    fn concurrent(&self) -> bool {
+        self.inner.concurrent()
+    }
+
+    async fn reserve(&self, sequence: usize) {
+        self.inner.reserve(sequence).await
+    }
+
+    async fn skip(&self, sequence: usize) {
+        self.inner.skip(sequence).await
+    }
+
+    async fn process(&self, work: Work<R, I, O>) -> Result<Work<R, I, O>, String> {
+        eprintln!();
+        eprintln!(
+            "{}",
+            Colours::theme()
+                .report_title
+                .apply(format!("## {}", self.kind().name()))
+        );
+        eprintln!();
+        eprintln!("{}", Colours::theme().report_label.apply("* Request:"));
+        eprintln!("{:#?}", work);
+
+        let start = Instant::now();
+        let result = self.inner.process(work).await;
+        let duration = Reporter::<R, I>::format_duration(start.elapsed());
+
+        match result.as_ref() {
+            Ok(work) => {
+                eprintln!(
+                    "{} {}",
+                    Colours::theme().report_label.apply("* Response:"),
+                    Colours::theme()
+                        .report_item_success
+                        .apply(format!("succeeded in {}", duration))
+                );
+                eprintln!("{:#?}", work);
+            }
+            Err(e) => {
+                eprintln!(
+                    "{} {}",
+                    Colours::theme().report_label.apply("* Response:"),
+                    Colours::theme()
+                        .report_item_failure
+                        .apply(format!("failed in {}", duration))
+                );
+                eprintln!("{}", Colours::theme().report_error_message.apply(e));
+            }
+        }
+
+        result
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Write as _,
+    fs, io,
+    path::Path,
+};
+
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+
+use crate::Durability;
+
+/// Append-only log of records being written to the output file.
+///
+/// A record is `claimed` before its batch is written, and `committed` once
+/// the batch has reached the disk, as far as `--durability` allows. After a
+/// crash, records that were claimed but not committed may have torn writes in
+/// the output file.
+#[derive(Debug)]
+pub struct Journal {
+    file: Mutex<File>,
+    durability: Durability,
+}
+
+/// What a journal says about records from earlier runs.
+#[derive(Clone, Debug, Default)]
+pub struct JournalState {
+    /// Records whose writes reached the disk.
+    pub committed: HashSet<usize>,
+    /// Records that were claimed but never committed.
+    pub torn: BTreeSet<usize>,
+}
+
+impl Journal {
+    /// Opens the journal, appending to it when `resume` is true, and
+    /// truncating it otherwise.
+    pub async fn open(path: &Path, resume: bool, durability: Durability) -> io::Result<Self> {
+        let file = if resume {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?
+        } else {
+            File::create(path).await?
+        };
+
+        Ok(Self {
+            file: Mutex::new(file),
+            durability,
+        })
+    }
+
+    /// Reads which records were committed or torn by earlier runs.
+    ///
+    /// A missing journal is treated as empty. A torn last line is ignored, as
+    /// the entry it was writing never completed.
+    pub fn read(path: &Path) -> io::Result<JournalState> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        // Only lines ending in a newline were written completely.
+        let complete = contents
+            .rsplit_once('\n')
+            .map(|(complete, _torn)| complete)
+            .unwrap_or_default();
+        let mut claimed = BTreeSet::new();
+        let mut committed = HashSet::new();
+        complete
+            .lines()
+            .filter_map(|line| {
+                let (entry, record_id) = line.split_once(' ')?;
+                Some((entry, record_id.parse::<usize>().ok()?))
+            })
+            .for_each(|(entry, record_id)| match entry {
+                "claimed" => {
+                    claimed.insert(record_id);
+                }
+                "committed" => {
+                    committed.insert(record_id);
+                }
+                _ => {}
+            });
+        let torn = claimed
+            .into_iter()
+            .filter(|record_id| !committed.contains(record_id))
+            .collect();
+
+        Ok(JournalState { committed, torn })
+    }
+
+    /// Records that these records are about to be written.
+    pub async fn claim(&self, record_ids: &[usize]) -> io::Result<()> {
+        self.append("claimed", record_ids).await
+    }
+
+    /// Records that these records' writes reached the disk.
+    pub async fn commit(&self, record_ids: &[usize]) -> io::Result<()> {
+        self.append("committed", record_ids).await
+    }
+
+    async fn append(&self, entry: &str, record_ids: &[usize]) -> io::Result<()> {
+        if record_ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = String::with_capacity(record_ids.len() * (entry.len() + 8));
+        record_ids.iter().for_each(|record_id| {
+            // Writing to a `String` doesn't fail.
+            let _ = writeln!(&mut lines, "{} {}", entry, record_id);
+        });
+
+        let mut file = self.file.lock().await;
+        file.write_all(lines.as_bytes()).await?;
+        match self.durability {
+            Durability::None => Ok(()),
+            Durability::Flush => file.flush().await,
+            Durability::Fsync => {
+                file.flush().await?;
+                file.sync_data().await
+            }
+        }
+    }
+
+    /// Flushes the journal, and `fsync`s it with [`Durability::Fsync`].
+    pub async fn finish(&self) -> io::Result<()> {
+        let mut file = self.file.lock().await;
+        file.flush().await?;
+        if self.durability == Durability::Fsync {
+            file.sync_all().await?;
+        }
+
+        Ok(())
+    }
+}
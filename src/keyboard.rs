@@ -0,0 +1,128 @@
+use std::{io, sync::Arc};
+
+use crossterm::{
+    event::{Event, EventStream, KeyCode, KeyEvent, KeyModifiers},
+    terminal,
+    tty::IsTty,
+};
+use futures::StreamExt;
+
+use crate::{
+    ConcurrencyLimit, InterruptReason, ProgressMessage, ProgressResize, RunControl, Suspend,
+};
+
+/// Pauses, resumes, and stops the run from key presses:
+///
+/// * `p`: pause taking in new records.
+/// * `r`: resume.
+/// * `q` or `Ctrl-C`: shut down, the same way as Ctrl-C.
+/// * `+` / `-`: raise or lower the concurrency limit.
+/// * `Ctrl-Z`: suspend, until continued with `fg`.
+///
+/// Terminal resizes are read along with key presses, and re-render the
+/// progress bar.
+///
+/// Raw mode is enabled while listening, and disabled when this is dropped or
+/// [`KeyboardControl::restore_terminal`] is called.
+#[derive(Debug)]
+pub struct KeyboardControl {
+    run_control: Arc<RunControl>,
+    concurrency_limit: Arc<ConcurrencyLimit>,
+    progress_message: ProgressMessage,
+    suspend: Suspend,
+    progress_resize: ProgressResize,
+}
+
+impl KeyboardControl {
+    /// Enables raw mode, and returns the keyboard control.
+    ///
+    /// Returns `None` if stdin is not a terminal.
+    pub fn new(
+        run_control: Arc<RunControl>,
+        concurrency_limit: Arc<ConcurrencyLimit>,
+        progress_message: ProgressMessage,
+        suspend: Suspend,
+        progress_resize: ProgressResize,
+    ) -> io::Result<Option<Self>> {
+        if !io::stdin().is_tty() {
+            return Ok(None);
+        }
+        terminal::enable_raw_mode().map_err(io::Error::other)?;
+
+        Ok(Some(Self {
+            run_control,
+            concurrency_limit,
+            progress_message,
+            suspend,
+            progress_resize,
+        }))
+    }
+
+    /// Handles key presses until stdin is closed.
+    pub async fn run(self) {
+        let mut events = EventStream::new();
+        while let Some(event) = events.next().await {
+            let key_event = match event {
+                Ok(Event::Key(key_event)) => key_event,
+                Ok(Event::Resize(columns, _rows)) => {
+                    self.progress_resize.resized(columns);
+                    continue;
+                }
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::warn!("Failed to read key press: {}", e);
+                    break;
+                }
+            };
+
+            match key_event {
+                KeyEvent {
+                    code: KeyCode::Char('p'),
+                    ..
+                } => self.run_control.pause(&self.progress_message),
+                KeyEvent {
+                    code: KeyCode::Char('r'),
+                    ..
+                } => self.run_control.resume(&self.progress_message),
+                KeyEvent {
+                    code: KeyCode::Char('q'),
+                    ..
+                } => self.run_control.stop(InterruptReason::Stop),
+                KeyEvent {
+                    code: KeyCode::Char('+'),
+                    ..
+                } => self.concurrency_limit.increase(),
+                KeyEvent {
+                    code: KeyCode::Char('-'),
+                    ..
+                } => self.concurrency_limit.decrease(),
+                // Raw mode stops Ctrl-C from sending `SIGINT`, and Ctrl-Z from
+                // sending `SIGTSTP`, so they are handled here.
+                KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers,
+                } if modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.run_control.stop(InterruptReason::CtrlC)
+                }
+                KeyEvent {
+                    code: KeyCode::Char('z'),
+                    modifiers,
+                } if modifiers.contains(KeyModifiers::CONTROL) => self.suspend.suspend(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Disables raw mode, so that the report is printed normally.
+    pub fn restore_terminal() {
+        if let Err(e) = terminal::disable_raw_mode() {
+            tracing::warn!("Failed to disable raw mode: {}", e);
+        }
+    }
+}
+
+impl Drop for KeyboardControl {
+    fn drop(&mut self) {
+        Self::restore_terminal();
+    }
+}
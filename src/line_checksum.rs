@@ -0,0 +1,107 @@
+use std::fmt::Write as _;
+
+/// Start of the checksum field, which is the last field of each line in the
+/// output file.
+const FIELD_PREFIX: &str = ",\"crc32\":\"";
+/// Length of the checksum field, including its leading comma and the line's
+/// closing brace, e.g. `,"crc32":"1c291ca3"}`.
+const FIELD_LEN: usize = FIELD_PREFIX.len() + 8 + "\"}".len();
+
+/// CRC32 of each line in the output file, so lines that were torn or changed
+/// after they were written can be detected.
+///
+/// The checksum is written as a `crc32` field at the end of the line's JSON
+/// object, and covers the line as it would be without that field.
+#[derive(Clone, Copy, Debug)]
+pub struct LineChecksum;
+
+impl LineChecksum {
+    /// Appends the checksum field to a line serialized as a JSON object,
+    /// without its trailing newline.
+    pub fn append(line: &mut Vec<u8>) {
+        let crc32 = crc32fast::hash(line);
+        let closing_brace = line.pop();
+        debug_assert_eq!(
+            closing_brace,
+            Some(b'}'),
+            "Output lines are serialized as JSON objects."
+        );
+
+        let mut field = String::with_capacity(FIELD_LEN);
+        field.push_str(FIELD_PREFIX);
+        write!(field, "{:08x}\"}}", crc32).expect("Failed to format checksum.");
+        line.extend_from_slice(field.as_bytes());
+    }
+
+    /// Returns whether the line matches its checksum, or `None` if it has no
+    /// checksum, e.g. lines written before checksums were added.
+    pub fn matches(line: &str) -> Option<bool> {
+        let line = line.trim_end();
+        let field_start = line.len().checked_sub(FIELD_LEN)?;
+        let field = line.get(field_start..)?;
+        let crc32 = field
+            .strip_prefix(FIELD_PREFIX)?
+            .strip_suffix("\"}")
+            .and_then(|crc32| u32::from_str_radix(crc32, 16).ok())?;
+
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&line.as_bytes()[..field_start]);
+        hasher.update(b"}");
+        Some(hasher.finalize() == crc32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineChecksum;
+
+    fn line_checksummed(line: &str) -> String {
+        let mut line = line.as_bytes().to_vec();
+        LineChecksum::append(&mut line);
+        String::from_utf8(line).expect("Checksummed line is not UTF-8.")
+    }
+
+    #[test]
+    fn append_then_matches_round_trips() {
+        let line = line_checksummed(r#"{"record_id":1,"status":"success"}"#);
+
+        assert!(line.starts_with(r#"{"record_id":1,"status":"success","crc32":""#));
+        assert_eq!(Some(true), LineChecksum::matches(&line));
+        assert_eq!(Some(true), LineChecksum::matches(&format!("{}\n", line)));
+    }
+
+    #[test]
+    fn matches_returns_false_when_line_changed() {
+        let line =
+            line_checksummed(r#"{"record_id":1,"status":"success"}"#).replace("success", "failure");
+
+        assert_eq!(Some(false), LineChecksum::matches(&line));
+    }
+
+    #[test]
+    fn matches_returns_false_when_torn_line_runs_into_next_line() {
+        let line_torn = r#"{"record_id":1,"sta"#;
+        let line_next = line_checksummed(r#"{"record_id":2,"status":"success"}"#);
+
+        assert_eq!(
+            Some(false),
+            LineChecksum::matches(&format!("{}{}", line_torn, line_next))
+        );
+    }
+
+    #[test]
+    fn matches_returns_none_when_line_truncated_before_checksum() {
+        let line = line_checksummed(r#"{"record_id":1,"status":"success"}"#);
+        let line_truncated = &line[..line.len() - 4];
+
+        assert_eq!(None, LineChecksum::matches(line_truncated));
+    }
+
+    #[test]
+    fn matches_returns_none_without_checksum() {
+        assert_eq!(
+            None,
+            LineChecksum::matches(r#"{"record_id":1,"status":"success"}"#)
+        );
+    }
+}
@@ -0,0 +1,425 @@
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use indicatif::ProgressBar;
+use opentelemetry::{sdk::Resource, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use serde_json::{Map, Value};
+use tracing::{
+    field::{Field, Visit},
+    level_filters::LevelFilter,
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    field::RecordFields,
+    fmt::{
+        format::{DefaultFields, JsonFields, Writer},
+        FmtContext, FormatEvent, FormatFields, FormattedFields, MakeWriter,
+    },
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    Layer, Registry,
+};
+
+use crate::TuiLog;
+
+/// Logging setup.
+pub struct Logging;
+
+impl Logging {
+    /// Initializes the global `tracing` subscriber.
+    ///
+    /// The verbosity is the number of times `-v` was passed:
+    ///
+    /// * `0`: warnings and errors
+    /// * `1`: info
+    /// * `2`: debug
+    /// * `3` or more: trace
+    ///
+    /// When `quiet` is set, only errors are logged to the terminal.
+    ///
+    /// When `tui_log` is given, terminal logs are shown on the `--tui`
+    /// dashboard instead, without colour.
+    ///
+    /// When a log file is given, it receives at least info level logs,
+    /// regardless of the verbosity.
+    ///
+    /// When an OTLP endpoint is given, the `record` and `stage` spans are
+    /// exported to it, so each record's traversal of the pipeline can be viewed
+    /// in a tracing backend such as Jaeger or Tempo.
+    #[allow(clippy::too_many_arguments)]
+    pub fn init(
+        verbosity: u8,
+        log_format: LogFormat,
+        progress_bar: ProgressBar,
+        tui_log: Option<TuiLog>,
+        log_file: Option<LogFile>,
+        otlp_endpoint: Option<String>,
+        color: bool,
+        quiet: bool,
+    ) -> io::Result<()> {
+        let level_filter = match verbosity {
+            0 if quiet => LevelFilter::ERROR,
+            0 => LevelFilter::WARN,
+            1 => LevelFilter::INFO,
+            2 => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
+        };
+
+        let color = color && tui_log.is_none();
+        let progress_bar_writer = ProgressBarWriter {
+            progress_bar,
+            tui_log,
+        };
+        let mut layers = Vec::<Box<dyn Layer<Registry> + Send + Sync>>::new();
+        let terminal_layer = match log_format {
+            LogFormat::Text => tracing_subscriber::fmt::layer()
+                .with_ansi(color)
+                .with_writer(progress_bar_writer)
+                .boxed(),
+            LogFormat::Json => tracing_subscriber::fmt::layer()
+                .fmt_fields(JsonFields::new())
+                .event_format(JsonFormat)
+                .with_writer(progress_bar_writer)
+                .boxed(),
+        };
+        layers.push(terminal_layer.with_filter(level_filter).boxed());
+
+        if let Some(log_file) = log_file {
+            let rotating_file = RotatingFile::open(log_file)?;
+            let file_layer = match log_format {
+                LogFormat::Text => tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .fmt_fields(PlainFields::default())
+                    .with_writer(rotating_file)
+                    .boxed(),
+                LogFormat::Json => tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .fmt_fields(JsonFields::new())
+                    .event_format(JsonFormat)
+                    .with_writer(rotating_file)
+                    .boxed(),
+            };
+            layers.push(
+                file_layer
+                    .with_filter(level_filter.max(LevelFilter::INFO))
+                    .boxed(),
+            );
+        }
+
+        if let Some(otlp_endpoint) = otlp_endpoint {
+            let tracer = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(
+                    opentelemetry_otlp::new_exporter()
+                        .tonic()
+                        .with_endpoint(otlp_endpoint),
+                )
+                .with_trace_config(opentelemetry::sdk::trace::config().with_resource(
+                    Resource::new(vec![KeyValue::new("service.name", env!("CARGO_PKG_NAME"))]),
+                ))
+                .install_batch(opentelemetry::runtime::Tokio)
+                .map_err(io::Error::other)?;
+            layers.push(
+                tracing_opentelemetry::layer()
+                    .with_tracer(tracer)
+                    .with_filter(LevelFilter::DEBUG)
+                    .boxed(),
+            );
+        }
+
+        tracing_subscriber::registry().with(layers).init();
+
+        Ok(())
+    }
+
+    /// Exports any spans that have not been sent to the OTLP endpoint yet.
+    pub fn shutdown() {
+        opentelemetry::global::shutdown_tracer_provider();
+    }
+}
+
+/// Format of log lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable lines.
+    Text,
+    /// A JSON object per line.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("`{}` is not one of `text`, `json`.", s)),
+        }
+    }
+}
+
+/// Formats each event as a flat JSON object.
+///
+/// Fields of the enclosing spans, such as `record_id` and `stage`, are merged
+/// into the object alongside the event's own fields.
+#[derive(Debug)]
+struct JsonFormat;
+
+impl<S> FormatEvent<S, JsonFields> for JsonFormat
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, JsonFields>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut object = Map::new();
+        object.insert(
+            String::from("timestamp"),
+            Value::from(humantime::format_rfc3339_micros(SystemTime::now()).to_string()),
+        );
+        object.insert(
+            String::from("level"),
+            Value::from(metadata.level().as_str()),
+        );
+        object.insert(String::from("target"), Value::from(metadata.target()));
+
+        // Outermost spans first, so inner span fields take precedence.
+        if let Some(scope) = ctx.event_scope() {
+            scope.from_root().for_each(|span| {
+                let extensions = span.extensions();
+                let span_fields = extensions
+                    .get::<FormattedFields<JsonFields>>()
+                    .and_then(|fields| serde_json::from_str::<Map<String, Value>>(fields).ok());
+                if let Some(span_fields) = span_fields {
+                    object.extend(span_fields);
+                }
+            });
+        }
+
+        event.record(&mut JsonVisitor(&mut object));
+
+        writeln!(writer, "{}", Value::Object(object))
+    }
+}
+
+/// Records event fields into a JSON object.
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(
+            field.name().to_string(),
+            Value::from(format!("{:?}", value)),
+        );
+    }
+}
+
+/// Formats fields the same way as `DefaultFields`.
+///
+/// Span fields are formatted once per field formatter type and cached, so the
+/// log file needs its own type to avoid reusing the terminal's coloured fields.
+#[derive(Debug, Default)]
+struct PlainFields(DefaultFields);
+
+impl<'writer> FormatFields<'writer> for PlainFields {
+    fn format_fields<R: RecordFields>(&self, writer: Writer<'writer>, fields: R) -> fmt::Result {
+        self.0.format_fields(writer, fields)
+    }
+}
+
+/// Writes log lines above the progress bar, so they don't corrupt it.
+#[derive(Clone, Debug)]
+pub struct ProgressBarWriter {
+    progress_bar: ProgressBar,
+    /// Log shown on the `--tui` dashboard, which receives the lines instead.
+    tui_log: Option<TuiLog>,
+}
+
+impl<'a> MakeWriter<'a> for ProgressBarWriter {
+    type Writer = ProgressBarLineWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        ProgressBarLineWriter {
+            progress_bar: self.progress_bar.clone(),
+            tui_log: self.tui_log.clone(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+/// Buffers a log line, and prints it above the progress bar when dropped.
+#[derive(Debug)]
+pub struct ProgressBarLineWriter {
+    progress_bar: ProgressBar,
+    tui_log: Option<TuiLog>,
+    buffer: Vec<u8>,
+}
+
+impl Write for ProgressBarLineWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for ProgressBarLineWriter {
+    fn drop(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let line = String::from_utf8_lossy(&self.buffer);
+        let line = line.trim_end_matches('\n');
+
+        // `ProgressBar::println` does nothing when the progress bar is hidden.
+        if let Some(tui_log) = self.tui_log.as_ref() {
+            tui_log.push(String::from(line));
+        } else if self.progress_bar.is_hidden() {
+            eprintln!("{}", line);
+        } else {
+            self.progress_bar.println(line);
+        }
+    }
+}
+
+/// Parameters for logging to a file.
+#[derive(Clone, Debug)]
+pub struct LogFile {
+    /// Path to the log file.
+    pub path: PathBuf,
+    /// Size in bytes after which the log file is rotated.
+    pub max_size: u64,
+    /// Number of rotated log files to keep, e.g. `run.log.1`, `run.log.2`.
+    pub keep: usize,
+}
+
+/// Log file that is rotated when it exceeds a maximum size.
+#[derive(Clone, Debug)]
+pub struct RotatingFile {
+    inner: Arc<Mutex<RotatingFileInner>>,
+}
+
+#[derive(Debug)]
+struct RotatingFileInner {
+    log_file: LogFile,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Opens the log file for appending.
+    pub fn open(log_file: LogFile) -> io::Result<Self> {
+        let file = Self::open_file(&log_file.path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(RotatingFileInner {
+                log_file,
+                file,
+                size,
+            })),
+        })
+    }
+
+    fn open_file(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    /// Returns the path of the `n`th rotated log file.
+    fn rotated_path(path: &Path, n: usize) -> PathBuf {
+        let mut rotated_path = path.as_os_str().to_owned();
+        rotated_path.push(format!(".{}", n));
+        PathBuf::from(rotated_path)
+    }
+}
+
+impl RotatingFileInner {
+    /// Shifts each rotated log file up by one, and starts a new log file.
+    fn rotate(&mut self) -> io::Result<()> {
+        let path = &self.log_file.path;
+        if self.log_file.keep > 0 {
+            (1..self.log_file.keep).rev().try_for_each(|n| {
+                let from = RotatingFile::rotated_path(path, n);
+                if from.exists() {
+                    fs::rename(from, RotatingFile::rotated_path(path, n + 1))
+                } else {
+                    Ok(())
+                }
+            })?;
+            fs::rename(path, RotatingFile::rotated_path(path, 1))?;
+            self.file = RotatingFile::open_file(path)?;
+        } else {
+            self.file.set_len(0)?;
+        }
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for RotatingFile {
+    type Writer = RotatingFile;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut inner = self.inner.lock().expect("Log file lock poisoned.");
+        if inner.size > 0 && inner.size + buf.len() as u64 > inner.log_file.max_size {
+            inner.rotate()?;
+        }
+
+        let n = inner.file.write(buf)?;
+        inner.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner
+            .lock()
+            .expect("Log file lock poisoned.")
+            .file
+            .flush()
+    }
+}
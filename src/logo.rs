@@ -0,0 +1,170 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, Write as _},
+    path::Path,
+};
+
+use crate::Colours;
+
+/// Banner printed to stderr at startup.
+///
+/// Each line is split into a left and right part, which are styled with the
+/// theme's `logo_left` and `logo_right` styles.
+#[derive(Clone, Debug)]
+pub struct Logo {
+    lines: Vec<(String, String)>,
+}
+
+impl Default for Logo {
+    /// Returns a stylized:
+    ///
+    /// ```text
+    ///              _   _ _   _
+    ///  ___ ___| |_|_| |_| |___
+    /// | . |_ -|  _| |  _| | -_|
+    /// |  _|___|_| |_|_| |_|___|
+    /// |_|
+    /// ```
+    fn default() -> Self {
+        let logo_left = ["    ", " ___ ___", "| . |_ -", "|  _|___", "|_|     ", ""];
+        let logo_right = [
+            "     _   _ _   _",
+            "| |_|_| |_| |___",
+            "|  _| |  _| | -_|",
+            "|_| |_|_| |_|___|",
+            "",
+            "",
+        ];
+        let lines = logo_left
+            .iter()
+            .zip(logo_right.iter())
+            .map(|(left, right)| (String::from(*left), String::from(*right)))
+            .collect();
+
+        Self { lines }
+    }
+}
+
+impl Logo {
+    /// Height of each glyph in [`Self::FONT`].
+    const FONT_HEIGHT: usize = 5;
+    /// Glyphs used by [`Logo::render`].
+    ///
+    /// Letters are rendered in upper case, and characters without a glyph are
+    /// rendered as `?`.
+    const FONT: &'static [(char, [&'static str; Self::FONT_HEIGHT])] = &[
+        ('A', [" ### ", "#   #", "#####", "#   #", "#   #"]),
+        ('B', ["#### ", "#   #", "#### ", "#   #", "#### "]),
+        ('C', [" ####", "#    ", "#    ", "#    ", " ####"]),
+        ('D', ["#### ", "#   #", "#   #", "#   #", "#### "]),
+        ('E', ["#####", "#    ", "#### ", "#    ", "#####"]),
+        ('F', ["#####", "#    ", "#### ", "#    ", "#    "]),
+        ('G', [" ####", "#    ", "#  ##", "#   #", " ####"]),
+        ('H', ["#   #", "#   #", "#####", "#   #", "#   #"]),
+        ('I', ["#####", "  #  ", "  #  ", "  #  ", "#####"]),
+        ('J', ["#####", "   # ", "   # ", "#  # ", " ##  "]),
+        ('K', ["#   #", "#  # ", "###  ", "#  # ", "#   #"]),
+        ('L', ["#    ", "#    ", "#    ", "#    ", "#####"]),
+        ('M', ["#   #", "## ##", "# # #", "#   #", "#   #"]),
+        ('N', ["#   #", "##  #", "# # #", "#  ##", "#   #"]),
+        ('O', [" ### ", "#   #", "#   #", "#   #", " ### "]),
+        ('P', ["#### ", "#   #", "#### ", "#    ", "#    "]),
+        ('Q', [" ### ", "#   #", "# # #", "#  # ", " ## #"]),
+        ('R', ["#### ", "#   #", "#### ", "#  # ", "#   #"]),
+        ('S', [" ####", "#    ", " ### ", "    #", "#### "]),
+        ('T', ["#####", "  #  ", "  #  ", "  #  ", "  #  "]),
+        ('U', ["#   #", "#   #", "#   #", "#   #", " ### "]),
+        ('V', ["#   #", "#   #", "#   #", " # # ", "  #  "]),
+        ('W', ["#   #", "#   #", "# # #", "## ##", "#   #"]),
+        ('X', ["#   #", " # # ", "  #  ", " # # ", "#   #"]),
+        ('Y', ["#   #", " # # ", "  #  ", "  #  ", "  #  "]),
+        ('Z', ["#####", "   # ", "  #  ", " #   ", "#####"]),
+        ('0', [" ### ", "#  ##", "# # #", "##  #", " ### "]),
+        ('1', ["  #  ", " ##  ", "  #  ", "  #  ", " ### "]),
+        ('2', [" ### ", "#   #", "  ## ", " #   ", "#####"]),
+        ('3', ["#### ", "    #", " ### ", "    #", "#### "]),
+        ('4', ["#   #", "#   #", "#####", "    #", "    #"]),
+        ('5', ["#####", "#    ", "#### ", "    #", "#### "]),
+        ('6', [" ### ", "#    ", "#### ", "#   #", " ### "]),
+        ('7', ["#####", "    #", "   # ", "  #  ", "  #  "]),
+        ('8', [" ### ", "#   #", " ### ", "#   #", " ### "]),
+        ('9', [" ### ", "#   #", " ####", "    #", " ### "]),
+        ('-', ["     ", "     ", "#####", "     ", "     "]),
+        ('_', ["     ", "     ", "     ", "     ", "#####"]),
+        ('.', ["   ", "   ", "   ", "   ", " # "]),
+        (' ', ["   ", "   ", "   ", "   ", "   "]),
+        ('?', [" ### ", "#   #", "  ## ", "     ", "  #  "]),
+    ];
+
+    /// Reads a logo from an ASCII-art file.
+    ///
+    /// The whole logo is styled with the theme's `logo_left` style.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let lines = fs::read_to_string(path)?
+            .lines()
+            .map(|line| (String::from(line), String::new()))
+            .chain(std::iter::once((String::new(), String::new())))
+            .collect();
+
+        Ok(Self { lines })
+    }
+
+    /// Renders text with the embedded banner font, e.g. the program name.
+    ///
+    /// The first half of the characters are styled with the theme's
+    /// `logo_left` style, and the rest with `logo_right`.
+    pub fn render(text: &str) -> Self {
+        let glyphs = text
+            .chars()
+            .map(|c| {
+                let c = c.to_ascii_uppercase();
+                Self::FONT
+                    .iter()
+                    .find(|(glyph_char, _)| *glyph_char == c)
+                    .or_else(|| Self::FONT.iter().find(|(glyph_char, _)| *glyph_char == '?'))
+                    .map(|(_, glyph)| glyph)
+                    .expect("Font is missing the `?` glyph.")
+            })
+            .collect::<Vec<_>>();
+        let split = glyphs.len() - glyphs.len() / 2;
+
+        let join = |glyphs: &[&[&str; Self::FONT_HEIGHT]], row: usize| {
+            glyphs
+                .iter()
+                .map(|glyph| glyph[row])
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let lines = (0..Self::FONT_HEIGHT)
+            .map(|row| {
+                let left = join(&glyphs[..split], row);
+                let right = join(&glyphs[split..], row);
+                if right.is_empty() {
+                    (left, right)
+                } else {
+                    (format!("{} ", left), right)
+                }
+            })
+            .chain(std::iter::once((String::new(), String::new())))
+            .collect();
+
+        Self { lines }
+    }
+
+    /// Writes the logo to stderr.
+    pub fn print(&self) -> io::Result<()> {
+        let mut logo = String::with_capacity(384);
+        self.lines.iter().for_each(|(left, right)| {
+            let left = Colours::theme().logo_left.apply(left);
+            let right = Colours::theme().logo_right.apply(right);
+
+            // Writing to a `String` doesn't fail.
+            let _ = writeln!(&mut logo, "{}{}", left, right);
+        });
+
+        let mut stderr = io::stderr();
+        stderr.write_all(logo.as_bytes())?;
+        stderr.flush()
+    }
+}
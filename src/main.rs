@@ -1,54 +1,534 @@
-use futures::{stream, StreamExt, TryStreamExt};
-use structopt::{clap::AppSettings, StructOpt};
-use tokio::sync::mpsc;
+use std::{
+    cell::OnceCell,
+    collections::BTreeMap,
+    io,
+    path::PathBuf,
+    process::ExitCode,
+    slice,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use clap::{
+    builder::RangedU64ValueParser, error::ErrorKind, value_parser, ArgAction, ArgGroup,
+    CommandFactory, Parser, Subcommand,
+};
+use clap_complete::Shell;
+use futures::{future, stream, StreamExt};
+use indicatif::ProgressBar;
 
+mod audit_log;
+mod classification;
 mod colours;
+mod concurrency_limit;
+mod config;
+mod connection_pool;
+mod control;
+mod convert;
+mod credentials;
+mod discovery;
+mod drain;
+mod duplicates;
+mod error;
+mod error_browser;
+mod eta;
+mod event_bus;
+mod events;
+mod history;
+mod hooks;
+mod http_options;
+mod http_server;
+mod input_watch;
+mod inspect;
+mod journal;
+mod keyboard;
+mod line_checksum;
+mod logging;
+mod logo;
+mod metrics;
+mod middleware;
+mod notify;
+mod output;
+mod output_lock;
+mod output_merge;
+mod output_parquet;
+mod output_schema;
+mod pipeline;
+mod pipeline_graph;
+mod profiler;
+mod progress_broadcast;
+mod progress_message;
+mod progress_resize;
+mod property_stages;
+mod publisher;
+mod record_filter;
+mod reorder_buffer;
 mod report;
+mod report_diff;
 mod reporter;
+mod run_metadata;
+mod sink;
+mod stage_progress;
+mod stage_timings;
+mod stats;
+mod status;
+mod stdin_records;
+mod store;
+mod suspend;
+mod task_graph;
+mod terminal;
+mod theme;
+mod throttle;
+mod tui;
+mod validate;
+mod verify;
+mod webhook;
+mod worker_progress;
 
 mod types {
-    #[derive(Clone, Copy, Debug)]
-    pub struct Credentials;
+    use std::{
+        collections::BTreeMap, fmt, iter::FromIterator, ops::AddAssign, str::FromStr,
+        time::Duration,
+    };
 
-    #[derive(Clone, Copy, Debug)]
+    use rand::Rng;
+    use rand_distr::{Distribution, Normal, Pareto, Uniform};
+    use serde::{Deserialize, Serialize};
+
+    use crate::{Error, LookupResult, Record, RecordStatus};
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
     pub struct PropertyRecord(pub usize);
 
-    #[derive(Clone, Copy, Debug)]
+    impl PropertyRecord {
+        /// Returns the title number for this record.
+        pub fn title_number(self) -> String {
+            format!("ABC123/{:02}", self.0)
+        }
+    }
+
+    impl Record for PropertyRecord {
+        fn id(self) -> usize {
+            self.0
+        }
+
+        fn label(self) -> String {
+            self.title_number()
+        }
+    }
+
+    #[derive(Clone, Debug)]
     pub struct PropertyRecordPopulated {
         pub record: PropertyRecord,
         pub info: PropertyInfoResult,
+        /// Whether the record's lookup from each `--enrich` source succeeded,
+        /// by source name.
+        pub sources: BTreeMap<String, bool>,
     }
 
-    #[derive(Clone, Copy, Debug)]
+    #[derive(Clone, Debug)]
     pub enum PropertyInfoResult {
         Success,
-        SuccessPartial,
-        Error(PropertyRecord, &'static str),
+        /// Some fields were missing, which are marked in the completeness.
+        SuccessPartial(Completeness),
+        Error(Error),
+    }
+
+    impl PropertyInfoResult {
+        /// Returns this result with whether `field` was retrieved.
+        ///
+        /// A successful result becomes partial if the field is missing.
+        pub fn with_field(self, field: PropertyField, present: bool) -> Self {
+            match self {
+                Self::Success if present => Self::Success,
+                Self::Success => {
+                    let mut completeness = PropertyField::TITLE
+                        .iter()
+                        .map(|field| (*field, true))
+                        .collect::<Completeness>();
+                    completeness.set(field, false);
+                    Self::SuccessPartial(completeness)
+                }
+                Self::SuccessPartial(mut completeness) => {
+                    completeness.set(field, present);
+                    Self::SuccessPartial(completeness)
+                }
+                Self::Error(error) => Self::Error(error),
+            }
+        }
+    }
+
+    impl LookupResult for PropertyInfoResult {
+        fn status(&self) -> RecordStatus {
+            match self {
+                Self::Success => RecordStatus::Success,
+                Self::SuccessPartial(..) => RecordStatus::SuccessPartial,
+                Self::Error(..) => RecordStatus::Error,
+            }
+        }
+
+        fn error(&self) -> Option<String> {
+            match self {
+                Self::Success | Self::SuccessPartial(..) => None,
+                Self::Error(error) => Some(error.to_string()),
+            }
+        }
+
+        fn missing_fields(&self) -> Vec<String> {
+            match self {
+                Self::SuccessPartial(completeness) => completeness
+                    .missing()
+                    .map(|field| field.name().to_string())
+                    .collect(),
+                Self::Success | Self::Error(..) => Vec::new(),
+            }
+        }
+    }
+
+    /// Piece of information retrieved for a property.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum PropertyField {
+        Address,
+        Tenure,
+        Proprietor,
+        PricePaid,
+        /// Retrieved with `--enrich valuation`.
+        Valuation,
+        /// Retrieved with `--enrich ownership`.
+        Ownership,
+    }
+
+    impl PropertyField {
+        /// Fields retrieved with the title information.
+        pub const TITLE: [Self; 4] = [
+            Self::Address,
+            Self::Tenure,
+            Self::Proprietor,
+            Self::PricePaid,
+        ];
+
+        /// Returns the field's name, as used in the output and the report.
+        pub fn name(self) -> &'static str {
+            match self {
+                Self::Address => "address",
+                Self::Tenure => "tenure",
+                Self::Proprietor => "proprietor",
+                Self::PricePaid => "price_paid",
+                Self::Valuation => "valuation",
+                Self::Ownership => "ownership",
+            }
+        }
+    }
+
+    /// Whether each field was retrieved for a record.
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    pub struct Completeness(BTreeMap<PropertyField, bool>);
+
+    impl Completeness {
+        /// Records whether `field` was retrieved.
+        pub fn set(&mut self, field: PropertyField, present: bool) {
+            self.0.insert(field, present);
+        }
+
+        /// Returns the fields that were not retrieved.
+        pub fn missing(&self) -> impl Iterator<Item = PropertyField> + '_ {
+            self.0
+                .iter()
+                .filter(|(_, present)| !**present)
+                .map(|(field, _)| *field)
+        }
+    }
+
+    impl FromIterator<(PropertyField, bool)> for Completeness {
+        fn from_iter<T: IntoIterator<Item = (PropertyField, bool)>>(iter: T) -> Self {
+            Self(iter.into_iter().collect())
+        }
+    }
+
+    /// Parameters controlling which records the simulator fails.
+    #[derive(Clone, Copy, Debug)]
+    pub struct FailureInjection {
+        /// Probability that a record fails to retrieve information.
+        pub error_rate: f64,
+        /// Probability that a record is missing some information.
+        pub partial_rate: f64,
+        /// Seed for the random number generator.
+        pub seed: u64,
+    }
+
+    /// Lookup that enriches each record alongside its title information,
+    /// chosen with `--enrich`.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum EnrichmentSource {
+        /// The property's estimated value.
+        Valuation,
+        /// The property's registered owners.
+        Ownership,
+    }
+
+    impl EnrichmentSource {
+        /// Returns the source's name, as used in the output and the report.
+        pub fn name(self) -> &'static str {
+            match self {
+                Self::Valuation => "valuation",
+                Self::Ownership => "ownership",
+            }
+        }
+
+        /// Returns the field the source retrieves.
+        pub fn field(self) -> PropertyField {
+            match self {
+                Self::Valuation => PropertyField::Valuation,
+                Self::Ownership => PropertyField::Ownership,
+            }
+        }
+    }
+
+    impl fmt::Display for EnrichmentSource {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.name())
+        }
+    }
+
+    impl FromStr for EnrichmentSource {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "valuation" => Ok(Self::Valuation),
+                "ownership" => Ok(Self::Ownership),
+                _ => Err(format!("`{}` is not one of `valuation`, `ownership`.", s)),
+            }
+        }
+    }
+
+    /// Shape of the simulated information retrieval latency.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum LatencyDistribution {
+        /// Every request takes exactly the base delay.
+        Constant,
+        /// Delays are spread evenly within `base ± spread`.
+        Uniform,
+        /// Delays are centred on the base delay, with `spread` standard deviation.
+        Normal,
+        /// Delays are at least the base delay, with a long tail.
+        Pareto,
+    }
+
+    impl FromStr for LatencyDistribution {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "constant" => Ok(Self::Constant),
+                "uniform" => Ok(Self::Uniform),
+                "normal" => Ok(Self::Normal),
+                "pareto" => Ok(Self::Pareto),
+                _ => Err(format!(
+                    "`{}` is not one of `constant`, `uniform`, `normal`, `pareto`.",
+                    s
+                )),
+            }
+        }
+    }
+
+    /// Parameters for simulated information retrieval latency.
+    #[derive(Clone, Copy, Debug)]
+    pub struct Latency {
+        /// Shape of the latency distribution.
+        pub distribution: LatencyDistribution,
+        /// Base delay.
+        pub base: Duration,
+        /// Spread of the delay, used by `uniform` and `normal`.
+        pub spread: Duration,
+        /// Shape parameter for `pareto`; smaller values produce a longer tail.
+        pub pareto_shape: f64,
+    }
+
+    impl Latency {
+        /// Returns a delay sampled from this latency distribution.
+        pub fn sample<R: Rng>(&self, rng: &mut R) -> Duration {
+            let base = self.base.as_secs_f64() * 1000.0;
+            let spread = self.spread.as_secs_f64() * 1000.0;
+            let millis = match self.distribution {
+                LatencyDistribution::Constant => base,
+                LatencyDistribution::Uniform => {
+                    Uniform::new_inclusive((base - spread).max(0.0), base + spread).sample(rng)
+                }
+                LatencyDistribution::Normal => Normal::new(base, spread)
+                    .expect("Standard deviation must be finite.")
+                    .sample(rng),
+                LatencyDistribution::Pareto => Pareto::new(base.max(1.0), self.pareto_shape)
+                    .expect("Pareto shape must be positive.")
+                    .sample(rng),
+            };
+
+            Duration::from_secs_f64(millis.max(0.0) / 1000.0)
+        }
+    }
+
+    /// Parameters for injecting transient faults into information retrieval.
+    #[derive(Clone, Copy, Debug)]
+    pub struct Chaos {
+        /// Probability that a retrieval attempt hits a fault.
+        pub rate: f64,
+        /// Number of times a record is retried after a fault.
+        pub retries: u32,
+        /// Time to back off after the first fault, doubled per attempt.
+        pub backoff: Duration,
+        /// `Retry-After` sent with `429 Too Many Requests` responses, if any.
+        pub retry_after: Option<Duration>,
+    }
+
+    impl Chaos {
+        /// Returns the fault to inject for an attempt, if any.
+        pub fn inject<R: Rng>(&self, rng: &mut R) -> Option<ChaosFault> {
+            if rng.gen::<f64>() >= self.rate {
+                return None;
+            }
+
+            match rng.gen_range(0..3) {
+                0 => Some(ChaosFault::ConnectionReset),
+                1 => Some(ChaosFault::RateLimited),
+                _ => Some(ChaosFault::AuthExpired),
+            }
+        }
+
+        /// Returns how long to wait before retrying the given attempt.
+        pub fn backoff(&self, attempt: u32) -> Duration {
+            self.backoff.saturating_mul(1 << attempt.min(16))
+        }
+    }
+
+    /// Transient fault injected by chaos mode.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum ChaosFault {
+        /// The server closed the connection.
+        ConnectionReset,
+        /// The server responded with `429 Too Many Requests`.
+        RateLimited,
+        /// The authentication token expired, so we need to authenticate again.
+        AuthExpired,
+    }
+
+    impl ChaosFault {
+        /// Returns the error when retries for this fault are exhausted.
+        pub fn error(self) -> Error {
+            match self {
+                Self::ConnectionReset => Error::ConnectionReset,
+                Self::RateLimited => Error::RateLimited,
+                Self::AuthExpired => Error::AuthExpired,
+            }
+        }
+    }
+
+    /// Number of chaos faults encountered.
+    #[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+    pub struct ChaosEvents {
+        /// Number of connection resets.
+        pub connection_reset_count: usize,
+        /// Number of `429 Too Many Requests` responses.
+        pub rate_limited_count: usize,
+        /// Number of times we had to authenticate again.
+        pub reauthentication_count: usize,
+    }
+
+    impl ChaosEvents {
+        /// Records that a fault was encountered.
+        pub fn record(&mut self, fault: ChaosFault) {
+            match fault {
+                ChaosFault::ConnectionReset => self.connection_reset_count += 1,
+                ChaosFault::RateLimited => self.rate_limited_count += 1,
+                ChaosFault::AuthExpired => self.reauthentication_count += 1,
+            }
+        }
+
+        /// Returns whether any fault was encountered.
+        pub fn any(&self) -> bool {
+            self.connection_reset_count > 0
+                || self.rate_limited_count > 0
+                || self.reauthentication_count > 0
+        }
+    }
+
+    impl AddAssign for ChaosEvents {
+        fn add_assign(&mut self, other: Self) {
+            self.connection_reset_count += other.connection_reset_count;
+            self.rate_limited_count += other.rate_limited_count;
+            self.reauthentication_count += other.reauthentication_count;
+        }
+    }
+
+    /// Progress update sent to the `Reporter` when a record is processed.
+    #[derive(Clone, Debug)]
+    pub struct RecordProgress<R = PropertyRecord, I = PropertyInfoResult> {
+        /// The record that was processed.
+        pub record: R,
+        /// Result of retrieving the record's information.
+        pub info: I,
+        /// Chaos faults encountered while retrieving the record.
+        pub chaos_events: ChaosEvents,
+        /// Number of attempts made to retrieve the record's information.
+        pub attempts: u32,
+        /// Time taken to retrieve the record's information, including retries.
+        pub duration: Duration,
+        /// Whether the record's lookup from each `--enrich` source succeeded,
+        /// by source name.
+        pub sources: BTreeMap<String, bool>,
     }
 }
 
 /// Startup tasks
 #[rustfmt::skip]
 mod startup {
-    use std::future::Future;
+    use std::{future::Future, io, path::Path, sync::Arc};
     use async_ctrlc::CtrlC;
-    use tokio::sync::mpsc::{self, Receiver};
-    use crate::{Credentials, PropertyRecord, Reporter};
+    use futures::{stream, Stream};
+    use crate::{CredentialRotation, Credentials, Error, EventBus, InterruptReason, PropertyRecord, Reporter, RunControl, RunEvent};
 
-    pub fn t00_setup_interrupt_handler() -> (impl Future<Output = ()>, Receiver<()>) {
-        let (tx, rx) = mpsc::channel::<()>(2);
+    /// Returns a future that publishes `Interrupted` on Ctrl-C, `SIGTERM`, Ctrl-Break or the console closing on Windows, or when the run is stopped.
+    pub fn t00_setup_interrupt_handler(run_control: Arc<RunControl>, event_bus: EventBus) -> Result<impl Future<Output = ()>, Error> {
+        let ctrl_c = CtrlC::new().map_err(Error::io("set Ctrl-C handler"))?;
+        // Registered after Ctrl-C, so on Windows these handlers are asked first.
+        let os_signal = os_signal().map_err(Error::io("set signal handler"))?;
 
-        let ctrl_c = CtrlC::new().expect("Error setting Ctrl-C handler");
-
-        let ctrl_c_future = async move {
-            ctrl_c.await;
-            tx.send(()).await.expect("Failed to send interrupt message.");
-        };
-
-        (ctrl_c_future, rx)
+        Ok(async move {
+            tokio::select! {
+                // So the pipeline stops taking in new records.
+                _ = ctrl_c => run_control.stop(InterruptReason::CtrlC),
+                reason = os_signal => run_control.stop(reason),
+                _ = run_control.stopped() => {}
+            }
+            let reason = run_control.stop_reason().expect("Run stopped without a reason.");
+            event_bus.publish(RunEvent::Interrupted(reason)).await;
+        })
+    }
+    /// Returns a future that completes on `SIGTERM`, which is registered now so it doesn't terminate the process.
+    #[cfg(unix)]
+    fn os_signal() -> io::Result<impl Future<Output = InterruptReason>> {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sigterm = signal(SignalKind::terminate())?;
+        Ok(async move { sigterm.recv().await; InterruptReason::Sigterm })
     }
-    pub fn t01_read_credentials() -> Credentials { Credentials }
-    pub fn t02_stream_property_title_records(n: usize) -> Vec<PropertyRecord> { (0..n).map(PropertyRecord).collect() }
+    /// Returns a future that completes on Ctrl-Break, or when the console window is closed.
+    #[cfg(windows)]
+    fn os_signal() -> io::Result<impl Future<Output = InterruptReason>> {
+        use tokio::signal::windows::{ctrl_break, ctrl_close};
+        let mut ctrl_break = ctrl_break()?;
+        let mut ctrl_close = ctrl_close()?;
+        Ok(async move {
+            tokio::select! {
+                _ = ctrl_break.recv() => InterruptReason::CtrlBreak,
+                _ = ctrl_close.recv() => InterruptReason::ConsoleClose,
+            }
+        })
+    }
+    /// Returns a future that never completes, as there are no other signals to handle.
+    #[cfg(not(any(unix, windows)))]
+    fn os_signal() -> io::Result<impl Future<Output = InterruptReason>> { Ok(futures::future::pending()) }
+    pub fn t01_read_credentials(path: Option<&Path>, rotation: CredentialRotation) -> io::Result<Credentials> { Credentials::read(path, rotation) }
+    pub fn t02_stream_property_title_records(n: usize) -> impl Stream<Item = PropertyRecord> { stream::iter((0..n).map(PropertyRecord)) }
     pub fn t03_read_output_file(processed_count: usize) -> usize { processed_count }
     pub fn t04_start_progress_bar(reporter: &mut Reporter) { reporter.progress_bar_startup(); }
 }
@@ -56,129 +536,2266 @@ mod startup {
 /// Looped tasks
 #[rustfmt::skip]
 mod looped {
-    use std::{time::Duration};
+    use std::{collections::BTreeMap, io, time::Duration};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
     use tokio::time::sleep;
-    use crate::{Credentials, PropertyRecord, PropertyInfoResult, PropertyRecordPopulated, Reporter};
-
-    pub async fn t05_rate_limit_requests(delay: u64) { sleep(Duration::from_millis(delay)).await }
-    pub async fn t06_authenticate_with_server(first_time: bool, _: Credentials, delay: u64) { if first_time { sleep(Duration::from_millis(delay)).await } }
-    pub async fn t07_retrieve_information(n: usize, property_record: PropertyRecord, delay: u64) -> PropertyInfoResult {
-        async {
-            sleep(Duration::from_millis(delay)).await;
-            if n % 11 == 0 && n % 3 == 0 { PropertyInfoResult::Error(property_record, "Could not find record information online.") }
-            else if n % 3 == 0 { PropertyInfoResult::SuccessPartial }
+    use crate::{ChaosEvents, ChaosFault, Credential, EnrichmentSource, Error, FailureInjection, PropertyRecord, PropertyField, PropertyInfoResult, PropertyRecordPopulated, RecordProgress, RecordSink, Reporter, RetrieveStage};
+
+    pub async fn t05_rate_limit_requests(delay: Duration) { sleep(delay).await }
+    pub async fn t06_authenticate_with_server(first_time: bool, _: &Credential, delay: Duration) { if first_time { sleep(delay).await } }
+    /// Retrieves the information for the records at positions `ns` in one request.
+    pub async fn t07_retrieve_information(
+        ns: &[usize],
+        retrieve_stage: &RetrieveStage,
+    ) -> (Vec<PropertyInfoResult>, ChaosEvents, u32) {
+        let RetrieveStage { latency, failure_injection, chaos, ref credentials, delay_auth, ref throttle, ref connection_pool, .. } = *retrieve_stage;
+        let FailureInjection { error_rate, partial_rate, seed } = failure_injection;
+        // Seed per record so the outcome doesn't depend on processing order or batching.
+        let mut rngs = ns.iter().map(|n| StdRng::seed_from_u64(seed.wrapping_add(*n as u64))).collect::<Vec<_>>();
+        let infos = rngs.iter_mut().map(|rng| {
+            let roll = rng.gen::<f64>();
+            if roll < error_rate { PropertyInfoResult::Error(Error::NotFound) }
+            else if roll < error_rate + partial_rate {
+                // At least one of the title fields is missing.
+                let missing = rng.gen_range(0..PropertyField::TITLE.len());
+                PropertyInfoResult::SuccessPartial(PropertyField::TITLE.iter().enumerate().map(|(index, field)| (*field, index != missing && rng.gen_bool(0.75))).collect())
+            }
             else { PropertyInfoResult::Success }
-        }.await
+        }).collect::<Vec<_>>();
+        // The request's latency and faults are drawn for its first record.
+        let rng = &mut rngs[0];
+        let mut chaos_events = ChaosEvents::default();
+
+        let mut attempt = 0;
+        loop {
+            let credential = credentials.acquire();
+            t06_authenticate_with_server(credentials.authenticate(credential), credential, delay_auth).await;
+            let connection = connection_pool.checkout().await;
+            sleep(latency.sample(rng)).await;
+            let (chaos, fault) = match chaos.and_then(|chaos| chaos.inject(rng).map(|fault| (chaos, fault))) {
+                Some(chaos_fault) => chaos_fault,
+                None => {
+                    connection_pool.checkin(connection);
+                    break;
+                }
+            };
+            match fault {
+                ChaosFault::ConnectionReset => connection_pool.close(connection),
+                ChaosFault::RateLimited | ChaosFault::AuthExpired => connection_pool.checkin(connection),
+            }
+            chaos_events.record(fault);
+            tracing::debug!(?fault, attempt, "Chaos fault injected.");
+            if attempt == chaos.retries {
+                return (ns.iter().map(|_| PropertyInfoResult::Error(fault.error())).collect(), chaos_events, attempt + 1);
+            }
+
+            match fault {
+                ChaosFault::ConnectionReset => sleep(chaos.backoff(attempt)).await,
+                // Retry straight away with the next key, if rotating keys on errors.
+                ChaosFault::RateLimited if credentials.rotate(credential) => {}
+                // Other requests would be rate limited too, so every record waits.
+                ChaosFault::RateLimited => {
+                    throttle.throttle(chaos.retry_after.unwrap_or_else(|| chaos.backoff(attempt))).await;
+                    throttle.wait().await
+                }
+                // The next attempt authenticates again before it is made.
+                ChaosFault::AuthExpired => {
+                    credentials.expired(credential);
+                    credentials.rotate(credential);
+                }
+            }
+            attempt += 1;
+        }
+
+        (infos, chaos_events, attempt + 1)
+    }
+    /// Looks up a record's information from an enrichment source, returning whether it succeeded.
+    pub async fn t07_retrieve_enrichment(n: usize, source: EnrichmentSource, retrieve_stage: &RetrieveStage) -> bool {
+        let RetrieveStage { latency, failure_injection, .. } = *retrieve_stage;
+        // Seeded per record and source, so each source fails independently of the others.
+        let mut rng = StdRng::seed_from_u64(failure_injection.seed.wrapping_add(n as u64) ^ ((source as u64 + 1) << 32));
+        let roll = rng.gen::<f64>();
+        sleep(latency.sample(&mut rng)).await;
+        roll >= failure_injection.error_rate
+    }
+    pub fn t08_augment_record(record: PropertyRecord, info: PropertyInfoResult, sources: BTreeMap<String, bool>) -> PropertyRecordPopulated { PropertyRecordPopulated { record, info, sources } }
+    pub async fn t09_output_record(sink: &dyn RecordSink, sequence: usize, property_record_populated: PropertyRecordPopulated, record_progress: &RecordProgress) -> io::Result<()> {
+        sleep(Duration::from_millis(10)).await;
+        sink.write(sequence, property_record_populated, record_progress).await
     }
-    pub fn t08_augment_record(record: PropertyRecord, info: PropertyInfoResult) -> PropertyRecordPopulated { PropertyRecordPopulated { record, info } }
-    pub async fn t09_output_record_to_file(_: PropertyRecordPopulated) { sleep(Duration::from_millis(10)).await }
     pub async fn t10_update_progress_bar(reporter: &mut Reporter) { reporter.progress_bar_sync().await }
 }
 
 // Final task
 mod last {
-    use crate::Reporter;
+    use std::path::Path;
 
-    pub fn t11_output_execution_report(reporter: &Reporter) {
-        reporter
-            .print_report()
-            .expect("Failed to print execution report.")
+    use crate::{Error, ErrorBrowser, History, HistoryEntry, Reporter};
+
+    pub fn t11_output_execution_report(reporter: &Reporter) -> Result<(), Error> {
+        reporter.print_report()
+    }
+
+    pub fn t12_write_errors_file(reporter: &Reporter, path: &Path) {
+        if let Err(e) = reporter.report().write_errors_csv(path) {
+            tracing::error!(path = %path.display(), "Failed to write errors file: {}", e);
+        }
+    }
+
+    pub fn t13_append_run_history(reporter: &Reporter) {
+        let history_entry = HistoryEntry::new(reporter.report());
+        if let Err(e) = History::append(&history_entry) {
+            tracing::warn!("Failed to append run to history: {}", e);
+        }
+    }
+
+    pub fn t14_write_report_file(reporter: &Reporter, path: &Path) {
+        if let Err(e) = reporter.report().write_json(path) {
+            tracing::error!(path = %path.display(), "Failed to write report file: {}", e);
+        }
+    }
+
+    pub fn t15_print_result_line(reporter: &Reporter) {
+        println!("{}", reporter.report().result_line());
+    }
+
+    pub fn t16_browse_errors(reporter: &Reporter) {
+        if let Some(error_browser) = ErrorBrowser::new(reporter.report()) {
+            if let Err(e) = error_browser.offer() {
+                tracing::warn!("Failed to browse errors: {}", e);
+            }
+        }
     }
 }
 
 use crate::{
-    colours::Colours, last::*, looped::*, report::Report, reporter::Reporter, startup::*, types::*,
+    audit_log::{AuditEntry, AuditLog, AuditLookup},
+    classification::{Classifier, ErrorClass},
+    colours::Colours,
+    concurrency_limit::ConcurrencyLimit,
+    config::{Config, StyleConfig},
+    connection_pool::{ConnectionPool, ConnectionStats, PoolOptions},
+    control::{ControlClient, ControlServer, CtlOpt, InterruptReason, RunControl},
+    convert::{ConvertOpt, OutputConvert},
+    credentials::{Credential, CredentialRotation, Credentials},
+    discovery::Discovery,
+    drain::{Drain, InFlight},
+    duplicates::Duplicates,
+    error::Error,
+    error_browser::ErrorBrowser,
+    eta::Eta,
+    event_bus::{Backpressure, EventBus, EventReceiver, RunEvent},
+    events::EventWriter,
+    history::{History, HistoryEntry, HistoryOpt},
+    hooks::Hooks,
+    http_options::HttpOptions,
+    http_server::HttpServer,
+    input_watch::InputWatch,
+    inspect::{InspectLayer, InspectOpt},
+    journal::{Journal, JournalState},
+    keyboard::KeyboardControl,
+    last::*,
+    line_checksum::LineChecksum,
+    logging::{LogFile, LogFormat, Logging},
+    logo::Logo,
+    looped::*,
+    metrics::Metrics,
+    middleware::{
+        AuditLayer, BatchLayer, BatchPolicy, CircuitBreakerLayer, CircuitBreakerPolicy,
+        CircuitState, Layer, LoggingLayer, RateLimitLayer, RetryLayer, RetryPolicy, TimingLayer,
+    },
+    notify::{Notifier, NotifyKind},
+    output::{
+        Compression, Durability, OutputCorruption, OutputFormat, OutputRecord, OutputStats,
+        OutputWriter, RecordStatus,
+    },
+    output_lock::OutputLock,
+    output_merge::{MergeOpt, OutputMerge},
+    output_parquet::ParquetWriter,
+    pipeline::{BatchStage, BoxStage, Lookup, LookupResult, Pipeline, Record, Stage, Work},
+    pipeline_graph::{GraphFormat, GraphOpt, PipelineGraph, StageNode},
+    profiler::{Profiler, Resources},
+    progress_broadcast::ProgressBroadcast,
+    progress_message::ProgressMessage,
+    progress_resize::ProgressResize,
+    property_stages::{AugmentStage, AuthenticateStage, OutputStage, RetrieveStage},
+    publisher::Publisher,
+    record_filter::{RecordFilter, RecordRange},
+    reorder_buffer::ReorderBuffer,
+    report::{Report, ReportOptions},
+    report_diff::{DiffOpt, ReportDiff},
+    reporter::{ProgressMode, ProgressOptions, Reporter},
+    run_metadata::RunMetadata,
+    sink::{
+        DbSink, FileSink, NullSink, ParquetSink, PublishSink, RecordSink, SinkKind, StdoutSink,
+        TeeSink,
+    },
+    stage_progress::StageProgress,
+    stage_timings::{StageKind, StageTimings},
+    startup::*,
+    stats::{Stats, StatsOpt},
+    status::Status,
+    stdin_records::StdinRecords,
+    store::Store,
+    suspend::Suspend,
+    task_graph::{dependency_order, TaskGraph},
+    terminal::{Background, ColorDepth, ColorMode, TerminalCapabilities},
+    theme::{Theme, ThemeName},
+    throttle::Throttle,
+    tui::{Tui, TuiLog},
+    types::*,
+    validate::Validation,
+    verify::{Verification, VerifyOpt},
+    webhook::{Webhook, WebhookFormat},
+    worker_progress::{WorkerBar, WorkerProgress},
 };
 
-#[derive(Debug, StructOpt)]
-#[structopt(
-    global_setting = AppSettings::ColoredHelp,
-    about = "Simulates online information lookup for records.",
-)]
+#[derive(Debug, Parser)]
+#[command(about = "Simulates online information lookup for records.", version)]
+#[command(group(ArgGroup::new("resume_from").args(["journal", "store"]).multiple(true)))]
 struct Opt {
     /// Total number of records.
-    #[structopt(short, long, default_value = "50")]
+    #[arg(short, long, default_value = "50")]
     count: usize,
+    /// Processes the record IDs in each new file added to this directory, one per line, instead
+    /// of `--count` records, until interrupted.
+    ///
+    /// Files already in the directory, and files whose names start with `.`, are ignored.
+    #[arg(long, conflicts_with_all = ["count", "skip"])]
+    watch: Option<PathBuf>,
+    /// Processes the record IDs read from stdin, one per line, instead of `--count` records,
+    /// until stdin is closed.
+    ///
+    /// The number of records isn't known in advance, so progress shows the number processed
+    /// and the rate instead of a bar, and the report covers the records since the run started.
+    #[arg(long, conflicts_with_all = ["count", "skip", "watch"])]
+    stdin: bool,
+    /// Lists the `--count` records from the simulated paginated listing endpoint while they are
+    /// processed, instead of listing them all before processing starts.
+    #[arg(long, conflicts_with_all = ["skip", "watch", "stdin"])]
+    discover: bool,
+    /// Maximum number of records on each page of the listing, with `--discover`.
+    #[arg(long, default_value = "100", requires = "discover", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    discover_page_size: usize,
     /// Number of records already processed.
-    #[structopt(short, long, default_value = "0")]
+    ///
+    /// Must not be greater than `--count`.
+    #[arg(short, long, default_value = "0")]
     skip: usize,
-    /// Number of milliseconds to sleep per record.
-    #[structopt(long, default_value = "50")]
-    delay_rate_limit: u64,
-    /// Number of milliseconds authentication takes.
-    #[structopt(long, default_value = "20")]
-    delay_auth: u64,
-    /// Number of milliseconds information retrieval takes.
-    #[structopt(long, default_value = "50")]
-    delay_retrieve: u64,
+    /// Only processes records with IDs in this range, e.g. `100..200` or `100..=199`.
+    #[arg(long)]
+    only: Option<RecordRange>,
+    /// Only processes records with IDs listed in this file, one per line.
+    #[arg(long)]
+    only_ids: Option<PathBuf>,
+    /// Doesn't process records with IDs listed in this file, one per line.
+    #[arg(long)]
+    exclude_ids: Option<PathBuf>,
+    /// Maximum number of records to write concurrently.
+    ///
+    /// Press `+` or `-` while running to change it.
+    #[arg(short = 'j', long, default_value = "10", value_parser = RangedU64ValueParser::<usize>::new().range(1..=1000))]
+    concurrency: usize,
+    /// Path to the config file.
+    ///
+    /// Defaults to `cli_async/config.toml` in the config directory, if it exists.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Stops the run after this long, e.g. `5m`, the same way as Ctrl-C.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    deadline: Option<Duration>,
+    /// Time to let records in flight finish after Ctrl-C or `--deadline`, e.g. `10s`, before
+    /// abandoning them.
+    #[arg(long, default_value = "10s", value_parser = humantime::parse_duration)]
+    drain_timeout: Duration,
+    /// Stops the run once this many records have failed, the same way as Ctrl-C.
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    fail_fast: Option<usize>,
+    /// Stops sending requests after this many records in a row fail to retrieve, then sends one
+    /// record after `--circuit-breaker-cool-down` to check whether the server has recovered.
+    #[arg(long, value_parser = value_parser!(u32).range(1..))]
+    circuit_breaker_threshold: Option<u32>,
+    /// Time to stop sending requests for once the circuit breaker opens, e.g. `30s`.
+    #[arg(long, default_value = "30s", value_parser = humantime::parse_duration, requires = "circuit_breaker_threshold")]
+    circuit_breaker_cool_down: Duration,
+    /// Also looks up each record from these sources, e.g. `valuation,ownership`, concurrently with
+    /// its title information.
+    ///
+    /// A record whose lookup from a source fails is missing that information.
+    #[arg(long, value_delimiter = ',')]
+    enrich: Vec<EnrichmentSource>,
+    /// Retrieves the information for up to this many records in one request, for servers with a
+    /// bulk lookup API.
+    ///
+    /// If a request fails, every record in its batch fails. Must not be greater than `--concurrency`,
+    /// as each record waiting for its batch to fill is counted as being written.
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    batch_size: Option<usize>,
+    /// Time to wait for a batch to fill before it is retrieved anyway, e.g. `20ms`.
+    #[arg(long, default_value = "20ms", value_parser = humantime::parse_duration, requires = "batch_size")]
+    batch_linger: Duration,
+    /// Number of events queued for each of the progress bars, `--events`, hooks, and the other
+    /// subscribers, before records wait for a subscriber that is lagging behind to catch up.
+    #[arg(long, default_value = "1024", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    event_buffer: usize,
+
+    /// Time to sleep per record, e.g. `50ms`.
+    #[arg(long, default_value = "50ms", value_parser = parse_delay, help_heading = "Simulator")]
+    delay_rate_limit: Duration,
+    /// Time authentication takes, e.g. `20ms`.
+    #[arg(long, default_value = "20ms", value_parser = parse_delay, help_heading = "Simulator")]
+    delay_auth: Duration,
+    /// Time a handshake takes to open a connection to the server, e.g. `30ms`.
+    #[arg(long, default_value = "30ms", value_parser = parse_delay, help_heading = "Simulator")]
+    delay_handshake: Duration,
+    /// Time each page of the listing takes to fetch with `--discover`, e.g. `200ms`.
+    #[arg(long, default_value = "200ms", value_parser = parse_delay, help_heading = "Simulator")]
+    delay_discover: Duration,
+    /// Time information retrieval takes, e.g. `50ms`.
+    #[arg(long, default_value = "50ms", value_parser = parse_delay, help_heading = "Simulator")]
+    delay_retrieve: Duration,
+    /// Distribution of information retrieval delays: constant, uniform, normal, or pareto.
+    #[arg(long, default_value = "constant", help_heading = "Simulator")]
+    latency_distribution: LatencyDistribution,
+    /// How far retrieval delays spread around `--delay-retrieve`, e.g. `20ms`.
+    ///
+    /// Used as the half-width for `uniform`, and the standard deviation for `normal`.
+    #[arg(long, default_value = "20ms", value_parser = parse_delay, help_heading = "Simulator")]
+    latency_spread: Duration,
+    /// Shape of the `pareto` distribution; smaller values produce longer tails.
+    #[arg(long, default_value = "2.0", help_heading = "Simulator")]
+    latency_pareto_shape: f64,
+    /// Probability (0.0 to 1.0) that a record fails to retrieve information.
+    #[arg(long, default_value = "0.03", value_parser = parse_rate, help_heading = "Simulator")]
+    error_rate: f64,
+    /// Probability (0.0 to 1.0) that a record is missing some information.
+    #[arg(long, default_value = "0.3", value_parser = parse_rate, help_heading = "Simulator")]
+    partial_rate: f64,
+    /// Seed for the simulated failures, so runs are reproducible.
+    #[arg(long, default_value = "0", help_heading = "Simulator")]
+    seed: u64,
+    /// Randomly injects connection resets, rate limiting, and authentication expiry.
+    #[arg(long, help_heading = "Simulator")]
+    chaos: bool,
+    /// Probability (0.0 to 1.0) that a retrieval attempt hits a chaos fault.
+    #[arg(long, default_value = "0.1", value_parser = parse_rate, help_heading = "Simulator")]
+    chaos_rate: f64,
+    /// Number of times to retry a record after a transient fault.
+    #[arg(long, default_value = "3", value_parser = value_parser!(u32).range(..=100), help_heading = "Simulator")]
+    retries: u32,
+    /// Time to back off after a transient fault, doubled per attempt, e.g. `100ms`.
+    #[arg(long, default_value = "100ms", value_parser = parse_delay, help_heading = "Simulator")]
+    retry_backoff: Duration,
+    /// `Retry-After` sent with simulated `429 Too Many Requests` responses, e.g. `2s`.
+    ///
+    /// Taking in new records is paused for this long, or for `--retry-backoff` if not given.
+    #[arg(long, value_parser = parse_delay, help_heading = "Simulator")]
+    chaos_retry_after: Option<Duration>,
+
+    /// How progress is shown: hidden, overall, per-worker for a bar per record being
+    /// processed, stages for a bar per stage, or plain for a periodic status line.
+    ///
+    /// Defaults to overall when stderr is a terminal, and plain otherwise.
+    #[arg(long, help_heading = "Display")]
+    progress: Option<ProgressMode>,
+    /// Time between status lines with `--progress plain`, e.g. `10s`.
+    #[arg(long, default_value = "10s", value_parser = parse_interval, help_heading = "Display")]
+    progress_interval: Duration,
+    /// Template for the overall progress bar, in `indicatif`'s template syntax.
+    ///
+    /// `{eta}` is estimated from the records finished in the last 30 seconds, and `{finish_at}` is
+    /// the local time the run is estimated to finish.
+    ///
+    /// Overrides `template` in the `[progress]` section of the config file.
+    #[arg(long, help_heading = "Display")]
+    progress_template: Option<String>,
+    /// Characters for the filled, current, and empty parts of progress bars, e.g. `#>-`.
+    ///
+    /// Overrides `chars` in the `[progress]` section of the config file.
+    #[arg(long, help_heading = "Display")]
+    progress_chars: Option<String>,
+    /// Only uses ASCII characters in progress bars.
+    ///
+    /// Defaults to on when the locale is not UTF-8.
+    #[arg(long, help_heading = "Display")]
+    ascii: bool,
+    /// Number of colours to use: 16, 256, or truecolor.
+    ///
+    /// Defaults to what `COLORTERM` and `TERM` indicate the terminal supports.
+    #[arg(long, help_heading = "Display")]
+    color_depth: Option<ColorDepth>,
+    /// When to colour output: auto, always, or never.
+    ///
+    /// `auto` colours output when stderr is a terminal and `NO_COLOR` is not set.
+    #[arg(long, default_value = "auto", help_heading = "Display")]
+    color: ColorMode,
+    /// Colour theme: default, solarized, monochrome, high-contrast, or light.
+    ///
+    /// Overrides `name` in the `[theme]` section of the config file. Defaults to
+    /// `light` when the terminal has a light background, otherwise `default`.
+    #[arg(long, help_heading = "Display")]
+    theme: Option<ThemeName>,
+    /// Doesn't print the logo at startup.
+    #[arg(long, help_heading = "Display")]
+    no_logo: bool,
+    /// Prints the ASCII art in this file as the logo.
+    ///
+    /// Overrides `path` in the `[logo]` section of the config file.
+    #[arg(long, conflicts_with = "logo_text", help_heading = "Display")]
+    logo: Option<PathBuf>,
+    /// Renders this text in a banner font as the logo, e.g. the program name.
+    ///
+    /// Overrides `text` in the `[logo]` section of the config file.
+    #[arg(long, help_heading = "Display")]
+    logo_text: Option<String>,
+    /// Only prints a summary line and errors, without the logo, progress bar, or report.
+    #[arg(short, long, conflicts_with_all = ["verbose", "progress"], help_heading = "Display")]
+    quiet: bool,
+    /// Disables the `p` (pause), `r` (resume), and `q` (quit) keys while running.
+    #[arg(long, help_heading = "Display")]
+    no_keyboard: bool,
+    /// Shows a full-screen dashboard instead of the progress bars, with each stage's throughput, the
+    /// most recent errors, and the log. Ignored when stderr isn't a terminal.
+    #[arg(long, conflicts_with_all = ["quiet", "progress"], help_heading = "Display")]
+    tui: bool,
+
+    /// Number of records at the start of the run to leave out of the throughput and latency
+    /// statistics, e.g. those slowed down by authenticating.
+    #[arg(long, default_value = "0", help_heading = "Output")]
+    warmup_records: usize,
+    /// Time at the start of the run to leave out of the throughput and latency statistics, e.g.
+    /// `5s`.
+    ///
+    /// With `--warmup-records`, the warmup lasts until both have passed.
+    #[arg(long, default_value = "0s", value_parser = humantime::parse_duration, help_heading = "Output")]
+    warmup_duration: Duration,
+    /// Samples memory, CPU, records in flight, and event queue depths while running, and adds a
+    /// "Resources" section with their peaks and averages to the report.
+    #[arg(long, help_heading = "Output")]
+    profile: bool,
+    /// Number of slowest records to list in the report.
+    #[arg(long, default_value = "5", help_heading = "Output")]
+    slowest: usize,
+    /// Lists every failed record in the report, instead of grouping errors by message.
+    #[arg(long, help_heading = "Output")]
+    errors_full: bool,
+    /// Number of rows the grouped errors and panics tables in the report list, with the rest
+    /// counted below them. `--errors-full` still lists every failed record.
+    #[arg(long, default_value = "50", value_parser = RangedU64ValueParser::<usize>::new().range(1..), help_heading = "Output")]
+    max_error_rows: usize,
+    /// Doesn't offer to browse the failed records after the report, when stdin and stderr are
+    /// terminals.
+    #[arg(long, help_heading = "Output")]
+    no_error_browser: bool,
+    /// Writes every failed record to this CSV file.
+    #[arg(long, help_heading = "Output")]
+    errors_out: Option<PathBuf>,
+    /// Appends populated records to this JSON lines file, or writes them to
+    /// this Parquet file with `--output-format parquet`.
+    ///
+    /// `s3://bucket/prefix/` uploads each run to `prefix/<run_id>.jsonl` as
+    /// records are processed, using credentials from the `AWS_*` environment
+    /// variables. The upload completes once the run finishes, so it can't be
+    /// used with `--journal` or `--resume`.
+    #[arg(short, long, help_heading = "Output")]
+    output: Option<PathBuf>,
+    /// Writes the report to this JSON file, for use with `diff`.
+    #[arg(long, help_heading = "Output")]
+    report_out: Option<PathBuf>,
+    /// Format of the output file: `jsonl`, or `parquet` for loading into
+    /// analytics systems.
+    ///
+    /// Parquet files are replaced by each run rather than appended to, and
+    /// can't be compressed, sharded, journaled, or resumed.
+    #[arg(
+        long,
+        default_value = "jsonl",
+        requires = "output",
+        help_heading = "Output"
+    )]
+    output_format: OutputFormat,
+    /// Number of records in each row group of `--output-format parquet`
+    /// files, which are buffered in memory until the group is written.
+    #[arg(
+        long,
+        default_value = "8192",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..),
+        requires = "output",
+        help_heading = "Output"
+    )]
+    row_group_size: usize,
+    /// Compresses the output file with `gzip` or `zstd`.
+    ///
+    /// Defaults to the output file's extension, e.g. `records.jsonl.gz`,
+    /// which must match it so that `merge` can read it.
+    #[arg(long, requires = "output", help_heading = "Output")]
+    compress: Option<Compression>,
+    /// Spreads records across this many output files, e.g. `out.000.jsonl`,
+    /// so that concurrent writes don't wait on each other.
+    ///
+    /// Use the `merge` subcommand to combine them afterwards.
+    #[arg(
+        long,
+        default_value = "1",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..=1000),
+        requires = "output",
+        help_heading = "Output"
+    )]
+    output_shards: usize,
+    /// Writes output records to disk in batches of this many records.
+    ///
+    /// Defaults to 1, unless `--flush-interval` is given.
+    #[arg(
+        long,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..),
+        requires = "output",
+        help_heading = "Output"
+    )]
+    flush_every: Option<usize>,
+    /// Writes batched output records to disk at this interval, e.g. `2s`.
+    ///
+    /// Plain numbers are seconds. Batches are also written when the run
+    /// finishes or is interrupted.
+    #[arg(long, value_parser = parse_interval, requires = "output", help_heading = "Output")]
+    flush_interval: Option<Duration>,
+    /// How far each batch of output records is pushed towards the disk:
+    /// `none` (only at the end of the run), `flush`, or `fsync`.
+    ///
+    /// `fsync` survives system crashes, at the cost of throughput.
+    #[arg(
+        long,
+        default_value = "flush",
+        requires = "output",
+        help_heading = "Output"
+    )]
+    durability: Durability,
+    /// Starts even if another run holds the lock on `--output`.
+    #[arg(long, requires = "output", help_heading = "Output")]
+    force: bool,
+    /// Logs which output records were claimed and committed to this file,
+    /// so that `--resume` can detect torn writes after a crash.
+    #[arg(long, requires = "output", help_heading = "Output")]
+    journal: Option<PathBuf>,
+    /// Writes every attempt at processing each record in each stage to this
+    /// JSON lines file, with when it started, how long it took, and why it
+    /// failed, for debugging specific records.
+    ///
+    /// Appended to when resuming, and truncated otherwise.
+    #[arg(long, help_heading = "Output")]
+    audit_log: Option<PathBuf>,
+    /// Keeps each record's status and result, and each run's metadata, in
+    /// this database, e.g. `sqlite:run.db` or `postgres://user@host/db`.
+    ///
+    /// Each record is upserted into the `records` table as it is processed.
+    #[arg(long, help_heading = "Output")]
+    store: Option<String>,
+    /// Publishes each record as it is processed to a Kafka topic or NATS
+    /// subject, e.g. `kafka://localhost:9092/records` or
+    /// `nats://localhost:4222/records`.
+    ///
+    /// Records are published as JSON, in the same form as `--output` lines.
+    #[arg(long, help_heading = "Output")]
+    publish: Option<String>,
+    /// Where processed records go: `file` (`--output`), `stdout`, `db`
+    /// (`--store`), `publish` (`--publish`), or `null`.
+    ///
+    /// Records are written to every sink given, e.g. `--sink file,stdout`.
+    /// Defaults to the sinks whose options are given. `--store` is still
+    /// used to `--resume` without the `db` sink.
+    #[arg(long, value_delimiter = ',', help_heading = "Output")]
+    sink: Vec<SinkKind>,
+    /// Number of times to retry writing a record to the sinks after it fails.
+    #[arg(long, default_value = "2", value_parser = value_parser!(u32).range(..=100), help_heading = "Output")]
+    write_retries: u32,
+    /// Time to back off after a failed write, doubled per attempt, e.g. `100ms`.
+    #[arg(long, default_value = "100ms", value_parser = parse_delay, help_heading = "Output")]
+    write_retry_backoff: Duration,
+    /// Skips records committed in `--journal` or completed in `--store` by
+    /// earlier runs.
+    ///
+    /// Records that were claimed but not committed in the journal, or that
+    /// failed, are processed again, as are records on lines of `--output`
+    /// that don't match their checksum, unless it is compressed.
+    #[arg(long, requires = "resume_from", help_heading = "Output")]
+    resume: bool,
+    /// Writes records to `--output` in input order, instead of completion order.
+    #[arg(long, requires = "output", help_heading = "Output")]
+    ordered: bool,
+    /// Number of records that may complete ahead of an earlier record with `--ordered`.
+    ///
+    /// Processing waits while this many records are buffered.
+    #[arg(
+        long,
+        default_value = "256",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..=65536),
+        help_heading = "Output"
+    )]
+    reorder_buffer: usize,
+    /// Writes the IDs of records that appear more than once in the input to this file.
+    #[arg(long, help_heading = "Output")]
+    dedupe_report: Option<PathBuf>,
+    /// Writes lifecycle events to stdout as JSON lines.
+    #[arg(long, help_heading = "Output")]
+    events: bool,
+
+    /// Logs more detail; repeat for more verbosity (`-v`, `-vv`, `-vvv`).
+    #[arg(short, long, action = ArgAction::Count, help_heading = "Logging")]
+    verbose: u8,
+    /// Format of log lines: text or json.
+    #[arg(long, default_value = "text", help_heading = "Logging")]
+    log_format: LogFormat,
+    /// Also writes uncoloured logs to this file, at least at info level.
+    #[arg(long, help_heading = "Logging")]
+    log_file: Option<PathBuf>,
+    /// Size in bytes after which the log file is rotated.
+    #[arg(long, default_value = "10485760", value_parser = value_parser!(u64).range(1..), help_heading = "Logging")]
+    log_file_max_size: u64,
+    /// Number of rotated log files to keep.
+    #[arg(long, default_value = "3", help_heading = "Logging")]
+    log_file_keep: usize,
+    /// Exports tracing spans to an OpenTelemetry collector, e.g. `http://localhost:4317`.
+    #[arg(long, help_heading = "Logging")]
+    otlp_endpoint: Option<String>,
+
+    /// Serves Prometheus metrics at `http://127.0.0.1:<port>/metrics`.
+    #[arg(long, help_heading = "Monitoring")]
+    metrics_port: Option<u16>,
+    /// Serves the run's progress as JSON at `http://127.0.0.1:<port>/status`.
+    #[arg(long, help_heading = "Monitoring")]
+    status_port: Option<u16>,
+    /// Broadcasts lifecycle events as JSON to WebSocket clients at `ws://127.0.0.1:<port>`.
+    #[arg(long, help_heading = "Monitoring")]
+    ws_port: Option<u16>,
+    /// Accepts `pause`, `resume`, `status`, and `stop` commands from `cli_async ctl`.
+    #[arg(long, help_heading = "Monitoring")]
+    control: bool,
+    /// Path to the control socket, or the pipe name on Windows. Implies `--control`.
+    ///
+    /// Defaults to `cli_async.sock` in the runtime directory.
+    #[arg(long, help_heading = "Monitoring")]
+    control_socket: Option<PathBuf>,
+
+    /// Runs this shell command for each record that fails.
+    ///
+    /// The record is given in the `CLI_ASYNC_RECORD_ID`, `CLI_ASYNC_TITLE_NUMBER`,
+    /// `CLI_ASYNC_STAGE`, and `CLI_ASYNC_ERROR` environment variables.
+    #[arg(long, help_heading = "Hooks")]
+    on_error: Option<String>,
+    /// Runs this shell command when the run finishes, including when it is interrupted.
+    ///
+    /// The result is given in the `CLI_ASYNC_STATUS`, `CLI_ASYNC_PROCESSED_COUNT`,
+    /// `CLI_ASYNC_PARTIAL_COUNT`, `CLI_ASYNC_FAILED_COUNT`, `CLI_ASYNC_SKIPPED_COUNT`, and
+    /// `CLI_ASYNC_DURATION_MS` environment variables.
+    #[arg(long, help_heading = "Hooks")]
+    on_complete: Option<String>,
+    /// Posts the report as JSON to this URL when the run finishes, including when it is
+    /// interrupted.
+    #[arg(long, help_heading = "Hooks")]
+    notify_webhook: Option<String>,
+    /// Payload posted to `--notify-webhook`: `json` for the report, or a `slack` or `teams`
+    /// message.
+    #[arg(
+        long,
+        default_value = "json",
+        requires = "notify_webhook",
+        help_heading = "Hooks"
+    )]
+    notify_webhook_format: WebhookFormat,
+    /// Alerts you when the run finishes or is interrupted: `desktop` for a desktop notification,
+    /// `bell` for the terminal bell, or `both`.
+    #[arg(long, help_heading = "Hooks")]
+    notify: Option<NotifyKind>,
+
+    /// Sends HTTP requests, to the `--notify-webhook` URL and `s3://` outputs, through this
+    /// proxy, e.g. `http://proxy.example.com:3128`.
+    #[arg(long, help_heading = "Network")]
+    proxy: Option<String>,
+    /// Trusts this PEM encoded CA certificate for HTTP requests, in addition to the system's
+    /// certificates, e.g. for a staging server with a self-signed certificate.
+    ///
+    /// For `s3://` outputs, it is only trusted together with `--proxy`.
+    #[arg(long, help_heading = "Network")]
+    ca_cert: Option<PathBuf>,
+    /// Accepts invalid certificates for HTTP requests. Only use this for testing.
+    #[arg(long, help_heading = "Network")]
+    insecure: bool,
+    /// Maximum number of idle connections kept open to each host, for later requests to reuse.
+    ///
+    /// Defaults to no limit.
+    #[arg(long, help_heading = "Network")]
+    pool_max_idle: Option<usize>,
+    /// Time an idle connection is kept open for later requests to reuse, e.g. `90s`.
+    ///
+    /// `0s` opens a new connection for every request.
+    #[arg(long, default_value = "90s", value_parser = humantime::parse_duration, help_heading = "Network")]
+    keep_alive: Duration,
+    /// Sends requests over HTTP/2, so concurrent requests to a host share one connection.
+    #[arg(long, help_heading = "Network")]
+    http2: bool,
+    /// Reads API keys to spread requests across from this file, one per line as `key` or
+    /// `name=key`.
+    ///
+    /// The report shows the number of requests made with each key.
+    #[arg(long, help_heading = "Network")]
+    credentials_file: Option<PathBuf>,
+    /// How requests are spread across the keys in `--credentials-file`: `round-robin` to use
+    /// each key in turn, or `on-error` to switch keys when the server rejects or rate limits
+    /// one.
+    #[arg(
+        long,
+        default_value = "round-robin",
+        requires = "credentials_file",
+        help_heading = "Network"
+    )]
+    credential_rotation: CredentialRotation,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Lists past runs.
+    History(HistoryOpt),
+    /// Compares two reports saved with `--report-out`.
+    Diff(DiffOpt),
+    /// Merges output shards written with `--output-shards` into one file,
+    /// sorted by record ID.
+    Merge(MergeOpt),
+    /// Prints statistics about an output file written with `--output`, e.g.
+    /// how many records succeeded and failed, and how many were processed
+    /// each day, without processing any records.
+    Stats(StatsOpt),
+    /// Converts an output file between JSON lines, CSV, and Parquet, chosen by
+    /// the files' extensions, e.g. `cli_async convert out.jsonl out.csv`.
+    Convert(ConvertOpt),
+    /// Checks that every input record is in an output file exactly once,
+    /// listing missing, duplicate, and corrupt records, and exits with an
+    /// error if any are found.
+    ///
+    /// The input is a list of record IDs given with `--input`, or the records
+    /// given with `--count`, e.g.
+    /// `cli_async --count 1000 verify --output records.jsonl`.
+    Verify(VerifyOpt),
+    /// Sends a command to a running instance started with `--control`.
+    Ctl(CtlOpt),
+    /// Checks that the config file is well-formed, output paths are writable,
+    /// and credentials resolve, without processing any records.
+    ///
+    /// Options for the run are given before `validate`, e.g.
+    /// `cli_async --output records.jsonl validate`.
+    Validate,
+    /// Prints the pipeline's stages and their dependencies, e.g. for Graphviz
+    /// with `cli_async graph --format dot | dot -Tsvg > pipeline.svg`.
+    ///
+    /// Options for the run are given before `graph`, e.g.
+    /// `cli_async --output records.jsonl graph`.
+    Graph(GraphOpt),
+    /// Processes one record through every stage, printing what goes in and
+    /// out of each stage and how long it took, without a progress bar or
+    /// writing any output.
+    ///
+    /// Options for the run are given before `inspect`, e.g.
+    /// `cli_async --chaos inspect 7`.
+    Inspect(InspectOpt),
+    /// Prints a shell completion script to stdout.
+    ///
+    /// For example, `cli_async completions bash > /etc/bash_completion.d/cli_async`.
+    Completions {
+        /// Shell to generate completions for.
+        #[arg(ignore_case = true)]
+        shell: Shell,
+    },
+}
+
+/// Parses a delay of up to one minute, e.g. `50ms`.
+///
+/// Plain numbers are milliseconds.
+fn parse_delay(s: &str) -> Result<Duration, String> {
+    let delay = match s.parse::<u64>() {
+        Ok(millis) => Duration::from_millis(millis),
+        Err(_) => humantime::parse_duration(s).map_err(|e| e.to_string())?,
+    };
+    if delay <= Duration::from_secs(60) {
+        Ok(delay)
+    } else {
+        Err(format!("`{}` is longer than 1 minute.", s))
+    }
+}
+
+/// Parses a non-zero interval, e.g. `10s`.
+///
+/// Plain numbers are seconds.
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    let interval = match s.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => humantime::parse_duration(s).map_err(|e| e.to_string())?,
+    };
+    if interval > Duration::from_secs(0) {
+        Ok(interval)
+    } else {
+        Err(format!("`{}` must be greater than 0.", s))
+    }
+}
+
+/// Parses a probability between `0.0` and `1.0` inclusive.
+fn parse_rate(s: &str) -> Result<f64, String> {
+    let rate = s.parse::<f64>().map_err(|e| e.to_string())?;
+    if (0.0..=1.0).contains(&rate) {
+        Ok(rate)
+    } else {
+        Err(format!("`{}` is not between 0.0 and 1.0.", rate))
+    }
 }
 
 #[tokio::main]
-async fn main() -> Result<(), ()> {
+async fn main() -> ExitCode {
+    miette::set_panic_hook();
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let exit_code = e.exit_code();
+            eprintln!("{:?}", miette::Report::new(e));
+            ExitCode::from(exit_code)
+        }
+    }
+}
+
+async fn run() -> Result<(), Error> {
     let Opt {
         count: record_count,
+        watch,
+        stdin,
+        discover,
+        discover_page_size,
         skip,
+        only,
+        only_ids,
+        exclude_ids,
         delay_rate_limit,
         delay_auth,
+        delay_handshake,
+        delay_discover,
         delay_retrieve,
-    } = Opt::from_args();
+        latency_distribution,
+        latency_spread,
+        latency_pareto_shape,
+        error_rate,
+        partial_rate,
+        progress,
+        progress_interval,
+        progress_template,
+        progress_chars,
+        ascii,
+        color_depth,
+        color,
+        theme,
+        no_logo,
+        logo,
+        logo_text,
+        config,
+        deadline,
+        drain_timeout,
+        fail_fast,
+        circuit_breaker_threshold,
+        circuit_breaker_cool_down,
+        enrich,
+        batch_size,
+        batch_linger,
+        event_buffer,
+        concurrency,
+        warmup_records,
+        warmup_duration,
+        profile,
+        slowest,
+        errors_full,
+        max_error_rows,
+        no_error_browser,
+        errors_out,
+        output,
+        verbose,
+        quiet,
+        log_format,
+        log_file,
+        log_file_max_size,
+        log_file_keep,
+        otlp_endpoint,
+        metrics_port,
+        status_port,
+        no_keyboard,
+        tui,
+        control,
+        control_socket,
+        ws_port,
+        events,
+        on_error,
+        on_complete,
+        notify_webhook,
+        notify_webhook_format,
+        notify,
+        proxy,
+        ca_cert,
+        insecure,
+        pool_max_idle,
+        keep_alive,
+        http2,
+        credentials_file,
+        credential_rotation,
+        report_out,
+        output_format,
+        row_group_size,
+        compress,
+        output_shards,
+        flush_every,
+        flush_interval,
+        durability,
+        force,
+        journal,
+        audit_log,
+        store,
+        publish,
+        sink,
+        write_retries,
+        write_retry_backoff,
+        resume,
+        dedupe_report,
+        ordered,
+        reorder_buffer,
+        seed,
+        chaos,
+        chaos_rate,
+        retries,
+        retry_backoff,
+        chaos_retry_after,
+        command,
+    } = Opt::parse();
+    let pool_options = PoolOptions {
+        max_idle_per_host: pool_max_idle,
+        keep_alive,
+        http2,
+    };
+
+    let terminal = TerminalCapabilities::detect();
+    let color = color.enabled(&terminal);
+    // `graph` and `inspect` need the stages, which are only known once the
+    // options are resolved.
+    let (graph_opt, inspect_opt) = match command {
+        Some(Command::History(history_opt)) => {
+            History::print(&history_opt).map_err(Error::io("read run history"))?;
+            return Ok(());
+        }
+        Some(Command::Diff(diff_opt)) => {
+            ReportDiff::print(&diff_opt).map_err(Error::io("compare reports"))?;
+            return Ok(());
+        }
+        Some(Command::Merge(merge_opt)) => {
+            OutputMerge::run(&merge_opt)
+                .await
+                .map_err(Error::io("merge output shards"))?;
+            return Ok(());
+        }
+        Some(Command::Stats(stats_opt)) => {
+            Colours::init(
+                Theme::named(theme.unwrap_or(ThemeName::Default)),
+                color,
+                color_depth.unwrap_or(terminal.color_depth),
+            );
+            Stats::print(&stats_opt)
+                .await
+                .map_err(Error::io("read output file"))?;
+            return Ok(());
+        }
+        Some(Command::Convert(convert_opt)) => {
+            OutputConvert::run(&convert_opt)
+                .await
+                .map_err(Error::io("convert output file"))?;
+            return Ok(());
+        }
+        Some(Command::Verify(verify_opt)) => {
+            Colours::init(
+                Theme::named(theme.unwrap_or(ThemeName::Default)),
+                color,
+                color_depth.unwrap_or(terminal.color_depth),
+            );
+            let matches = Verification::run(&verify_opt, record_count)
+                .await
+                .map_err(Error::io("verify output file"))?;
+            return if matches {
+                Ok(())
+            } else {
+                Err(Error::VerificationFailed)
+            };
+        }
+        Some(Command::Ctl(ctl_opt)) => {
+            return ControlClient::run(&ctl_opt)
+                .await
+                .map_err(Error::network("send control command"));
+        }
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(
+                shell,
+                &mut Opt::command(),
+                env!("CARGO_PKG_NAME"),
+                &mut io::stdout(),
+            );
+            return Ok(());
+        }
+        Some(Command::Validate) => {
+            Colours::init(
+                Theme::named(theme.unwrap_or(ThemeName::Default)),
+                color,
+                color_depth.unwrap_or(terminal.color_depth),
+            );
+
+            let mut validation = Validation::default();
+            let config_name = match config.clone().or_else(Config::path) {
+                Some(path) => format!("config file `{}`", path.display()),
+                None => String::from("config file"),
+            };
+            match Config::load(config.as_deref()) {
+                Ok(config) => {
+                    validation.check(config_name, Ok::<_, String>(()));
+                    validation.check(
+                        "theme styles",
+                        Theme::named(theme.or(config.theme.name).unwrap_or(ThemeName::Default))
+                            .with_overrides(&config.theme.styles)
+                            .map(|_| ()),
+                    );
+                    if let Some(logo) = logo.or(config.logo.path) {
+                        validation.check(
+                            format!("logo file `{}`", logo.display()),
+                            Logo::load(&logo).map(|_| ()),
+                        );
+                    }
+                    let progress_template = progress_template.or(config.progress.template);
+                    let progress_options = ProgressOptions {
+                        mode: ProgressMode::Overall,
+                        template_auto: progress_template.is_none(),
+                        template: progress_template
+                            .unwrap_or_else(|| String::from(ProgressOptions::TEMPLATE_DEFAULT)),
+                        chars: progress_chars
+                            .or(config.progress.chars)
+                            .unwrap_or_else(|| String::from(ProgressOptions::CHARS_DEFAULT)),
+                        ascii,
+                        plain_interval: progress_interval,
+                        eta: Eta::default(),
+                    };
+                    let progress_style = if progress_options.chars.chars().count() < 2 {
+                        Err(String::from(
+                            "progress chars must have at least 2 characters.",
+                        ))
+                    } else {
+                        progress_options
+                            .style()
+                            .map(|_| ())
+                            .map_err(|e| e.to_string())
+                    };
+                    validation.check("progress template and chars", progress_style);
+                }
+                Err(e) => validation.check(config_name, Err(e)),
+            }
+            [
+                ("only IDs file", only_ids.as_deref()),
+                ("exclude IDs file", exclude_ids.as_deref()),
+            ]
+            .iter()
+            .filter_map(|(name, path)| path.map(|path| (name, path)))
+            .for_each(|(name, path)| {
+                validation.check(
+                    format!("{} `{}`", name, path.display()),
+                    RecordFilter::read_ids(path).map(|_| ()),
+                )
+            });
+            if let Some(publish) = publish.as_deref() {
+                validation.check(
+                    format!("publish broker `{}`", publish),
+                    Publisher::connect(publish).await.map(|_| ()),
+                );
+            }
+            let http_options =
+                match HttpOptions::new(proxy, ca_cert.as_deref(), insecure, pool_options) {
+                    Ok(http_options) => http_options,
+                    Err(e) => {
+                        if let Some(ca_cert) = ca_cert.as_deref() {
+                            validation
+                                .check(format!("CA certificate `{}`", ca_cert.display()), Err(e));
+                        }
+                        HttpOptions::default()
+                    }
+                };
+            let output_url = output.as_deref().and_then(OutputWriter::object_url);
+            if let Some(output_url) = output_url {
+                validation.check(
+                    format!("output bucket `{}`", output_url),
+                    OutputWriter::check_object_store(output_url, &http_options).await,
+                );
+            }
+            output
+                .iter()
+                .filter(|_| output_url.is_none())
+                .flat_map(|output| OutputWriter::shard_paths(output, output_shards))
+                .for_each(|path| validation.check_writable("output file", &path));
+            if let Some(output) = output.as_deref().filter(|_| output_url.is_none()) {
+                let holder = OutputLock::holder(output).map_err(|e| e.to_string());
+                validation.check(
+                    format!("output lock `{}`", OutputLock::path(output).display()),
+                    match holder {
+                        Ok(Some(pid)) if !force => Err(format!("Held by process {}.", pid)),
+                        Ok(_) => Ok(()),
+                        Err(e) => Err(e),
+                    },
+                );
+            }
+            [
+                ("errors file", errors_out.as_deref()),
+                ("report file", report_out.as_deref()),
+                ("dedupe report file", dedupe_report.as_deref()),
+                ("journal file", journal.as_deref()),
+                ("audit log file", audit_log.as_deref()),
+                ("log file", log_file.as_deref()),
+            ]
+            .iter()
+            .filter_map(|(name, path)| path.map(|path| (name, path)))
+            .for_each(|(name, path)| validation.check_writable(name, path));
+            let credentials =
+                t01_read_credentials(credentials_file.as_deref(), credential_rotation);
+            match credentials_file.as_deref() {
+                Some(credentials_file) => validation.check(
+                    format!("credentials file `{}`", credentials_file.display()),
+                    credentials.map(|_| ()),
+                ),
+                None => validation.check("credentials", credentials.map(|_| ())),
+            }
+
+            validation
+                .print()
+                .map_err(Error::io("print validation results"))?;
+            return if validation.passed() {
+                Ok(())
+            } else {
+                Err(Error::ValidationFailed)
+            };
+        }
+        Some(Command::Graph(graph_opt)) => (Some(graph_opt), None),
+        Some(Command::Inspect(inspect_opt)) => (None, Some(inspect_opt)),
+        None => (None, None),
+    };
+    let run_metadata = RunMetadata::new(std::env::args().skip(1).collect());
+    let config = Config::load(config.as_deref()).map_err(Error::config("read config file"))?;
+    let ascii = ascii || !terminal.unicode;
+    let theme_name = theme.or(config.theme.name).unwrap_or_else(|| {
+        // Only ask the terminal when the answer would be used.
+        if color && Background::detect() == Some(Background::Light) {
+            ThemeName::Light
+        } else {
+            ThemeName::Default
+        }
+    });
+    let theme = Theme::named(theme_name)
+        .with_overrides(&config.theme.styles)
+        .unwrap_or_else(|e| {
+            Opt::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!("`[theme.styles]` in the config file is invalid: {}", e),
+                )
+                .exit()
+        });
+    Colours::init(theme, color, color_depth.unwrap_or(terminal.color_depth));
+    console::set_colors_enabled(color);
+    console::set_colors_enabled_stderr(color);
+
+    if error_rate + partial_rate > 1.0 {
+        Opt::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "`--error-rate` and `--partial-rate` must not add up to more than 1.0.",
+            )
+            .exit();
+    }
+    if !latency_pareto_shape.is_finite() || latency_pareto_shape <= 0.0 {
+        Opt::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "`--latency-pareto-shape` must be a finite number greater than 0.",
+            )
+            .exit();
+    }
+    if skip > record_count {
+        Opt::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "`--skip` ({}) must not be greater than `--count` ({}).",
+                    skip, record_count
+                ),
+            )
+            .exit();
+    }
+    if let Some(batch_size) = batch_size.filter(|batch_size| *batch_size > concurrency) {
+        Opt::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "`--batch-size` ({}) must not be greater than `--concurrency` ({}).",
+                    batch_size, concurrency
+                ),
+            )
+            .exit();
+    }
+    let sink_kinds = if sink.is_empty() {
+        [
+            (SinkKind::File, output.is_some()),
+            (SinkKind::Db, store.is_some()),
+            (SinkKind::Publish, publish.is_some()),
+        ]
+        .iter()
+        .filter(|(_, given)| *given)
+        .map(|(sink_kind, _)| *sink_kind)
+        .collect::<Vec<_>>()
+    } else {
+        sink.iter().fold(Vec::new(), |mut sink_kinds, sink_kind| {
+            if !sink_kinds.contains(sink_kind) {
+                sink_kinds.push(*sink_kind);
+            }
+            sink_kinds
+        })
+    };
+    [
+        (SinkKind::File, output.is_some(), "--output"),
+        (SinkKind::Db, store.is_some(), "--store"),
+        (SinkKind::Publish, publish.is_some(), "--publish"),
+    ]
+    .iter()
+    .filter(|(sink_kind, given, _)| !given && sink_kinds.contains(sink_kind))
+    .for_each(|(sink_kind, _, option)| {
+        Opt::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                format!("`--sink {}` requires `{}`.", sink_kind, option),
+            )
+            .exit()
+    });
+    if output_format == OutputFormat::Parquet {
+        [
+            (compress.is_some(), "--compress"),
+            (output_shards > 1, "--output-shards"),
+            (journal.is_some(), "--journal"),
+            (resume, "--resume"),
+            (ordered, "--ordered"),
+            (flush_every.is_some(), "--flush-every"),
+            (flush_interval.is_some(), "--flush-interval"),
+        ]
+        .iter()
+        .filter(|(given, _)| *given)
+        .for_each(|(_, option)| {
+            Opt::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    format!("`--output-format parquet` can't be used with `{}`.", option),
+                )
+                .exit()
+        });
+        if output
+            .as_deref()
+            .and_then(OutputWriter::object_url)
+            .is_some()
+        {
+            Opt::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "`--output-format parquet` can't be used with an `s3://` `--output`.",
+                )
+                .exit()
+        }
+    }
+    let compress = match (
+        compress,
+        output
+            .as_deref()
+            .filter(|_| output_format == OutputFormat::Jsonl)
+            .and_then(Compression::from_path),
+    ) {
+        (Some(compress), Some(compress_extension)) if compress != compress_extension => {
+            Opt::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    format!(
+                        "`--compress {}` doesn't match the `.{}` extension of `--output`.",
+                        compress,
+                        compress_extension.extension()
+                    ),
+                )
+                .exit()
+        }
+        (compress, compress_extension) => compress.or(compress_extension),
+    };
+    if output
+        .as_deref()
+        .and_then(OutputWriter::object_url)
+        .is_some()
+    {
+        // The upload only completes once the run finishes, so records
+        // committed before then would be lost if the run is interrupted.
+        [(journal.is_some(), "--journal"), (resume, "--resume")]
+            .iter()
+            .filter(|(given, _)| *given)
+            .for_each(|(_, option)| {
+                Opt::command()
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        format!("`{}` can't be used with an `s3://` `--output`.", option),
+                    )
+                    .exit()
+            });
+    }
+    let progress_template = progress_template.or(config.progress.template);
+    let tui = tui && terminal.is_tty;
+    let progress_options = ProgressOptions {
+        mode: progress.unwrap_or(if quiet || tui {
+            ProgressMode::Hidden
+        } else if terminal.is_tty {
+            ProgressMode::Overall
+        } else {
+            ProgressMode::Plain
+        }),
+        template_auto: progress_template.is_none(),
+        template: progress_template.unwrap_or_else(|| {
+            if terminal.is_narrow() {
+                String::from(ProgressOptions::TEMPLATE_NARROW)
+            } else {
+                String::from(ProgressOptions::TEMPLATE_DEFAULT)
+            }
+        }),
+        chars: progress_chars.or(config.progress.chars).unwrap_or_else(|| {
+            if ascii {
+                String::from(ProgressOptions::CHARS_ASCII)
+            } else {
+                String::from(ProgressOptions::CHARS_DEFAULT)
+            }
+        }),
+        ascii,
+        plain_interval: progress_interval,
+        eta: Eta::default(),
+    };
+    if progress_options.chars.chars().count() < 2 {
+        Opt::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "`--progress-chars` must have at least 2 characters.",
+            )
+            .exit();
+    }
+    if let Err(e) = progress_options.style() {
+        Opt::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!("`--progress-template` is invalid: {}", e),
+            )
+            .exit();
+    }
+    let latency = Latency {
+        distribution: latency_distribution,
+        base: delay_retrieve,
+        spread: latency_spread,
+        pareto_shape: latency_pareto_shape,
+    };
+    let failure_injection = FailureInjection {
+        error_rate,
+        partial_rate,
+        seed,
+    };
+    let chaos = if chaos {
+        Some(Chaos {
+            rate: chaos_rate,
+            retries,
+            backoff: retry_backoff,
+            retry_after: chaos_retry_after,
+        })
+    } else {
+        None
+    };
+    let http_options = HttpOptions::new(proxy, ca_cert.as_deref(), insecure, pool_options)
+        .map_err(Error::config("read CA certificate"))?;
+    let event_bus = <EventBus>::new(event_buffer);
+    let throttle = Arc::new(Throttle::new(event_bus.clone()));
+    let connection_pool = Arc::new(ConnectionPool::new(pool_options, delay_handshake));
+    let enrichment_sources = enrich.iter().fold(Vec::new(), |mut sources, source| {
+        if !sources.contains(source) {
+            sources.push(*source);
+        }
+        sources
+    });
+    // `graph` draws the same stages that process the records.
+    let pipeline_builder = |credentials: Arc<Credentials>,
+                            sink: Arc<dyn RecordSink>,
+                            output_writer: Option<Arc<OutputWriter>>,
+                            stage_timings: Arc<StageTimings>,
+                            metrics: Arc<Metrics>,
+                            audit_log: Option<Arc<AuditLog>>| {
+        // Stages are audited inside any retries, so that each attempt is written.
+        let audited = |stage: BoxStage<_, _, _>| -> BoxStage<_, _, _> {
+            match audit_log.as_ref() {
+                Some(audit_log) => Box::new(AuditLayer::new(Arc::clone(audit_log)).layer(stage)),
+                None => stage,
+            }
+        };
+        let retrieve_stage = RateLimitLayer::new(delay_rate_limit, Arc::clone(&stage_timings))
+            .layer(RetrieveStage {
+                latency,
+                failure_injection,
+                chaos,
+                credentials: Arc::clone(&credentials),
+                delay_auth,
+                metrics,
+                throttle: Arc::clone(&throttle),
+                connection_pool: Arc::clone(&connection_pool),
+                enrichment_sources: enrichment_sources.clone(),
+            });
+        let retrieve_stage: BoxStage<_, _, _> = match batch_size {
+            Some(size) => {
+                let policy = BatchPolicy {
+                    size,
+                    linger: batch_linger,
+                };
+                Box::new(BatchLayer::new(policy).layer(retrieve_stage))
+            }
+            None => Box::new(retrieve_stage),
+        };
+        let retrieve_stage = match circuit_breaker_threshold {
+            Some(failure_threshold) => {
+                let policy = CircuitBreakerPolicy {
+                    failure_threshold,
+                    cool_down: circuit_breaker_cool_down,
+                };
+                Box::new(CircuitBreakerLayer::new(policy, event_bus.clone()).layer(retrieve_stage))
+            }
+            None => retrieve_stage,
+        };
+        Pipeline::builder()
+            .layer(LoggingLayer)
+            .layer(TimingLayer::new(stage_timings))
+            .stage(audited(Box::new(AuthenticateStage {
+                credentials,
+                delay: delay_auth,
+            })))
+            .stage(audited(retrieve_stage))
+            .stage(audited(Box::new(AugmentStage)))
+            .stage(
+                RetryLayer::new(RetryPolicy {
+                    retries: write_retries,
+                    backoff: write_retry_backoff,
+                })
+                .layer(audited(Box::new(OutputStage {
+                    sink,
+                    output_writer,
+                }))),
+            )
+    };
+    if let Some(graph_opt) = graph_opt {
+        let stage_average_durations = match graph_opt.report.as_deref() {
+            Some(report) => {
+                <Report>::read_json(report)
+                    .map_err(Error::io("read report"))?
+                    .stage_average_durations
+            }
+            None => BTreeMap::new(),
+        };
+        let pipeline_graph = pipeline_builder(
+            Arc::new(
+                t01_read_credentials(None, credential_rotation)
+                    .map_err(Error::auth("read credentials file"))?,
+            ),
+            Arc::new(NullSink),
+            None,
+            Arc::new(StageTimings::default()),
+            Arc::new(Metrics::new()),
+            None,
+        )
+        .graph();
+        match graph_opt.format {
+            GraphFormat::Dot => print!("{}", pipeline_graph.dot(&stage_average_durations)),
+        }
+        return Ok(());
+    }
+    if let Some(InspectOpt { record_id }) = inspect_opt {
+        if record_id >= record_count {
+            Opt::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!(
+                        "Record {} is not in the input, which has {} records.",
+                        record_id, record_count
+                    ),
+                )
+                .exit();
+        }
+        let credentials = t01_read_credentials(credentials_file.as_deref(), credential_rotation)
+            .map_err(Error::auth("read credentials file"))?;
+        let metrics = Arc::new(Metrics::new());
+        let pipeline = pipeline_builder(
+            Arc::new(credentials),
+            Arc::new(NullSink),
+            None,
+            Arc::new(StageTimings::default()),
+            Arc::clone(&metrics),
+            None,
+        )
+        .layer(InspectLayer)
+        .build(
+            event_bus,
+            metrics,
+            Arc::new(RunControl::default()),
+            Arc::new(ConcurrencyLimit::new(1, ProgressBar::hidden())),
+        );
+        pipeline
+            .run(stream::iter([(record_id, PropertyRecord(record_id))]))
+            .await;
+        return Ok(());
+    }
+
+    let input_watch = watch
+        .as_deref()
+        .map(InputWatch::new)
+        .transpose()
+        .map_err(Error::io("watch input directory"))?;
+    let discovery =
+        discover.then(|| Discovery::new(record_count, discover_page_size, delay_discover));
+    // Records only come from the files added to the watched directory, from
+    // stdin, or from the listing.
+    let record_count = if input_watch.is_some() || stdin || discovery.is_some() {
+        0
+    } else {
+        record_count
+    };
+    if !quiet && !no_logo && !config.logo.hidden {
+        let logo = match (logo, logo_text) {
+            (Some(path), _) => Logo::load(&path).map_err(Error::config("read logo file"))?,
+            (None, Some(text)) => Logo::render(&text),
+            (None, None) => match (config.logo.path, config.logo.text) {
+                (Some(path), _) => Logo::load(&path).map_err(Error::config("read logo file"))?,
+                (None, Some(text)) => Logo::render(&text),
+                (None, None) => Logo::default(),
+            },
+        };
+        logo.print().map_err(Error::io("print logo"))?;
+    }
+
+    let run_control = Arc::new(RunControl::default());
+    let ctrl_c_future = t00_setup_interrupt_handler(Arc::clone(&run_control), event_bus.clone())?;
+    if let Some(deadline) = deadline {
+        let run_control = Arc::clone(&run_control);
+        tokio::spawn(async move {
+            tokio::time::sleep(deadline).await;
+            tracing::warn!(
+                "Deadline of {} reached, stopping.",
+                humantime::format_duration(deadline)
+            );
+            run_control.stop(InterruptReason::Deadline);
+        });
+    }
+    if let Some(fail_fast) = fail_fast {
+        tokio::spawn(
+            Arc::clone(&run_control).stop_after_failures(fail_fast, event_bus.subscribe()),
+        );
+    }
+    // Each run is uploaded to its own object, so they don't need a lock.
+    let output = output.filter(|_| sink_kinds.contains(&SinkKind::File));
+    let _output_lock = output
+        .as_deref()
+        .filter(|output| OutputWriter::object_url(output).is_none())
+        .map(|output| OutputLock::acquire(output, force))
+        .transpose()
+        .map_err(Error::io("lock output file"))?;
+
+    // Startup tasks run as soon as the tasks they depend on have finished,
+    // and share their results through these cells, so each cell is filled
+    // before the tasks that depend on it start.
+    let startup_result = "Startup task didn't run.";
+    let credentials = OnceCell::new();
+    let records_precompleted = OnceCell::new();
+    let record_filter = OnceCell::new();
+    let journal_state = OnceCell::new();
+    let store_opened = OnceCell::new();
+    let publisher = OnceCell::new();
+    let output_corruption = OnceCell::new();
+    let records_committed = OnceCell::new();
+    let output_writer = OnceCell::new();
+    let parquet_writer = OnceCell::new();
+    TaskGraph::<Error>::new()
+        .task("read credentials", &[], async {
+            let read = t01_read_credentials(credentials_file.as_deref(), credential_rotation)
+                .map_err(Error::auth("read credentials file"))?;
+            credentials.get_or_init(|| Arc::new(read));
+            Ok(())
+        })
+        .task("read output file", &[], async {
+            records_precompleted.get_or_init(|| t03_read_output_file(skip));
+            Ok(())
+        })
+        .task("read record filter", &[], async {
+            let only_ids = only_ids
+                .as_deref()
+                .map(RecordFilter::read_ids)
+                .transpose()
+                .map_err(Error::io("read `--only-ids` file"))?;
+            let exclude_ids = exclude_ids
+                .as_deref()
+                .map(RecordFilter::read_ids)
+                .transpose()
+                .map_err(Error::io("read `--exclude-ids` file"))?
+                .unwrap_or_default();
+            record_filter.get_or_init(|| RecordFilter {
+                only: only.map(|RecordRange(range)| range),
+                only_ids,
+                exclude_ids,
+            });
+            Ok(())
+        })
+        .task("read journal", &[], async {
+            let read = match journal.as_deref() {
+                Some(journal) if resume => {
+                    Journal::read(journal).map_err(Error::io("read journal"))?
+                }
+                _ => JournalState::default(),
+            };
+            journal_state.get_or_init(|| read);
+            Ok(())
+        })
+        .task("open store", &[], async {
+            let store = match store.as_deref() {
+                Some(store) => Some(
+                    Store::open(store, &run_metadata)
+                        .await
+                        .map_err(Error::network("open store"))?,
+                ),
+                None => None,
+            };
+            store_opened.get_or_init(|| store);
+            Ok(())
+        })
+        .task("connect publisher", &[], async {
+            let connected = match publish
+                .as_deref()
+                .filter(|_| sink_kinds.contains(&SinkKind::Publish))
+            {
+                Some(publish) => Some(
+                    Publisher::connect(publish)
+                        .await
+                        .map_err(Error::network("connect to publish broker"))?,
+                ),
+                None => None,
+            };
+            publisher.get_or_init(|| connected);
+            Ok(())
+        })
+        .task("check output checksums", &[], async {
+            // Compressed and uploaded outputs can't be read back line by line
+            // after a crash.
+            let found = match output.as_deref().filter(|output| {
+                resume
+                    && compress.is_none()
+                    && Compression::from_path(output).is_none()
+                    && OutputWriter::object_url(output).is_none()
+            }) {
+                Some(output) => {
+                    OutputCorruption::find(&OutputWriter::shard_paths(output, output_shards))
+                        .await
+                        .map_err(Error::io("check output file"))?
+                }
+                None => OutputCorruption::default(),
+            };
+            output_corruption.get_or_init(|| found);
+            Ok(())
+        })
+        .task(
+            "read committed records",
+            &["read journal", "open store", "check output checksums"],
+            async {
+                let mut committed = journal_state.get().expect(startup_result).committed.clone();
+                if let (Some(store), true) = (store_opened.get().expect(startup_result), resume) {
+                    committed.extend(
+                        store
+                            .completed_record_ids()
+                            .await
+                            .map_err(Error::network("read completed records from store"))?,
+                    );
+                }
+                // Records on corrupt lines are processed again, even if the
+                // journal says they were committed.
+                let output_corruption = output_corruption.get().expect(startup_result);
+                committed.retain(|record_id| !output_corruption.record_ids.contains(record_id));
+                records_committed.get_or_init(|| committed);
+                Ok(())
+            },
+        )
+        .task(
+            "start run in store",
+            &[
+                "read output file",
+                "read record filter",
+                "read committed records",
+            ],
+            async {
+                if let (Some(store), true) = (
+                    store_opened.get().expect(startup_result),
+                    sink_kinds.contains(&SinkKind::Db),
+                ) {
+                    let records_committed = records_committed.get().expect(startup_result);
+                    let record_filter = record_filter.get().expect(startup_result);
+                    let records_pending = t02_stream_property_title_records(record_count)
+                        .skip(*records_precompleted.get().expect(startup_result))
+                        .filter(|record| {
+                            future::ready(
+                                !records_committed.contains(&record.0)
+                                    && record_filter.matches(*record),
+                            )
+                        });
+                    store
+                        .start_run(&run_metadata, records_pending)
+                        .await
+                        .map_err(Error::network("record run in store"))?;
+                }
+                Ok(())
+            },
+        )
+        .task("open output file", &[], async {
+            let opened = match (output.as_deref(), output_format) {
+                (Some(output), OutputFormat::Parquet) => Some(
+                    ParquetWriter::create(output, slice::from_ref(&run_metadata), row_group_size)
+                        .map_err(Error::io("open output file"))?,
+                ),
+                _ => None,
+            };
+            parquet_writer.get_or_init(|| opened);
 
-    let (progress_tx, progress_rx) = mpsc::unbounded_channel::<PropertyInfoResult>();
-    Reporter::print_logo().expect("Failed to print logo.");
+            let opened = match output
+                .as_deref()
+                .filter(|_| output_format == OutputFormat::Jsonl)
+            {
+                Some(output) => {
+                    let flush_every = match (flush_every, flush_interval) {
+                        (Some(flush_every), _) => flush_every,
+                        (None, Some(_)) => usize::MAX,
+                        (None, None) => 1,
+                    };
+                    let output_writer = OutputWriter::open(
+                        output,
+                        output_shards,
+                        compress,
+                        &run_metadata,
+                        &http_options,
+                    )
+                    .await
+                    .map_err(Error::io("open output file"))?
+                    .flush_every(flush_every)
+                    .durability(durability);
+                    let output_writer = match journal.as_deref() {
+                        Some(journal) => output_writer.journal(
+                            Journal::open(journal, resume, durability)
+                                .await
+                                .map_err(Error::io("open journal"))?,
+                        ),
+                        None => output_writer,
+                    };
+                    if ordered {
+                        Some(Arc::new(output_writer.ordered(reorder_buffer)))
+                    } else {
+                        Some(Arc::new(output_writer))
+                    }
+                }
+                None => None,
+            };
+            output_writer.get_or_init(|| opened);
+            Ok(())
+        })
+        .run()
+        .await?;
 
-    let (ctrl_c_future, interrupt_rx) = t00_setup_interrupt_handler();
-    let credentials = t01_read_credentials();
-    let records = t02_stream_property_title_records(record_count);
-    let records_precompleted = t03_read_output_file(skip);
+    let credentials = credentials.into_inner().expect(startup_result);
+    let records_precompleted = records_precompleted.into_inner().expect(startup_result);
+    let record_filter = record_filter.into_inner().expect(startup_result);
+    let records_torn = journal_state.into_inner().expect(startup_result).torn;
+    let store = store_opened.into_inner().expect(startup_result);
+    let publisher = publisher.into_inner().expect(startup_result);
+    let output_corruption = output_corruption.into_inner().expect(startup_result);
+    let records_committed = records_committed.into_inner().expect(startup_result);
+    let output_writer = output_writer.into_inner().expect(startup_result);
+    let mut parquet_writer = parquet_writer.into_inner().expect(startup_result);
+    let audit_log = match audit_log.as_deref() {
+        Some(audit_log) => Some(Arc::new(
+            AuditLog::open(audit_log, resume, &run_metadata)
+                .await
+                .map_err(Error::io("open audit log"))?,
+        )),
+        None => None,
+    };
+    // Records are streamed from the input each time they're needed, instead of
+    // being held in memory.
+    let records = || t02_stream_property_title_records(record_count);
+    let records_resumed = records()
+        .skip(records_precompleted)
+        .filter(|record| future::ready(records_committed.contains(&record.0)))
+        .count()
+        .await;
+    let records_filtered = records()
+        .skip(records_precompleted)
+        .filter(|record| {
+            future::ready(!records_committed.contains(&record.0) && !record_filter.matches(*record))
+        })
+        .count()
+        .await;
+    if let (Some(output_writer), Some(flush_interval)) = (output_writer.as_ref(), flush_interval) {
+        let output_writer = Arc::clone(output_writer);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + flush_interval,
+                flush_interval,
+            );
+            loop {
+                interval.tick().await;
+                if let Err(e) = output_writer.flush().await {
+                    tracing::error!("Failed to flush output file: {}", e);
+                }
+            }
+        });
+    }
+    let sink: Arc<dyn RecordSink> = Arc::new(TeeSink(
+        sink_kinds
+            .iter()
+            .filter_map(|sink_kind| -> Option<Box<dyn RecordSink>> {
+                match sink_kind {
+                    SinkKind::File => match output_writer.clone() {
+                        Some(output_writer) => Some(Box::new(FileSink(output_writer))),
+                        None => parquet_writer
+                            .take()
+                            .map(|parquet_writer| Box::new(ParquetSink::new(parquet_writer)) as _),
+                    },
+                    SinkKind::Stdout => Some(Box::new(StdoutSink::new())),
+                    SinkKind::Db => store.clone().map(|store| Box::new(DbSink(store)) as _),
+                    SinkKind::Publish => publisher
+                        .clone()
+                        .map(|publisher| Box::new(PublishSink(publisher)) as _),
+                    SinkKind::Null => Some(Box::new(NullSink)),
+                }
+            })
+            .collect(),
+    ));
+    let stage_timings = Arc::new(StageTimings::default());
+    let metrics = Arc::new(Metrics::new());
+    tokio::spawn(Arc::clone(&metrics).record_events(event_bus.subscribe()));
+    if let Some(metrics_port) = metrics_port {
+        let metrics_server = Metrics::serve(Arc::clone(&metrics), metrics_port)
+            .map_err(Error::network("bind metrics port"))?;
+        tokio::spawn(async move {
+            if let Err(e) = metrics_server.await {
+                tracing::error!("Metrics server failed: {}", e);
+            }
+        });
+    }
+    let mut report = Report::new(
+        run_metadata,
+        records_precompleted + records_resumed,
+        records_filtered,
+    );
+    report.streamed = stdin;
     let mut reporter = Reporter::new(
-        record_count as u64,
-        records_precompleted as u64,
-        progress_rx,
-        true,
-        Some(interrupt_rx),
+        (!stdin).then_some(record_count as u64),
+        report,
+        event_bus.subscribe(),
+        progress_options.clone(),
+        ReportOptions {
+            slowest_count: slowest,
+            errors_full,
+            max_error_rows,
+            warmup_record_count: warmup_records,
+            warmup_duration,
+            summary_only: quiet,
+        },
+        Arc::clone(&stage_timings),
     );
+    reporter.set_classifier(Classifier::new(&config.classification));
+    let concurrency_limit = Arc::new(ConcurrencyLimit::new(concurrency, reporter.progress_bar()));
+    if let Some(status_port) = status_port {
+        let status_server = Status::serve(
+            Arc::clone(&metrics),
+            reporter.progress_bar(),
+            Arc::clone(&run_control),
+            status_port,
+        )
+        .map_err(Error::network("bind status port"))?;
+        tokio::spawn(async move {
+            if let Err(e) = status_server.await {
+                tracing::error!("Status server failed: {}", e);
+            }
+        });
+    }
+    let _control_socket_guard = if control || control_socket.is_some() {
+        let control_socket = control_socket.unwrap_or_else(ControlServer::default_path);
+        let control_server = ControlServer::new(
+            Arc::clone(&run_control),
+            Arc::clone(&metrics),
+            reporter.progress_bar(),
+            reporter.progress_message(),
+        );
+        let (control_socket_guard, control_future) = control_server
+            .serve(&control_socket)
+            .map_err(Error::io("open control socket"))?;
+        tokio::spawn(control_future);
+        Some(control_socket_guard)
+    } else {
+        None
+    };
+    let log_file = log_file.map(|path| LogFile {
+        path,
+        max_size: log_file_max_size,
+        keep: log_file_keep,
+    });
+    let tui_log = tui.then(TuiLog::default);
+    Logging::init(
+        verbose,
+        log_format,
+        reporter.progress_bar(),
+        tui_log.clone(),
+        log_file,
+        otlp_endpoint,
+        color,
+        quiet,
+    )
+    .map_err(Error::io("initialize logging"))?;
+    if insecure {
+        tracing::warn!("Certificate verification is disabled for HTTP requests.");
+    }
+    if http_options.object_store_ignores_ca_cert()
+        && output
+            .as_deref()
+            .and_then(OutputWriter::object_url)
+            .is_some()
+    {
+        tracing::warn!(
+            "`--ca-cert` isn't trusted for the `s3://` output without `--proxy`, so the upload uses the system's certificates."
+        );
+    }
+    if !records_torn.is_empty() {
+        tracing::warn!(
+            torn = ?records_torn,
+            "{} records were claimed but not committed by an earlier run, and are processed again.",
+            records_torn.len()
+        );
+    }
+    if output_corruption.line_count > 0 {
+        tracing::warn!(
+            corrupt = ?output_corruption.record_ids,
+            "{} lines in the output file don't match their checksum or are torn, and {} of their records are processed again.",
+            output_corruption.line_count,
+            output_corruption.record_ids.len()
+        );
+    }
     t04_start_progress_bar(&mut reporter);
+    let suspend = Suspend::new(reporter.progress_bar());
+    tokio::spawn(suspend.clone().handle_sigtstp());
+    let progress_resize = ProgressResize::new(reporter.progress_bar(), progress_options);
+    tokio::spawn(progress_resize.clone().handle_sigwinch());
+    // Key presses would be read as records.
+    let mut keyboard_handle = None;
+    if !no_keyboard && !stdin && !tui {
+        let keyboard_control = KeyboardControl::new(
+            Arc::clone(&run_control),
+            Arc::clone(&concurrency_limit),
+            reporter.progress_message(),
+            suspend,
+            progress_resize,
+        )
+        .map_err(Error::io("enable keyboard control"))?;
+        if let Some(keyboard_control) = keyboard_control {
+            keyboard_handle = Some(tokio::spawn(keyboard_control.run()));
+        }
+    }
 
+    let progress_broadcast = match ws_port {
+        Some(ws_port) => {
+            let (progress_broadcast, ws_server) =
+                ProgressBroadcast::serve(ws_port).map_err(Error::network("bind WebSocket port"))?;
+            tokio::spawn(ws_server);
+            Some(progress_broadcast)
+        }
+        None => None,
+    };
+    // The `run_finished` event summarizes the run instead, so stdout is only
+    // JSON lines.
+    let result_line = !(events || sink_kinds.contains(&SinkKind::Stdout));
+    let event_writer = if events || progress_broadcast.is_some() {
+        Some(EventWriter::new(
+            &reporter.report().run,
+            events,
+            progress_broadcast,
+        ))
+    } else {
+        None
+    };
+    let event_writer_handle = event_writer
+        .map(|event_writer| tokio::spawn(event_writer.write_events(event_bus.subscribe())));
+    let hooks_handle = Hooks::new(&reporter.report().run, on_error, on_complete)
+        .map(|hooks| tokio::spawn(hooks.run_hooks(event_bus.subscribe())));
+    let webhook_handle = match notify_webhook {
+        Some(url) => {
+            let webhook = Webhook::new(url, notify_webhook_format, &http_options)
+                .map_err(Error::network("create webhook client"))?;
+            Some(tokio::spawn(webhook.notify(event_bus.subscribe())))
+        }
+        None => None,
+    };
+    let notifier_handle =
+        notify.map(|notify| tokio::spawn(Notifier::new(notify).notify(event_bus.subscribe())));
+    let in_flight = Arc::new(InFlight::default());
+    let profiler = profile.then(|| {
+        let profiler = Profiler::new(event_bus.clone(), Arc::clone(&in_flight));
+        tokio::spawn(profiler.clone().sample());
+        profiler
+    });
+    let tui_handle = tui_log.map(|tui_log| {
+        let tui = Tui::new(
+            Arc::clone(&run_control),
+            Arc::clone(&concurrency_limit),
+            reporter.progress_message(),
+            Arc::clone(&stage_timings),
+            Arc::clone(&in_flight),
+            tui_log,
+        );
+        tokio::spawn(tui.run(event_bus.subscribe()))
+    });
+    event_bus
+        .publish(RunEvent::RunStarted {
+            record_count: (!stdin).then_some(record_count),
+            record_skipped_count: reporter.report().record_skipped_count,
+        })
+        .await;
+
+    let worker_progress = reporter.worker_progress();
+    let stage_progress = reporter.stage_progress();
+    let event_bus_reporter = event_bus.clone();
+    let sink_reporter = Arc::clone(&sink);
+    let credentials_reporter = Arc::clone(&credentials);
+    let connection_pool_reporter = Arc::clone(&connection_pool);
+    let drain = Drain::new(
+        Arc::clone(&in_flight),
+        drain_timeout,
+        reporter.progress_message(),
+    );
     let reporter_future = async move {
         t10_update_progress_bar(&mut reporter).await;
-        t11_output_execution_report(&reporter);
+        // So key presses are read by the error browser instead.
+        if let Some(keyboard_handle) = keyboard_handle {
+            keyboard_handle.abort();
+            let _ = keyboard_handle.await;
+        }
+        KeyboardControl::restore_terminal();
+        if let Some(credential_usage) = credentials_reporter.usage() {
+            reporter.set_credential_usage(credential_usage);
+        }
+        reporter.set_connection_stats(connection_pool_reporter.stats());
+        reporter.set_event_backpressure(event_bus_reporter.backpressure());
+        if let Some(profiler) = profiler.as_ref() {
+            reporter.set_resources(profiler.resources());
+        }
+        // Reported once the report is printed, so the records that were
+        // written are still counted.
+        let finished = match sink_reporter.finish().await {
+            Ok(output_stats) => {
+                if let Some(output_stats) = output_stats {
+                    reporter.set_output_stats(output_stats);
+                }
+                Ok(())
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = sink_reporter.run_finished(reporter.report()).await {
+            tracing::error!("Failed to record end of run: {}", e);
+        }
+        event_bus_reporter
+            .publish(RunEvent::RunFinished(Arc::new(reporter.report().clone())))
+            .await;
+        // So the `run_finished` event is written before the report.
+        if let Some(event_writer_handle) = event_writer_handle {
+            let _ = event_writer_handle.await;
+        }
+        // So the report is printed after the dashboard leaves the alternate screen.
+        if let Some(tui_handle) = tui_handle {
+            let _ = tui_handle.await;
+        }
+        t11_output_execution_report(&reporter)?;
+        if let Some(errors_out) = errors_out.as_deref() {
+            t12_write_errors_file(&reporter, errors_out);
+        }
+        t13_append_run_history(&reporter);
+        if let Some(report_out) = report_out.as_deref() {
+            t14_write_report_file(&reporter, report_out);
+        }
+        if result_line {
+            t15_print_result_line(&reporter);
+        }
+        // Listing every failure was asked for, and stdin may be the records.
+        if !(no_error_browser || quiet || errors_full || stdin) {
+            // So hooks and notifications keep running while the errors are browsed.
+            tokio::task::block_in_place(|| t16_browse_errors(&reporter));
+        }
+        finished.map_err(Error::io("finish writing records"))?;
+        Ok::<_, Error>(reporter.report().interrupted)
     };
 
+    let pipeline = pipeline_builder(
+        credentials,
+        sink,
+        output_writer,
+        stage_timings,
+        Arc::clone(&metrics),
+        audit_log,
+    )
+    .worker_progress(worker_progress)
+    .stage_progress(stage_progress)
+    .throttle(throttle)
+    .in_flight(in_flight)
+    .build(
+        event_bus.clone(),
+        metrics,
+        Arc::clone(&run_control),
+        Arc::clone(&concurrency_limit),
+    );
+    let event_bus_source = event_bus.clone();
+    let records = records();
+    let duplicates = Arc::new(Mutex::new(Duplicates::default()));
+    let duplicates_found = Arc::clone(&duplicates);
     let processing_future = async move {
-        // Hacks for futures:
-        let progress_tx = &progress_tx;
-
-        stream::iter(records.into_iter().enumerate().skip(records_precompleted))
-            .then(move |(n, record)| async move {
-                t05_rate_limit_requests(delay_rate_limit).await;
-                t06_authenticate_with_server(n == 0, credentials, delay_auth).await;
-                let info = t07_retrieve_information(n, record, delay_retrieve).await;
-                progress_tx
-                    .send(info)
-                    .expect("Failed to send progress update.");
-                Result::<_, ()>::Ok(t08_augment_record(record, info))
-            })
-            .try_for_each_concurrent(10, move |property_record_populated| async move {
-                t09_output_record_to_file(property_record_populated).await;
-
-                Ok(())
-            })
-            .await
+        let record_pending = |(_, record): &(usize, PropertyRecord)| {
+            !records_committed.contains(&record.0) && record_filter.matches(*record)
+        };
+        let mut n_next = record_count;
+        let records = records
+            .enumerate()
+            .skip(records_precompleted)
+            .filter(|record| future::ready(record_pending(record)));
+        // Each watched file or listing page continues the input, so records
+        // keep their position across batches.
+        let batch_pending = |batch: Vec<PropertyRecord>| {
+            let n_start = n_next;
+            n_next += batch.len();
+            let batch = (n_start..)
+                .zip(batch)
+                .filter(record_pending)
+                .collect::<Vec<_>>();
+            let event_bus_source = event_bus_source.clone();
+            async move {
+                event_bus_source
+                    .publish(RunEvent::RecordsDiscovered {
+                        record_count: batch.len(),
+                    })
+                    .await;
+                stream::iter(batch)
+            }
+        };
+        // Duplicates are removed from every source together, as a record may
+        // reappear in a later file, listing page, or line of stdin.
+        let event_bus_duplicates = event_bus_source.clone();
+        match (input_watch, discovery) {
+            (Some(input_watch), _) => {
+                let records_watched = input_watch.batches().then(batch_pending).flatten();
+                let records = records.chain(records_watched);
+                pipeline
+                    .run(Duplicates::remove_from(
+                        duplicates,
+                        records,
+                        event_bus_duplicates,
+                    ))
+                    .await
+            }
+            (None, Some(discovery)) => {
+                let records_discovered = discovery.pages().then(batch_pending).flatten();
+                let records = records.chain(records_discovered);
+                pipeline
+                    .run(Duplicates::remove_from(
+                        duplicates,
+                        records,
+                        event_bus_duplicates,
+                    ))
+                    .await
+            }
+            (None, None) if stdin => {
+                let records_stdin = StdinRecords::stream()
+                    .enumerate()
+                    .map(move |(n, record)| (n_next + n, record))
+                    .filter(|record| future::ready(record_pending(record)));
+                let records = records.chain(records_stdin);
+                pipeline
+                    .run(Duplicates::remove_from(
+                        duplicates,
+                        records,
+                        event_bus_duplicates,
+                    ))
+                    .await
+            }
+            (None, None) => {
+                pipeline
+                    .run(Duplicates::remove_from(
+                        duplicates,
+                        records,
+                        event_bus_duplicates,
+                    ))
+                    .await
+            }
+        }
     };
 
     let reporter_handle = tokio::spawn(reporter_future);
 
     let ctrl_c_handle = tokio::spawn(ctrl_c_future);
-    let processing_handle = tokio::spawn(processing_future);
+    let mut processing_handle = tokio::spawn(processing_future);
 
     let processed_or_interrupted = async {
         tokio::select! {
-            _ = ctrl_c_handle => {}
-            _ = processing_handle => {}
+            _ = ctrl_c_handle => {
+                let reason = run_control.stop_reason().expect("Run stopped without a reason.");
+                let record_count = drain.drain(processing_handle, reason).await;
+                event_bus.publish(RunEvent::RecordsAbandoned { record_count }).await;
+            }
+            _ = &mut processing_handle => {}
         }
+        event_bus.publish(RunEvent::ProcessingFinished).await;
     };
 
-    let (_, _) = tokio::join!(reporter_handle, processed_or_interrupted);
+    let (reported, _) = tokio::join!(reporter_handle, processed_or_interrupted);
+    if let Some(hooks_handle) = hooks_handle {
+        let _ = hooks_handle.await;
+    }
+    if let Some(webhook_handle) = webhook_handle {
+        let _ = webhook_handle.await;
+    }
+    if let Some(notifier_handle) = notifier_handle {
+        let _ = notifier_handle.await;
+    }
+    let duplicates = duplicates_found.lock().expect("Duplicates lock poisoned.");
+    if duplicates.count() > 0 {
+        tracing::warn!(
+            "Found {} duplicate records in the input, each was processed once.",
+            duplicates.count()
+        );
+    }
+    if let Some(dedupe_report) = dedupe_report.as_deref() {
+        if let Err(e) = duplicates.write(dedupe_report) {
+            tracing::error!(
+                path = %dedupe_report.display(),
+                "Failed to write dedupe report: {}",
+                e
+            );
+        }
+    }
+    Logging::shutdown();
 
-    Ok(())
+    match reported {
+        Ok(Ok(true)) => Err(Error::Interrupted),
+        Ok(Ok(false)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        // The panic message has already been printed, but the report wasn't,
+        // so the run mustn't look like it succeeded.
+        Err(_) => Err(Error::Panicked { task: "reporter" }),
+    }
 }
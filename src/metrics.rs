@@ -0,0 +1,181 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use hyper::{header::CONTENT_TYPE, Body, Method, Request, Response, StatusCode};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+use crate::{EventReceiver, HttpServer, LookupResult, RecordStatus, RunEvent};
+
+/// Prometheus metrics for the run.
+#[derive(Debug)]
+pub struct Metrics {
+    registry: Registry,
+    /// Number of records processed, by result.
+    records_processed_total: IntCounterVec,
+    /// Number of information retrieval requests in flight.
+    requests_in_flight: IntGauge,
+    /// Number of populated records waiting to be written to the output.
+    output_queue_depth: IntGauge,
+    /// Time taken to retrieve a record's information, including retries.
+    retrieval_duration_seconds: Histogram,
+    /// `1` if the run was interrupted, otherwise `0`.
+    interrupted: IntGauge,
+}
+
+impl Metrics {
+    /// Returns a new set of metrics, all starting at zero.
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let records_processed_total = IntCounterVec::new(
+            Opts::new(
+                "cli_async_records_processed_total",
+                "Number of records processed, by result.",
+            ),
+            &["result"],
+        )
+        .expect("Invalid `records_processed_total` metric.");
+        let requests_in_flight = IntGauge::new(
+            "cli_async_requests_in_flight",
+            "Number of information retrieval requests in flight.",
+        )
+        .expect("Invalid `requests_in_flight` metric.");
+        let output_queue_depth = IntGauge::new(
+            "cli_async_output_queue_depth",
+            "Number of populated records waiting to be written to the output.",
+        )
+        .expect("Invalid `output_queue_depth` metric.");
+        let retrieval_duration_seconds = Histogram::with_opts(HistogramOpts::new(
+            "cli_async_retrieval_duration_seconds",
+            "Time taken to retrieve a record's information, including retries.",
+        ))
+        .expect("Invalid `retrieval_duration_seconds` metric.");
+        let interrupted = IntGauge::new(
+            "cli_async_interrupted",
+            "1 if the run was interrupted, otherwise 0.",
+        )
+        .expect("Invalid `interrupted` metric.");
+
+        registry
+            .register(Box::new(records_processed_total.clone()))
+            .and_then(|()| registry.register(Box::new(requests_in_flight.clone())))
+            .and_then(|()| registry.register(Box::new(output_queue_depth.clone())))
+            .and_then(|()| registry.register(Box::new(retrieval_duration_seconds.clone())))
+            .and_then(|()| registry.register(Box::new(interrupted.clone())))
+            .expect("Failed to register metrics.");
+
+        Self {
+            registry,
+            records_processed_total,
+            requests_in_flight,
+            output_queue_depth,
+            retrieval_duration_seconds,
+            interrupted,
+        }
+    }
+
+    /// Records that a retrieval request was sent.
+    pub fn request_started(&self) {
+        self.requests_in_flight.inc();
+    }
+
+    /// Records that a retrieval request finished.
+    pub fn request_finished(&self, info: &impl LookupResult, duration: Duration) {
+        self.requests_in_flight.dec();
+        self.retrieval_duration_seconds
+            .observe(duration.as_secs_f64());
+
+        self.records_processed_total
+            .with_label_values(&[Self::result_label(info.status())])
+            .inc();
+    }
+
+    /// Records that the run was interrupted.
+    pub fn interrupted(&self) {
+        self.interrupted.set(1);
+    }
+
+    /// Returns whether the run was interrupted.
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.get() != 0
+    }
+
+    /// Returns the number of records processed with the given result, one of
+    /// `success`, `partial`, or `error`.
+    pub fn records_processed(&self, result: &str) -> u64 {
+        self.records_processed_total
+            .with_label_values(&[result])
+            .get()
+    }
+
+    fn result_label(status: RecordStatus) -> &'static str {
+        match status {
+            RecordStatus::Success => "success",
+            RecordStatus::SuccessPartial => "partial",
+            RecordStatus::Error => "error",
+        }
+    }
+
+    /// Records that a populated record is waiting to be written.
+    pub fn output_queued(&self) {
+        self.output_queue_depth.inc();
+    }
+
+    /// Records that a populated record was written.
+    pub fn output_written(&self) {
+        self.output_queue_depth.dec();
+    }
+
+    /// Updates the metrics from events published on the [`EventBus`], until
+    /// the run finishes.
+    ///
+    /// [`EventBus`]: crate::EventBus
+    pub async fn record_events<R, I>(self: Arc<Self>, mut events: EventReceiver<R, I>) {
+        while let Some(event) = events.recv().await {
+            match event {
+                RunEvent::RecordWritten { .. } => self.output_written(),
+                RunEvent::Interrupted(_) => self.interrupted(),
+                RunEvent::RunFinished(_) => break,
+                RunEvent::RunStarted { .. }
+                | RunEvent::RecordsDiscovered { .. }
+                | RunEvent::RecordDuplicate { .. }
+                | RunEvent::Throttled { .. }
+                | RunEvent::CircuitChanged(_)
+                | RunEvent::RecordRetrieved(_)
+                | RunEvent::RecordFailed { .. }
+                | RunEvent::RecordPanicked { .. }
+                | RunEvent::RecordsAbandoned { .. }
+                | RunEvent::ProcessingFinished => {}
+            }
+        }
+    }
+
+    /// Returns the metrics in the Prometheus text exposition format.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("Failed to encode metrics.");
+        buffer
+    }
+
+    /// Serves `/metrics` on `127.0.0.1` at the given port until the process exits.
+    pub fn serve(
+        metrics: Arc<Metrics>,
+        port: u16,
+    ) -> hyper::Result<impl Future<Output = hyper::Result<()>>> {
+        HttpServer::serve(port, move |request| Self::respond(&metrics, request))
+    }
+
+    fn respond(metrics: &Metrics, request: &Request<Body>) -> Response<Body> {
+        match (request.method(), request.uri().path()) {
+            (&Method::GET, "/metrics") => Response::builder()
+                .header(CONTENT_TYPE, TextEncoder::new().format_type())
+                .body(Body::from(metrics.encode())),
+            _ => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty()),
+        }
+        .expect("Failed to build metrics response.")
+    }
+}
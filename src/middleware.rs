@@ -0,0 +1,870 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    marker::PhantomData,
+    mem,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use async_trait::async_trait;
+use tokio::{
+    sync::{oneshot, Notify},
+    time::{sleep, sleep_until},
+};
+use tracing::Instrument;
+
+use crate::{
+    t05_rate_limit_requests, AuditEntry, AuditLog, AuditLookup, BatchStage, EventBus, LookupResult,
+    Record, RecordStatus, RunEvent, Stage, StageKind, StageTimings, Work,
+};
+
+/// Wraps a [`Stage`] in another stage that adds behaviour around it, e.g.
+/// timing or retries, in the same way as `tower`'s `Layer`.
+///
+/// Layers given to [`PipelineBuilder::layer`] wrap every stage, and a layer
+/// can wrap a single stage with [`Layer::layer`].
+///
+/// [`PipelineBuilder::layer`]: crate::pipeline::PipelineBuilder::layer
+pub trait Layer<S> {
+    /// Stage that wraps the inner stage.
+    type Stage;
+
+    /// Wraps the stage.
+    fn layer(&self, stage: S) -> Self::Stage;
+}
+
+/// Records time spent in each stage in the [`StageTimings`].
+#[derive(Clone, Debug)]
+pub struct TimingLayer {
+    stage_timings: Arc<StageTimings>,
+}
+
+impl TimingLayer {
+    /// Returns a layer that records stage timings in `stage_timings`.
+    pub fn new(stage_timings: Arc<StageTimings>) -> Self {
+        Self { stage_timings }
+    }
+}
+
+impl<S> Layer<S> for TimingLayer {
+    type Stage = Timed<S>;
+
+    fn layer(&self, stage: S) -> Self::Stage {
+        Timed {
+            inner: stage,
+            stage_timings: Arc::clone(&self.stage_timings),
+        }
+    }
+}
+
+/// Stage wrapped by a [`TimingLayer`].
+#[derive(Debug)]
+pub struct Timed<S> {
+    inner: S,
+    stage_timings: Arc<StageTimings>,
+}
+
+#[async_trait]
+impl<R, I, O, S> Stage<R, I, O> for Timed<S>
+where
+    R: Record,
+    I: LookupResult,
+    O: Send + 'static,
+    S: Stage<R, I, O>,
+{
+    fn kind(&self) -> StageKind {
+        self.inner.kind()
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        self.inner.depends_on()
+    }
+
+    fn concurrent(&self) -> bool {
+        self.inner.concurrent()
+    }
+
+    async fn reserve(&self, sequence: usize) {
+        self.inner.reserve(sequence).await
+    }
+
+    async fn skip(&self, sequence: usize) {
+        self.inner.skip(sequence).await
+    }
+
+    async fn process(&self, work: Work<R, I, O>) -> Result<Work<R, I, O>, String> {
+        let start = Instant::now();
+        let result = self.inner.process(work).await;
+        self.stage_timings.record(self.kind(), start.elapsed());
+        result
+    }
+}
+
+/// Runs each stage in a `stage` span, and logs when it finishes.
+#[derive(Clone, Copy, Debug)]
+pub struct LoggingLayer;
+
+impl<S> Layer<S> for LoggingLayer {
+    type Stage = Logged<S>;
+
+    fn layer(&self, stage: S) -> Self::Stage {
+        Logged { inner: stage }
+    }
+}
+
+/// Stage wrapped by a [`LoggingLayer`].
+#[derive(Debug)]
+pub struct Logged<S> {
+    inner: S,
+}
+
+#[async_trait]
+impl<R, I, O, S> Stage<R, I, O> for Logged<S>
+where
+    R: Record,
+    I: LookupResult,
+    O: Send + 'static,
+    S: Stage<R, I, O>,
+{
+    fn kind(&self) -> StageKind {
+        self.inner.kind()
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        self.inner.depends_on()
+    }
+
+    fn concurrent(&self) -> bool {
+        self.inner.concurrent()
+    }
+
+    async fn reserve(&self, sequence: usize) {
+        self.inner.reserve(sequence).await
+    }
+
+    async fn skip(&self, sequence: usize) {
+        self.inner.skip(sequence).await
+    }
+
+    async fn process(&self, work: Work<R, I, O>) -> Result<Work<R, I, O>, String> {
+        let stage = self.kind().name();
+        async move {
+            let start = Instant::now();
+            let result = self.inner.process(work).await;
+            let duration = start.elapsed();
+            match result.as_ref() {
+                Ok(_) => tracing::trace!(stage, ?duration, "Stage finished."),
+                Err(e) => tracing::debug!(stage, ?duration, "Stage failed: {}", e),
+            }
+            result
+        }
+        .instrument(tracing::debug_span!("stage", stage))
+        .await
+    }
+}
+
+/// How many times to retry a stage that fails, and how long to wait between
+/// attempts.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Number of times to retry after the first attempt.
+    pub retries: u32,
+    /// Time to back off after the first failure, doubled per attempt.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Returns how long to wait before retrying the given attempt.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.backoff.saturating_mul(1 << attempt.min(16))
+    }
+}
+
+/// Retries a stage that fails, according to a [`RetryPolicy`].
+#[derive(Clone, Copy, Debug)]
+pub struct RetryLayer {
+    policy: RetryPolicy,
+}
+
+impl RetryLayer {
+    /// Returns a layer that retries stages with the given policy.
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S> Layer<S> for RetryLayer {
+    type Stage = Retry<S>;
+
+    fn layer(&self, stage: S) -> Self::Stage {
+        Retry {
+            inner: stage,
+            policy: self.policy,
+        }
+    }
+}
+
+/// Stage wrapped by a [`RetryLayer`].
+#[derive(Debug)]
+pub struct Retry<S> {
+    inner: S,
+    policy: RetryPolicy,
+}
+
+#[async_trait]
+impl<R, I, O, S> Stage<R, I, O> for Retry<S>
+where
+    R: Record,
+    I: LookupResult,
+    O: Clone + Send + Sync + 'static,
+    S: Stage<R, I, O>,
+{
+    fn kind(&self) -> StageKind {
+        self.inner.kind()
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        self.inner.depends_on()
+    }
+
+    fn concurrent(&self) -> bool {
+        self.inner.concurrent()
+    }
+
+    async fn reserve(&self, sequence: usize) {
+        self.inner.reserve(sequence).await
+    }
+
+    async fn skip(&self, sequence: usize) {
+        self.inner.skip(sequence).await
+    }
+
+    async fn process(&self, work: Work<R, I, O>) -> Result<Work<R, I, O>, String> {
+        let mut attempt = 0;
+        loop {
+            match self.inner.process(work.clone()).await {
+                Ok(work) => return Ok(work),
+                Err(e) if attempt < self.policy.retries => {
+                    tracing::warn!(
+                        stage = self.kind().name(),
+                        attempt,
+                        "Retrying stage after error: {}",
+                        e
+                    );
+                    sleep(self.policy.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Writes every attempt at processing a record in a stage to an [`AuditLog`].
+///
+/// Wrap a stage in this layer before a [`RetryLayer`], so that each retry is
+/// written.
+#[derive(Clone, Debug)]
+pub struct AuditLayer {
+    audit_log: Arc<AuditLog>,
+}
+
+impl AuditLayer {
+    /// Returns a layer that writes attempts to `audit_log`.
+    pub fn new(audit_log: Arc<AuditLog>) -> Self {
+        Self { audit_log }
+    }
+}
+
+impl<S> Layer<S> for AuditLayer {
+    type Stage = Audited<S>;
+
+    fn layer(&self, stage: S) -> Self::Stage {
+        Audited {
+            inner: stage,
+            audit_log: Arc::clone(&self.audit_log),
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Stage wrapped by an [`AuditLayer`].
+#[derive(Debug)]
+pub struct Audited<S> {
+    inner: S,
+    audit_log: Arc<AuditLog>,
+    /// Number of attempts made for each record that failed this stage, by
+    /// record ID, in case it is retried.
+    attempts: Mutex<HashMap<usize, u32>>,
+}
+
+#[async_trait]
+impl<R, I, O, S> Stage<R, I, O> for Audited<S>
+where
+    R: Record,
+    I: LookupResult,
+    O: Send + 'static,
+    S: Stage<R, I, O>,
+{
+    fn kind(&self) -> StageKind {
+        self.inner.kind()
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        self.inner.depends_on()
+    }
+
+    fn concurrent(&self) -> bool {
+        self.inner.concurrent()
+    }
+
+    async fn reserve(&self, sequence: usize) {
+        self.inner.reserve(sequence).await
+    }
+
+    async fn skip(&self, sequence: usize) {
+        self.inner.skip(sequence).await
+    }
+
+    async fn process(&self, work: Work<R, I, O>) -> Result<Work<R, I, O>, String> {
+        let record = work.record;
+        let looked_up = work.lookup.is_some();
+        let started = SystemTime::now();
+        let start = Instant::now();
+        let result = self.inner.process(work).await;
+        let duration = start.elapsed();
+
+        let attempt = {
+            let mut attempts = self.attempts.lock().expect("Audit attempts lock poisoned.");
+            let attempt = attempts.get(&record.id()).copied().unwrap_or(0) + 1;
+            match result {
+                Ok(_) => attempts.remove(&record.id()),
+                Err(_) => attempts.insert(record.id(), attempt),
+            };
+            attempt
+        };
+        let lookup = match result.as_ref() {
+            Ok(work) if !looked_up => work.lookup.as_ref().map(|lookup| AuditLookup {
+                status: lookup.info.status(),
+                error: lookup.info.error(),
+                attempts: lookup.attempts,
+                chaos_events: lookup.chaos_events,
+            }),
+            Ok(_) | Err(_) => None,
+        };
+        let audit_entry = AuditEntry {
+            record_id: record.id(),
+            title_number: record.label(),
+            stage: self.kind().name(),
+            attempt,
+            started,
+            duration,
+            error: result.as_ref().err().map(String::as_str),
+            lookup,
+        };
+        if let Err(e) = self.audit_log.write(&audit_entry).await {
+            tracing::warn!("Failed to write to audit log: {}", e);
+        }
+
+        result
+    }
+}
+
+/// Waits before each record passes through a stage, to stay within a server's
+/// rate limit.
+///
+/// The wait happens while the record is reserved, before any stage processes
+/// it, and is recorded as the [`StageKind::RateLimit`] stage.
+#[derive(Clone, Debug)]
+pub struct RateLimitLayer {
+    /// Delay before each record.
+    delay: Duration,
+    stage_timings: Arc<StageTimings>,
+}
+
+impl RateLimitLayer {
+    /// Returns a layer that waits `delay` before each record.
+    pub fn new(delay: Duration, stage_timings: Arc<StageTimings>) -> Self {
+        Self {
+            delay,
+            stage_timings,
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Stage = RateLimited<S>;
+
+    fn layer(&self, stage: S) -> Self::Stage {
+        RateLimited {
+            inner: stage,
+            delay: self.delay,
+            stage_timings: Arc::clone(&self.stage_timings),
+        }
+    }
+}
+
+/// Stage wrapped by a [`RateLimitLayer`].
+#[derive(Debug)]
+pub struct RateLimited<S> {
+    inner: S,
+    delay: Duration,
+    stage_timings: Arc<StageTimings>,
+}
+
+#[async_trait]
+impl<R, I, O, S> Stage<R, I, O> for RateLimited<S>
+where
+    R: Record,
+    I: LookupResult,
+    O: Send + 'static,
+    S: Stage<R, I, O>,
+{
+    fn kind(&self) -> StageKind {
+        self.inner.kind()
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        self.inner.depends_on()
+    }
+
+    fn concurrent(&self) -> bool {
+        self.inner.concurrent()
+    }
+
+    async fn reserve(&self, sequence: usize) {
+        self.inner.reserve(sequence).await;
+
+        let start = Instant::now();
+        t05_rate_limit_requests(self.delay)
+            .instrument(tracing::debug_span!(
+                "stage",
+                stage = StageKind::RateLimit.name()
+            ))
+            .await;
+        self.stage_timings
+            .record(StageKind::RateLimit, start.elapsed());
+    }
+
+    async fn skip(&self, sequence: usize) {
+        self.inner.skip(sequence).await
+    }
+
+    async fn process(&self, work: Work<R, I, O>) -> Result<Work<R, I, O>, String> {
+        self.inner.process(work).await
+    }
+}
+
+#[async_trait]
+impl<R, I, O, S> BatchStage<R, I, O> for RateLimited<S>
+where
+    R: Record,
+    I: LookupResult,
+    O: Send + 'static,
+    S: BatchStage<R, I, O>,
+{
+    async fn process_batch(
+        &self,
+        works: Vec<Work<R, I, O>>,
+    ) -> Result<Vec<Result<Work<R, I, O>, String>>, String> {
+        self.inner.process_batch(works).await
+    }
+}
+
+/// How a [`BatchLayer`] groups records.
+#[derive(Clone, Copy, Debug)]
+pub struct BatchPolicy {
+    /// Largest number of records in a batch.
+    pub size: usize,
+    /// Time to wait for a batch to fill before it is processed anyway.
+    pub linger: Duration,
+}
+
+/// Groups the records passing through a [`BatchStage`], so each batch is
+/// processed in one call, then passes each record on with its own result.
+///
+/// Records wait together for their batch, so the wrapped stage is
+/// concurrent.
+#[derive(Debug)]
+pub struct BatchLayer<R, I, O> {
+    policy: BatchPolicy,
+    /// Work the layered stages process.
+    marker: PhantomData<Work<R, I, O>>,
+}
+
+impl<R, I, O> BatchLayer<R, I, O> {
+    /// Returns a layer that groups records with the given policy.
+    pub fn new(policy: BatchPolicy) -> Self {
+        Self {
+            policy,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<R, I, O, S> Layer<S> for BatchLayer<R, I, O> {
+    type Stage = Batched<S, R, I, O>;
+
+    fn layer(&self, stage: S) -> Self::Stage {
+        Batched {
+            inner: stage,
+            policy: self.policy,
+            batch: Mutex::new(Batch {
+                id: 0,
+                works: Vec::new(),
+            }),
+        }
+    }
+}
+
+/// Stage wrapped by a [`BatchLayer`].
+#[derive(Debug)]
+pub struct Batched<S, R, I, O> {
+    inner: S,
+    policy: BatchPolicy,
+    batch: Mutex<Batch<R, I, O>>,
+}
+
+/// Records waiting for a [`Batched`] stage to process them.
+#[derive(Debug)]
+struct Batch<R, I, O> {
+    /// Increases each time the records are taken to be processed, so the first
+    /// record can tell whether its batch filled while it waited.
+    id: u64,
+    works: Vec<BatchEntry<R, I, O>>,
+}
+
+/// A record in a [`Batch`], with where to send its result.
+type BatchEntry<R, I, O> = (
+    Work<R, I, O>,
+    oneshot::Sender<Result<Work<R, I, O>, String>>,
+);
+
+impl<R, I, O> Batch<R, I, O> {
+    /// Takes the records to be processed, starting the next batch.
+    fn take(&mut self) -> Vec<BatchEntry<R, I, O>> {
+        self.id += 1;
+        mem::take(&mut self.works)
+    }
+}
+
+impl<S, R, I, O> Batched<S, R, I, O>
+where
+    S: BatchStage<R, I, O>,
+{
+    /// Processes a batch, and sends each record its result.
+    ///
+    /// If the batch fails, every record in it fails with the batch's error.
+    async fn run_batch(&self, batch: Vec<BatchEntry<R, I, O>>) {
+        let batch_size = batch.len();
+        let (works, result_txs): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+        match self.inner.process_batch(works).await {
+            Ok(results) => results
+                .into_iter()
+                .zip(result_txs)
+                .for_each(|(result, result_tx)| {
+                    let _ = result_tx.send(result);
+                }),
+            Err(error) => {
+                tracing::warn!(batch_size, "Batch failed: {}", error);
+                result_txs.into_iter().for_each(|result_tx| {
+                    let _ = result_tx.send(Err(format!(
+                        "Batch of {} records failed: {}",
+                        batch_size, error
+                    )));
+                });
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<R, I, O, S> Stage<R, I, O> for Batched<S, R, I, O>
+where
+    R: Record,
+    I: LookupResult,
+    O: fmt::Debug + Send + 'static,
+    S: BatchStage<R, I, O>,
+{
+    fn kind(&self) -> StageKind {
+        self.inner.kind()
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        self.inner.depends_on()
+    }
+
+    fn concurrent(&self) -> bool {
+        true
+    }
+
+    async fn reserve(&self, sequence: usize) {
+        self.inner.reserve(sequence).await
+    }
+
+    async fn skip(&self, sequence: usize) {
+        self.inner.skip(sequence).await
+    }
+
+    async fn process(&self, work: Work<R, I, O>) -> Result<Work<R, I, O>, String> {
+        let (result_tx, result_rx) = oneshot::channel();
+        let (batch_full, batch_id) = {
+            let mut batch = self.batch.lock().expect("Batch lock poisoned.");
+            batch.works.push((work, result_tx));
+            if batch.works.len() >= self.policy.size {
+                (Some(batch.take()), None)
+            } else if batch.works.len() == 1 {
+                (None, Some(batch.id))
+            } else {
+                (None, None)
+            }
+        };
+
+        if let Some(batch_full) = batch_full {
+            self.run_batch(batch_full).await;
+        } else if let Some(batch_id) = batch_id {
+            // The first record processes the batch if it doesn't fill in time.
+            sleep(self.policy.linger).await;
+            let batch_lingered = {
+                let mut batch = self.batch.lock().expect("Batch lock poisoned.");
+                (batch.id == batch_id).then(|| batch.take())
+            };
+            if let Some(batch_lingered) = batch_lingered {
+                self.run_batch(batch_lingered).await;
+            }
+        }
+
+        result_rx.await.unwrap_or_else(|_| {
+            Err(String::from(
+                "Batch stopped before the record was processed.",
+            ))
+        })
+    }
+}
+
+/// When a [`CircuitBreakerLayer`] stops passing records to its stage.
+#[derive(Clone, Copy, Debug)]
+pub struct CircuitBreakerPolicy {
+    /// Number of consecutive failures that open the circuit.
+    pub failure_threshold: u32,
+    /// Time the circuit stays open before a record is let through to probe
+    /// whether the stage has recovered.
+    pub cool_down: Duration,
+}
+
+/// Whether a [`CircuitBreakerLayer`] is passing records to its stage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Records pass through.
+    #[default]
+    Closed,
+    /// Records wait until the cool-down has passed.
+    Open,
+    /// One record passes through to probe whether the stage has recovered,
+    /// while the others wait.
+    HalfOpen,
+}
+
+impl fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed => write!(f, "closed"),
+            Self::Open => write!(f, "open"),
+            Self::HalfOpen => write!(f, "half-open"),
+        }
+    }
+}
+
+/// Stops passing records to a stage for a cool-down period after it fails
+/// for several records in a row, so a server that is down isn't sent requests
+/// that will fail too.
+///
+/// A record fails if the stage returns an error, or if its information could
+/// not be looked up. Each change of state is published on the [`EventBus`].
+#[derive(Clone, Debug)]
+pub struct CircuitBreakerLayer {
+    policy: CircuitBreakerPolicy,
+    event_bus: EventBus,
+}
+
+impl CircuitBreakerLayer {
+    /// Returns a layer that opens the circuit according to `policy`.
+    pub fn new(policy: CircuitBreakerPolicy, event_bus: EventBus) -> Self {
+        Self { policy, event_bus }
+    }
+}
+
+impl<S> Layer<S> for CircuitBreakerLayer {
+    type Stage = CircuitBroken<S>;
+
+    fn layer(&self, stage: S) -> Self::Stage {
+        CircuitBroken {
+            inner: stage,
+            policy: self.policy,
+            circuit: Mutex::new(Circuit {
+                state: CircuitState::Closed,
+                failure_count: 0,
+                open_until: Instant::now(),
+            }),
+            probe_finished: Notify::new(),
+            event_bus: self.event_bus.clone(),
+        }
+    }
+}
+
+/// State of a [`CircuitBroken`] stage's circuit.
+#[derive(Debug)]
+struct Circuit {
+    state: CircuitState,
+    /// Number of consecutive failures while closed.
+    failure_count: u32,
+    /// When the cool-down ends, while open.
+    open_until: Instant,
+}
+
+/// Stage wrapped by a [`CircuitBreakerLayer`].
+#[derive(Debug)]
+pub struct CircuitBroken<S> {
+    inner: S,
+    policy: CircuitBreakerPolicy,
+    circuit: Mutex<Circuit>,
+    /// Wakes records waiting for the half-open probe to finish.
+    probe_finished: Notify,
+    event_bus: EventBus,
+}
+
+impl<S> CircuitBroken<S> {
+    /// Waits until a record may pass through to the inner stage.
+    async fn wait_until_closed(&self) {
+        loop {
+            // Registered before checking, so a probe finishing in between is
+            // not missed.
+            let probe_finished = self.probe_finished.notified();
+            let (open_until, probe_state) = {
+                let mut circuit = self.circuit.lock().expect("Circuit lock poisoned.");
+                match circuit.state {
+                    CircuitState::Closed => return,
+                    // This record is the probe.
+                    CircuitState::Open if Instant::now() >= circuit.open_until => (
+                        None,
+                        Some(self.transition(&mut circuit, CircuitState::HalfOpen)),
+                    ),
+                    CircuitState::Open => (Some(circuit.open_until), None),
+                    CircuitState::HalfOpen => (None, None),
+                }
+            };
+            if let Some(probe_state) = probe_state {
+                self.publish(probe_state).await;
+                return;
+            }
+            match open_until {
+                Some(open_until) => sleep_until(open_until.into()).await,
+                None => probe_finished.await,
+            }
+        }
+    }
+
+    /// Updates the circuit with whether a record failed in the inner stage,
+    /// returning the state it changed to, if it changed.
+    fn record_outcome(&self, failed: bool) -> Option<CircuitState> {
+        let mut circuit = self.circuit.lock().expect("Circuit lock poisoned.");
+        match (circuit.state, failed) {
+            (CircuitState::Closed, false) => {
+                circuit.failure_count = 0;
+                None
+            }
+            (CircuitState::Closed, true) => {
+                circuit.failure_count += 1;
+                (circuit.failure_count >= self.policy.failure_threshold)
+                    .then(|| self.open(&mut circuit))
+            }
+            (CircuitState::HalfOpen, false) => {
+                circuit.failure_count = 0;
+                let state = self.transition(&mut circuit, CircuitState::Closed);
+                self.probe_finished.notify_waiters();
+                Some(state)
+            }
+            (CircuitState::HalfOpen, true) => {
+                let state = self.open(&mut circuit);
+                self.probe_finished.notify_waiters();
+                Some(state)
+            }
+            // Records that were already in the stage when the circuit opened.
+            (CircuitState::Open, _) => None,
+        }
+    }
+
+    fn open(&self, circuit: &mut Circuit) -> CircuitState {
+        circuit.open_until = Instant::now() + self.policy.cool_down;
+        self.transition(circuit, CircuitState::Open)
+    }
+
+    /// Changes the circuit's state, which is returned to be published once
+    /// the circuit is unlocked.
+    fn transition(&self, circuit: &mut Circuit, state: CircuitState) -> CircuitState {
+        circuit.state = state;
+        match state {
+            CircuitState::Open => tracing::warn!(
+                failure_count = circuit.failure_count,
+                "Circuit open, pausing requests for {}.",
+                humantime::format_duration(self.policy.cool_down)
+            ),
+            CircuitState::HalfOpen => tracing::info!("Circuit half-open, probing with one record."),
+            CircuitState::Closed => tracing::info!("Circuit closed, resuming requests."),
+        }
+        state
+    }
+
+    /// Publishes a change of the circuit's state.
+    async fn publish(&self, state: CircuitState) {
+        self.event_bus
+            .publish(RunEvent::CircuitChanged(state))
+            .await;
+    }
+}
+
+#[async_trait]
+impl<R, I, O, S> Stage<R, I, O> for CircuitBroken<S>
+where
+    R: Record,
+    I: LookupResult,
+    O: Send + 'static,
+    S: Stage<R, I, O>,
+{
+    fn kind(&self) -> StageKind {
+        self.inner.kind()
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        self.inner.depends_on()
+    }
+
+    fn concurrent(&self) -> bool {
+        self.inner.concurrent()
+    }
+
+    async fn reserve(&self, sequence: usize) {
+        self.inner.reserve(sequence).await
+    }
+
+    async fn skip(&self, sequence: usize) {
+        self.inner.skip(sequence).await
+    }
+
+    async fn process(&self, work: Work<R, I, O>) -> Result<Work<R, I, O>, String> {
+        self.wait_until_closed().await;
+        let result = self.inner.process(work).await;
+        let failed = match result.as_ref() {
+            Ok(work) => work
+                .lookup
+                .as_ref()
+                .is_some_and(|lookup| lookup.info.status() == RecordStatus::Error),
+            Err(_) => true,
+        };
+        if let Some(state) = self.record_outcome(failed) {
+            self.publish(state).await;
+        }
+        result
+    }
+}
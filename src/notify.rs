@@ -0,0 +1,122 @@
+use std::{
+    fmt,
+    io::{self, Write as _},
+    str::FromStr,
+};
+
+use notify_rust::{Notification, Urgency};
+
+use crate::{history::RunStatus, EventReceiver, Report, RunEvent};
+
+/// How to alert the user when the run finishes, for `--notify`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotifyKind {
+    /// A desktop notification.
+    Desktop,
+    /// The terminal bell.
+    Bell,
+    /// A desktop notification and the terminal bell.
+    Both,
+}
+
+impl NotifyKind {
+    fn desktop(self) -> bool {
+        matches!(self, Self::Desktop | Self::Both)
+    }
+
+    fn bell(self) -> bool {
+        matches!(self, Self::Bell | Self::Both)
+    }
+}
+
+impl fmt::Display for NotifyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Desktop => write!(f, "desktop"),
+            Self::Bell => write!(f, "bell"),
+            Self::Both => write!(f, "both"),
+        }
+    }
+}
+
+impl FromStr for NotifyKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "desktop" => Ok(Self::Desktop),
+            "bell" => Ok(Self::Bell),
+            "both" => Ok(Self::Both),
+            _ => Err(format!("`{}` is not one of `desktop`, `bell`, `both`.", s)),
+        }
+    }
+}
+
+/// Alerts the user when the run finishes or is interrupted, e.g. when it is
+/// running in a background terminal tab.
+#[derive(Clone, Copy, Debug)]
+pub struct Notifier {
+    kind: NotifyKind,
+}
+
+impl Notifier {
+    /// Returns a notifier that alerts the user in the given way.
+    pub fn new(kind: NotifyKind) -> Self {
+        Self { kind }
+    }
+
+    /// Alerts the user once the run finishes, as published on the
+    /// [`EventBus`].
+    ///
+    /// [`EventBus`]: crate::EventBus
+    pub async fn notify(self, mut events: EventReceiver) {
+        while let Some(event) = events.recv().await {
+            if let RunEvent::RunFinished(report) = event {
+                if self.kind.bell() {
+                    Self::bell();
+                }
+                if self.kind.desktop() {
+                    let notification = Self::notification(&report);
+                    // Showing the notification blocks while it talks to the
+                    // notification server.
+                    let shown =
+                        tokio::task::spawn_blocking(move || notification.show().map(|_| ())).await;
+                    match shown {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => tracing::error!("Failed to show desktop notification: {}", e),
+                        Err(e) => tracing::error!("Failed to show desktop notification: {}", e),
+                    }
+                }
+                break;
+            }
+        }
+    }
+
+    /// Rings the terminal bell.
+    ///
+    /// The bell is written to stderr, so it doesn't mix with events written
+    /// to stdout.
+    fn bell() {
+        let mut stderr = io::stderr();
+        if let Err(e) = stderr.write_all(b"\x07").and_then(|()| stderr.flush()) {
+            tracing::error!("Failed to ring the terminal bell: {}", e);
+        }
+    }
+
+    /// Returns the desktop notification for the finished run.
+    fn notification(report: &Report) -> Notification {
+        let run_status = RunStatus::from_report(report);
+        let urgency = match run_status {
+            RunStatus::Completed => Urgency::Normal,
+            RunStatus::CompletedWithErrors | RunStatus::Interrupted => Urgency::Critical,
+        };
+
+        let mut notification = Notification::new();
+        notification
+            .appname("cli_async")
+            .summary(&format!("cli_async run {}", run_status))
+            .body(&report.message())
+            .urgency(urgency);
+        notification
+    }
+}
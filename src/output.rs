@@ -0,0 +1,987 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use async_compression::tokio::{
+    bufread::{GzipDecoder, ZstdDecoder},
+    write::{GzipEncoder, ZstdEncoder},
+};
+use object_store::{aws::AmazonS3Builder, buffered::BufWriter, ObjectStore};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines},
+    sync::Mutex,
+};
+
+use crate::{
+    output_schema::{OutputSchema, SCHEMA_VERSION},
+    HttpOptions, Journal, LineChecksum, LookupResult, PropertyInfoResult, PropertyRecordPopulated,
+    ReorderBuffer, RunMetadata,
+};
+
+/// Whether information was retrieved for a record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordStatus {
+    /// All information was retrieved.
+    Success,
+    /// Some information was missing.
+    SuccessPartial,
+    /// Information could not be retrieved.
+    Error,
+}
+
+impl fmt::Display for RecordStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Success => write!(f, "success"),
+            Self::SuccessPartial => write!(f, "success_partial"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl FromStr for RecordStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "success" => Ok(Self::Success),
+            "success_partial" => Ok(Self::SuccessPartial),
+            "error" => Ok(Self::Error),
+            _ => Err(format!(
+                "`{}` is not one of `success`, `success_partial`, `error`.",
+                s
+            )),
+        }
+    }
+}
+
+/// A populated record as written to the output file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OutputRecord {
+    /// ID of the record.
+    pub record_id: usize,
+    /// Title number of the record.
+    pub title_number: String,
+    /// Whether information was retrieved for the record.
+    pub status: RecordStatus,
+    /// Why information could not be retrieved, if it failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Fields that were not retrieved, if some information was missing.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_fields: Vec<String>,
+    /// Whether the record's lookup from each `--enrich` source succeeded, by
+    /// source name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sources: BTreeMap<String, bool>,
+}
+
+impl From<PropertyRecordPopulated> for OutputRecord {
+    fn from(property_record_populated: PropertyRecordPopulated) -> Self {
+        let PropertyRecordPopulated {
+            record,
+            info,
+            sources,
+        } = property_record_populated;
+        let (status, error) = match &info {
+            PropertyInfoResult::Success => (RecordStatus::Success, None),
+            PropertyInfoResult::SuccessPartial(..) => (RecordStatus::SuccessPartial, None),
+            PropertyInfoResult::Error(error) => (RecordStatus::Error, Some(error.to_string())),
+        };
+        let missing_fields = info.missing_fields();
+
+        Self {
+            record_id: record.0,
+            title_number: record.title_number(),
+            status,
+            error,
+            missing_fields,
+            sources,
+        }
+    }
+}
+
+/// A line in the output file.
+///
+/// Each run appends a header line, followed by a line per record.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputLine {
+    /// Metadata of the run that wrote the following records.
+    Header(RunMetadata),
+    /// A populated record.
+    Record(OutputRecord),
+}
+
+impl OutputLine {
+    /// Serializes the line with its checksum and a trailing newline.
+    ///
+    /// Header lines are stamped with the current schema version.
+    pub fn to_line(&self) -> serde_json::Result<Vec<u8>> {
+        let mut line = OutputSchema::to_vec(self)?;
+        LineChecksum::append(&mut line);
+        line.push(b'\n');
+        Ok(line)
+    }
+}
+
+/// Why a line in an output file could not be read.
+#[derive(Debug)]
+pub enum OutputLineError {
+    /// The line isn't a valid output line, e.g. because it was torn.
+    Parse(serde_json::Error),
+    /// The line doesn't match its checksum, so it was changed after it was
+    /// written.
+    ChecksumMismatch {
+        /// ID of the record on the line, if it could still be read.
+        record_id: Option<usize>,
+    },
+    /// The line is the header of a run written by a newer version of
+    /// `cli_async`, whose lines can't be read.
+    SchemaUnsupported {
+        /// Schema version of the run.
+        schema_version: u32,
+    },
+}
+
+impl fmt::Display for OutputLineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(e) => e.fmt(f),
+            Self::ChecksumMismatch { .. } => write!(
+                f,
+                "Line does not match its checksum, so it was changed after it was written."
+            ),
+            Self::SchemaUnsupported { schema_version } => write!(
+                f,
+                "Run was written with output schema version {}, but this version of \
+                 `cli_async` reads up to version {}. Upgrade `cli_async` to read it.",
+                schema_version, SCHEMA_VERSION
+            ),
+        }
+    }
+}
+
+/// Lines in output files that were torn or changed after they were written,
+/// found by [`OutputCorruption::find`].
+#[derive(Clone, Debug, Default)]
+pub struct OutputCorruption {
+    /// Records on lines that don't match their checksum, and that aren't on
+    /// any valid line, e.g. from a run that resumed after the corruption.
+    pub record_ids: BTreeSet<usize>,
+    /// Number of corrupt lines, including those whose record can't be read.
+    pub line_count: usize,
+}
+
+impl OutputCorruption {
+    /// Reads the output files, e.g. the shards of `--output`, and returns
+    /// which of their lines are corrupt.
+    ///
+    /// Missing files are treated as empty.
+    pub async fn find(paths: &[PathBuf]) -> io::Result<Self> {
+        let mut output_corruption = Self::default();
+        let mut records_valid = HashSet::new();
+        for path in paths {
+            let mut output_lines = match OutputLineReader::open(path).await {
+                Ok(output_lines) => output_lines,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            while let Some(output_line) = output_lines.try_next_line().await? {
+                match output_line {
+                    Ok(OutputLine::Header(_)) => {}
+                    Ok(OutputLine::Record(output_record)) => {
+                        records_valid.insert(output_record.record_id);
+                    }
+                    Err(e) => {
+                        output_corruption.line_count += 1;
+                        if let OutputLineError::ChecksumMismatch {
+                            record_id: Some(record_id),
+                        } = e
+                        {
+                            output_corruption.record_ids.insert(record_id);
+                        }
+                    }
+                }
+            }
+        }
+        output_corruption
+            .record_ids
+            .retain(|record_id| !records_valid.contains(record_id));
+
+        Ok(output_corruption)
+    }
+}
+
+/// Reads the lines of an output file, e.g. to merge shards or to print
+/// statistics.
+pub struct OutputLineReader {
+    path: PathBuf,
+    lines: Lines<BufReader<Box<dyn AsyncRead + Send + Unpin>>>,
+    /// Number of the line last read, starting from 1.
+    line_number: usize,
+    /// Migrates lines written by earlier versions.
+    output_schema: OutputSchema,
+}
+
+impl OutputLineReader {
+    /// Opens an output file, decompressing it if it ends in `.gz` or `.zst`.
+    pub async fn open(path: &Path) -> io::Result<Self> {
+        let file = BufReader::new(File::open(path).await?);
+        let reader: Box<dyn AsyncRead + Send + Unpin> = match Compression::from_path(path) {
+            Some(compression) => compression.decoder(file),
+            None => Box::new(file),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            lines: BufReader::new(reader).lines(),
+            line_number: 0,
+            output_schema: OutputSchema::default(),
+        })
+    }
+
+    /// Returns the next line, skipping blank lines, or `None` at the end of
+    /// the file.
+    pub async fn next_line(&mut self) -> io::Result<Option<OutputLine>> {
+        match self.try_next_line().await? {
+            Some(Ok(output_line)) => Ok(Some(output_line)),
+            Some(Err(e)) => Err(self.invalid_data(e)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the next line, skipping blank lines, or `None` at the end of
+    /// the file.
+    ///
+    /// Unlike [`OutputLineReader::next_line`], a line that can't be parsed or
+    /// doesn't match its checksum is returned as an error without stopping the
+    /// reader, so the lines after it can still be read. Runs written by newer
+    /// versions stop the reader, as their lines can't be read.
+    pub async fn try_next_line(
+        &mut self,
+    ) -> io::Result<Option<Result<OutputLine, OutputLineError>>> {
+        while let Some(line) = self.lines.next_line().await? {
+            self.line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let output_line = match self.output_schema.parse(&line) {
+                Err(e @ OutputLineError::SchemaUnsupported { .. }) => {
+                    return Err(self.invalid_data(e))
+                }
+                output_line => output_line,
+            };
+            if LineChecksum::matches(&line) == Some(false) {
+                let record_id = match output_line {
+                    Ok(OutputLine::Record(output_record)) => Some(output_record.record_id),
+                    Ok(OutputLine::Header(_)) | Err(_) => None,
+                };
+                return Ok(Some(Err(OutputLineError::ChecksumMismatch { record_id })));
+            }
+            return Ok(Some(output_line));
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the number of the line last read, starting from 1.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// Returns an error about the line last read, with its path and line
+    /// number.
+    pub fn invalid_data(&self, message: impl fmt::Display) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}:{}: {}", self.path.display(), self.line_number, message),
+        )
+    }
+}
+
+/// Compression applied to the output file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    /// gzip, conventionally with the `.gz` extension.
+    Gzip,
+    /// Zstandard, conventionally with the `.zst` extension.
+    Zstd,
+}
+
+impl Compression {
+    /// Returns the compression conventionally used for a file with this
+    /// path's extension, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gz" => Some(Self::Gzip),
+            "zst" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Returns the extension conventionally used for this compression.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+
+    /// Wraps a writer so that what is written to it is compressed.
+    ///
+    /// Compressed streams appended to an existing file are concatenated, which
+    /// both formats support, so each run may append to the same file.
+    pub fn encoder<W>(self, writer: W) -> Box<dyn AsyncWrite + Send + Unpin>
+    where
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        match self {
+            Self::Gzip => Box::new(GzipEncoder::new(writer)),
+            Self::Zstd => Box::new(ZstdEncoder::new(writer)),
+        }
+    }
+
+    /// Wraps a reader so that what is read from it is decompressed.
+    pub fn decoder<R>(self, reader: R) -> Box<dyn AsyncRead + Send + Unpin>
+    where
+        R: AsyncBufRead + Send + Unpin + 'static,
+    {
+        match self {
+            Self::Gzip => {
+                let mut decoder = GzipDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::new(decoder)
+            }
+            Self::Zstd => {
+                let mut decoder = ZstdDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::new(decoder)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gzip => write!(f, "gzip"),
+            Self::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            _ => Err(format!("`{}` is not one of `gzip`, `zstd`.", s)),
+        }
+    }
+}
+
+/// Format of the output file, chosen with `--output-format`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// JSON lines, appended to by each run after a header line.
+    #[default]
+    Jsonl,
+    /// Parquet, replaced by each run, for loading into analytics systems.
+    Parquet,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jsonl => write!(f, "jsonl"),
+            Self::Parquet => write!(f, "parquet"),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(Self::Jsonl),
+            "parquet" => Ok(Self::Parquet),
+            _ => Err(format!("`{}` is not one of `jsonl`, `parquet`.", s)),
+        }
+    }
+}
+
+/// How far batches of output lines are pushed towards the disk before the
+/// next batch is started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// Batches are handed to the writer, and only flushed when the run ends.
+    None,
+    /// Batches are flushed to the operating system.
+    #[default]
+    Flush,
+    /// Batches are flushed and `fsync`ed, so they survive a system crash.
+    ///
+    /// Objects uploaded to S3 are only flushed.
+    Fsync,
+}
+
+impl fmt::Display for Durability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Flush => write!(f, "flush"),
+            Self::Fsync => write!(f, "fsync"),
+        }
+    }
+}
+
+impl FromStr for Durability {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "flush" => Ok(Self::Flush),
+            "fsync" => Ok(Self::Fsync),
+            _ => Err(format!("`{}` is not one of `none`, `flush`, `fsync`.", s)),
+        }
+    }
+}
+
+/// Bytes written to the output file in a run.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct OutputStats {
+    /// Compression applied to the output, if any.
+    pub compression: Option<Compression>,
+    /// Bytes of JSON lines written, before compression, or 0 for Parquet
+    /// output.
+    pub uncompressed_bytes: u64,
+    /// Bytes added to the output files.
+    pub written_bytes: u64,
+}
+
+impl OutputStats {
+    /// Returns how many times smaller the written bytes are than the JSON
+    /// lines, or `None` if nothing was written.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        if self.written_bytes == 0 {
+            None
+        } else {
+            Some(self.uncompressed_bytes as f64 / self.written_bytes as f64)
+        }
+    }
+}
+
+/// Where a shard's lines end up.
+enum ShardTarget {
+    /// A local file, and how long it was before this run.
+    File {
+        /// Handle to the file beneath the shard's writer, used to `fsync` it.
+        file: File,
+        len_before: u64,
+    },
+    /// An object uploaded in parts as lines are written.
+    Object {
+        store: Arc<dyn ObjectStore>,
+        location: object_store::path::Path,
+    },
+}
+
+impl ShardTarget {
+    /// Returns the number of bytes added to the file at `path`, or the object,
+    /// in this run.
+    async fn written_bytes(&self, path: &Path) -> io::Result<u64> {
+        match self {
+            Self::File { len_before, .. } => {
+                let len = fs::metadata(path).await?.len();
+                Ok(len.saturating_sub(*len_before))
+            }
+            Self::Object { store, location } => store
+                .head(location)
+                .await
+                .map(|object_meta| object_meta.size as u64)
+                .map_err(io::Error::other),
+        }
+    }
+}
+
+/// An output file or object.
+struct Shard {
+    /// Path or URL of the output, for messages.
+    path: PathBuf,
+    target: ShardTarget,
+    /// `None` once the output is [finished].
+    ///
+    /// [finished]: OutputWriter::finish
+    writer: Option<Box<dyn AsyncWrite + Send + Unpin>>,
+    /// Lines not yet written to the file.
+    batch: Vec<u8>,
+    /// Number of lines in `batch`.
+    batch_lines: usize,
+    /// IDs of the records in `batch`.
+    batch_record_ids: Vec<usize>,
+    /// IDs of records written to `writer`, but not yet committed to the
+    /// journal.
+    uncommitted_record_ids: Vec<usize>,
+}
+
+impl Shard {
+    /// Writes the batched lines to the file.
+    ///
+    /// The batch's records are claimed in the journal beforehand, and
+    /// committed afterwards, unless nothing is flushed until the end.
+    async fn flush(&mut self, durability: Durability, journal: Option<&Journal>) -> io::Result<()> {
+        if self.batch_lines == 0 {
+            return Ok(());
+        }
+        if let Some(writer) = self.writer.as_mut() {
+            if let Some(journal) = journal {
+                journal.claim(&self.batch_record_ids).await?;
+            }
+            writer.write_all(&self.batch).await?;
+            match durability {
+                Durability::None => {}
+                Durability::Flush => writer.flush().await?,
+                Durability::Fsync => {
+                    writer.flush().await?;
+                    if let ShardTarget::File { file, .. } = &self.target {
+                        file.sync_data().await?;
+                    }
+                }
+            }
+            self.uncommitted_record_ids
+                .append(&mut self.batch_record_ids);
+            if durability != Durability::None {
+                self.commit(journal).await?;
+            }
+        }
+        self.batch.clear();
+        self.batch_lines = 0;
+        self.batch_record_ids.clear();
+
+        Ok(())
+    }
+
+    /// Commits records written to the file in the journal.
+    async fn commit(&mut self, journal: Option<&Journal>) -> io::Result<()> {
+        if let Some(journal) = journal {
+            journal.commit(&self.uncommitted_record_ids).await?;
+        }
+        self.uncommitted_record_ids.clear();
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shard")
+            .field("path", &self.path)
+            .field("finished", &self.writer.is_none())
+            .field("batch_lines", &self.batch_lines)
+            .finish()
+    }
+}
+
+/// Writes populated records to the output file as JSON lines.
+///
+/// Records are written as they complete, so this is shared between the
+/// concurrent output tasks. When [`ordered`], records are instead written in
+/// input order.
+///
+/// Records may be spread across shards, each with its own lock, so that
+/// concurrent writes don't wait on each other.
+///
+/// Outputs given as `s3://bucket/prefix/` URLs are uploaded as objects
+/// instead, in multipart chunks as lines are written, so no local disk is
+/// needed. See [`object_url`].
+///
+/// Lines are written to each file in batches of [`flush_every`] lines, and
+/// the output must be [`finish`]ed so that the last batch is written and
+/// compressed streams are complete.
+///
+/// [`flush_every`]: Self::flush_every
+/// [`finish`]: Self::finish
+/// [`object_url`]: Self::object_url
+/// [`ordered`]: Self::ordered
+#[derive(Debug)]
+pub struct OutputWriter {
+    shards: Vec<Mutex<Shard>>,
+    compression: Option<Compression>,
+    uncompressed_bytes: AtomicU64,
+    /// Number of lines batched before they are written to a file.
+    flush_every: usize,
+    /// How far each batch is pushed towards the disk.
+    durability: Durability,
+    /// Log of which records were claimed and committed, if any.
+    journal: Option<Journal>,
+    /// Held while releasing records from the reorder buffer, so they are
+    /// written in the order they are released.
+    reorder_buffer: Option<(ReorderBuffer<PropertyRecordPopulated>, Mutex<()>)>,
+}
+
+impl OutputWriter {
+    /// Opens `shard_count` output files for appending, and writes the run
+    /// header to each of them.
+    ///
+    /// See [`shard_paths`] for the file names, and [`object_url`] for
+    /// outputs uploaded to S3.
+    ///
+    /// [`object_url`]: Self::object_url
+    /// [`shard_paths`]: Self::shard_paths
+    pub async fn open(
+        path: &Path,
+        shard_count: usize,
+        compression: Option<Compression>,
+        run_metadata: &RunMetadata,
+        http_options: &HttpOptions,
+    ) -> io::Result<Self> {
+        let mut shards = Vec::with_capacity(shard_count);
+        match Self::object_url(path) {
+            Some(url) => {
+                let (store, key) = Self::object_store(url, http_options)?;
+                let bucket_url = &url[..url.len() - key.len()];
+                let key = if key.is_empty() || key.ends_with('/') {
+                    let extension = match compression {
+                        Some(compression) => format!(".{}", compression.extension()),
+                        None => String::new(),
+                    };
+                    format!("{}{}.jsonl{}", key, run_metadata.run_id, extension)
+                } else {
+                    key
+                };
+                for key in Self::shard_paths(Path::new(&key), shard_count) {
+                    let key = key.to_string_lossy();
+                    let location = object_store::path::Path::from(key.as_ref());
+                    let writer = BufWriter::new(Arc::clone(&store), location.clone());
+                    shards.push(Shard {
+                        path: PathBuf::from(format!("{}{}", bucket_url, key)),
+                        target: ShardTarget::Object {
+                            store: Arc::clone(&store),
+                            location,
+                        },
+                        writer: Some(Self::encoder(compression, writer)),
+                        batch: Vec::new(),
+                        batch_lines: 0,
+                        batch_record_ids: Vec::new(),
+                        uncommitted_record_ids: Vec::new(),
+                    });
+                }
+            }
+            None => {
+                for path in Self::shard_paths(path, shard_count) {
+                    let file = OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&path)
+                        .await?;
+                    let len_before = file.metadata().await?.len();
+                    let sync_file = file.try_clone().await?;
+                    shards.push(Shard {
+                        path,
+                        target: ShardTarget::File {
+                            file: sync_file,
+                            len_before,
+                        },
+                        writer: Some(Self::encoder(compression, file)),
+                        batch: Vec::new(),
+                        batch_lines: 0,
+                        batch_record_ids: Vec::new(),
+                        uncommitted_record_ids: Vec::new(),
+                    });
+                }
+            }
+        }
+        let output_writer = Self {
+            shards: shards.into_iter().map(Mutex::new).collect(),
+            compression,
+            uncompressed_bytes: AtomicU64::new(0),
+            flush_every: 1,
+            durability: Durability::default(),
+            journal: None,
+            reorder_buffer: None,
+        };
+        let header = OutputLine::Header(run_metadata.clone());
+        for shard in 0..output_writer.shards.len() {
+            output_writer.write_line(shard, None, &header).await?;
+        }
+
+        Ok(output_writer)
+    }
+
+    /// Returns the output's URL if it is an `s3://` URL, rather than a local
+    /// path.
+    ///
+    /// A URL ending in `/`, e.g. `s3://bucket/prefix/`, is a prefix, and each
+    /// run is uploaded as `prefix/<run_id>.jsonl`, with the compression's
+    /// extension if any. Otherwise the URL is the object's key, which is
+    /// replaced by each run, as objects can't be appended to.
+    ///
+    /// Credentials and the region are read from the usual `AWS_*` environment
+    /// variables.
+    pub fn object_url(path: &Path) -> Option<&str> {
+        path.to_str().filter(|path| path.starts_with("s3://"))
+    }
+
+    /// Checks that the objects under an `s3://` output URL can be listed.
+    pub async fn check_object_store(url: &str, http_options: &HttpOptions) -> io::Result<()> {
+        let (store, key) = Self::object_store(url, http_options)?;
+        let prefix = object_store::path::Path::from(key.as_str());
+        store
+            .list_with_delimiter(Some(&prefix))
+            .await
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+
+    /// Returns the store for an `s3://` URL's bucket, and the key within it.
+    fn object_store(
+        url: &str,
+        http_options: &HttpOptions,
+    ) -> io::Result<(Arc<dyn ObjectStore>, String)> {
+        let key = url
+            .strip_prefix("s3://")
+            .and_then(|bucket_and_key| bucket_and_key.split_once('/'))
+            .map(|(_, key)| String::from(key))
+            .unwrap_or_default();
+        let store = AmazonS3Builder::from_env()
+            .with_url(url)
+            .with_client_options(http_options.client_options())
+            .build()
+            .map_err(io::Error::other)?;
+
+        Ok((Arc::new(store), key))
+    }
+
+    /// Wraps the writer in the compression's encoder, if any.
+    fn encoder<W>(compression: Option<Compression>, writer: W) -> Box<dyn AsyncWrite + Send + Unpin>
+    where
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        match compression {
+            Some(compression) => compression.encoder(writer),
+            None => Box::new(writer),
+        }
+    }
+
+    /// Returns the paths of the output files for `path`.
+    ///
+    /// With more than one shard, the shard number is inserted before the
+    /// extension, e.g. `out.000.jsonl` to `out.015.jsonl` for 16 shards.
+    pub fn shard_paths(path: &Path, shard_count: usize) -> Vec<PathBuf> {
+        if shard_count <= 1 {
+            return vec![path.to_path_buf()];
+        }
+
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        (0..shard_count)
+            .map(|shard| {
+                let file_name = match path.extension() {
+                    Some(extension) => {
+                        format!("{}.{:03}.{}", stem, shard, extension.to_string_lossy())
+                    }
+                    None => format!("{}.{:03}", stem, shard),
+                };
+                path.with_file_name(file_name)
+            })
+            .collect()
+    }
+
+    /// Writes records in input order, buffering at most `capacity` records
+    /// that complete ahead of an earlier one.
+    ///
+    /// Records still buffered when the run is interrupted are not written, so
+    /// the output is always a prefix of the input.
+    pub fn ordered(mut self, capacity: usize) -> Self {
+        self.reorder_buffer = Some((ReorderBuffer::new(capacity), Mutex::new(())));
+        self
+    }
+
+    /// Batches this many lines before writing them to a file, instead of
+    /// writing each line as it comes.
+    ///
+    /// Use [`flush`] to write batches that aren't full yet.
+    ///
+    /// [`flush`]: Self::flush
+    pub fn flush_every(mut self, flush_every: usize) -> Self {
+        self.flush_every = flush_every.max(1);
+        self
+    }
+
+    /// Sets how far each batch is pushed towards the disk.
+    ///
+    /// Whatever the durability, the output is flushed when it is
+    /// [`finish`]ed, and `fsync`ed too with [`Durability::Fsync`].
+    ///
+    /// [`finish`]: Self::finish
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Claims and commits records in a journal as they are written, so that
+    /// torn writes can be detected after a crash.
+    pub fn journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Waits until the record with this sequence number can be buffered.
+    ///
+    /// This returns immediately unless the writer is [`ordered`].
+    ///
+    /// [`ordered`]: Self::ordered
+    pub async fn reserve(&self, sequence: usize) {
+        if let Some((reorder_buffer, _)) = self.reorder_buffer.as_ref() {
+            reorder_buffer.reserve(sequence).await;
+        }
+    }
+
+    /// Writes a populated record to the output file.
+    ///
+    /// `sequence` is the record's position among the records processed in
+    /// this run, which is used to restore input order.
+    pub async fn write_record(
+        &self,
+        sequence: usize,
+        property_record_populated: PropertyRecordPopulated,
+    ) -> io::Result<()> {
+        match self.reorder_buffer.as_ref() {
+            Some((reorder_buffer, release_lock)) => {
+                let _release_guard = release_lock.lock().await;
+                let released = reorder_buffer.push(sequence, property_record_populated);
+                self.write_released(released).await;
+                Ok(())
+            }
+            None => self.write_record_line(property_record_populated).await,
+        }
+    }
+
+    /// Gives up the place of a record that won't be written, e.g. because it
+    /// failed before reaching the output, and writes the records after it
+    /// that were waiting for it.
+    ///
+    /// This does nothing unless the writer is [`ordered`].
+    ///
+    /// [`ordered`]: Self::ordered
+    pub async fn skip(&self, sequence: usize) {
+        if let Some((reorder_buffer, release_lock)) = self.reorder_buffer.as_ref() {
+            let _release_guard = release_lock.lock().await;
+            let released = reorder_buffer.skip(sequence);
+            self.write_released(released).await;
+        }
+    }
+
+    /// Writes records released from the reorder buffer, in order.
+    ///
+    /// Released records may not include the one that released them, so
+    /// failures are logged against the record that failed to be written.
+    async fn write_released(&self, released: Vec<PropertyRecordPopulated>) {
+        for property_record_populated in released {
+            let record_id = property_record_populated.record.0;
+            if let Err(e) = self.write_record_line(property_record_populated).await {
+                tracing::error!(record_id, "Failed to write record to output file: {}", e);
+            }
+        }
+    }
+
+    /// Writes the lines batched so far to the output files.
+    pub async fn flush(&self) -> io::Result<()> {
+        for shard in self.shards.iter() {
+            shard
+                .lock()
+                .await
+                .flush(self.durability, self.journal.as_ref())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the last batch, completes the compressed streams, and flushes
+    /// the output files, then returns the number of bytes written.
+    ///
+    /// Records written afterwards, e.g. by tasks still running after an
+    /// interruption, are discarded.
+    pub async fn finish(&self) -> io::Result<OutputStats> {
+        let mut written_bytes = 0;
+        for shard in self.shards.iter() {
+            let mut shard = shard.lock().await;
+            shard.flush(self.durability, self.journal.as_ref()).await?;
+            if let Some(mut writer) = shard.writer.take() {
+                writer.shutdown().await?;
+                if let (Durability::Fsync, ShardTarget::File { file, .. }) =
+                    (self.durability, &shard.target)
+                {
+                    file.sync_all().await?;
+                }
+                shard.commit(self.journal.as_ref()).await?;
+            }
+            written_bytes += shard.target.written_bytes(&shard.path).await?;
+        }
+        if let Some(journal) = self.journal.as_ref() {
+            journal.finish().await?;
+        }
+
+        Ok(OutputStats {
+            compression: self.compression,
+            uncompressed_bytes: self.uncompressed_bytes.load(Ordering::SeqCst),
+            written_bytes,
+        })
+    }
+
+    /// Writes a record to the shard for its ID.
+    async fn write_record_line(
+        &self,
+        property_record_populated: PropertyRecordPopulated,
+    ) -> io::Result<()> {
+        let record_id = property_record_populated.record.0;
+        self.write_line(
+            record_id % self.shards.len(),
+            Some(record_id),
+            &OutputLine::Record(OutputRecord::from(property_record_populated)),
+        )
+        .await
+    }
+
+    async fn write_line(
+        &self,
+        shard: usize,
+        record_id: Option<usize>,
+        output_line: &OutputLine,
+    ) -> io::Result<()> {
+        let line = output_line.to_line()?;
+
+        let mut shard = self.shards[shard].lock().await;
+        if shard.writer.is_none() {
+            tracing::debug!("Output already finished, discarding line.");
+            return Ok(());
+        }
+        shard.batch.extend_from_slice(&line);
+        shard.batch_lines += 1;
+        shard.batch_record_ids.extend(record_id);
+        self.uncompressed_bytes
+            .fetch_add(line.len() as u64, Ordering::SeqCst);
+
+        if shard.batch_lines >= self.flush_every {
+            shard.flush(self.durability, self.journal.as_ref()).await
+        } else {
+            Ok(())
+        }
+    }
+}
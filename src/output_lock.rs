@@ -0,0 +1,122 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+};
+
+/// Lock file that keeps two runs from writing to the same output file.
+///
+/// The lock file holds the ID of the process that owns it, and is removed
+/// when this is dropped.
+#[derive(Debug)]
+pub struct OutputLock {
+    path: PathBuf,
+}
+
+impl OutputLock {
+    /// Returns the path of the lock file for an output file, e.g.
+    /// `records.jsonl.lock`.
+    pub fn path(output: &Path) -> PathBuf {
+        let mut file_name = output.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".lock");
+        output.with_file_name(file_name)
+    }
+
+    /// Returns the ID of the running process that holds the lock for an
+    /// output file, if any.
+    ///
+    /// Lock files left behind by processes that are no longer running, or
+    /// without a process ID, are not held.
+    pub fn holder(output: &Path) -> io::Result<Option<u32>> {
+        let contents = match fs::read_to_string(Self::path(output)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        match contents.trim().parse::<u32>() {
+            Ok(pid) if Self::process_running(pid) => Ok(Some(pid)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Creates the lock file for an output file.
+    ///
+    /// Fails if a running process holds the lock, unless `force` is true.
+    pub fn acquire(output: &Path, force: bool) -> io::Result<Self> {
+        let path = Self::path(output);
+        match Self::holder(output)? {
+            Some(pid) if !force => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "`{}` is locked by process {} (see `{}`). Use `--force` to start anyway.",
+                        output.display(),
+                        pid,
+                        path.display()
+                    ),
+                ));
+            }
+            Some(pid) => {
+                tracing::warn!(pid, "Overriding lock on `{}`.", output.display());
+                fs::remove_file(&path)?;
+            }
+            None if path.exists() => {
+                tracing::debug!("Removing stale lock file `{}`.", path.display());
+                fs::remove_file(&path)?;
+            }
+            None => {}
+        }
+
+        // `create_new` fails if another run took the lock in the meantime.
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        writeln!(file, "{}", std::process::id())?;
+
+        Ok(Self { path })
+    }
+
+    #[cfg(unix)]
+    fn process_running(pid: u32) -> bool {
+        // Signal 0 only checks whether the process exists.
+        let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+        result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+
+    #[cfg(windows)]
+    fn process_running(pid: u32) -> bool {
+        use windows_sys::Win32::{
+            Foundation::{CloseHandle, GetLastError, ERROR_ACCESS_DENIED, STILL_ACTIVE},
+            System::Threading::{
+                GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+            },
+        };
+
+        let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+        if process.is_null() {
+            // The process exists, but belongs to another user.
+            return unsafe { GetLastError() } == ERROR_ACCESS_DENIED;
+        }
+        // An exited process can still be opened while another handle to it is
+        // open, so check that it hasn't exited.
+        let mut exit_code = 0;
+        let queried = unsafe { GetExitCodeProcess(process, &mut exit_code) } != 0;
+        unsafe { CloseHandle(process) };
+        !queried || exit_code == STILL_ACTIVE as u32
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn process_running(_pid: u32) -> bool {
+        // Without a way to check, treat the lock as left behind, so it
+        // doesn't stop every later run.
+        false
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,102 @@
+use std::{collections::HashMap, io, path::PathBuf};
+
+use clap::Args;
+use tokio::{
+    fs::File,
+    io::{AsyncWrite, AsyncWriteExt},
+};
+
+use crate::{
+    output::{Compression, OutputLine, OutputLineReader, OutputRecord},
+    RunMetadata,
+};
+
+/// Merges output shards written with `--output-shards`.
+#[derive(Debug, Args)]
+pub struct MergeOpt {
+    /// Shards to merge, e.g. `out.*.jsonl`.
+    ///
+    /// Shards ending in `.gz` or `.zst` are decompressed.
+    #[arg(required = true)]
+    shards: Vec<PathBuf>,
+    /// File to write the merged records to.
+    ///
+    /// The file is compressed if it ends in `.gz` or `.zst`.
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+/// Concatenates output shards into a single output file.
+pub struct OutputMerge;
+
+impl OutputMerge {
+    /// Merges the shards into the output file, and writes a summary to stderr.
+    ///
+    /// Each run is written as its header followed by its records, sorted by
+    /// record ID. Runs are sorted by when they started.
+    pub async fn run(merge_opt: &MergeOpt) -> io::Result<()> {
+        let mut runs = Vec::<(RunMetadata, Vec<OutputRecord>)>::new();
+        let mut run_indices = HashMap::<String, usize>::new();
+
+        for shard in merge_opt.shards.iter() {
+            let mut output_lines = OutputLineReader::open(shard).await?;
+            let mut run_index = None;
+            while let Some(output_line) = output_lines.next_line().await? {
+                match output_line {
+                    OutputLine::Header(run_metadata) => {
+                        let next_run_index = runs.len();
+                        let header_run_index = *run_indices
+                            .entry(run_metadata.run_id.clone())
+                            .or_insert(next_run_index);
+                        if header_run_index == next_run_index {
+                            runs.push((run_metadata, Vec::new()));
+                        }
+                        run_index = Some(header_run_index);
+                    }
+                    OutputLine::Record(output_record) => {
+                        let record_run_index = run_index.ok_or_else(|| {
+                            output_lines.invalid_data("Record found before a run header.")
+                        })?;
+                        runs[record_run_index].1.push(output_record);
+                    }
+                }
+            }
+        }
+
+        runs.sort_by(|(run_a, _), (run_b, _)| run_a.started_at.cmp(&run_b.started_at));
+        runs.iter_mut()
+            .for_each(|(_, records)| records.sort_by_key(|record| record.record_id));
+
+        let file = File::create(&merge_opt.output).await?;
+        let mut output: Box<dyn AsyncWrite + Send + Unpin> =
+            match Compression::from_path(&merge_opt.output) {
+                Some(compression) => compression.encoder(file),
+                None => Box::new(tokio::io::BufWriter::new(file)),
+            };
+        let mut record_count = 0;
+        for (run_metadata, records) in runs {
+            Self::write_line(&mut output, &OutputLine::Header(run_metadata)).await?;
+            record_count += records.len();
+            for record in records {
+                Self::write_line(&mut output, &OutputLine::Record(record)).await?;
+            }
+        }
+        output.shutdown().await?;
+
+        eprintln!(
+            "Merged {} records from {} shards into `{}`.",
+            record_count,
+            merge_opt.shards.len(),
+            merge_opt.output.display()
+        );
+
+        Ok(())
+    }
+
+    async fn write_line(
+        output: &mut Box<dyn AsyncWrite + Send + Unpin>,
+        output_line: &OutputLine,
+    ) -> io::Result<()> {
+        output.write_all(&output_line.to_line()?).await
+    }
+}
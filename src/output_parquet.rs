@@ -0,0 +1,262 @@
+use std::{collections::BTreeMap, fmt, fs::File, io, path::Path, sync::Arc};
+
+use arrow_array::{
+    builder::{BooleanBuilder, ListBuilder, MapBuilder, StringBuilder, UInt64Builder},
+    cast::AsArray,
+    types::UInt64Type,
+    Array, ArrayRef, RecordBatch,
+};
+use arrow_schema::{Field, Schema};
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+    basic::Compression,
+    file::properties::WriterProperties,
+    format::KeyValue,
+};
+
+use crate::{output::OutputRecord, output_schema::SCHEMA_VERSION, RunMetadata};
+
+/// Key of the file metadata holding the runs that wrote the records, as a
+/// JSON array of [`RunMetadata`].
+const RUNS_KEY: &str = "cli_async.runs";
+/// Key of the file metadata holding the schema version the file was written
+/// with.
+const SCHEMA_VERSION_KEY: &str = "cli_async.schema_version";
+
+/// Writes records to a Parquet file, for loading into analytics systems.
+///
+/// There is a column for each field of [`OutputRecord`], and a `run_id`
+/// column. The runs' metadata, which JSON lines files write as header lines,
+/// is stored in the file's key-value metadata.
+///
+/// Parquet files can't be appended to, so each run replaces the file.
+pub struct ParquetWriter {
+    writer: ArrowWriter<File>,
+    /// IDs of the runs, indexed by the run index given to [`write`].
+    ///
+    /// [`write`]: Self::write
+    run_ids: Vec<String>,
+    /// Number of rows in each row group.
+    row_group_size: usize,
+    /// Rows buffered for the next row group, with their run index.
+    rows: Vec<(usize, OutputRecord)>,
+}
+
+impl ParquetWriter {
+    /// Creates the Parquet file, replacing it if it exists.
+    pub fn create(path: &Path, runs: &[RunMetadata], row_group_size: usize) -> io::Result<Self> {
+        let file = File::create(path)?;
+        let writer_properties = WriterProperties::builder()
+            .set_compression(Compression::GZIP(Default::default()))
+            .set_max_row_group_size(row_group_size)
+            .build();
+        let schema = Self::record_batch(&[], &[])?.schema();
+        let mut writer = ArrowWriter::try_new(file, schema, Some(writer_properties))?;
+        writer.append_key_value_metadata(KeyValue::new(
+            SCHEMA_VERSION_KEY.to_string(),
+            SCHEMA_VERSION.to_string(),
+        ));
+        writer.append_key_value_metadata(KeyValue::new(
+            RUNS_KEY.to_string(),
+            serde_json::to_string(runs)?,
+        ));
+
+        Ok(Self {
+            writer,
+            run_ids: runs.iter().map(|run| run.run_id.clone()).collect(),
+            row_group_size,
+            rows: Vec::with_capacity(row_group_size),
+        })
+    }
+
+    /// Buffers a record of the run at `run_index`, and writes the buffered
+    /// records as a row group once there are enough of them.
+    pub fn write(&mut self, run_index: usize, output_record: OutputRecord) -> io::Result<()> {
+        self.rows.push((run_index, output_record));
+        if self.rows.len() >= self.row_group_size {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the last row group and the file's footer, then returns the
+    /// number of bytes written.
+    pub fn finish(mut self) -> io::Result<u64> {
+        self.flush()?;
+        self.writer.finish()?;
+        Ok(self.writer.bytes_written() as u64)
+    }
+
+    /// Writes the buffered records as a row group.
+    fn flush(&mut self) -> io::Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+
+        let record_batch = Self::record_batch(&self.run_ids, &self.rows)?;
+        self.rows.clear();
+        self.writer.write(&record_batch)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn record_batch(run_ids: &[String], rows: &[(usize, OutputRecord)]) -> io::Result<RecordBatch> {
+        let mut run_id = StringBuilder::new();
+        let mut record_id = UInt64Builder::new();
+        let mut title_number = StringBuilder::new();
+        let mut status = StringBuilder::new();
+        let mut error = StringBuilder::new();
+        let mut missing_fields = ListBuilder::new(StringBuilder::new());
+        let mut sources = MapBuilder::new(None, StringBuilder::new(), BooleanBuilder::new());
+        for (run_index, output_record) in rows {
+            run_id.append_value(&run_ids[*run_index]);
+            record_id.append_value(output_record.record_id as u64);
+            title_number.append_value(&output_record.title_number);
+            status.append_value(output_record.status.to_string());
+            error.append_option(output_record.error.as_deref());
+            output_record
+                .missing_fields
+                .iter()
+                .for_each(|missing_field| missing_fields.values().append_value(missing_field));
+            missing_fields.append(true);
+            output_record
+                .sources
+                .iter()
+                .for_each(|(source, succeeded)| {
+                    sources.keys().append_value(source);
+                    sources.values().append_value(*succeeded);
+                });
+            sources.append(true).map_err(io::Error::other)?;
+        }
+
+        let columns: Vec<(&str, ArrayRef, bool)> = vec![
+            ("run_id", Arc::new(run_id.finish()), false),
+            ("record_id", Arc::new(record_id.finish()), false),
+            ("title_number", Arc::new(title_number.finish()), false),
+            ("status", Arc::new(status.finish()), false),
+            ("error", Arc::new(error.finish()), true),
+            ("missing_fields", Arc::new(missing_fields.finish()), false),
+            ("sources", Arc::new(sources.finish()), false),
+        ];
+        let schema = Schema::new(
+            columns
+                .iter()
+                .map(|(name, array, nullable)| {
+                    Field::new(*name, array.data_type().clone(), *nullable)
+                })
+                .collect::<Vec<_>>(),
+        );
+        RecordBatch::try_new(
+            Arc::new(schema),
+            columns.into_iter().map(|(_, array, _)| array).collect(),
+        )
+        .map_err(io::Error::other)
+    }
+}
+
+impl fmt::Debug for ParquetWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParquetWriter")
+            .field("run_ids", &self.run_ids)
+            .field("row_group_size", &self.row_group_size)
+            .field("rows", &self.rows.len())
+            .finish()
+    }
+}
+
+/// Reads records from Parquet files written by [`ParquetWriter`].
+pub struct ParquetReader;
+
+impl ParquetReader {
+    /// Reads the records of each run, in the order they were written.
+    pub fn read(path: &Path) -> io::Result<Vec<(RunMetadata, Vec<OutputRecord>)>> {
+        let invalid_data = |message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), message),
+            )
+        };
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+        let metadata_value = |key: &str| {
+            builder
+                .metadata()
+                .file_metadata()
+                .key_value_metadata()
+                .and_then(|key_values| key_values.iter().find(|key_value| key_value.key == key))
+                .and_then(|key_value| key_value.value.as_deref())
+        };
+        let schema_version = metadata_value(SCHEMA_VERSION_KEY)
+            .and_then(|schema_version| schema_version.parse::<u32>().ok())
+            .unwrap_or(SCHEMA_VERSION);
+        if schema_version > SCHEMA_VERSION {
+            return Err(invalid_data(format!(
+                "Written with output schema version {}, but this version of `cli_async` reads \
+                 up to version {}. Upgrade `cli_async` to read it.",
+                schema_version, SCHEMA_VERSION
+            )));
+        }
+        let runs_json = metadata_value(RUNS_KEY)
+            .ok_or_else(|| invalid_data(format!("No `{}` metadata.", RUNS_KEY)))?;
+        let mut runs = serde_json::from_str::<Vec<RunMetadata>>(runs_json)?
+            .into_iter()
+            .map(|run_metadata| (run_metadata, Vec::new()))
+            .collect::<Vec<_>>();
+        let run_indices = runs
+            .iter()
+            .enumerate()
+            .map(|(run_index, (run_metadata, _))| (run_metadata.run_id.clone(), run_index))
+            .collect::<BTreeMap<_, _>>();
+
+        for record_batch in builder.build()? {
+            let record_batch = record_batch.map_err(io::Error::other)?;
+            let column = |name: &str| {
+                record_batch
+                    .column_by_name(name)
+                    .ok_or_else(|| invalid_data(format!("No `{}` column.", name)))
+            };
+            let run_id = column("run_id")?.as_string::<i32>();
+            let record_id = column("record_id")?.as_primitive::<UInt64Type>();
+            let title_number = column("title_number")?.as_string::<i32>();
+            let status = column("status")?.as_string::<i32>();
+            let error = column("error")?.as_string::<i32>();
+            let missing_fields = column("missing_fields")?.as_list::<i32>();
+            let sources = column("sources")?.as_map();
+
+            for row in 0..record_batch.num_rows() {
+                let run_index = *run_indices.get(run_id.value(row)).ok_or_else(|| {
+                    invalid_data(format!("No metadata for run `{}`.", run_id.value(row)))
+                })?;
+                let missing_fields = missing_fields.value(row);
+                let missing_fields = missing_fields
+                    .as_string::<i32>()
+                    .iter()
+                    .flatten()
+                    .map(str::to_string)
+                    .collect();
+                let sources = sources.value(row);
+                let sources = sources
+                    .column(0)
+                    .as_string::<i32>()
+                    .iter()
+                    .zip(sources.column(1).as_boolean().iter())
+                    .filter_map(|(source, succeeded)| Some((source?.to_string(), succeeded?)))
+                    .collect();
+
+                runs[run_index].1.push(OutputRecord {
+                    record_id: record_id.value(row) as usize,
+                    title_number: title_number.value(row).to_string(),
+                    status: status.value(row).parse().map_err(invalid_data)?,
+                    error: Some(error)
+                        .filter(|error| error.is_valid(row))
+                        .map(|error| error.value(row).to_string()),
+                    missing_fields,
+                    sources,
+                });
+            }
+        }
+
+        Ok(runs)
+    }
+}
@@ -0,0 +1,214 @@
+use std::convert::TryFrom;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    output::{OutputLine, OutputLineError},
+    RunMetadata,
+};
+
+/// Version of the output file's schema that this version of `cli_async`
+/// writes, stamped on each run's header line.
+///
+/// Increment this when the shape of output lines changes, and add a migration
+/// to [`MIGRATIONS`] so that files written by earlier versions can still be
+/// read.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Version of output files written before headers were stamped with their
+/// schema version.
+const SCHEMA_VERSION_UNSTAMPED: u32 = 1;
+
+/// Migrates a line's fields from the previous schema version.
+type Migration = fn(&mut Map<String, Value>);
+
+/// Migrations of a line from the version before each one's version.
+///
+/// Lines are migrated through each version after the one their run was
+/// written with, in order.
+const MIGRATIONS: &[(u32, Migration)] = &[(2, OutputSchema::migrate_v2)];
+
+/// Header line of a run, as written to the output file.
+#[derive(Serialize)]
+struct HeaderLine<'a> {
+    #[serde(rename = "type")]
+    line_type: &'static str,
+    schema_version: u32,
+    #[serde(flatten)]
+    run_metadata: &'a RunMetadata,
+}
+
+/// Reads output lines in the current shape, migrating lines written by
+/// earlier versions of `cli_async`.
+///
+/// Each run's lines are migrated from the schema version on its header line.
+#[derive(Clone, Copy, Debug)]
+pub struct OutputSchema {
+    /// Schema version of the run whose lines are being read.
+    schema_version: u32,
+}
+
+impl OutputSchema {
+    /// Serializes a line, stamping header lines with the current schema
+    /// version.
+    pub fn to_vec(output_line: &OutputLine) -> serde_json::Result<Vec<u8>> {
+        match output_line {
+            OutputLine::Header(run_metadata) => serde_json::to_vec(&HeaderLine {
+                line_type: "header",
+                schema_version: SCHEMA_VERSION,
+                run_metadata,
+            }),
+            OutputLine::Record(_) => serde_json::to_vec(output_line),
+        }
+    }
+
+    /// Parses a line, migrating it from the schema version of its run.
+    pub fn parse(&mut self, line: &str) -> Result<OutputLine, OutputLineError> {
+        let mut fields =
+            serde_json::from_str::<Map<String, Value>>(line).map_err(OutputLineError::Parse)?;
+        if fields.get("type").and_then(Value::as_str) == Some("header") {
+            let schema_version = fields
+                .remove("schema_version")
+                .and_then(|schema_version| schema_version.as_u64())
+                .map_or(SCHEMA_VERSION_UNSTAMPED, |schema_version| {
+                    u32::try_from(schema_version).unwrap_or(u32::MAX)
+                });
+            if schema_version > SCHEMA_VERSION {
+                return Err(OutputLineError::SchemaUnsupported { schema_version });
+            }
+            self.schema_version = schema_version;
+        }
+
+        MIGRATIONS
+            .iter()
+            .filter(|(schema_version, _)| *schema_version > self.schema_version)
+            .for_each(|(_, migrate)| migrate(&mut fields));
+
+        serde_json::from_value(Value::Object(fields)).map_err(OutputLineError::Parse)
+    }
+
+    /// Version 2 records list the fields missing from partial results.
+    ///
+    /// Version 1 didn't record which fields were missing, so they are listed
+    /// as `unknown`.
+    fn migrate_v2(fields: &mut Map<String, Value>) {
+        if fields.get("status").and_then(Value::as_str) == Some("success_partial") {
+            fields
+                .entry("missing_fields")
+                .or_insert_with(|| Value::from(vec!["unknown"]));
+        }
+    }
+}
+
+impl Default for OutputSchema {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION_UNSTAMPED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutputSchema, SCHEMA_VERSION};
+    use crate::{
+        output::{OutputLine, OutputLineError, OutputRecord, RecordStatus},
+        RunMetadata,
+    };
+
+    const HEADER_V1: &str = r#"{"type":"header","run_id":"v1","version":"0.0.9","args":[],"started_at":"2022-01-01T00:00:00Z"}"#;
+    const RECORD_PARTIAL: &str =
+        r#"{"type":"record","record_id":1,"title_number":"ABC123/01","status":"success_partial"}"#;
+
+    fn output_record(output_schema: &mut OutputSchema, line: &str) -> OutputRecord {
+        match output_schema.parse(line) {
+            Ok(OutputLine::Record(output_record)) => output_record,
+            output_line => panic!("Expected a record line, but got: {:?}", output_line),
+        }
+    }
+
+    fn header(schema_version: u32) -> String {
+        format!(
+            r#"{{"type":"header","schema_version":{},"run_id":"v{}","version":"0.1.0","args":[],"started_at":"2022-01-01T00:00:00Z"}}"#,
+            schema_version, schema_version
+        )
+    }
+
+    #[test]
+    fn parse_migrates_v1_partial_record_missing_fields() {
+        let mut output_schema = OutputSchema::default();
+        assert!(matches!(
+            output_schema.parse(HEADER_V1),
+            Ok(OutputLine::Header(_))
+        ));
+
+        let output_record = output_record(&mut output_schema, RECORD_PARTIAL);
+
+        assert_eq!(RecordStatus::SuccessPartial, output_record.status);
+        assert_eq!(vec![String::from("unknown")], output_record.missing_fields);
+    }
+
+    #[test]
+    fn parse_keeps_v1_success_record_unchanged() {
+        let mut output_schema = OutputSchema::default();
+        output_schema
+            .parse(HEADER_V1)
+            .expect("Failed to parse header.");
+
+        let output_record = output_record(
+            &mut output_schema,
+            r#"{"type":"record","record_id":1,"title_number":"ABC123/01","status":"success"}"#,
+        );
+
+        assert_eq!(RecordStatus::Success, output_record.status);
+        assert!(output_record.missing_fields.is_empty());
+    }
+
+    #[test]
+    fn parse_migrates_each_run_from_its_header_schema_version() {
+        let mut output_schema = OutputSchema::default();
+        output_schema
+            .parse(&header(2))
+            .expect("Failed to parse header.");
+        let output_record_v2 = output_record(&mut output_schema, RECORD_PARTIAL);
+        output_schema
+            .parse(HEADER_V1)
+            .expect("Failed to parse header.");
+        let output_record_v1 = output_record(&mut output_schema, RECORD_PARTIAL);
+
+        assert!(output_record_v2.missing_fields.is_empty());
+        assert_eq!(
+            vec![String::from("unknown")],
+            output_record_v1.missing_fields
+        );
+    }
+
+    #[test]
+    fn parse_rejects_newer_schema_version() {
+        let mut output_schema = OutputSchema::default();
+
+        let output_line = output_schema.parse(&header(SCHEMA_VERSION + 1));
+
+        assert!(matches!(
+            output_line,
+            Err(OutputLineError::SchemaUnsupported { schema_version })
+                if schema_version == SCHEMA_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn to_vec_stamps_header_with_current_schema_version() {
+        let run_metadata = RunMetadata::new(Vec::new());
+
+        let line = OutputSchema::to_vec(&OutputLine::Header(run_metadata))
+            .expect("Failed to serialize header.");
+        let fields =
+            serde_json::from_slice::<serde_json::Value>(&line).expect("Failed to parse header.");
+
+        assert_eq!(
+            Some(u64::from(SCHEMA_VERSION)),
+            fields["schema_version"].as_u64()
+        );
+    }
+}
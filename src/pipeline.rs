@@ -0,0 +1,571 @@
+use std::{
+    any::Any, collections::BTreeMap, fmt, hash::Hash, panic::AssertUnwindSafe, sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::{FutureExt, Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::Instrument;
+
+use crate::{
+    dependency_order, ChaosEvents, ConcurrencyLimit, EventBus, InFlight, Layer, Metrics,
+    PipelineGraph, RecordProgress, RecordStatus, RunControl, RunEvent, StageKind, StageNode,
+    StageProgress, Throttle, WorkerBar, WorkerProgress,
+};
+
+/// A record that a [`Pipeline`] looks up information for, e.g. a
+/// [`PropertyRecord`].
+///
+/// [`PropertyRecord`]: crate::PropertyRecord
+pub trait Record:
+    Copy + fmt::Debug + Eq + Hash + Serialize + DeserializeOwned + Send + Sync + 'static
+{
+    /// Returns the record's ID, which is unique within the input.
+    fn id(self) -> usize;
+
+    /// Returns how the record is shown to people, e.g. in progress bars and
+    /// the report.
+    fn label(self) -> String;
+}
+
+/// Information looked up for a [`Record`], e.g. a [`PropertyInfoResult`].
+///
+/// [`PropertyInfoResult`]: crate::PropertyInfoResult
+pub trait LookupResult: Clone + fmt::Debug + Send + Sync + 'static {
+    /// Returns whether all, some, or none of the information was retrieved.
+    fn status(&self) -> RecordStatus;
+
+    /// Returns why the information could not be retrieved, if it failed.
+    fn error(&self) -> Option<String>;
+
+    /// Returns the names of the fields that were not retrieved, if some of
+    /// the information was missing.
+    fn missing_fields(&self) -> Vec<String>;
+}
+
+/// Outcome of looking up a record's information.
+#[derive(Clone, Debug)]
+pub struct Lookup<I> {
+    /// Information retrieved for the record.
+    pub info: I,
+    /// Chaos faults encountered while retrieving the information.
+    pub chaos_events: ChaosEvents,
+    /// Number of attempts made to retrieve the information.
+    pub attempts: u32,
+    /// Time taken to retrieve the information, including retries.
+    pub duration: Duration,
+    /// Whether the lookup from each enrichment source succeeded, by source
+    /// name.
+    pub sources: BTreeMap<String, bool>,
+}
+
+/// A record moving through a [`Pipeline`]'s stages, along with what the
+/// stages have produced for it so far.
+#[derive(Clone, Debug)]
+pub struct Work<R, I, O> {
+    /// Position of the record in the input.
+    pub n: usize,
+    /// Position of the record among the records processed in this run.
+    pub sequence: usize,
+    pub record: R,
+    /// Outcome of looking up the record's information, once a stage has.
+    pub lookup: Option<Lookup<I>>,
+    /// Record combined with its information, ready to be written, once a
+    /// stage has combined them.
+    pub output: Option<O>,
+}
+
+impl<R, I, O> Work<R, I, O>
+where
+    R: Record,
+    I: LookupResult,
+{
+    /// Returns the record's progress, if its information has been looked up.
+    pub fn record_progress(&self) -> Option<RecordProgress<R, I>> {
+        self.lookup.as_ref().map(|lookup| RecordProgress {
+            record: self.record,
+            info: lookup.info.clone(),
+            chaos_events: lookup.chaos_events,
+            attempts: lookup.attempts,
+            duration: lookup.duration,
+            sources: lookup.sources.clone(),
+        })
+    }
+}
+
+/// One step of the processing for each record, e.g. authenticating or
+/// retrieving information.
+#[async_trait]
+pub trait Stage<R, I, O>: fmt::Debug + Send + Sync {
+    /// Kind of stage, for timings and progress bars.
+    fn kind(&self) -> StageKind;
+
+    /// Kinds of stage that must process a record before this stage.
+    ///
+    /// The pipeline orders its stages by these dependencies, so a stage can
+    /// be added in any order. Dependencies on stages that aren't in the
+    /// pipeline are ignored.
+    fn depends_on(&self) -> &'static [StageKind] {
+        &[]
+    }
+
+    /// Whether records may pass through this stage concurrently.
+    ///
+    /// Stages before the first concurrent stage process one record at a
+    /// time, in input order.
+    fn concurrent(&self) -> bool {
+        false
+    }
+
+    /// Waits until the record with this sequence number may be processed, e.g.
+    /// so that output can be written in input order.
+    async fn reserve(&self, _sequence: usize) {}
+
+    /// Gives up the reservation of a record that failed before this stage
+    /// processed it, so records after it aren't held waiting for it.
+    async fn skip(&self, _sequence: usize) {}
+
+    /// Processes a record, returning it for the next stage.
+    ///
+    /// An error stops the record from going through the remaining stages.
+    async fn process(&self, work: Work<R, I, O>) -> Result<Work<R, I, O>, String>;
+}
+
+/// A [`Stage`] that can process several records in one call, e.g. with a bulk
+/// lookup API, so a [`BatchLayer`] can group records for it.
+///
+/// [`BatchLayer`]: crate::BatchLayer
+#[async_trait]
+pub trait BatchStage<R, I, O>: Stage<R, I, O> {
+    /// Processes the records together, returning each record's result in the
+    /// same order as `works`.
+    ///
+    /// An error fails every record in the batch.
+    async fn process_batch(
+        &self,
+        works: Vec<Work<R, I, O>>,
+    ) -> Result<Vec<Result<Work<R, I, O>, String>>, String>;
+}
+
+/// A stage of any type, e.g. after it is wrapped in [`Layer`]s.
+pub type BoxStage<R, I, O> = Box<dyn Stage<R, I, O>>;
+
+#[async_trait]
+impl<R, I, O> Stage<R, I, O> for BoxStage<R, I, O>
+where
+    R: Record,
+    I: LookupResult,
+    O: Send + 'static,
+{
+    fn kind(&self) -> StageKind {
+        self.as_ref().kind()
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        self.as_ref().depends_on()
+    }
+
+    fn concurrent(&self) -> bool {
+        self.as_ref().concurrent()
+    }
+
+    async fn reserve(&self, sequence: usize) {
+        self.as_ref().reserve(sequence).await
+    }
+
+    async fn skip(&self, sequence: usize) {
+        self.as_ref().skip(sequence).await
+    }
+
+    async fn process(&self, work: Work<R, I, O>) -> Result<Work<R, I, O>, String> {
+        self.as_ref().process(work).await
+    }
+}
+
+/// Wraps a stage in a layer, for [`PipelineBuilder::layer`].
+type LayerFn<R, I, O> = Box<dyn Fn(BoxStage<R, I, O>) -> BoxStage<R, I, O> + Send + Sync>;
+
+/// Builds a [`Pipeline`] from its stages, which each record passes through in
+/// the order of their dependencies, then in the order they are added.
+pub struct PipelineBuilder<R, I, O> {
+    stages: Vec<BoxStage<R, I, O>>,
+    /// Layers that wrap every stage, outermost first.
+    layers: Vec<LayerFn<R, I, O>>,
+    worker_progress: WorkerProgress,
+    stage_progress: StageProgress,
+    throttle: Option<Arc<Throttle>>,
+    in_flight: Arc<InFlight>,
+}
+
+impl<R, I, O> PipelineBuilder<R, I, O>
+where
+    R: Record,
+    I: LookupResult,
+    O: Send + 'static,
+{
+    /// Adds a stage, which runs after the stages it depends on.
+    pub fn stage(mut self, stage: impl Stage<R, I, O> + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Wraps every stage in a layer, e.g. to time each stage.
+    ///
+    /// Layers added first are outermost, and apply to stages added before or
+    /// after them.
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<BoxStage<R, I, O>> + Send + Sync + 'static,
+        L::Stage: Stage<R, I, O> + 'static,
+    {
+        self.layers
+            .push(Box::new(move |stage| Box::new(layer.layer(stage))));
+        self
+    }
+
+    /// Shows each record's current stage while it is processed.
+    pub fn worker_progress(mut self, worker_progress: WorkerProgress) -> Self {
+        self.worker_progress = worker_progress;
+        self
+    }
+
+    /// Shows how many records have passed through each stage.
+    pub fn stage_progress(mut self, stage_progress: StageProgress) -> Self {
+        self.stage_progress = stage_progress;
+        self
+    }
+
+    /// Pauses taking in new records while the server is throttling requests.
+    pub fn throttle(mut self, throttle: Arc<Throttle>) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Counts the records passing through the stages, e.g. to drain them
+    /// after an interrupt.
+    pub fn in_flight(mut self, in_flight: Arc<InFlight>) -> Self {
+        self.in_flight = in_flight;
+        self
+    }
+
+    /// Returns the stages in the order records pass through them, with their
+    /// dependencies, e.g. to draw the pipeline.
+    pub fn graph(&self) -> PipelineGraph {
+        let stages = self
+            .order()
+            .into_iter()
+            .map(|index| {
+                let stage = &self.stages[index];
+                StageNode {
+                    kind: stage.kind(),
+                    depends_on: self.depends_on(stage),
+                    concurrent: stage.concurrent(),
+                }
+            })
+            .collect();
+
+        PipelineGraph { stages }
+    }
+
+    /// Returns the kinds of the stages in the pipeline that a stage depends
+    /// on.
+    fn depends_on(&self, stage: &BoxStage<R, I, O>) -> Vec<StageKind> {
+        stage
+            .depends_on()
+            .iter()
+            .filter(|kind| self.stages.iter().any(|stage| stage.kind() == **kind))
+            .copied()
+            .collect()
+    }
+
+    /// Returns the indices of the stages, ordered by their dependencies.
+    fn order(&self) -> Vec<usize> {
+        let nodes = self
+            .stages
+            .iter()
+            .map(|stage| {
+                let depends_on = self
+                    .depends_on(stage)
+                    .into_iter()
+                    .map(StageKind::name)
+                    .collect();
+                (stage.kind().name(), depends_on)
+            })
+            .collect::<Vec<_>>();
+        dependency_order(&nodes)
+            .unwrap_or_else(|e| panic!("Failed to order pipeline stages: {}", e))
+    }
+
+    /// Returns a pipeline that publishes each record's progress on the
+    /// `event_bus`.
+    pub fn build(
+        self,
+        event_bus: EventBus<R, I>,
+        metrics: Arc<Metrics>,
+        run_control: Arc<RunControl>,
+        concurrency_limit: Arc<ConcurrencyLimit>,
+    ) -> Pipeline<R, I, O> {
+        let order = self.order();
+        let layers = self.layers;
+        let mut stages = self.stages.into_iter().map(Some).collect::<Vec<_>>();
+        let stages = order
+            .into_iter()
+            .filter_map(|index| stages[index].take())
+            .map(|stage| layers.iter().rev().fold(stage, |stage, layer| layer(stage)))
+            .collect::<Vec<_>>();
+        let concurrent_from = stages
+            .iter()
+            .position(|stage| stage.concurrent())
+            .unwrap_or(stages.len());
+
+        Pipeline {
+            stages,
+            concurrent_from,
+            event_bus,
+            metrics,
+            run_control,
+            concurrency_limit,
+            worker_progress: self.worker_progress,
+            stage_progress: self.stage_progress,
+            throttle: self.throttle,
+            in_flight: self.in_flight,
+        }
+    }
+}
+
+/// Drives records through its [`Stage`]s, updating the progress bars and
+/// publishing each record's progress on the [`EventBus`] for the
+/// [`Reporter`], metrics, and events.
+///
+/// The pipeline is generic over the record type `R`, the lookup result type
+/// `I`, and the output type `O`, so the same progress and report machinery can
+/// drive different lookup jobs.
+///
+/// [`Reporter`]: crate::Reporter
+#[derive(Debug)]
+pub struct Pipeline<R, I, O> {
+    stages: Vec<BoxStage<R, I, O>>,
+    /// Index of the first stage that records may pass through concurrently.
+    concurrent_from: usize,
+    /// Publishes each record's progress.
+    event_bus: EventBus<R, I>,
+    metrics: Arc<Metrics>,
+    /// Pauses processing between records.
+    run_control: Arc<RunControl>,
+    /// Limits how many records pass through the concurrent stages at once.
+    concurrency_limit: Arc<ConcurrencyLimit>,
+    worker_progress: WorkerProgress,
+    stage_progress: StageProgress,
+    /// Pauses processing between records while the server is throttling
+    /// requests.
+    throttle: Option<Arc<Throttle>>,
+    /// Number of records that have started passing through the stages.
+    in_flight: Arc<InFlight>,
+}
+
+impl<R, I, O> Pipeline<R, I, O>
+where
+    R: Record,
+    I: LookupResult,
+    O: Send + 'static,
+{
+    /// Returns a builder to add the pipeline's stages to.
+    pub fn builder() -> PipelineBuilder<R, I, O> {
+        PipelineBuilder {
+            stages: Vec::new(),
+            layers: Vec::new(),
+            worker_progress: WorkerProgress::hidden(),
+            stage_progress: StageProgress::hidden(),
+            throttle: None,
+            in_flight: Arc::new(InFlight::default()),
+        }
+    }
+
+    /// Processes each record through the stages.
+    ///
+    /// Records are given with their position in the input, and pass through
+    /// the stages before the first concurrent stage one after another, then
+    /// through the remaining stages concurrently. Records may arrive while
+    /// earlier records are processed, e.g. from `--watch`.
+    ///
+    /// Once the run is stopped, no more records are taken in, and the records
+    /// in flight finish. A record that fails or panics in a stage gives up its
+    /// reservations, so stages that hold records in order, e.g. `--ordered`
+    /// output, don't wait for it.
+    pub async fn run(&self, records: impl Stream<Item = (usize, R)>) {
+        let (sequential_stages, concurrent_stages) = self.stages.split_at(self.concurrent_from);
+        records
+            .take_until(self.run_control.stopped())
+            .enumerate()
+            .then(|(sequence, (n, record))| {
+                async move {
+                    let reserved = async {
+                        for stage in self.stages.iter() {
+                            stage.reserve(sequence).await;
+                        }
+                    };
+                    // A stage waiting for earlier records, e.g. a full reorder
+                    // buffer, doesn't hold the run open once it is stopped.
+                    tokio::select! {
+                        () = reserved => {}
+                        () = self.run_control.stopped() => return Err(()),
+                    }
+                    self.run_control.wait_while_paused().await;
+                    if let Some(throttle) = self.throttle.as_deref() {
+                        throttle.wait().await;
+                    }
+                    // Taken before the record leaves the sequential stages, so
+                    // no more records are taken in while the limit is reached.
+                    let permit = self.concurrency_limit.acquire().await;
+                    let worker_bar = self.worker_progress.start(record);
+                    let in_flight_record = self.in_flight.start();
+                    let work = Work {
+                        n,
+                        sequence,
+                        record,
+                        lookup: None,
+                        output: None,
+                    };
+                    let work = match self.process(sequential_stages, work, &worker_bar).await {
+                        Ok(work) => work,
+                        Err(()) => {
+                            self.skip(sequence).await;
+                            return Err(());
+                        }
+                    };
+                    self.metrics.output_queued();
+
+                    // The remaining stages happen outside the record's span, so carry
+                    // it along.
+                    Result::<_, ()>::Ok((
+                        work,
+                        tracing::Span::current(),
+                        worker_bar,
+                        in_flight_record,
+                        permit,
+                    ))
+                }
+                .instrument(tracing::info_span!(
+                    "record",
+                    record_id = n,
+                    title_number = %record.label()
+                ))
+            })
+            // A record that fails a stage is skipped, rather than stopping the run.
+            .filter_map(|work| async move { work.ok() })
+            .for_each_concurrent(
+                None,
+                |(work, record_span, worker_bar, in_flight_record, permit)| {
+                    async move {
+                        // Held until the record finishes, so it counts as in flight.
+                        let _in_flight_record = in_flight_record;
+                        let _permit = permit;
+                        let sequence = work.sequence;
+                        match self.process(concurrent_stages, work, &worker_bar).await {
+                            Ok(work) => {
+                                self.event_bus
+                                    .publish(RunEvent::RecordWritten {
+                                        record: work.record,
+                                    })
+                                    .await
+                            }
+                            Err(()) => self.skip(sequence).await,
+                        }
+                    }
+                    .instrument(record_span)
+                },
+            )
+            .await;
+    }
+
+    /// Gives up the reservations of a record that failed, so no stage waits
+    /// for it.
+    async fn skip(&self, sequence: usize) {
+        for stage in self.stages.iter() {
+            stage.skip(sequence).await;
+        }
+    }
+
+    /// Passes a record through each stage in turn.
+    ///
+    /// A stage that panics fails the record, without stopping the run.
+    async fn process(
+        &self,
+        stages: &[BoxStage<R, I, O>],
+        mut work: Work<R, I, O>,
+        worker_bar: &WorkerBar,
+    ) -> Result<Work<R, I, O>, ()> {
+        for stage in stages {
+            let kind = stage.kind();
+            let looked_up = work.lookup.is_some();
+            let record = work.record;
+            worker_bar.stage(kind);
+            let processed = AssertUnwindSafe(stage.process(work)).catch_unwind().await;
+            work = match processed {
+                Ok(Ok(work)) => work,
+                Ok(Err(error)) => {
+                    tracing::error!(stage = kind.name(), "Failed to process record: {}", error);
+                    self.event_bus
+                        .publish(RunEvent::RecordFailed {
+                            record,
+                            stage: kind,
+                            error,
+                        })
+                        .await;
+                    return Err(());
+                }
+                Err(panic) => {
+                    let message = Self::panic_message(panic.as_ref());
+                    tracing::error!(stage = kind.name(), "Stage panicked: {}", message);
+                    self.event_bus
+                        .publish(RunEvent::RecordPanicked {
+                            record,
+                            stage: kind,
+                            message,
+                            looked_up,
+                        })
+                        .await;
+                    return Err(());
+                }
+            };
+            match kind {
+                StageKind::Retrieve => self.stage_progress.retrieved(),
+                StageKind::Augment => self.stage_progress.augmented(),
+                StageKind::Output => self.stage_progress.written(),
+                StageKind::RateLimit | StageKind::Authenticate => {}
+            }
+
+            if !looked_up {
+                if let Some(record_progress) = work.record_progress() {
+                    self.record_looked_up(record_progress).await;
+                }
+            }
+        }
+
+        Ok(work)
+    }
+
+    /// Returns the message a stage panicked with.
+    fn panic_message(panic: &(dyn Any + Send)) -> String {
+        match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+            (Some(message), _) => String::from(*message),
+            (None, Some(message)) => message.clone(),
+            (None, None) => String::from("Unknown panic."),
+        }
+    }
+
+    /// Publishes a record's progress once its information has been looked up.
+    async fn record_looked_up(&self, record_progress: RecordProgress<R, I>) {
+        let RecordProgress {
+            ref info,
+            attempts,
+            duration,
+            ..
+        } = record_progress;
+        tracing::debug!(?info, attempts, ?duration, "Retrieved record information.");
+        self.event_bus
+            .publish(RunEvent::RecordRetrieved(record_progress))
+            .await;
+    }
+}
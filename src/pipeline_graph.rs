@@ -0,0 +1,107 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+use clap::Args;
+
+use crate::{Reporter, StageKind};
+
+/// Prints the pipeline's stages and their dependencies.
+#[derive(Debug, Args)]
+pub struct GraphOpt {
+    /// Format to print the graph in: `dot` for Graphviz.
+    #[arg(long, default_value = "dot")]
+    pub format: GraphFormat,
+    /// Report saved with `--report-out`, whose average stage timings are
+    /// shown on each stage.
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+}
+
+/// Format to print the pipeline graph in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz `dot` language, e.g. for `dot -Tsvg`.
+    Dot,
+}
+
+impl fmt::Display for GraphFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dot => write!(f, "dot"),
+        }
+    }
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Self::Dot),
+            _ => Err(format!("`{}` is not one of `dot`.", s)),
+        }
+    }
+}
+
+/// A stage in a [`PipelineGraph`].
+#[derive(Clone, Debug)]
+pub struct StageNode {
+    pub kind: StageKind,
+    /// Kinds of stage in the pipeline that process a record before this one.
+    pub depends_on: Vec<StageKind>,
+    /// Whether records pass through this stage concurrently.
+    pub concurrent: bool,
+}
+
+/// Stages of a pipeline and their dependencies, in the order records pass
+/// through them.
+#[derive(Clone, Debug)]
+pub struct PipelineGraph {
+    pub stages: Vec<StageNode>,
+}
+
+impl PipelineGraph {
+    /// Returns the graph in the Graphviz `dot` language.
+    ///
+    /// Each stage is labelled with its average duration, if given. Stages
+    /// that records pass through concurrently are drawn with a dashed border.
+    pub fn dot(&self, stage_average_durations: &BTreeMap<String, Duration>) -> String {
+        let mut dot = String::from("digraph pipeline {\n    rankdir=LR;\n    node [shape=box];\n");
+        // Writing to a `String` doesn't fail.
+        self.stages.iter().for_each(|stage| {
+            let name = stage.kind.name();
+            let label = match stage_average_durations.get(name) {
+                Some(duration) => format!(
+                    "{}\\naverage {}",
+                    name,
+                    <Reporter>::format_duration(*duration)
+                ),
+                None => String::from(name),
+            };
+            let style = if stage.concurrent {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            let _ = writeln!(dot, "    \"{}\" [label=\"{}\"{}];", name, label, style);
+        });
+        self.stages.iter().for_each(|stage| {
+            stage.depends_on.iter().for_each(|dependency| {
+                let _ = writeln!(
+                    dot,
+                    "    \"{}\" -> \"{}\";",
+                    dependency.name(),
+                    stage.kind.name()
+                );
+            });
+        });
+        dot.push_str("}\n");
+
+        dot
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::time;
+
+use crate::{EventBus, InFlight};
+
+/// Resources the process used during a run, sampled with `--profile`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct Resources {
+    /// Number of samples taken.
+    pub sample_count: usize,
+    /// Resident memory in bytes, or `None` if it can't be read on this
+    /// platform.
+    pub memory_bytes: Option<Usage>,
+    /// CPU time used as a percentage of one core, or `None` if it can't be
+    /// read on this platform.
+    pub cpu_percent: Option<Usage>,
+    /// Number of records being processed concurrently, each in its own
+    /// future.
+    pub record_in_flight_count: Usage,
+    /// Largest number of events waiting to be received by any one
+    /// [`EventBus`] subscriber.
+    pub event_queue_depth: Usage,
+}
+
+/// Peak and average of a sampled value.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct Usage {
+    /// Highest value sampled.
+    pub peak: f64,
+    /// Mean of the values sampled.
+    pub average: f64,
+}
+
+impl Usage {
+    /// Adds a sample, given the number of samples before it.
+    fn add(&mut self, value: f64, sample_count: usize) {
+        self.peak = self.peak.max(value);
+        self.average += (value - self.average) / (sample_count + 1) as f64;
+    }
+}
+
+/// Samples the process's memory and CPU use, the records in flight, and the
+/// event queues during a run, for `--profile`.
+#[derive(Clone, Debug)]
+pub struct Profiler<R, I> {
+    event_bus: EventBus<R, I>,
+    in_flight: Arc<InFlight>,
+    resources: Arc<Mutex<Resources>>,
+}
+
+impl<R, I> Profiler<R, I>
+where
+    R: Clone,
+    I: Clone,
+{
+    /// How often resources are sampled.
+    const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Returns a profiler for the run's events and records in flight.
+    pub fn new(event_bus: EventBus<R, I>, in_flight: Arc<InFlight>) -> Self {
+        Self {
+            event_bus,
+            in_flight,
+            resources: Arc::new(Mutex::new(Resources::default())),
+        }
+    }
+
+    /// Samples resources until the task is dropped.
+    pub async fn sample(self) {
+        let mut interval = time::interval(Self::SAMPLE_INTERVAL);
+        // The first tick is immediate, so CPU use is measured from here.
+        interval.tick().await;
+        let mut cpu_time_previous = Self::cpu_time().map(|cpu_time| (Instant::now(), cpu_time));
+        loop {
+            interval.tick().await;
+
+            let cpu_time_now = Self::cpu_time().map(|cpu_time| (Instant::now(), cpu_time));
+            let cpu_percent = match (cpu_time_previous, cpu_time_now) {
+                (Some((instant_previous, cpu_time_previous)), Some((instant, cpu_time))) => {
+                    let elapsed = instant.duration_since(instant_previous).as_secs_f64();
+                    (elapsed > 0.0).then(|| {
+                        cpu_time.saturating_sub(cpu_time_previous).as_secs_f64() / elapsed * 100.0
+                    })
+                }
+                _ => None,
+            };
+            cpu_time_previous = cpu_time_now;
+            let memory_bytes = Self::memory_bytes();
+
+            let mut resources = self.resources.lock().expect("Resources lock poisoned.");
+            let sample_count = resources.sample_count;
+            if let Some(memory_bytes) = memory_bytes {
+                resources
+                    .memory_bytes
+                    .get_or_insert_with(Usage::default)
+                    .add(memory_bytes as f64, sample_count);
+            }
+            if let Some(cpu_percent) = cpu_percent {
+                resources
+                    .cpu_percent
+                    .get_or_insert_with(Usage::default)
+                    .add(cpu_percent, sample_count);
+            }
+            resources
+                .record_in_flight_count
+                .add(self.in_flight.count() as f64, sample_count);
+            resources
+                .event_queue_depth
+                .add(self.event_bus.queue_depth() as f64, sample_count);
+            resources.sample_count += 1;
+        }
+    }
+
+    /// Returns the resources sampled so far.
+    pub fn resources(&self) -> Resources {
+        *self.resources.lock().expect("Resources lock poisoned.")
+    }
+
+    /// Returns the CPU time the process has used, in user and system mode.
+    #[cfg(unix)]
+    fn cpu_time() -> Option<Duration> {
+        let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+        // SAFETY: `getrusage` fills in `usage` when it returns 0.
+        let usage = unsafe {
+            if libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) != 0 {
+                return None;
+            }
+            usage.assume_init()
+        };
+        let timeval_duration = |timeval: libc::timeval| {
+            Duration::from_secs(timeval.tv_sec as u64)
+                + Duration::from_micros(timeval.tv_usec as u64)
+        };
+
+        Some(timeval_duration(usage.ru_utime) + timeval_duration(usage.ru_stime))
+    }
+
+    #[cfg(not(unix))]
+    fn cpu_time() -> Option<Duration> {
+        None
+    }
+
+    /// Returns the process's resident memory in bytes.
+    #[cfg(target_os = "linux")]
+    fn memory_bytes() -> Option<u64> {
+        // The second field is the resident set size, in pages.
+        let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+        let page_count = statm.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+        // SAFETY: `sysconf` has no preconditions.
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+
+        (page_size > 0).then(|| page_count * page_size as u64)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn memory_bytes() -> Option<u64> {
+        None
+    }
+}
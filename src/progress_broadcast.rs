@@ -0,0 +1,87 @@
+use std::{future::Future, io, net::SocketAddr};
+
+use futures::SinkExt;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast::{self, error::RecvError},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Number of events buffered for each WebSocket client before it starts
+/// missing events.
+const CLIENT_BUFFER: usize = 1024;
+
+/// Broadcasts progress events to WebSocket clients.
+#[derive(Clone, Debug)]
+pub struct ProgressBroadcast {
+    tx: broadcast::Sender<String>,
+}
+
+impl ProgressBroadcast {
+    /// Listens for WebSocket clients on `127.0.0.1` at the given port.
+    ///
+    /// The port is bound before this returns, so errors are reported
+    /// immediately. The returned future accepts clients until the process
+    /// exits.
+    pub fn serve(port: u16) -> io::Result<(Self, impl Future<Output = ()>)> {
+        let listener = std::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port)))?;
+        listener.set_nonblocking(true)?;
+        let listener = TcpListener::from_std(listener)?;
+
+        let (tx, _rx) = broadcast::channel(CLIENT_BUFFER);
+        let progress_broadcast = Self { tx };
+        let accept_tx = progress_broadcast.tx.clone();
+        let accept_future = async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, addr)) => {
+                        tokio::spawn(Self::send_to_client(stream, addr, accept_tx.subscribe()));
+                    }
+                    Err(e) => tracing::warn!("Failed to accept WebSocket client: {}", e),
+                }
+            }
+        };
+
+        Ok((progress_broadcast, accept_future))
+    }
+
+    /// Sends an event to every connected client.
+    pub fn send(&self, event: String) {
+        // No receivers just means no clients are connected.
+        let _ = self.tx.send(event);
+    }
+
+    async fn send_to_client(
+        stream: TcpStream,
+        addr: SocketAddr,
+        mut rx: broadcast::Receiver<String>,
+    ) {
+        let mut websocket = match tokio_tungstenite::accept_async(stream).await {
+            Ok(websocket) => websocket,
+            Err(e) => {
+                tracing::debug!(%addr, "WebSocket handshake failed: {}", e);
+                return;
+            }
+        };
+        tracing::debug!(%addr, "WebSocket client connected.");
+
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(%addr, skipped, "WebSocket client is too slow, skipped events.");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            if websocket.send(Message::Text(event)).await.is_err() {
+                tracing::debug!(%addr, "WebSocket client disconnected.");
+                return;
+            }
+        }
+
+        if let Err(e) = websocket.close(None).await {
+            tracing::debug!(%addr, "Failed to close WebSocket connection: {}", e);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use indicatif::ProgressBar;
+
+use crate::{CircuitState, Colours};
+
+/// Message shown after the overall progress bar.
+///
+/// Shows whether the run is paused, throttled, or its circuit breaker is
+/// open, the records still finishing after an interrupt, the most recently
+/// processed record, and the latest error.
+#[derive(Clone, Debug)]
+pub struct ProgressMessage {
+    progress_bar: ProgressBar,
+    state: Arc<Mutex<ProgressMessageState>>,
+}
+
+#[derive(Debug, Default)]
+struct ProgressMessageState {
+    paused: bool,
+    /// Whether the server is throttling requests.
+    throttled: bool,
+    /// State of the circuit breaker around retrieving information.
+    circuit_state: CircuitState,
+    /// Number of records in flight and the time left to finish them, while
+    /// draining after an interrupt.
+    draining: Option<(usize, Duration)>,
+    /// Title number of the most recently processed record.
+    title_number: Option<String>,
+    /// Title number and error of the most recently failed record.
+    error: Option<(String, String)>,
+}
+
+impl ProgressMessage {
+    /// Returns a message for the progress bar.
+    pub fn new(progress_bar: ProgressBar) -> Self {
+        Self {
+            progress_bar,
+            state: Arc::new(Mutex::new(ProgressMessageState::default())),
+        }
+    }
+
+    /// Sets whether the run is paused.
+    pub fn set_paused(&self, paused: bool) {
+        self.update(|state| state.paused = paused);
+    }
+
+    /// Sets whether the server is throttling requests.
+    pub fn set_throttled(&self, throttled: bool) {
+        self.update(|state| state.throttled = throttled);
+    }
+
+    /// Sets the state of the circuit breaker around retrieving information.
+    pub fn set_circuit_state(&self, circuit_state: CircuitState) {
+        self.update(|state| state.circuit_state = circuit_state);
+    }
+
+    /// Sets the number of records in flight and the time left to finish
+    /// them, or `None` once they have finished or been abandoned.
+    pub fn set_draining(&self, draining: Option<(usize, Duration)>) {
+        self.update(|state| state.draining = draining);
+    }
+
+    /// Sets the most recently processed record.
+    pub fn set_record(&self, title_number: String) {
+        self.update(|state| state.title_number = Some(title_number));
+    }
+
+    /// Sets the latest error.
+    pub fn set_error(&self, title_number: String, error: &str) {
+        // Only the first line, so the progress bar stays on one line.
+        let error = error.lines().next().unwrap_or_default().to_string();
+        self.update(|state| state.error = Some((title_number, error)));
+    }
+
+    fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut ProgressMessageState),
+    {
+        let mut state = self.state.lock().expect("Progress message lock poisoned.");
+        f(&mut state);
+
+        let mut message = String::new();
+        if state.paused {
+            message.push_str("PAUSED ");
+        }
+        if state.throttled {
+            message.push_str("THROTTLED ");
+        }
+        match state.circuit_state {
+            CircuitState::Closed => {}
+            CircuitState::Open => message.push_str("CIRCUIT OPEN "),
+            CircuitState::HalfOpen => message.push_str("CIRCUIT HALF-OPEN "),
+        }
+        if let Some((in_flight_count, remaining)) = state.draining {
+            // Rounded up, so the countdown reaches 0s as it times out.
+            let remaining_secs = remaining.as_millis().div_ceil(1000);
+            message.push_str(&format!(
+                "finishing {} in-flight records… {}s ",
+                in_flight_count, remaining_secs
+            ));
+        }
+        if let Some(title_number) = state.title_number.as_deref() {
+            message.push_str(title_number);
+        }
+        if let Some((title_number, error)) = state.error.as_ref() {
+            let error = format!(" last error: {}: {}", title_number, error);
+            message.push_str(&Colours::theme().progress_error.apply(error).to_string());
+        }
+        self.progress_bar.set_message(message);
+    }
+}
@@ -0,0 +1,72 @@
+use indicatif::ProgressBar;
+
+use crate::{ProgressOptions, TerminalCapabilities};
+
+/// Re-renders the overall progress bar when the terminal is resized,
+/// switching between the default and narrow templates.
+#[derive(Clone, Debug)]
+pub struct ProgressResize {
+    progress_bar: ProgressBar,
+    progress_options: ProgressOptions,
+}
+
+impl ProgressResize {
+    /// Returns a handle that re-renders `progress_bar` in the given style.
+    pub fn new(progress_bar: ProgressBar, progress_options: ProgressOptions) -> Self {
+        Self {
+            progress_bar,
+            progress_options,
+        }
+    }
+
+    /// Re-renders the progress bar for a terminal that is now `width` columns
+    /// wide.
+    ///
+    /// The template only changes if it wasn't set with `--progress-template`
+    /// or the config file, and the number of records is known.
+    pub fn resized(&self, width: u16) {
+        if self.progress_options.template_auto && self.progress_bar.length().is_some() {
+            let template = if width < TerminalCapabilities::WIDTH_NARROW {
+                ProgressOptions::TEMPLATE_NARROW
+            } else {
+                ProgressOptions::TEMPLATE_DEFAULT
+            };
+            let progress_options = ProgressOptions {
+                template: String::from(template),
+                ..self.progress_options.clone()
+            };
+            self.progress_bar.set_style(
+                progress_options
+                    .style()
+                    .expect("Invalid progress bar template."),
+            );
+        }
+
+        // Redraws at the new width now, instead of at the next update.
+        self.progress_bar.tick();
+    }
+
+    /// Re-renders the progress bar on `SIGWINCH`.
+    ///
+    /// Outside Unix, resizes are only seen as key events while keyboard
+    /// control is enabled.
+    pub async fn handle_sigwinch(self) {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let mut sigwinch = match signal(SignalKind::window_change()) {
+                Ok(sigwinch) => sigwinch,
+                Err(e) => {
+                    tracing::warn!("Failed to set SIGWINCH handler: {}", e);
+                    return;
+                }
+            };
+            while sigwinch.recv().await.is_some() {
+                if let Ok((columns, _rows)) = crossterm::terminal::size() {
+                    self.resized(columns);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures::future;
+
+use crate::{
+    t06_authenticate_with_server, t07_retrieve_enrichment, t07_retrieve_information,
+    t08_augment_record, t09_output_record, BatchStage, Chaos, ChaosEvents, ConnectionPool,
+    Credentials, EnrichmentSource, FailureInjection, Latency, Lookup, Metrics, OutputWriter,
+    PropertyInfoResult, PropertyRecord, PropertyRecordPopulated, RecordSink, Stage, StageKind,
+    Throttle, Work,
+};
+
+/// Work item for the stages that look up property records.
+type PropertyWork = Work<PropertyRecord, PropertyInfoResult, PropertyRecordPopulated>;
+
+/// Authenticates with the server before the first record.
+#[derive(Debug)]
+pub struct AuthenticateStage {
+    pub credentials: Arc<Credentials>,
+    /// Time taken to authenticate with the server.
+    pub delay: Duration,
+}
+
+#[async_trait]
+impl Stage<PropertyRecord, PropertyInfoResult, PropertyRecordPopulated> for AuthenticateStage {
+    fn kind(&self) -> StageKind {
+        StageKind::Authenticate
+    }
+
+    async fn process(&self, work: PropertyWork) -> Result<PropertyWork, String> {
+        if work.n == 0 {
+            let credential = self.credentials.current();
+            t06_authenticate_with_server(
+                self.credentials.authenticate(credential),
+                credential,
+                self.delay,
+            )
+            .await;
+        }
+        Ok(work)
+    }
+}
+
+/// Retrieves each record's information from the simulated server, one record
+/// per request, or a batch per request with a [`BatchLayer`].
+///
+/// [`BatchLayer`]: crate::BatchLayer
+#[derive(Debug)]
+pub struct RetrieveStage {
+    pub latency: Latency,
+    pub failure_injection: FailureInjection,
+    pub chaos: Option<Chaos>,
+    /// Keys to make requests with, authenticating each before its first
+    /// request and again when the server's authentication expires.
+    pub credentials: Arc<Credentials>,
+    /// Time taken to authenticate with the server.
+    pub delay_auth: Duration,
+    pub metrics: Arc<Metrics>,
+    /// Pauses taking in new records when the server throttles requests.
+    pub throttle: Arc<Throttle>,
+    /// Connections to the server, reused across requests.
+    pub connection_pool: Arc<ConnectionPool>,
+    /// Sources each record is also looked up from, concurrently with its
+    /// title information.
+    pub enrichment_sources: Vec<EnrichmentSource>,
+}
+
+#[async_trait]
+impl Stage<PropertyRecord, PropertyInfoResult, PropertyRecordPopulated> for RetrieveStage {
+    fn kind(&self) -> StageKind {
+        StageKind::Retrieve
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        &[StageKind::Authenticate]
+    }
+
+    async fn process(&self, work: PropertyWork) -> Result<PropertyWork, String> {
+        self.process_batch(vec![work])
+            .await?
+            .pop()
+            .expect("Retrieved a batch without its record.")
+    }
+}
+
+#[async_trait]
+impl BatchStage<PropertyRecord, PropertyInfoResult, PropertyRecordPopulated> for RetrieveStage {
+    async fn process_batch(
+        &self,
+        works: Vec<PropertyWork>,
+    ) -> Result<Vec<Result<PropertyWork, String>>, String> {
+        let retrieve_start = Instant::now();
+        works.iter().for_each(|_| self.metrics.request_started());
+        let ns = works.iter().map(|work| work.n).collect::<Vec<_>>();
+        let enrichments = ns.iter().map(|n| {
+            future::join_all(
+                self.enrichment_sources
+                    .iter()
+                    .map(move |source| async move {
+                        let succeeded = t07_retrieve_enrichment(*n, *source, self).await;
+                        (*source, succeeded)
+                    }),
+            )
+        });
+        let ((infos, chaos_events, attempts), enrichments) = future::join(
+            t07_retrieve_information(&ns, self),
+            future::join_all(enrichments),
+        )
+        .await;
+        let duration = retrieve_start.elapsed();
+        if infos.len() != works.len() {
+            return Err(format!(
+                "Retrieved information for {} of {} records.",
+                infos.len(),
+                works.len()
+            ));
+        }
+
+        let works = works
+            .into_iter()
+            .zip(infos.into_iter().zip(enrichments))
+            .enumerate()
+            .map(|(index, (mut work, (info, enrichments)))| {
+                let sources = enrichments
+                    .iter()
+                    .map(|(source, succeeded)| (source.name().to_string(), *succeeded))
+                    .collect::<BTreeMap<_, _>>();
+                // Information from a source that failed is missing from the record.
+                let info = enrichments
+                    .into_iter()
+                    .fold(info, |info, (source, succeeded)| {
+                        info.with_field(source.field(), succeeded)
+                    });
+                self.metrics.request_finished(&info, duration);
+                work.lookup = Some(Lookup {
+                    info,
+                    // The request's faults are counted once, with its first record.
+                    chaos_events: if index == 0 {
+                        chaos_events
+                    } else {
+                        ChaosEvents::default()
+                    },
+                    attempts,
+                    duration,
+                    sources,
+                });
+                Ok(work)
+            })
+            .collect();
+        Ok(works)
+    }
+}
+
+/// Combines each record with its retrieved information.
+#[derive(Debug)]
+pub struct AugmentStage;
+
+#[async_trait]
+impl Stage<PropertyRecord, PropertyInfoResult, PropertyRecordPopulated> for AugmentStage {
+    fn kind(&self) -> StageKind {
+        StageKind::Augment
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        &[StageKind::Retrieve]
+    }
+
+    async fn process(&self, mut work: PropertyWork) -> Result<PropertyWork, String> {
+        let (info, sources) = work
+            .lookup
+            .as_ref()
+            .map(|lookup| (lookup.info.clone(), lookup.sources.clone()))
+            .ok_or("Record information has not been retrieved.")?;
+        work.output = Some(t08_augment_record(work.record, info, sources));
+        Ok(work)
+    }
+}
+
+/// Writes each populated record to the sinks.
+#[derive(Debug)]
+pub struct OutputStage {
+    pub sink: Arc<dyn RecordSink>,
+    /// Output files, used to write records in input order with `--ordered`.
+    pub output_writer: Option<Arc<OutputWriter>>,
+}
+
+#[async_trait]
+impl Stage<PropertyRecord, PropertyInfoResult, PropertyRecordPopulated> for OutputStage {
+    fn kind(&self) -> StageKind {
+        StageKind::Output
+    }
+
+    fn depends_on(&self) -> &'static [StageKind] {
+        &[StageKind::Augment]
+    }
+
+    fn concurrent(&self) -> bool {
+        true
+    }
+
+    async fn reserve(&self, sequence: usize) {
+        if let Some(output_writer) = self.output_writer.as_ref() {
+            output_writer.reserve(sequence).await;
+        }
+    }
+
+    async fn skip(&self, sequence: usize) {
+        if let Some(output_writer) = self.output_writer.as_ref() {
+            output_writer.skip(sequence).await;
+        }
+    }
+
+    async fn process(&self, work: PropertyWork) -> Result<PropertyWork, String> {
+        let record_progress = work
+            .record_progress()
+            .ok_or("Record information has not been retrieved.")?;
+        let property_record_populated = work
+            .output
+            .clone()
+            .ok_or("Record has not been combined with its information.")?;
+        t09_output_record(
+            self.sink.as_ref(),
+            work.sequence,
+            property_record_populated,
+            &record_progress,
+        )
+        .await
+        .map_err(|e| format!("Failed to write record: {}", e))?;
+        Ok(work)
+    }
+}
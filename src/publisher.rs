@@ -0,0 +1,137 @@
+use std::{fmt, io, time::Duration};
+
+use bytes::Bytes;
+use rdkafka::{
+    producer::{FutureProducer, FutureRecord, Producer},
+    ClientConfig,
+};
+
+use crate::{OutputRecord, PropertyRecordPopulated};
+
+/// How long Kafka may take to deliver each record before it fails.
+const KAFKA_MESSAGE_TIMEOUT_MS: &str = "10000";
+/// How long to wait for the Kafka producer's queue to have room for a record.
+const KAFKA_QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long to wait for queued records to be delivered when the run ends.
+const KAFKA_FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Publishes each populated record as it is processed, given with
+/// `--publish`, so downstream consumers receive results in real time.
+///
+/// Records are published as JSON, in the same form as lines in the output
+/// file.
+#[derive(Clone)]
+pub enum Publisher {
+    /// Publishes to a Kafka topic, with the record ID as the message key.
+    Kafka {
+        producer: FutureProducer,
+        topic: String,
+    },
+    /// Publishes to a NATS subject.
+    Nats {
+        client: async_nats::Client,
+        subject: String,
+    },
+}
+
+impl Publisher {
+    /// Connects to the broker in a URL such as `kafka://localhost:9092/records`
+    /// or `nats://localhost:4222/records`, where the path is the topic or
+    /// subject.
+    ///
+    /// Kafka brokers may be comma separated, e.g.
+    /// `kafka://broker-1:9092,broker-2:9092/records`.
+    pub async fn connect(url: &str) -> io::Result<Self> {
+        let invalid_input = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let (scheme, servers_and_name) = url
+            .split_once("://")
+            .ok_or_else(|| invalid_input(format!("`{}` is not a URL.", url)))?;
+        let (servers, name) = servers_and_name
+            .split_once('/')
+            .filter(|(_, name)| !name.is_empty())
+            .ok_or_else(|| {
+                invalid_input(format!(
+                    "`{}` has no topic or subject, e.g. `{}://localhost/records`.",
+                    url, scheme
+                ))
+            })?;
+
+        match scheme {
+            "kafka" => {
+                let producer = ClientConfig::new()
+                    .set("bootstrap.servers", servers)
+                    .set("message.timeout.ms", KAFKA_MESSAGE_TIMEOUT_MS)
+                    .create()
+                    .map_err(io::Error::other)?;
+                Ok(Self::Kafka {
+                    producer,
+                    topic: String::from(name),
+                })
+            }
+            "nats" => {
+                let client = async_nats::connect(servers)
+                    .await
+                    .map_err(io::Error::other)?;
+                Ok(Self::Nats {
+                    client,
+                    subject: String::from(name),
+                })
+            }
+            _ => Err(invalid_input(format!(
+                "`{}` is not one of `kafka`, `nats`.",
+                scheme
+            ))),
+        }
+    }
+
+    /// Publishes a populated record.
+    ///
+    /// For Kafka, this waits until the record is delivered.
+    pub async fn publish(
+        &self,
+        property_record_populated: PropertyRecordPopulated,
+    ) -> io::Result<()> {
+        let record_id = property_record_populated.record.0;
+        let payload = serde_json::to_vec(&OutputRecord::from(property_record_populated))?;
+
+        match self {
+            Self::Kafka { producer, topic } => {
+                let key = record_id.to_string();
+                producer
+                    .send(
+                        FutureRecord::to(topic).key(&key).payload(&payload),
+                        KAFKA_QUEUE_TIMEOUT,
+                    )
+                    .await
+                    .map(|_| ())
+                    .map_err(|(e, _)| io::Error::other(e))
+            }
+            Self::Nats { client, subject } => client
+                .publish(subject.clone(), Bytes::from(payload))
+                .await
+                .map_err(io::Error::other),
+        }
+    }
+
+    /// Waits until published records have been sent to the broker.
+    pub async fn finish(&self) -> io::Result<()> {
+        match self {
+            Self::Kafka { producer, .. } => {
+                let producer = producer.clone();
+                tokio::task::spawn_blocking(move || producer.flush(KAFKA_FLUSH_TIMEOUT))
+                    .await?
+                    .map_err(io::Error::other)
+            }
+            Self::Nats { client, .. } => client.flush().await.map_err(io::Error::other),
+        }
+    }
+}
+
+impl fmt::Debug for Publisher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Kafka { topic, .. } => f.debug_struct("Kafka").field("topic", topic).finish(),
+            Self::Nats { subject, .. } => f.debug_struct("Nats").field("subject", subject).finish(),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    ops::Range,
+    path::Path,
+    str::FromStr,
+};
+
+use crate::PropertyRecord;
+
+/// Range of record IDs given to `--only`, e.g. `100..200` or `100..=199`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordRange(pub Range<usize>);
+
+impl FromStr for RecordRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_bound = |bound: &str| {
+            bound
+                .trim()
+                .parse::<usize>()
+                .map_err(|e| format!("`{}` is not a record ID: {}", bound, e))
+        };
+
+        let range = if let Some((start, end)) = s.split_once("..=") {
+            parse_bound(start)?..parse_bound(end)?.saturating_add(1)
+        } else if let Some((start, end)) = s.split_once("..") {
+            parse_bound(start)?..parse_bound(end)?
+        } else {
+            return Err(format!("`{}` is not a range such as `100..200`.", s));
+        };
+
+        if range.start < range.end {
+            Ok(Self(range))
+        } else {
+            Err(format!("`{}` is an empty range.", s))
+        }
+    }
+}
+
+/// Selects which records to process.
+#[derive(Clone, Debug, Default)]
+pub struct RecordFilter {
+    /// Only records with IDs in this range are processed.
+    pub only: Option<Range<usize>>,
+    /// Only records with these IDs are processed.
+    pub only_ids: Option<HashSet<usize>>,
+    /// Records with these IDs are not processed.
+    pub exclude_ids: HashSet<usize>,
+}
+
+impl RecordFilter {
+    /// Returns whether the record should be processed.
+    pub fn matches(&self, record: PropertyRecord) -> bool {
+        let id = record.0;
+        self.only
+            .as_ref()
+            .map(|only| only.contains(&id))
+            .unwrap_or(true)
+            && self
+                .only_ids
+                .as_ref()
+                .map(|only_ids| only_ids.contains(&id))
+                .unwrap_or(true)
+            && !self.exclude_ids.contains(&id)
+    }
+
+    /// Reads record IDs from a file, one per line.
+    ///
+    /// Anything after a `#` is a comment, and blank lines are ignored.
+    pub fn read_ids(path: &Path) -> io::Result<HashSet<usize>> {
+        Self::read_id_list(path).map(|ids| ids.into_iter().collect())
+    }
+
+    /// Reads record IDs from a file, one per line, in the order they are
+    /// listed.
+    ///
+    /// The file has the same format as for [`RecordFilter::read_ids`].
+    pub fn read_id_list(path: &Path) -> io::Result<Vec<usize>> {
+        BufReader::new(File::open(path)?)
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| match line {
+                Ok(line) => Self::parse_id_line(&line).map(|id| {
+                    id.map_err(|e| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("line {}: {}", index + 1, e),
+                        )
+                    })
+                }),
+                Err(e) => Some(Err(e)),
+            })
+            .collect()
+    }
+
+    /// Parses a line of a record ID list, e.g. `123  # comment`.
+    ///
+    /// Returns `None` if the line is blank or only a comment.
+    pub fn parse_id_line(line: &str) -> Option<Result<usize, String>> {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            None
+        } else {
+            Some(
+                line.parse::<usize>()
+                    .map_err(|e| format!("`{}` is not a record ID: {}", line, e)),
+            )
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use tokio::sync::Notify;
+
+/// Releases items in sequence order, when they are pushed out of order.
+///
+/// At most `capacity` items after the next one to be released may be in
+/// flight, so callers [`reserve`] a sequence number before starting on an
+/// item, which waits while the buffer is full. Every reserved sequence number
+/// must be either [`push`]ed or [`skip`]ped, or the items after it are never
+/// released.
+///
+/// [`push`]: Self::push
+/// [`reserve`]: Self::reserve
+/// [`skip`]: Self::skip
+#[derive(Debug)]
+pub struct ReorderBuffer<T> {
+    /// Maximum distance between the next item to release and any other item.
+    capacity: usize,
+    /// Sequence number of the next item to release, and items waiting for it.
+    ///
+    /// Sequence numbers that were skipped wait as `None`.
+    state: Mutex<(usize, BTreeMap<usize, Option<T>>)>,
+    /// Notified whenever items are released.
+    released: Notify,
+}
+
+impl<T> ReorderBuffer<T> {
+    /// Returns a reorder buffer, whose capacity is at least 1.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::new((0, BTreeMap::new())),
+            released: Notify::new(),
+        }
+    }
+
+    /// Waits until there is room in the buffer for the item with this
+    /// sequence number.
+    pub async fn reserve(&self, sequence: usize) {
+        loop {
+            // Created before checking, so a release in between isn't missed.
+            let released = self.released.notified();
+            {
+                let state = self.state.lock().expect("Reorder buffer lock poisoned.");
+                if sequence < state.0 + self.capacity {
+                    return;
+                }
+            }
+            released.await;
+        }
+    }
+
+    /// Buffers an item, and returns every item that is now in order.
+    ///
+    /// Callers that release items concurrently must serialize handling them,
+    /// if their order is to be preserved.
+    pub fn push(&self, sequence: usize, item: T) -> Vec<T> {
+        self.insert(sequence, Some(item))
+    }
+
+    /// Marks a sequence number as having no item, e.g. because its item
+    /// failed before it could be pushed, and returns every item that is now
+    /// in order.
+    ///
+    /// Sequence numbers that were already pushed or skipped are left as they
+    /// are.
+    pub fn skip(&self, sequence: usize) -> Vec<T> {
+        self.insert(sequence, None)
+    }
+
+    fn insert(&self, sequence: usize, item: Option<T>) -> Vec<T> {
+        let mut state = self.state.lock().expect("Reorder buffer lock poisoned.");
+        let (next, pending) = &mut *state;
+        match item {
+            Some(item) => {
+                pending.insert(sequence, Some(item));
+            }
+            None if sequence >= *next => {
+                pending.entry(sequence).or_insert(None);
+            }
+            None => {}
+        }
+
+        let mut released = Vec::new();
+        while let Some(item) = pending.remove(next) {
+            released.extend(item);
+            *next += 1;
+        }
+        drop(state);
+
+        if !released.is_empty() {
+            self.released.notify_waiters();
+        }
+        released
+    }
+}
@@ -1,14 +1,538 @@
-use crate::PropertyRecord;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write as _},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    history::RunStatus, Backpressure, ChaosEvents, ConnectionStats, ErrorClass, InterruptReason,
+    OutputStats, PropertyRecord, Record, Reporter, Resources, RunMetadata,
+};
+
+/// Number of records an enrichment source's lookup succeeded and failed for.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct SourceCounts {
+    /// Records the source's information was retrieved for.
+    pub succeeded_count: usize,
+    /// Records the source's information could not be retrieved for.
+    pub failed_count: usize,
+}
+
+/// Options for how the report is printed.
+#[derive(Clone, Copy, Debug)]
+pub struct ReportOptions {
+    /// Number of slowest records to list.
+    pub slowest_count: usize,
+    /// Whether to list every failed record instead of grouping errors by message.
+    pub errors_full: bool,
+    /// Number of rows each error table is truncated to.
+    pub max_error_rows: usize,
+    /// Number of records at the start of the run left out of the throughput
+    /// and latency statistics.
+    pub warmup_record_count: usize,
+    /// Time at the start of the run left out of the throughput and latency
+    /// statistics.
+    ///
+    /// The warmup lasts until both this and `warmup_record_count` have passed.
+    pub warmup_duration: Duration,
+    /// Whether to print a single summary line instead of the full report.
+    pub summary_only: bool,
+}
+
+/// A record that failed to process.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecordFailure<R = PropertyRecord> {
+    /// The record that failed.
+    pub record: R,
+    /// Why the record failed.
+    pub error: String,
+    /// Number of attempts made to retrieve the record's information.
+    pub attempts: u32,
+    /// Class of the error, from the `[classification]` rules in the config
+    /// file.
+    #[serde(default)]
+    pub class: ErrorClass,
+    /// When the record failed.
+    pub timestamp: SystemTime,
+}
+
+/// Start of a run left out of the throughput and latency statistics, e.g.
+/// while authenticating, so they reflect the steady state.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct Warmup {
+    /// Number of records processed during the warmup.
+    pub record_count: usize,
+    /// How long the warmup lasted.
+    pub duration: Duration,
+}
+
+/// Growth of each latency bucket's upper bound over the previous one's, so
+/// percentiles are estimated within 1%.
+const LATENCY_BUCKET_GROWTH: f64 = 1.01;
+
+/// Counts of record durations in buckets that widen as durations grow, so
+/// latency percentiles are estimated without keeping every duration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LatencyHistogram {
+    /// Number of durations in each bucket, by bucket index.
+    buckets: BTreeMap<u32, usize>,
+    /// Number of durations counted.
+    count: usize,
+    /// Shortest duration counted.
+    min: Option<Duration>,
+    /// Longest duration counted.
+    max: Option<Duration>,
+}
+
+impl LatencyHistogram {
+    /// Counts a record's duration.
+    pub fn record(&mut self, duration: Duration) {
+        let micros = (duration.as_micros() as f64).max(1.0);
+        let bucket = (micros.ln() / LATENCY_BUCKET_GROWTH.ln()) as u32;
+        *self.buckets.entry(bucket).or_default() += 1;
+        self.count += 1;
+        self.min = Some(self.min.map_or(duration, |min| min.min(duration)));
+        self.max = Some(self.max.map_or(duration, |max| max.max(duration)));
+    }
+
+    /// Returns the shortest duration counted.
+    pub fn min(&self) -> Option<Duration> {
+        self.min
+    }
+
+    /// Returns the longest duration counted.
+    pub fn max(&self) -> Option<Duration> {
+        self.max
+    }
+
+    /// Returns the duration at the given percentile, using the nearest-rank
+    /// method, as the upper bound of the bucket it falls in.
+    ///
+    /// `percentile` must be between `0.0` and `100.0`.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        let (min, max) = (self.min?, self.max?);
+        let rank = ((percentile / 100.0 * self.count as f64).ceil() as usize).max(1);
+        let mut count_cumulative = 0;
+        let bucket = self.buckets.iter().find_map(|(bucket, count)| {
+            count_cumulative += count;
+            Some(*bucket).filter(|_| count_cumulative >= rank)
+        })?;
+
+        let micros = LATENCY_BUCKET_GROWTH.powi(bucket as i32 + 1);
+        Some(Duration::from_secs_f64(micros / 1_000_000.0).clamp(min, max))
+    }
+}
+
+/// How and when a run was interrupted.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Interruption {
+    /// What stopped the run.
+    pub reason: InterruptReason,
+    /// When the run was interrupted.
+    pub timestamp: SystemTime,
+    /// Number of records left unprocessed, including any abandoned while
+    /// draining, or `None` if the total isn't known.
+    pub record_remaining_count: Option<usize>,
+}
 
 /// Report containing information about the execution.
-#[derive(Debug, Default)]
-pub struct Report {
+///
+/// Records are [`PropertyRecord`]s, unless the report is for another kind of
+/// [`Record`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Report<R = PropertyRecord> {
+    /// Identifies the run this report is for.
+    pub run: RunMetadata,
     /// Number of records already in the output before the execution.
     pub record_skipped_count: usize,
+    /// Number of records not processed because of `--only`, `--only-ids`, or `--exclude-ids`.
+    #[serde(default)]
+    pub record_filtered_count: usize,
+    /// Number of records not processed because their ID appeared earlier in the input.
+    #[serde(default)]
+    pub record_duplicate_count: usize,
+    /// Bytes written to the output file, if any.
+    #[serde(default)]
+    pub output_stats: Option<OutputStats>,
     /// Number of records that we successfully processed.
     pub record_processed_successful_count: usize,
     /// Number of records that have some information missing.
     pub record_processed_info_missing_count: usize,
     /// Errors for records that failed to process.
-    pub records_processed_failed: Vec<(PropertyRecord, &'static str)>,
+    pub records_processed_failed: Vec<RecordFailure<R>>,
+    /// Faults injected by chaos mode.
+    pub chaos_events: ChaosEvents,
+    /// Time taken to retrieve information for processed records, after the
+    /// warmup.
+    #[serde(default)]
+    pub latency_histogram: LatencyHistogram,
+    /// The `--slowest` records to retrieve information for after the warmup,
+    /// slowest first.
+    #[serde(default = "Vec::new")]
+    pub records_slowest: Vec<(R, Duration)>,
+    /// Number of records processed in each minute of the execution, from the
+    /// end of the warmup.
+    pub records_per_minute: Vec<usize>,
+    /// Start of the execution left out of the throughput and latency
+    /// statistics, if there was one.
+    #[serde(default)]
+    pub warmup: Option<Warmup>,
+    /// Wall-clock duration of the execution.
+    pub duration: Duration,
+    /// Whether the execution was interrupted before all records were processed.
+    pub interrupted: bool,
+    /// Why and when the execution was interrupted, if it was.
+    #[serde(default)]
+    pub interruption: Option<Interruption>,
+    /// Average time spent in each stage, by stage name.
+    #[serde(default)]
+    pub stage_average_durations: BTreeMap<String, Duration>,
+    /// Number of times the server throttled requests, pausing intake.
+    #[serde(default)]
+    pub throttle_count: usize,
+    /// Time intake was paused because the server throttled requests.
+    #[serde(default)]
+    pub throttled_duration: Duration,
+    /// Whether records were streamed in without a known total, e.g. from
+    /// stdin, so the counts are totals since the run started.
+    #[serde(default)]
+    pub streamed: bool,
+    /// Number of requests made with each key in `--credentials-file`, by the
+    /// key's name or redacted key.
+    #[serde(default)]
+    pub credential_usage: BTreeMap<String, usize>,
+    /// Number of connections opened and reused to retrieve information.
+    #[serde(default)]
+    pub connection_stats: Option<ConnectionStats>,
+    /// Number of records each `--enrich` source succeeded and failed for, by
+    /// source name.
+    #[serde(default)]
+    pub source_counts: BTreeMap<String, SourceCounts>,
+    /// Number of records processed with missing info that each field was
+    /// missing from, by field name.
+    #[serde(default)]
+    pub missing_field_counts: BTreeMap<String, usize>,
+    /// Whether failed records were classified by `[classification]` rules in
+    /// the config file.
+    #[serde(default)]
+    pub classified: bool,
+    /// How often records waited for a subscriber of the run's events, e.g.
+    /// the progress bars, to catch up.
+    #[serde(default)]
+    pub event_backpressure: Option<Backpressure>,
+    /// Records that a stage panicked on, which are also counted as failed if
+    /// they panicked before their information was looked up.
+    #[serde(default = "Vec::new")]
+    pub records_panicked: Vec<RecordFailure<R>>,
+    /// Number of records still in flight when the drain timeout passed after
+    /// an interrupt, which were aborted mid-flight.
+    #[serde(default)]
+    pub record_abandoned_count: usize,
+    /// Resources used during the execution, if sampled with `--profile`.
+    #[serde(default)]
+    pub resources: Option<Resources>,
+}
+
+impl<R> Report<R>
+where
+    R: Record,
+{
+    /// Returns a new report for a run.
+    pub fn new(
+        run: RunMetadata,
+        record_skipped_count: usize,
+        record_filtered_count: usize,
+    ) -> Self {
+        Self {
+            run,
+            record_skipped_count,
+            record_filtered_count,
+            record_duplicate_count: 0,
+            output_stats: None,
+            record_processed_successful_count: 0,
+            record_processed_info_missing_count: 0,
+            records_processed_failed: Vec::new(),
+            chaos_events: ChaosEvents::default(),
+            latency_histogram: LatencyHistogram::default(),
+            records_slowest: Vec::new(),
+            records_per_minute: Vec::new(),
+            warmup: None,
+            duration: Duration::ZERO,
+            interrupted: false,
+            interruption: None,
+            stage_average_durations: BTreeMap::new(),
+            throttle_count: 0,
+            throttled_duration: Duration::ZERO,
+            streamed: false,
+            credential_usage: BTreeMap::new(),
+            connection_stats: None,
+            source_counts: BTreeMap::new(),
+            missing_field_counts: BTreeMap::new(),
+            classified: false,
+            event_backpressure: None,
+            records_panicked: Vec::new(),
+            record_abandoned_count: 0,
+            resources: None,
+        }
+    }
+
+    /// Returns the number of records processed in this execution.
+    pub fn record_processed_count(&self) -> usize {
+        self.record_processed_successful_count
+            + self.record_processed_info_missing_count
+            + self.records_processed_failed.len()
+    }
+
+    /// Returns the number of records not processed in this execution, because
+    /// they were pre-existing, filtered out, or duplicates.
+    pub fn record_skipped_total_count(&self) -> usize {
+        self.record_skipped_count + self.record_filtered_count + self.record_duplicate_count
+    }
+
+    /// Returns a line summarizing the execution for scripts to parse, e.g.
+    /// `result=ok processed=47 partial=12 failed=3 skipped=5 filtered=0 duplicates=0 duration_ms=10234`.
+    ///
+    /// `result` is `interrupted` if the execution was interrupted, `failed` if
+    /// any record failed, and `ok` otherwise.
+    pub fn result_line(&self) -> String {
+        let failed_count = self.records_processed_failed.len();
+        let result = if self.interrupted {
+            "interrupted"
+        } else if failed_count > 0 || !self.records_panicked.is_empty() {
+            "failed"
+        } else {
+            "ok"
+        };
+
+        format!(
+            "result={} processed={} partial={} failed={} skipped={} filtered={} duplicates={} duration_ms={}",
+            result,
+            self.record_processed_count(),
+            self.record_processed_info_missing_count,
+            failed_count,
+            self.record_skipped_count,
+            self.record_filtered_count,
+            self.record_duplicate_count,
+            self.duration.as_millis()
+        )
+    }
+
+    /// Returns a sentence describing the run for notifications, e.g.
+    /// `cli_async run completed_with_errors in 10s 234ms: 47 processed (12
+    /// missing info), 3 failed, 5 skipped.`
+    pub fn message(&self) -> String {
+        format!(
+            "cli_async run {} in {}: {} processed ({} missing info), {} failed, {} skipped.",
+            RunStatus::from_report(self),
+            <Reporter>::format_duration(self.duration),
+            self.record_processed_successful_count + self.record_processed_info_missing_count,
+            self.record_processed_info_missing_count,
+            self.records_processed_failed.len(),
+            self.record_skipped_count,
+        )
+    }
+
+    /// Returns the failed records grouped by error message, most common first.
+    pub fn errors_by_message(&self) -> Vec<(&str, Vec<R>)> {
+        let mut errors_by_message = Vec::<(&str, Vec<R>)>::new();
+        self.records_processed_failed
+            .iter()
+            .for_each(|record_failure| {
+                match errors_by_message
+                    .iter_mut()
+                    .find(|(message, _)| *message == record_failure.error.as_str())
+                {
+                    Some((_, property_records)) => property_records.push(record_failure.record),
+                    None => {
+                        errors_by_message.push((&record_failure.error, vec![record_failure.record]))
+                    }
+                }
+            });
+        errors_by_message.sort_by(|(message_a, records_a), (message_b, records_b)| {
+            records_b
+                .len()
+                .cmp(&records_a.len())
+                .then_with(|| message_a.cmp(message_b))
+        });
+
+        errors_by_message
+    }
+
+    /// Writes every failed record to a CSV file.
+    pub fn write_errors_csv(&self, path: &Path) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record([
+            "record_id",
+            "title_number",
+            "error",
+            "timestamp",
+            "attempts",
+            "class",
+        ])?;
+        self.records_processed_failed
+            .iter()
+            .try_for_each(|record_failure| {
+                writer.write_record(&[
+                    record_failure.record.id().to_string(),
+                    record_failure.record.label(),
+                    record_failure.error.clone(),
+                    humantime::format_rfc3339_millis(record_failure.timestamp).to_string(),
+                    record_failure.attempts.to_string(),
+                    record_failure.class.to_string(),
+                ])
+            })?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Writes the report to a JSON file.
+    pub fn write_json(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+
+    /// Reads a report from a JSON file written by [`Report::write_json`].
+    pub fn read_json(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Returns how long the execution ran after the warmup.
+    pub fn duration_after_warmup(&self) -> Duration {
+        let warmup_duration = self
+            .warmup
+            .map(|warmup| warmup.duration)
+            .unwrap_or_default();
+        self.duration.saturating_sub(warmup_duration)
+    }
+
+    /// Returns the average number of records processed per second, after the
+    /// warmup.
+    pub fn throughput_average(&self) -> f64 {
+        let warmup_record_count = self
+            .warmup
+            .map(|warmup| warmup.record_count)
+            .unwrap_or_default();
+        let seconds = self.duration_after_warmup().as_secs_f64();
+        if seconds > 0.0 {
+            self.record_processed_count()
+                .saturating_sub(warmup_record_count) as f64
+                / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the highest number of records processed per second in any one minute.
+    ///
+    /// Only complete minutes are counted, as a burst in the last, partial minute
+    /// would otherwise be divided by a fraction of a minute. Executions shorter
+    /// than a minute use the rate over the time they ran.
+    pub fn throughput_peak(&self) -> f64 {
+        let seconds_total = self.duration_after_warmup().as_secs_f64();
+        let minutes_complete = (seconds_total / 60.0) as usize;
+        if minutes_complete == 0 {
+            return match self.records_per_minute.first() {
+                Some(count) if seconds_total > 0.0 => *count as f64 / seconds_total,
+                _ => 0.0,
+            };
+        }
+
+        self.records_per_minute
+            .iter()
+            .take(minutes_complete)
+            .map(|count| *count as f64 / 60.0)
+            .fold(0.0, f64::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::LatencyHistogram;
+
+    /// Returns whether `duration` is within 1% of `expected`.
+    fn within_bucket(duration: Duration, expected: Duration) -> bool {
+        let difference = duration.as_secs_f64() - expected.as_secs_f64();
+        difference.abs() <= expected.as_secs_f64() * 0.01
+    }
+
+    fn latency_histogram_1_to_100_ms() -> LatencyHistogram {
+        let mut latency_histogram = LatencyHistogram::default();
+        (1..=100).for_each(|millis| latency_histogram.record(Duration::from_millis(millis)));
+        latency_histogram
+    }
+
+    #[test]
+    fn percentile_returns_none_when_empty() {
+        let latency_histogram = LatencyHistogram::default();
+
+        assert_eq!(None, latency_histogram.percentile(0.0));
+        assert_eq!(None, latency_histogram.percentile(50.0));
+        assert_eq!(None, latency_histogram.percentile(100.0));
+    }
+
+    #[test]
+    fn percentile_returns_the_duration_when_one_is_counted() {
+        let mut latency_histogram = LatencyHistogram::default();
+        latency_histogram.record(Duration::from_millis(42));
+
+        [0.0, 50.0, 100.0].iter().for_each(|percentile| {
+            assert_eq!(
+                Some(Duration::from_millis(42)),
+                latency_histogram.percentile(*percentile)
+            )
+        });
+    }
+
+    #[test]
+    fn percentile_is_clamped_to_min_and_max() {
+        let latency_histogram = latency_histogram_1_to_100_ms();
+
+        let p0 = latency_histogram
+            .percentile(0.0)
+            .expect("No 0th percentile.");
+        let p100 = latency_histogram
+            .percentile(100.0)
+            .expect("No 100th percentile.");
+
+        assert!(p0 >= Duration::from_millis(1));
+        assert!(within_bucket(p0, Duration::from_millis(1)));
+        assert_eq!(Duration::from_millis(100), p100);
+    }
+
+    #[test]
+    fn percentile_is_within_one_percent_of_nearest_rank() {
+        let latency_histogram = latency_histogram_1_to_100_ms();
+
+        [(50.0, 50), (90.0, 90), (99.0, 99)]
+            .iter()
+            .for_each(|(percentile, millis)| {
+                let duration = latency_histogram
+                    .percentile(*percentile)
+                    .expect("No percentile.");
+                assert!(
+                    within_bucket(duration, Duration::from_millis(*millis)),
+                    "p{} was {:?}",
+                    percentile,
+                    duration
+                );
+            });
+    }
+
+    #[test]
+    fn percentile_of_zero_durations_is_zero() {
+        let mut latency_histogram = LatencyHistogram::default();
+        latency_histogram.record(Duration::ZERO);
+        latency_histogram.record(Duration::ZERO);
+
+        assert_eq!(Some(Duration::ZERO), latency_histogram.percentile(99.0));
+    }
 }
@@ -0,0 +1,116 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write as _},
+    path::PathBuf,
+};
+
+use clap::Args;
+
+use crate::{Colours, PropertyRecord, Report};
+
+/// Compares two reports saved with `--report-out`.
+#[derive(Debug, Args)]
+pub struct DiffOpt {
+    /// Report of the earlier run.
+    run_a: PathBuf,
+    /// Report of the later run.
+    run_b: PathBuf,
+}
+
+/// Differences between two runs.
+pub struct ReportDiff;
+
+impl ReportDiff {
+    /// Writes the differences between the two reports to stdout.
+    pub fn print(diff_opt: &DiffOpt) -> io::Result<()> {
+        let report_a = Report::read_json(&diff_opt.run_a)?;
+        let report_b = Report::read_json(&diff_opt.run_b)?;
+
+        let mut stdout = io::stdout();
+        writeln!(
+            stdout,
+            "{label:<35} {a:>9} {b:>9} {delta:>9}",
+            label = "",
+            a = Colours::theme().report_label.apply("run a"),
+            b = Colours::theme().report_label.apply("run b"),
+            delta = Colours::theme().report_label.apply("delta"),
+        )?;
+
+        let counts = [
+            (
+                "* Records processed:",
+                report_a.record_processed_successful_count,
+                report_b.record_processed_successful_count,
+            ),
+            (
+                "* Records processed (missing info):",
+                report_a.record_processed_info_missing_count,
+                report_b.record_processed_info_missing_count,
+            ),
+            (
+                "* Records with errors:",
+                report_a.records_processed_failed.len(),
+                report_b.records_processed_failed.len(),
+            ),
+        ];
+        counts.iter().try_for_each(|(label, a, b)| {
+            writeln!(
+                stdout,
+                "{label:<35} {a:>9} {b:>9} {delta:>+9}",
+                label = Colours::theme().report_label.apply(*label),
+                a = a,
+                b = b,
+                delta = *b as i64 - *a as i64,
+            )
+        })?;
+
+        let throughput_a = report_a.throughput_average();
+        let throughput_b = report_b.throughput_average();
+        writeln!(
+            stdout,
+            "{label:<35} {a:>9} {b:>9} {delta:>9}",
+            label = Colours::theme()
+                .report_label
+                .apply("* Throughput (average):"),
+            a = format!("{:.1}/s", throughput_a),
+            b = format!("{:.1}/s", throughput_b),
+            delta = format!("{:+.1}/s", throughput_b - throughput_a),
+        )?;
+
+        let records_failed_a = report_a
+            .records_processed_failed
+            .iter()
+            .map(|record_failure| record_failure.record)
+            .collect::<HashSet<PropertyRecord>>();
+        let records_newly_failed = report_b
+            .records_processed_failed
+            .iter()
+            .filter(|record_failure| !records_failed_a.contains(&record_failure.record))
+            .collect::<Vec<_>>();
+        if !records_newly_failed.is_empty() {
+            writeln!(stdout)?;
+            writeln!(
+                stdout,
+                "{}",
+                Colours::theme()
+                    .report_title_error
+                    .apply("## Newly Failing Records")
+            )?;
+            writeln!(stdout)?;
+            records_newly_failed.iter().try_for_each(|record_failure| {
+                writeln!(
+                    stdout,
+                    "{title_number:<13} | {error}",
+                    title_number = Colours::theme()
+                        .report_error_item
+                        .apply(record_failure.record.title_number()),
+                    error = Colours::theme()
+                        .report_error_message
+                        .apply(record_failure.error.as_str()),
+                )
+            })?;
+        }
+
+        stdout.flush()
+    }
+}
@@ -1,139 +1,1066 @@
-use std::{fmt, fmt::Write as _, io, io::Write as _};
+use std::{
+    borrow::Cow,
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BinaryHeap},
+    fmt,
+    fmt::Write as _,
+    io,
+    io::Write as _,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
-use indicatif::{ProgressBar, ProgressStyle};
-use tokio::sync::mpsc::{Receiver, UnboundedReceiver};
+use crossterm::style::ContentStyle;
+use futures::future;
+use indicatif::{
+    style::TemplateError, HumanBytes, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle,
+};
 
-use crate::{Colours, PropertyInfoResult, Report};
+use crate::{
+    report::{Interruption, RecordFailure, Warmup},
+    Backpressure, CircuitState, Classifier, Colours, ConnectionStats, Error, ErrorClass, Eta,
+    EventReceiver, InterruptReason, LookupResult, OutputStats, ProgressMessage, PropertyInfoResult,
+    PropertyRecord, Record, RecordProgress, RecordStatus, Report, ReportOptions, Resources,
+    RunEvent, StageKind, StageProgress, StageTimings, TerminalCapabilities, WorkerProgress,
+};
 
+/// Shows progress as records are processed, and the report afterwards.
+///
+/// Records are [`PropertyRecord`]s with [`PropertyInfoResult`]s, unless the
+/// reporter is for another kind of [`Pipeline`].
+///
+/// [`Pipeline`]: crate::Pipeline
 #[derive(Debug)]
-pub struct Reporter {
+pub struct Reporter<R = PropertyRecord, I = PropertyInfoResult> {
     /// `ProgressBar` for the overall progress.
     progress_overall: ProgressBar,
-    /// Receiver to receive updates when a record is processed.
-    progress_receiver: UnboundedReceiver<PropertyInfoResult>,
+    /// Receives events from the [`EventBus`], e.g. when a record is processed
+    /// or the run is interrupted.
+    ///
+    /// [`EventBus`]: crate::EventBus
+    events: EventReceiver<R, I>,
     /// Process report of records.
-    report: Report,
-    /// Interrupt handler.
-    interrupt_rx: Option<Receiver<()>>,
+    report: Report<R>,
+    /// When processing started.
+    start: Instant,
+    /// When the warmup ended, or `None` while it is ongoing.
+    warmup_end: Option<Instant>,
+    /// Options for how the report is printed.
+    report_options: ReportOptions,
+    /// Time spent in each processing stage.
+    stage_timings: Arc<StageTimings>,
+    /// Progress bars for records being processed.
+    worker_progress: WorkerProgress,
+    /// Progress bars for each stage.
+    stage_progress: StageProgress,
+    /// Message shown after the overall progress bar.
+    progress_message: ProgressMessage,
+    /// How often to print the status line, in plain mode.
+    plain_interval: Option<Duration>,
+    /// Time remaining, from the records finished in this run.
+    eta: Eta,
+    /// When the server started throttling requests, if it is throttling.
+    throttled_since: Option<Instant>,
+    /// When the server allows requests again, if it is throttling.
+    throttled_until: Option<Instant>,
+    /// State of the circuit breaker around retrieving information.
+    circuit_state: CircuitState,
+    /// Classifies errors by the `[classification]` rules in the config file.
+    classifier: Classifier,
+    /// The `--slowest` records so far, with the fastest of them on top, so it
+    /// is replaced when a slower record finishes.
+    records_slowest: BinaryHeap<Reverse<RecordDuration<R>>>,
+}
+
+/// How long a record took to retrieve information for, ordered by the
+/// duration.
+#[derive(Clone, Copy, Debug)]
+struct RecordDuration<R> {
+    duration: Duration,
+    record: R,
+}
+
+impl<R> PartialEq for RecordDuration<R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.duration == other.duration
+    }
+}
+
+impl<R> Eq for RecordDuration<R> {}
+
+impl<R> PartialOrd for RecordDuration<R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<R> Ord for RecordDuration<R> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.duration.cmp(&other.duration)
+    }
+}
+
+/// How progress is shown while running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// No progress bars.
+    Hidden,
+    /// A single bar for all records.
+    Overall,
+    /// The overall bar, with a bar beneath it for each record being processed.
+    PerWorker,
+    /// The overall bar, with bars beneath it for records retrieved, augmented, and written.
+    Stages,
+    /// A line of text printed periodically, for logs that aren't a terminal.
+    Plain,
+}
+
+impl FromStr for ProgressMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hidden" => Ok(Self::Hidden),
+            "overall" => Ok(Self::Overall),
+            "per-worker" => Ok(Self::PerWorker),
+            "stages" => Ok(Self::Stages),
+            "plain" => Ok(Self::Plain),
+            _ => Err(format!(
+                "`{}` is not one of `hidden`, `overall`, `per-worker`, `stages`, `plain`.",
+                s
+            )),
+        }
+    }
+}
+
+/// How the progress bars look.
+#[derive(Clone, Debug)]
+pub struct ProgressOptions {
+    /// Which progress bars to show.
+    pub mode: ProgressMode,
+    /// `indicatif` template for the overall progress bar.
+    pub template: String,
+    /// Whether the template follows the terminal's width, as it wasn't set
+    /// with `--progress-template` or the config file.
+    pub template_auto: bool,
+    /// Characters for the filled, current, and empty parts of progress bars.
+    pub chars: String,
+    /// Whether to only use ASCII characters for spinners.
+    pub ascii: bool,
+    /// How often the status line is printed in plain mode.
+    pub plain_interval: Duration,
+    /// Estimate for the `{eta}` and `{finish_at}` template keys.
+    pub eta: Eta,
+}
+
+impl ProgressOptions {
+    /// Default template for the overall progress bar.
+    pub const TEMPLATE_DEFAULT: &'static str = "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}, done at {finish_at}) [concurrency: {prefix}] {msg}";
+    /// Default template for the overall progress bar on narrow terminals.
+    pub const TEMPLATE_NARROW: &'static str =
+        "{spinner:.green} [{bar:20.cyan/blue}] {pos}/{len} ({eta}) {msg}";
+    /// Template for the overall progress bar when the number of records isn't
+    /// known, e.g. when they are read from stdin.
+    pub const TEMPLATE_STREAMING: &'static str = "{spinner:.green} [{elapsed_precise}] {pos} processed ({per_sec}) [concurrency: {prefix}] {msg}";
+    /// Default progress bar characters.
+    pub const CHARS_DEFAULT: &'static str = "█▒░";
+    /// Default progress bar characters for terminals that can't display Unicode.
+    pub const CHARS_ASCII: &'static str = "#>-";
+    /// Spinner characters for terminals that can't display Unicode.
+    pub const TICK_CHARS_ASCII: &'static str = "-\\|/ ";
+
+    /// Returns the style for the overall progress bar.
+    pub fn style(&self) -> Result<ProgressStyle, TemplateError> {
+        let style = self.eta.style(
+            ProgressStyle::default_bar()
+                .template(&self.template)?
+                .progress_chars(&self.chars),
+        );
+        if self.ascii {
+            Ok(style.tick_chars(Self::TICK_CHARS_ASCII))
+        } else {
+            Ok(style)
+        }
+    }
+
+    /// Returns the style for the overall progress bar when the number of
+    /// records isn't known.
+    ///
+    /// This shows the throughput instead of a bar, so it doesn't use the
+    /// progress bar template.
+    pub fn style_streaming(&self) -> ProgressStyle {
+        let style = ProgressStyle::default_spinner()
+            .template(Self::TEMPLATE_STREAMING)
+            .expect("Invalid streaming progress bar template.");
+        if self.ascii {
+            style.tick_chars(Self::TICK_CHARS_ASCII)
+        } else {
+            style
+        }
+    }
 }
 
-impl Reporter {
+impl<R, I> Reporter<R, I>
+where
+    R: Record,
+    I: LookupResult,
+{
+    /// Returns a reporter for `record_count` records, or an unknown number of
+    /// records if it is `None`, e.g. when they are read from stdin.
+    ///
+    /// The total grows as [`RunEvent::RecordsDiscovered`] events are received.
     pub fn new(
-        record_count: u64,
-        record_count_processed: u64,
-        progress_receiver: UnboundedReceiver<PropertyInfoResult>,
-        show_progress: bool,
-        interrupt_rx: Option<Receiver<()>>,
+        record_count: Option<u64>,
+        report: Report<R>,
+        events: EventReceiver<R, I>,
+        progress_options: ProgressOptions,
+        report_options: ReportOptions,
+        stage_timings: Arc<StageTimings>,
     ) -> Self {
-        // Can't support `MultiProgress`: <https://github.com/mitsuhiko/indicatif/issues/125>
+        let progress_overall = match progress_options.mode {
+            // Still tracks the position and length for the status line, and
+            // the records remaining if interrupted.
+            ProgressMode::Hidden | ProgressMode::Plain => {
+                ProgressBar::with_draw_target(record_count, ProgressDrawTarget::hidden())
+            }
+            ProgressMode::Overall | ProgressMode::PerWorker | ProgressMode::Stages => {
+                ProgressBar::with_draw_target(record_count, ProgressDrawTarget::stderr())
+            }
+        };
+        match record_count {
+            Some(_) => progress_overall.set_style(
+                progress_options
+                    .style()
+                    .expect("Invalid progress bar template."),
+            ),
+            None => {
+                progress_overall.set_style(progress_options.style_streaming());
+                progress_overall.enable_steady_tick(Duration::from_millis(100));
+            }
+        }
+        progress_overall.set_position(report.record_skipped_total_count() as u64);
+
+        let (worker_progress, stage_progress) = match progress_options.mode {
+            ProgressMode::Hidden | ProgressMode::Overall | ProgressMode::Plain => {
+                (WorkerProgress::hidden(), StageProgress::hidden())
+            }
+            ProgressMode::PerWorker => {
+                let multi_progress = MultiProgress::new();
+                multi_progress.add(progress_overall.clone());
+                (
+                    WorkerProgress::new(multi_progress, progress_options.ascii),
+                    StageProgress::hidden(),
+                )
+            }
+            ProgressMode::Stages => {
+                let multi_progress = MultiProgress::new();
+                multi_progress.add(progress_overall.clone());
+                let stage_progress = StageProgress::new(
+                    &multi_progress,
+                    &progress_options.chars,
+                    record_count,
+                    report.record_skipped_total_count() as u64,
+                );
+                (WorkerProgress::hidden(), stage_progress)
+            }
+        };
 
-        let progress_overall = if show_progress {
-            ProgressBar::new(record_count)
+        let progress_message = ProgressMessage::new(progress_overall.clone());
+        let plain_interval = if progress_options.mode == ProgressMode::Plain {
+            Some(progress_options.plain_interval)
         } else {
-            ProgressBar::hidden()
+            None
         };
-        progress_overall.set_style(
-            ProgressStyle::default_bar()
-                .template(
-                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
-                )
-                .progress_chars("█▒░"),
-        );
-        progress_overall.set_position(record_count_processed);
 
-        let report = Report {
-            record_skipped_count: record_count_processed as usize,
-            ..Default::default()
+        let start = Instant::now();
+        let warmup_end = if report_options.warmup_record_count == 0
+            && report_options.warmup_duration.is_zero()
+        {
+            Some(start)
+        } else {
+            None
         };
 
         Self {
             progress_overall,
-            progress_receiver,
+            events,
             report,
-            interrupt_rx,
+            start,
+            warmup_end,
+            report_options,
+            stage_timings,
+            worker_progress,
+            stage_progress,
+            progress_message,
+            plain_interval,
+            eta: progress_options.eta,
+            throttled_since: None,
+            throttled_until: None,
+            circuit_state: CircuitState::Closed,
+            classifier: Classifier::default(),
+            records_slowest: BinaryHeap::with_capacity(report_options.slowest_count + 1),
         }
     }
 
-    /// Writes the logo to stderr.
-    ///
-    /// The logo should be a stylized:
-    ///
-    /// ```text
-    ///              _   _ _   _
-    ///  ___ ___| |_|_| |_| |___
-    /// | . |_ -|  _| |  _| | -_|
-    /// |  _|___|_| |_|_| |_|___|
-    /// |_|
-    /// ```
-    pub fn print_logo() -> crossterm::Result<()> {
-        let logo_left = ["    ", " ___ ___", "| . |_ -", "|  _|___", "|_|     ", ""];
-        let logo_right = [
-            "     _   _ _   _",
-            "| |_|_| |_| |___",
-            "|  _| |  _| | -_|",
-            "|_| |_|_| |_|___|",
-            "",
-            "",
-        ];
-        let prompt = logo_left
-            .iter()
-            .zip(logo_right.iter())
-            .try_fold(String::with_capacity(384), |mut buffer, (left, right)| {
-                let left = Colours::LOGO_LEFT.apply(left);
-                let right = Colours::LOGO_RIGHT.apply(right);
+    pub fn progress_bar_startup(&mut self) {}
 
-                write!(&mut buffer, "{}", left)?;
-                writeln!(&mut buffer, "{}", right)?;
+    /// Returns a handle to the overall progress bar.
+    pub fn progress_bar(&self) -> ProgressBar {
+        self.progress_overall.clone()
+    }
 
-                Result::<String, fmt::Error>::Ok(buffer)
-            })
-            .expect("Failed to construct logo.");
-        let mut stderr = io::stderr();
-        stderr.write_all(prompt.as_bytes())?;
-        stderr.flush()?;
+    /// Returns a handle to the per-record progress bars.
+    pub fn worker_progress(&self) -> WorkerProgress {
+        self.worker_progress.clone()
+    }
 
-        Ok(())
+    /// Returns a handle to the message shown after the overall progress bar.
+    pub fn progress_message(&self) -> ProgressMessage {
+        self.progress_message.clone()
     }
 
-    pub fn progress_bar_startup(&mut self) {}
+    /// Returns a handle to the per-stage progress bars.
+    pub fn stage_progress(&self) -> StageProgress {
+        self.stage_progress.clone()
+    }
+
+    /// Returns the report of records processed so far.
+    pub fn report(&self) -> &Report<R> {
+        &self.report
+    }
+
+    /// Records the bytes written to the output file.
+    pub fn set_output_stats(&mut self, output_stats: OutputStats) {
+        self.report.output_stats = Some(output_stats);
+    }
+
+    /// Records the number of requests made with each key.
+    pub fn set_credential_usage(&mut self, credential_usage: BTreeMap<String, usize>) {
+        self.report.credential_usage = credential_usage;
+    }
+
+    /// Records the number of connections opened and reused.
+    pub fn set_connection_stats(&mut self, connection_stats: ConnectionStats) {
+        self.report.connection_stats = Some(connection_stats);
+    }
+
+    /// Classifies failed records by the `[classification]` rules in the
+    /// config file.
+    pub fn set_classifier(&mut self, classifier: Classifier) {
+        self.report.classified = !classifier.is_empty();
+        self.classifier = classifier;
+    }
+
+    /// Records how often records waited for an event subscriber.
+    pub fn set_event_backpressure(&mut self, event_backpressure: Backpressure) {
+        self.report.event_backpressure = Some(event_backpressure);
+    }
+
+    /// Records the resources sampled with `--profile`.
+    pub fn set_resources(&mut self, resources: Resources) {
+        self.report.resources = Some(resources);
+    }
 
     /// Synchronizes the progress bar with the state of processing.
     pub async fn progress_bar_sync(&mut self) {
-        if let Some(mut interrupt_rx) = self.interrupt_rx.take() {
+        self.progress_bar_sync_internal().await;
+        if !self.report.interrupted {
+            self.progress_overall.finish();
+        }
+        self.worker_progress.finish();
+        self.stage_progress.finish();
+
+        self.report.duration = self.start.elapsed();
+        self.report.records_slowest = self
+            .records_slowest
+            .clone()
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(record_duration)| (record_duration.record, record_duration.duration))
+            .collect();
+        self.report.stage_average_durations = StageKind::ALL
+            .iter()
+            .filter_map(|stage| {
+                self.stage_timings
+                    .average(*stage)
+                    .map(|average| (String::from(stage.name()), average))
+            })
+            .collect();
+    }
+
+    async fn progress_bar_sync_internal(&mut self) {
+        let mut plain_interval = self.plain_interval.map(|plain_interval| {
+            tokio::time::interval_at(tokio::time::Instant::now() + plain_interval, plain_interval)
+        });
+
+        loop {
+            let plain_tick = async {
+                match plain_interval.as_mut() {
+                    Some(plain_interval) => {
+                        plain_interval.tick().await;
+                    }
+                    None => future::pending().await,
+                }
+            };
+            let throttled_until = self.throttled_until;
+            let throttle_end = async move {
+                match throttled_until {
+                    Some(throttled_until) => tokio::time::sleep_until(throttled_until.into()).await,
+                    None => future::pending().await,
+                }
+            };
+
             tokio::select! {
-                () = self.progress_bar_sync_internal() => {
-                    self.progress_overall.finish();
+                event = self.events.recv() => match event {
+                    Some(RunEvent::RecordsDiscovered { record_count }) => {
+                        self.records_discovered(record_count)
+                    }
+                    Some(RunEvent::RecordDuplicate { .. }) => self.record_duplicate(),
+                    Some(RunEvent::Throttled { retry_after }) => self.throttled(retry_after),
+                    Some(RunEvent::CircuitChanged(circuit_state)) => {
+                        self.circuit_state = circuit_state;
+                        self.progress_message.set_circuit_state(circuit_state)
+                    }
+                    Some(RunEvent::RecordRetrieved(record_progress)) => {
+                        self.record_progress_update(record_progress)
+                    }
+                    Some(RunEvent::RecordWritten { record }) => {
+                        self.progress_message.set_record(record.label())
+                    }
+                    Some(RunEvent::RecordFailed { record, stage, error }) => {
+                        self.progress_message.set_error(
+                            record.label(),
+                            &format!("{} failed: {}", stage.name(), error),
+                        )
+                    }
+                    Some(RunEvent::RecordPanicked { record, stage, message, looked_up }) => {
+                        self.record_panicked(record, stage, &message, looked_up)
+                    }
+                    // Records in flight are still reported, until processing
+                    // finishes or they are abandoned.
+                    Some(RunEvent::Interrupted(reason)) => self.interrupted(reason),
+                    Some(RunEvent::RecordsAbandoned { record_count }) => {
+                        self.report.record_abandoned_count = record_count
+                    }
+                    Some(RunEvent::ProcessingFinished) | None => break,
+                    Some(
+                        RunEvent::RunStarted { .. } | RunEvent::RunFinished(_),
+                    ) => {}
                 },
-                _ = interrupt_rx.recv() => {},
+                () = plain_tick => self.print_plain_status(),
+                () = throttle_end => self.throttle_ended(),
             }
+        }
 
-        // Empty remaining queue.
-        // self.progress_bar_sync_internal().await;
-        } else {
-            self.progress_bar_sync_internal().await;
-            self.progress_overall.finish();
+        // Later events aren't needed, so publishing them doesn't wait for the
+        // reporter.
+        self.events.close();
+
+        if self.throttled_since.is_some() {
+            self.throttle_ended();
+        }
+
+        let progress_overall = &self.progress_overall;
+        if let Some(interruption) = self.report.interruption.as_mut() {
+            interruption.record_remaining_count = progress_overall
+                .length()
+                .map(|length| length.saturating_sub(progress_overall.position()) as usize);
+        }
+
+        if self.plain_interval.is_some() {
+            self.print_plain_status();
         }
     }
 
-    async fn progress_bar_sync_internal(&mut self) {
-        while let Some(process_result) = self.progress_receiver.recv().await {
-            match process_result {
-                PropertyInfoResult::Success => {
-                    self.report.record_processed_successful_count += 1;
+    /// Extends the progress bars to include records found after the run
+    /// started.
+    ///
+    /// Progress without a total stays that way, as the total is still unknown.
+    fn records_discovered(&self, record_count: usize) {
+        if let Some(length) = self.progress_overall.length() {
+            let length = length + record_count as u64;
+            self.progress_overall.set_length(length);
+            self.stage_progress.set_length(length);
+        }
+    }
+
+    /// Counts a record left out because its ID appeared earlier in the input.
+    fn record_duplicate(&mut self) {
+        self.report.record_duplicate_count += 1;
+        self.progress_overall.inc(1);
+        self.stage_progress.skipped();
+    }
+
+    /// Shows that the server is throttling requests, until `retry_after` has
+    /// passed.
+    fn throttled(&mut self, retry_after: Duration) {
+        let now = Instant::now();
+        let throttled_until = now + retry_after;
+        self.report.throttle_count += 1;
+        if self.throttled_since.is_none() {
+            self.throttled_since = Some(now);
+            self.progress_message.set_throttled(true);
+        }
+        self.throttled_until = Some(
+            self.throttled_until
+                .map_or(throttled_until, |until| until.max(throttled_until)),
+        );
+    }
+
+    /// Records how long the server throttled requests for, once it allows
+    /// requests again.
+    fn throttle_ended(&mut self) {
+        if let Some(throttled_since) = self.throttled_since.take() {
+            self.report.throttled_duration += throttled_since.elapsed();
+        }
+        self.throttled_until = None;
+        self.progress_message.set_throttled(false);
+    }
+
+    /// Records why the run was interrupted.
+    ///
+    /// The records remaining are counted once processing finishes, as records
+    /// in flight may still finish.
+    fn interrupted(&mut self, reason: InterruptReason) {
+        self.report.interrupted = true;
+        self.report.interruption = Some(Interruption {
+            reason,
+            timestamp: SystemTime::now(),
+            record_remaining_count: None,
+        });
+    }
+
+    /// Records that a stage panicked while processing a record.
+    ///
+    /// Records that panicked before their information was looked up haven't
+    /// been counted yet, so they are counted as failed.
+    fn record_panicked(&mut self, record: R, stage: StageKind, message: &str, looked_up: bool) {
+        let error = format!("{} stage panicked: {}", stage.name(), message);
+        self.progress_message.set_error(record.label(), &error);
+        let record_failure = RecordFailure {
+            record,
+            error,
+            attempts: 0,
+            class: ErrorClass::Fatal,
+            timestamp: SystemTime::now(),
+        };
+        if !looked_up {
+            self.report
+                .records_processed_failed
+                .push(record_failure.clone());
+            self.progress_overall.inc(1);
+            self.eta.record_completed();
+        }
+        self.report.records_panicked.push(record_failure);
+    }
+
+    fn record_progress_update(&mut self, record_progress: RecordProgress<R, I>) {
+        let RecordProgress {
+            record,
+            info,
+            chaos_events,
+            attempts,
+            duration,
+            sources,
+        } = record_progress;
+
+        self.report.chaos_events += chaos_events;
+        sources.into_iter().for_each(|(source, succeeded)| {
+            let source_counts = self.report.source_counts.entry(source).or_default();
+            if succeeded {
+                source_counts.succeeded_count += 1;
+            } else {
+                source_counts.failed_count += 1;
+            }
+        });
+        if let Some(warmup_end) = self.warmup_end() {
+            self.report.latency_histogram.record(duration);
+            if self.report_options.slowest_count > 0 {
+                self.records_slowest
+                    .push(Reverse(RecordDuration { duration, record }));
+                if self.records_slowest.len() > self.report_options.slowest_count {
+                    self.records_slowest.pop();
                 }
-                PropertyInfoResult::SuccessPartial => {
+            }
+
+            let minute = (warmup_end.elapsed().as_secs() / 60) as usize;
+            if self.report.records_per_minute.len() <= minute {
+                self.report.records_per_minute.resize(minute + 1, 0);
+            }
+            self.report.records_per_minute[minute] += 1;
+        }
+        match info.status() {
+            RecordStatus::Success => {
+                self.report.record_processed_successful_count += 1;
+            }
+            RecordStatus::SuccessPartial => {
+                self.report.record_processed_info_missing_count += 1;
+                info.missing_fields().into_iter().for_each(|field| {
+                    *self.report.missing_field_counts.entry(field).or_default() += 1;
+                });
+            }
+            RecordStatus::Error => {
+                let error = info.error().unwrap_or_default();
+                let class = self.classifier.classify(&error);
+                if class == ErrorClass::Partial {
                     self.report.record_processed_info_missing_count += 1;
-                }
-                PropertyInfoResult::Error(record, error) => {
-                    self.report.records_processed_failed.push((record, error));
+                } else {
+                    self.progress_message.set_error(record.label(), &error);
+                    self.report.records_processed_failed.push(RecordFailure {
+                        record,
+                        error,
+                        attempts,
+                        class,
+                        timestamp: SystemTime::now(),
+                    });
                 }
             }
-            self.progress_overall.inc(1);
         }
+        self.progress_overall.inc(1);
+        self.eta.record_completed();
+    }
+
+    /// Returns when the warmup ended, or `None` if the record that just
+    /// finished is part of it.
+    ///
+    /// The warmup ends once both `--warmup-records` records have finished and
+    /// `--warmup-duration` has passed.
+    fn warmup_end(&mut self) -> Option<Instant> {
+        if self.warmup_end.is_some() {
+            return self.warmup_end;
+        }
+
+        let ReportOptions {
+            warmup_record_count,
+            warmup_duration,
+            ..
+        } = self.report_options;
+        let warmup = self.report.warmup.get_or_insert_with(Warmup::default);
+        let elapsed = self.start.elapsed();
+        if warmup.record_count < warmup_record_count || elapsed < warmup_duration {
+            warmup.record_count += 1;
+            warmup.duration = elapsed;
+            None
+        } else {
+            warmup.duration = warmup.duration.max(warmup_duration);
+            let warmup_end = self.start + warmup.duration;
+            self.warmup_end = Some(warmup_end);
+            self.warmup_end
+        }
+    }
+
+    /// Prints a status line to stderr, e.g.
+    /// `processed 120/500, 3 errors, eta 2m10s (done at 14:32:05)`,
+    /// or `processed 120, 3 errors, 4.2/s` when the number of records isn't known.
+    ///
+    /// `, throttled` is appended while the server is throttling requests, and
+    /// `, circuit open` while the circuit breaker is open.
+    fn print_plain_status(&self) {
+        let throttled = if self.throttled_since.is_some() {
+            ", throttled"
+        } else {
+            ""
+        };
+        let throttled = match self.circuit_state {
+            CircuitState::Closed => String::from(throttled),
+            circuit_state => format!("{}, circuit {}", throttled, circuit_state),
+        };
+        if self.progress_overall.length().is_none() {
+            eprintln!(
+                "processed {}, {} errors, {:.1}/s{}",
+                self.progress_overall.position(),
+                self.report.records_processed_failed.len(),
+                self.progress_overall.per_sec(),
+                throttled
+            );
+            return;
+        }
+
+        let record_remaining_count = self
+            .progress_overall
+            .length()
+            .unwrap_or_default()
+            .saturating_sub(self.progress_overall.position());
+        let eta = match self.eta.remaining(record_remaining_count) {
+            Some(remaining) => format!(
+                "{} (done at {})",
+                humantime::format_duration(Duration::from_secs(remaining.as_secs()))
+                    .to_string()
+                    .replace(' ', ""),
+                Eta::finish_at(remaining)
+            ),
+            None => String::from("-"),
+        };
+        eprintln!(
+            "processed {}/{}, {} errors, eta {}{}",
+            self.progress_overall.position(),
+            self.progress_overall.length().unwrap_or_default(),
+            self.report.records_processed_failed.len(),
+            eta,
+            throttled
+        );
+    }
+
+    /// Prints a summary line to stderr, e.g.
+    /// `processed 47 records (12 missing info), 3 errors, 5 skipped in 10s 234ms`.
+    fn print_summary(&self) {
+        let report = &self.report;
+        eprintln!(
+            "processed {} records ({} missing info), {} errors, {} skipped in {}{}",
+            report.record_processed_successful_count + report.record_processed_info_missing_count,
+            report.record_processed_info_missing_count,
+            report.records_processed_failed.len(),
+            report.record_skipped_count,
+            Self::format_duration(report.duration),
+            if report.interrupted {
+                " (interrupted)"
+            } else {
+                ""
+            }
+        );
+    }
+
+    /// Formats a duration for humans, e.g. `1s 234ms`.
+    ///
+    /// Durations are truncated to milliseconds, or microseconds if they are
+    /// shorter than a millisecond.
+    pub fn format_duration(duration: Duration) -> String {
+        let duration = if duration < Duration::from_millis(1) {
+            Duration::from_micros(duration.as_micros() as u64)
+        } else {
+            Duration::from_millis(duration.as_millis() as u64)
+        };
+        humantime::format_duration(duration).to_string()
+    }
+
+    /// Returns the widest an error message can be in a table whose other
+    /// columns take `columns_width`, so that rows fit the terminal, or `None`
+    /// if stderr isn't a terminal.
+    fn error_width(columns_width: usize) -> Option<usize> {
+        const ERROR_WIDTH_MIN: usize = 20;
+
+        let terminal = TerminalCapabilities::detect();
+        terminal.width.filter(|_| terminal.is_tty).map(|width| {
+            usize::from(width)
+                .saturating_sub(columns_width)
+                .max(ERROR_WIDTH_MIN)
+        })
+    }
+
+    /// Returns the error message, truncated with `…` if it is wider than
+    /// `error_width`.
+    fn error_truncate(error: &str, error_width: Option<usize>) -> Cow<'_, str> {
+        match error_width {
+            Some(error_width) => console::truncate_str(error, error_width, "…"),
+            None => Cow::Borrowed(error),
+        }
+    }
+
+    /// Writes a bar showing the shares of records that were processed, were
+    /// missing information, failed, and were skipped, as wide as the terminal.
+    ///
+    /// Each share has its own block character, so the bar can be read
+    /// without colour.
+    fn write_outcome_chart(report: &mut String, self_report: &Report<R>) -> fmt::Result {
+        const WIDTH_DEFAULT: usize = 60;
+
+        let theme = Colours::theme();
+        let skip_style = ContentStyle::default();
+        let outcomes = [
+            (
+                '█',
+                "processed",
+                self_report.record_processed_successful_count,
+                &theme.report_item_success,
+            ),
+            (
+                '▓',
+                "missing info",
+                self_report.record_processed_info_missing_count,
+                &theme.report_item_partial_success,
+            ),
+            (
+                '▒',
+                "errors",
+                self_report.records_processed_failed.len(),
+                &theme.report_item_failure,
+            ),
+            (
+                '░',
+                "skipped",
+                self_report.record_skipped_count
+                    + self_report.record_filtered_count
+                    + self_report.record_duplicate_count,
+                &skip_style,
+            ),
+        ];
+        let total = outcomes.iter().map(|(_, _, count, _)| count).sum::<usize>();
+        if total == 0 {
+            return Ok(());
+        }
+
+        let terminal = TerminalCapabilities::detect();
+        let width = terminal
+            .width
+            .filter(|_| terminal.is_tty)
+            .map(usize::from)
+            .unwrap_or(WIDTH_DEFAULT);
+
+        // Rounds where each share ends, so the shares add up to the width.
+        let mut count_cumulative = 0;
+        let mut cell_end_previous = 0;
+        outcomes.iter().try_for_each(|(block, _, count, style)| {
+            count_cumulative += count;
+            let cell_end = (count_cumulative * width + total / 2) / total;
+            let cell_count = cell_end - cell_end_previous;
+            cell_end_previous = cell_end;
+            if cell_count > 0 {
+                write!(
+                    report,
+                    "{}",
+                    style.apply(block.to_string().repeat(cell_count))
+                )?;
+            }
+            Ok(())
+        })?;
+        writeln!(report)?;
+
+        let legend = outcomes
+            .iter()
+            .filter(|(_, _, count, _)| *count > 0)
+            .map(|(block, name, count, style)| {
+                format!(
+                    "{} {} {:.0}%",
+                    style.apply(block),
+                    name,
+                    *count as f64 * 100.0 / total as f64
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(report, "{}", legend)
+    }
+
+    /// Writes how many records processed with missing info each field was
+    /// missing from, most often missing first.
+    fn write_missing_fields(report: &mut String, self_report: &Report<R>) -> fmt::Result {
+        writeln!(
+            report,
+            "{count:>5} | {field:12} | {share:>7}",
+            count = Colours::theme().report_label.apply("count"),
+            field = Colours::theme().report_label.apply("field"),
+            share = Colours::theme().report_label.apply("share")
+        )?;
+        writeln!(report, "----- | ------------ | -------")?;
+
+        let mut missing_field_counts = self_report.missing_field_counts.iter().collect::<Vec<_>>();
+        missing_field_counts.sort_by(|(field_a, count_a), (field_b, count_b)| {
+            count_b.cmp(count_a).then_with(|| field_a.cmp(field_b))
+        });
+        let record_count = self_report.record_processed_info_missing_count.max(1);
+        missing_field_counts
+            .into_iter()
+            .try_for_each(|(field, count)| {
+                writeln!(
+                    report,
+                    "{count:5} | {field:12} | {share:>6.1}%",
+                    count = count,
+                    field = Colours::theme().report_item_partial_success.apply(field),
+                    share = *count as f64 / record_count as f64 * 100.0
+                )
+            })
+    }
+
+    /// Writes the peak and average of each resource sampled with `--profile`.
+    fn write_resources(report: &mut String, resources: &Resources) -> fmt::Result {
+        let Resources {
+            sample_count,
+            memory_bytes,
+            cpu_percent,
+            record_in_flight_count,
+            event_queue_depth,
+        } = resources;
+        writeln!(
+            report,
+            "{:<35} {:>7}",
+            Colours::theme().report_label.apply("* Samples:"),
+            sample_count
+        )?;
+        if let Some(memory_bytes) = memory_bytes {
+            writeln!(
+                report,
+                "{:<35} {:>7}",
+                Colours::theme()
+                    .report_label
+                    .apply("* Memory (peak / average):"),
+                format!(
+                    "{} / {}",
+                    HumanBytes(memory_bytes.peak as u64),
+                    HumanBytes(memory_bytes.average as u64)
+                )
+            )?;
+        }
+        if let Some(cpu_percent) = cpu_percent {
+            writeln!(
+                report,
+                "{:<35} {:>7}",
+                Colours::theme()
+                    .report_label
+                    .apply("* CPU (peak / average):"),
+                format!("{:.0}% / {:.0}%", cpu_percent.peak, cpu_percent.average)
+            )?;
+        }
+        writeln!(
+            report,
+            "{:<35} {:>7}",
+            Colours::theme()
+                .report_label
+                .apply("* In flight (peak / average):"),
+            format!(
+                "{:.0} / {:.1}",
+                record_in_flight_count.peak, record_in_flight_count.average
+            )
+        )?;
+        writeln!(
+            report,
+            "{:<35} {:>7}",
+            Colours::theme()
+                .report_label
+                .apply("* Event queue (peak / average):"),
+            format!(
+                "{:.0} / {:.1}",
+                event_queue_depth.peak, event_queue_depth.average
+            )
+        )
+    }
+
+    /// Writes a line counting the rows left out of an error table, if any.
+    fn write_error_rows_omitted(report: &mut String, row_omitted_count: usize) -> fmt::Result {
+        if row_omitted_count > 0 {
+            writeln!(
+                report,
+                "… and {} more (see --errors-out)",
+                row_omitted_count
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes a row for every failed record, up to `max_rows`.
+    fn write_errors_full(
+        report: &mut String,
+        record_failures: &[RecordFailure<R>],
+        max_rows: usize,
+    ) -> fmt::Result {
+        // Width of the `#` and `title_number` columns, with separators.
+        let error_width = Self::error_width(24);
+
+        // Error table headings
+        writeln!(
+            report,
+            "{row_index:>5} | {title_number:<13} | {error:30}",
+            row_index = Colours::theme().report_label.apply("#"),
+            title_number = Colours::theme().report_label.apply("title_number"),
+            error = Colours::theme().report_label.apply("error")
+        )?;
+        writeln!(
+            report,
+            "----- | ------------- | ------------------------------"
+        )?;
+        record_failures
+            .iter()
+            .take(max_rows)
+            .try_for_each(|record_failure| {
+                writeln!(
+                    report,
+                    "{row_index:5} | {title_number:<13} | {error:30}",
+                    row_index = record_failure.record.id(),
+                    title_number = Colours::theme()
+                        .report_error_item
+                        .apply(record_failure.record.label()),
+                    error = Colours::theme()
+                        .report_error_message
+                        .apply(Self::error_truncate(&record_failure.error, error_width))
+                )
+            })?;
+
+        Self::write_error_rows_omitted(report, record_failures.len().saturating_sub(max_rows))
+    }
+
+    /// Writes a row per error message, up to `max_rows`, with the number of
+    /// records and some examples.
+    fn write_errors_grouped(
+        report: &mut String,
+        self_report: &Report<R>,
+        max_rows: usize,
+    ) -> fmt::Result {
+        const EXAMPLE_COUNT: usize = 3;
+
+        // Width of the `count` column, and room for some examples.
+        let error_width = Self::error_width(41);
+
+        // Error table headings
+        writeln!(
+            report,
+            "{count:>5} | {error:30} | {examples}",
+            count = Colours::theme().report_label.apply("count"),
+            error = Colours::theme().report_label.apply("error"),
+            examples = Colours::theme().report_label.apply("examples")
+        )?;
+        writeln!(
+            report,
+            "----- | ------------------------------ | ------------------------------"
+        )?;
+        let errors_by_message = self_report.errors_by_message();
+        errors_by_message
+            .iter()
+            .take(max_rows)
+            .try_for_each(|(error, property_records)| {
+                let mut examples = property_records
+                    .iter()
+                    .take(EXAMPLE_COUNT)
+                    .map(|property_record| property_record.label())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if property_records.len() > EXAMPLE_COUNT {
+                    write!(
+                        &mut examples,
+                        ", … (+{})",
+                        property_records.len() - EXAMPLE_COUNT
+                    )?;
+                }
+
+                writeln!(
+                    report,
+                    "{count:5} | {error:30} | {examples}",
+                    count = property_records.len(),
+                    error = Colours::theme()
+                        .report_error_message
+                        .apply(Self::error_truncate(error, error_width)),
+                    examples = Colours::theme().report_error_item.apply(examples)
+                )
+            })?;
+        Self::write_error_rows_omitted(report, errors_by_message.len().saturating_sub(max_rows))?;
+
+        let truncated = errors_by_message
+            .iter()
+            .any(|(_, property_records)| property_records.len() > EXAMPLE_COUNT);
+        if truncated {
+            writeln!(report)?;
+            writeln!(report, "Use `--errors-full` to list every failed record.")?;
+        }
+
+        Ok(())
     }
 
     /// Writes the report to stderr.
-    pub fn print_report(&self) -> fmt::Result {
+    pub fn print_report(&self) -> Result<(), Error> {
+        if self.report_options.summary_only {
+            self.print_summary();
+            return Ok(());
+        }
+
         let self_report = &self.report;
         let failed_count = self_report.records_processed_failed.len();
 
@@ -142,27 +1069,65 @@ impl Reporter {
         writeln!(
             &mut report,
             "{}",
-            Colours::REPORT_BORDER
+            Colours::theme()
+                .report_border
                 .apply("------------------------------------------------------------")
         )?;
 
-        writeln!(&mut report, "{}", Colours::REPORT_TITLE.apply("# Report"))?;
+        writeln!(
+            &mut report,
+            "{}",
+            Colours::theme().report_title.apply("# Report")
+        )?;
         writeln!(&mut report)?;
 
-        writeln!(&mut report, "{}", Colours::REPORT_TITLE.apply("## Summary"))?;
+        // Run metadata
+        writeln!(
+            &mut report,
+            "{:<35} {}",
+            Colours::theme().report_label.apply("* Run ID:"),
+            self_report.run.run_id
+        )?;
+        writeln!(
+            &mut report,
+            "{:<35} {}",
+            Colours::theme().report_label.apply("* Version:"),
+            self_report.run.version
+        )?;
+        writeln!(
+            &mut report,
+            "{:<35} {}",
+            Colours::theme().report_label.apply("* Started:"),
+            self_report.run.started_at
+        )?;
+        writeln!(&mut report)?;
+
+        let summary_title = if self_report.streamed {
+            "## Summary (since start)"
+        } else {
+            "## Summary"
+        };
+        writeln!(
+            &mut report,
+            "{}",
+            Colours::theme().report_title.apply(summary_title)
+        )?;
+        writeln!(&mut report)?;
+        Self::write_outcome_chart(&mut report, self_report)?;
         writeln!(&mut report)?;
 
         // Processed item count
         write!(
             &mut report,
             "{:<35} ",
-            Colours::REPORT_LABEL.apply("* Records processed:"),
+            Colours::theme().report_label.apply("* Records processed:"),
         )?;
         if self_report.record_processed_successful_count > 0 {
             writeln!(
                 &mut report,
                 "{:>7}",
-                Colours::REPORT_ITEM_SUCCESS
+                Colours::theme()
+                    .report_item_success
                     .apply(self_report.record_processed_successful_count.to_string())
             )?;
         } else {
@@ -177,13 +1142,16 @@ impl Reporter {
         write!(
             &mut report,
             "{:<35} ",
-            Colours::REPORT_LABEL.apply("* Records processed (missing info):"),
+            Colours::theme()
+                .report_label
+                .apply("* Records processed (missing info):"),
         )?;
         if self_report.record_processed_info_missing_count > 0 {
             writeln!(
                 &mut report,
                 "{:>7}",
-                Colours::REPORT_ITEM_PARTIAL_SUCCESS
+                Colours::theme()
+                    .report_item_partial_success
                     .apply(self_report.record_processed_info_missing_count.to_string())
             )?;
         } else {
@@ -198,74 +1166,477 @@ impl Reporter {
         write!(
             &mut report,
             "{:<35} ",
-            Colours::REPORT_LABEL.apply("* Records with errors:"),
+            Colours::theme()
+                .report_label
+                .apply("* Records with errors:"),
         )?;
         if failed_count > 0 {
             writeln!(
                 &mut report,
                 "{:>7}",
-                Colours::REPORT_ITEM_FAILURE.apply(failed_count.to_string())
+                Colours::theme()
+                    .report_item_failure
+                    .apply(failed_count.to_string())
             )?;
         } else {
             writeln!(&mut report, "{:>7}", failed_count)?;
         }
+        if self_report.classified {
+            let retryable_count = self_report
+                .records_processed_failed
+                .iter()
+                .filter(|record_failure| record_failure.class == ErrorClass::Retryable)
+                .count();
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme()
+                    .report_label
+                    .apply("* Errors (retryable / fatal):"),
+                format!(
+                    "{} / {}",
+                    retryable_count,
+                    self_report.records_processed_failed.len() - retryable_count
+                )
+            )?;
+        }
+        if self_report.record_abandoned_count > 0 {
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme()
+                    .report_label
+                    .apply("* Records abandoned (in flight):"),
+                Colours::theme()
+                    .report_item_failure
+                    .apply(self_report.record_abandoned_count.to_string())
+            )?;
+        }
+        if !self_report.records_panicked.is_empty() {
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme().report_label.apply("* Records panicked:"),
+                Colours::theme()
+                    .report_item_failure
+                    .apply(self_report.records_panicked.len().to_string())
+            )?;
+        }
 
         // Skipped item count
         writeln!(
             &mut report,
             "{:<35} {:>7}",
-            Colours::REPORT_LABEL.apply("* Records skipped (pre-existing):"),
+            Colours::theme()
+                .report_label
+                .apply("* Records skipped (pre-existing):"),
             self_report.record_skipped_count
         )?;
+        if self_report.record_filtered_count > 0 {
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme()
+                    .report_label
+                    .apply("* Records skipped (filtered):"),
+                self_report.record_filtered_count
+            )?;
+        }
+        if self_report.record_duplicate_count > 0 {
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme()
+                    .report_label
+                    .apply("* Records skipped (duplicate):"),
+                self_report.record_duplicate_count
+            )?;
+        }
 
-        if failed_count > 0 {
+        if self_report.throttle_count > 0 {
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme()
+                    .report_label
+                    .apply("* Throttled by server:"),
+                format!(
+                    "{} times, {}",
+                    self_report.throttle_count,
+                    Self::format_duration(self_report.throttled_duration)
+                )
+            )?;
+        }
+
+        // Throughput
+        writeln!(
+            &mut report,
+            "{:<35} {:>7}",
+            Colours::theme().report_label.apply("* Duration:"),
+            Self::format_duration(self_report.duration)
+        )?;
+        if let Some(warmup) = self_report.warmup {
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme()
+                    .report_label
+                    .apply("* Warmup (excluded from stats):"),
+                format!(
+                    "{} records, {}",
+                    warmup.record_count,
+                    Self::format_duration(warmup.duration)
+                )
+            )?;
+        }
+        writeln!(
+            &mut report,
+            "{:<35} {:>7}",
+            Colours::theme()
+                .report_label
+                .apply("* Throughput (average):"),
+            format!("{:.1}/s", self_report.throughput_average())
+        )?;
+        writeln!(
+            &mut report,
+            "{:<35} {:>7}",
+            Colours::theme()
+                .report_label
+                .apply("* Throughput (peak minute):"),
+            format!("{:.1}/s", self_report.throughput_peak())
+        )?;
+
+        if let Some(connection_stats) = self_report.connection_stats {
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme()
+                    .report_label
+                    .apply("* Connections (opened / reused):"),
+                format!(
+                    "{} / {}",
+                    connection_stats.opened_count, connection_stats.reused_count
+                )
+            )?;
+        }
+
+        if let Some(event_backpressure) = self_report.event_backpressure {
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme()
+                    .report_label
+                    .apply("* Event waits (count / time):"),
+                format!(
+                    "{} / {}",
+                    event_backpressure.wait_count,
+                    Self::format_duration(event_backpressure.wait_duration)
+                )
+            )?;
+        }
+
+        // Output size
+        if let Some(output_stats) = self_report.output_stats {
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme()
+                    .report_label
+                    .apply("* Output bytes written:"),
+                output_stats.written_bytes
+            )?;
+            if let (Some(compression), Some(compression_ratio)) =
+                (output_stats.compression, output_stats.compression_ratio())
+            {
+                writeln!(
+                    &mut report,
+                    "{:<35} {:>7}",
+                    Colours::theme()
+                        .report_label
+                        .apply(format!("* Compression ratio ({}):", compression)),
+                    format!("{:.1}x", compression_ratio)
+                )?;
+            }
+        }
+
+        let latency_histogram = &self_report.latency_histogram;
+        if let (Some(min), Some(max)) = (latency_histogram.min(), latency_histogram.max()) {
+            writeln!(&mut report)?;
+            writeln!(
+                &mut report,
+                "{}",
+                Colours::theme().report_title.apply("## Latency")
+            )?;
+            writeln!(&mut report)?;
+
+            let percentiles = [("* p50:", 50.0), ("* p95:", 95.0), ("* p99:", 99.0)]
+                .iter()
+                .filter_map(|(label, percentile)| {
+                    latency_histogram
+                        .percentile(*percentile)
+                        .map(|duration| (*label, duration))
+                })
+                .collect::<Vec<_>>();
+            std::iter::once(("* Min:", min))
+                .chain(percentiles)
+                .chain(std::iter::once(("* Max:", max)))
+                .try_for_each(|(label, duration)| {
+                    writeln!(
+                        &mut report,
+                        "{:<35} {:>7}",
+                        Colours::theme().report_label.apply(label),
+                        Self::format_duration(duration)
+                    )
+                })?;
+        }
+
+        if self.stage_timings.count(StageKind::RateLimit) > 0 {
             writeln!(&mut report)?;
             writeln!(
                 &mut report,
                 "{}",
-                Colours::REPORT_TITLE_ERROR.apply("## Errors"),
+                Colours::theme().report_title.apply("## Stages")
             )?;
             writeln!(&mut report)?;
 
-            // Error table headings
+            // Stage table headings
+            writeln!(
+                &mut report,
+                "{stage:<14} | {total:>10} | {average:>10}",
+                stage = Colours::theme().report_label.apply("stage"),
+                total = Colours::theme().report_label.apply("total"),
+                average = Colours::theme().report_label.apply("average")
+            )?;
+            writeln!(&mut report, "-------------- | ---------- | ----------")?;
+            StageKind::ALL.iter().try_for_each(|stage| {
+                writeln!(
+                    &mut report,
+                    "{stage:<14} | {total:>10} | {average:>10}",
+                    stage = stage.name(),
+                    total = Self::format_duration(self.stage_timings.total(*stage)),
+                    average = self
+                        .stage_timings
+                        .average(*stage)
+                        .map(Self::format_duration)
+                        .unwrap_or_else(|| String::from("-"))
+                )
+            })?;
+        }
+
+        let ReportOptions {
+            slowest_count,
+            errors_full,
+            max_error_rows,
+            warmup_record_count: _,
+            warmup_duration: _,
+            summary_only: _,
+        } = self.report_options;
+        if slowest_count > 0 && !self_report.records_slowest.is_empty() {
+            writeln!(&mut report)?;
             writeln!(
                 &mut report,
-                "{row_index:>5} | {title_number:<13} | {error:30}",
-                row_index = Colours::REPORT_LABEL.apply("#"),
-                title_number = Colours::REPORT_LABEL.apply("title_number"),
-                error = Colours::REPORT_LABEL.apply("error")
+                "{}",
+                Colours::theme().report_title.apply("## Slowest Records")
             )?;
+            writeln!(&mut report)?;
+
+            // Slowest records table headings
             writeln!(
                 &mut report,
-                "----- | ------------- | ------------------------------"
+                "{row_index:>5} | {title_number:<13} | {duration:>10}",
+                row_index = Colours::theme().report_label.apply("#"),
+                title_number = Colours::theme().report_label.apply("title_number"),
+                duration = Colours::theme().report_label.apply("duration")
             )?;
-            self_report.records_processed_failed.iter().try_for_each(
-                |(property_record_meta, error)| {
+            writeln!(&mut report, "----- | ------------- | ----------")?;
+            self_report
+                .records_slowest
+                .iter()
+                .try_for_each(|(property_record, duration)| {
                     writeln!(
                         &mut report,
-                        "{row_index:5} | {title_number:<13} | {error:30}",
-                        row_index = property_record_meta.0,
-                        title_number = Colours::REPORT_ERROR_ITEM
-                            .apply(&format!("ABC123/{:02}", property_record_meta.0)),
-                        error = Colours::REPORT_ERROR_MESSAGE.apply(error.to_string().as_str())
+                        "{row_index:5} | {title_number:<13} | {duration:>10}",
+                        row_index = property_record.id(),
+                        title_number = Colours::theme()
+                            .report_error_item
+                            .apply(property_record.label()),
+                        duration = Self::format_duration(*duration)
                     )
-                },
+                })?;
+        }
+
+        if !self_report.credential_usage.is_empty() {
+            writeln!(&mut report)?;
+            writeln!(
+                &mut report,
+                "{}",
+                Colours::theme().report_title.apply("## Credentials")
+            )?;
+            writeln!(&mut report)?;
+            self_report
+                .credential_usage
+                .iter()
+                .try_for_each(|(label, request_count)| {
+                    writeln!(
+                        &mut report,
+                        "{:<35} {:>7}",
+                        Colours::theme().report_label.apply(format!("* {}:", label)),
+                        request_count
+                    )
+                })?;
+        }
+
+        if !self_report.missing_field_counts.is_empty() {
+            writeln!(&mut report)?;
+            writeln!(
+                &mut report,
+                "{}",
+                Colours::theme().report_title.apply("## Missing Fields")
+            )?;
+            writeln!(&mut report)?;
+            Self::write_missing_fields(&mut report, self_report)?;
+        }
+
+        if !self_report.source_counts.is_empty() {
+            writeln!(&mut report)?;
+            writeln!(
+                &mut report,
+                "{}",
+                Colours::theme().report_title.apply("## Enrichment")
+            )?;
+            writeln!(&mut report)?;
+            self_report
+                .source_counts
+                .iter()
+                .try_for_each(|(source, source_counts)| {
+                    writeln!(
+                        &mut report,
+                        "{:<35} {:>7}",
+                        Colours::theme()
+                            .report_label
+                            .apply(format!("* {} (succeeded / failed):", source)),
+                        format!(
+                            "{} / {}",
+                            source_counts.succeeded_count, source_counts.failed_count
+                        )
+                    )
+                })?;
+        }
+
+        let chaos_events = &self_report.chaos_events;
+        if chaos_events.any() {
+            writeln!(&mut report)?;
+            writeln!(
+                &mut report,
+                "{}",
+                Colours::theme().report_title.apply("## Chaos")
+            )?;
+            writeln!(&mut report)?;
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme().report_label.apply("* Connection resets:"),
+                chaos_events.connection_reset_count
+            )?;
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme().report_label.apply("* Rate limited (429):"),
+                chaos_events.rate_limited_count
+            )?;
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme().report_label.apply("* Re-authentications:"),
+                chaos_events.reauthentication_count
+            )?;
+        }
+
+        if let Some(interruption) = self_report.interruption.as_ref() {
+            writeln!(&mut report)?;
+            writeln!(
+                &mut report,
+                "{}",
+                Colours::theme().report_title_error.apply("## Interrupted"),
+            )?;
+            writeln!(&mut report)?;
+            writeln!(
+                &mut report,
+                "{:<35} {:>7}",
+                Colours::theme().report_label.apply("* Reason:"),
+                interruption.reason
+            )?;
+            writeln!(
+                &mut report,
+                "{:<35} {}",
+                Colours::theme().report_label.apply("* At:"),
+                humantime::format_rfc3339_millis(interruption.timestamp)
             )?;
+            if let Some(record_remaining_count) = interruption.record_remaining_count {
+                writeln!(
+                    &mut report,
+                    "{:<35} {:>7}",
+                    Colours::theme().report_label.apply("* Records remaining:"),
+                    record_remaining_count
+                )?;
+            }
+        }
+
+        if let Some(resources) = self_report.resources {
+            writeln!(&mut report)?;
+            writeln!(
+                &mut report,
+                "{}",
+                Colours::theme().report_title.apply("## Resources")
+            )?;
+            writeln!(&mut report)?;
+            Self::write_resources(&mut report, &resources)?;
+        }
+
+        if !self_report.records_panicked.is_empty() {
+            writeln!(&mut report)?;
+            writeln!(
+                &mut report,
+                "{}",
+                Colours::theme().report_title_error.apply("## Panics"),
+            )?;
+            writeln!(&mut report)?;
+            Self::write_errors_full(&mut report, &self_report.records_panicked, max_error_rows)?;
+        }
+
+        if failed_count > 0 {
+            writeln!(&mut report)?;
+            writeln!(
+                &mut report,
+                "{}",
+                Colours::theme().report_title_error.apply("## Errors"),
+            )?;
+            writeln!(&mut report)?;
+
+            if errors_full {
+                // `--errors-full` lists every failed record, so isn't truncated.
+                Self::write_errors_full(
+                    &mut report,
+                    &self_report.records_processed_failed,
+                    usize::MAX,
+                )?;
+            } else {
+                Self::write_errors_grouped(&mut report, self_report, max_error_rows)?;
+            }
         }
 
         writeln!(
             &mut report,
             "{}",
-            Colours::REPORT_BORDER
+            Colours::theme()
+                .report_border
                 .apply("------------------------------------------------------------")
         )?;
 
         let mut stderr = io::stderr();
         stderr
             .write_all(report.as_bytes())
-            .expect("Failed to write to stdout.");
-        stderr.flush().expect("Failed to flush stdout.");
-
-        Ok(())
+            .and_then(|()| stderr.flush())
+            .map_err(Error::io("write report to stderr"))
     }
 }
@@ -0,0 +1,332 @@
+use std::{fmt, io, str::FromStr, sync::Arc};
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use tokio::{
+    io::{AsyncWriteExt, Stdout},
+    sync::Mutex,
+};
+
+use crate::{
+    OutputRecord, OutputStats, OutputWriter, ParquetWriter, PropertyRecordPopulated, Publisher,
+    RecordProgress, Report, Store,
+};
+
+/// Kind of [`RecordSink`], chosen with `--sink`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SinkKind {
+    /// Writes records to `--output`.
+    File,
+    /// Writes records to stdout as JSON lines.
+    Stdout,
+    /// Upserts records into `--store`.
+    Db,
+    /// Publishes records to `--publish`.
+    Publish,
+    /// Discards records.
+    Null,
+}
+
+impl fmt::Display for SinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File => write!(f, "file"),
+            Self::Stdout => write!(f, "stdout"),
+            Self::Db => write!(f, "db"),
+            Self::Publish => write!(f, "publish"),
+            Self::Null => write!(f, "null"),
+        }
+    }
+}
+
+impl FromStr for SinkKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(Self::File),
+            "stdout" => Ok(Self::Stdout),
+            "db" => Ok(Self::Db),
+            "publish" => Ok(Self::Publish),
+            "null" => Ok(Self::Null),
+            _ => Err(format!(
+                "`{}` is not one of `file`, `stdout`, `db`, `publish`, `null`.",
+                s
+            )),
+        }
+    }
+}
+
+/// Destination for processed records.
+#[async_trait]
+pub trait RecordSink: fmt::Debug + Send + Sync {
+    /// Name of the sink, for messages.
+    fn name(&self) -> &'static str;
+
+    /// Writes a processed record.
+    ///
+    /// `sequence` is the record's position among the records processed in
+    /// this run.
+    async fn write(
+        &self,
+        sequence: usize,
+        property_record_populated: PropertyRecordPopulated,
+        record_progress: &RecordProgress,
+    ) -> io::Result<()>;
+
+    /// Writes anything still buffered, after the last record.
+    ///
+    /// Returns the bytes written, for sinks that write output files.
+    async fn finish(&self) -> io::Result<Option<OutputStats>> {
+        Ok(None)
+    }
+
+    /// Records the end of the run, once its report is complete.
+    async fn run_finished(&self, _report: &Report) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes records to the output files.
+#[derive(Debug)]
+pub struct FileSink(pub Arc<OutputWriter>);
+
+#[async_trait]
+impl RecordSink for FileSink {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn write(
+        &self,
+        sequence: usize,
+        property_record_populated: PropertyRecordPopulated,
+        _record_progress: &RecordProgress,
+    ) -> io::Result<()> {
+        self.0
+            .write_record(sequence, property_record_populated)
+            .await
+    }
+
+    async fn finish(&self) -> io::Result<Option<OutputStats>> {
+        self.0.finish().await.map(Some)
+    }
+}
+
+/// Writes records to the output file as Parquet, with `--output-format
+/// parquet`.
+#[derive(Debug)]
+pub struct ParquetSink(Mutex<Option<ParquetWriter>>);
+
+impl ParquetSink {
+    /// Returns a sink that writes to the Parquet file.
+    pub fn new(parquet_writer: ParquetWriter) -> Self {
+        Self(Mutex::new(Some(parquet_writer)))
+    }
+}
+
+#[async_trait]
+impl RecordSink for ParquetSink {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn write(
+        &self,
+        _sequence: usize,
+        property_record_populated: PropertyRecordPopulated,
+        _record_progress: &RecordProgress,
+    ) -> io::Result<()> {
+        let mut parquet_writer = self.0.lock().await;
+        match parquet_writer.as_mut() {
+            // Writing a full row group blocks while it is encoded.
+            Some(parquet_writer) => tokio::task::block_in_place(|| {
+                parquet_writer.write(0, OutputRecord::from(property_record_populated))
+            }),
+            None => {
+                tracing::debug!("Output already finished, discarding record.");
+                Ok(())
+            }
+        }
+    }
+
+    async fn finish(&self) -> io::Result<Option<OutputStats>> {
+        match self.0.lock().await.take() {
+            Some(parquet_writer) => {
+                let written_bytes = tokio::task::block_in_place(|| parquet_writer.finish())?;
+                Ok(Some(OutputStats {
+                    compression: None,
+                    uncompressed_bytes: 0,
+                    written_bytes,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Writes records to stdout as JSON lines, in the same form as the output
+/// file.
+#[derive(Debug)]
+pub struct StdoutSink(Mutex<Stdout>);
+
+impl StdoutSink {
+    /// Returns a sink that writes to stdout.
+    pub fn new() -> Self {
+        Self(Mutex::new(tokio::io::stdout()))
+    }
+}
+
+#[async_trait]
+impl RecordSink for StdoutSink {
+    fn name(&self) -> &'static str {
+        "stdout"
+    }
+
+    async fn write(
+        &self,
+        _sequence: usize,
+        property_record_populated: PropertyRecordPopulated,
+        _record_progress: &RecordProgress,
+    ) -> io::Result<()> {
+        let mut line = serde_json::to_vec(&OutputRecord::from(property_record_populated))?;
+        line.push(b'\n');
+
+        let mut stdout = self.0.lock().await;
+        stdout.write_all(&line).await?;
+        stdout.flush().await
+    }
+}
+
+/// Upserts records into the store.
+#[derive(Debug)]
+pub struct DbSink(pub Store);
+
+#[async_trait]
+impl RecordSink for DbSink {
+    fn name(&self) -> &'static str {
+        "db"
+    }
+
+    async fn write(
+        &self,
+        _sequence: usize,
+        _property_record_populated: PropertyRecordPopulated,
+        record_progress: &RecordProgress,
+    ) -> io::Result<()> {
+        self.0
+            .record_processed(record_progress)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    async fn run_finished(&self, report: &Report) -> io::Result<()> {
+        self.0.finish_run(report).await.map_err(io::Error::other)
+    }
+}
+
+/// Publishes records to a Kafka topic or NATS subject.
+#[derive(Debug)]
+pub struct PublishSink(pub Publisher);
+
+#[async_trait]
+impl RecordSink for PublishSink {
+    fn name(&self) -> &'static str {
+        "publish"
+    }
+
+    async fn write(
+        &self,
+        _sequence: usize,
+        property_record_populated: PropertyRecordPopulated,
+        _record_progress: &RecordProgress,
+    ) -> io::Result<()> {
+        self.0.publish(property_record_populated).await
+    }
+
+    async fn finish(&self) -> io::Result<Option<OutputStats>> {
+        self.0.finish().await.map(|()| None)
+    }
+}
+
+/// Discards records, e.g. to measure processing without output.
+#[derive(Debug)]
+pub struct NullSink;
+
+#[async_trait]
+impl RecordSink for NullSink {
+    fn name(&self) -> &'static str {
+        "null"
+    }
+
+    async fn write(
+        &self,
+        _sequence: usize,
+        _property_record_populated: PropertyRecordPopulated,
+        _record_progress: &RecordProgress,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes each record to every one of its sinks.
+///
+/// Records are written to the sinks concurrently, and a sink that fails
+/// doesn't stop the others from being written to. The error names each sink
+/// that failed.
+#[derive(Debug)]
+pub struct TeeSink(pub Vec<Box<dyn RecordSink>>);
+
+impl TeeSink {
+    /// Combines the errors of the sinks that failed, if any.
+    fn result<T>(&self, results: Vec<io::Result<T>>) -> io::Result<Vec<T>> {
+        let mut values = Vec::with_capacity(results.len());
+        let mut errors = Vec::new();
+        self.0
+            .iter()
+            .zip(results)
+            .for_each(|(sink, result)| match result {
+                Ok(value) => values.push(value),
+                Err(e) => errors.push(format!("`{}` sink: {}", sink.name(), e)),
+            });
+
+        if errors.is_empty() {
+            Ok(values)
+        } else {
+            Err(io::Error::other(errors.join("; ")))
+        }
+    }
+}
+
+#[async_trait]
+impl RecordSink for TeeSink {
+    fn name(&self) -> &'static str {
+        "tee"
+    }
+
+    async fn write(
+        &self,
+        sequence: usize,
+        property_record_populated: PropertyRecordPopulated,
+        record_progress: &RecordProgress,
+    ) -> io::Result<()> {
+        let results =
+            join_all(self.0.iter().map(|sink| {
+                sink.write(sequence, property_record_populated.clone(), record_progress)
+            }))
+            .await;
+        self.result(results).map(|_| ())
+    }
+
+    /// Returns the bytes written by the first sink that writes output files.
+    async fn finish(&self) -> io::Result<Option<OutputStats>> {
+        let results = join_all(self.0.iter().map(|sink| sink.finish())).await;
+        self.result(results)
+            .map(|output_stats| output_stats.into_iter().flatten().next())
+    }
+
+    async fn run_finished(&self, report: &Report) -> io::Result<()> {
+        let results = join_all(self.0.iter().map(|sink| sink.run_finished(report))).await;
+        self.result(results).map(|_| ())
+    }
+}
@@ -0,0 +1,96 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+/// Progress bars for records retrieved, augmented, and written, shown beneath
+/// the overall bar with `--progress stages`.
+///
+/// Each bar advances independently, so a slow stage shows as a bar lagging
+/// behind the one before it.
+#[derive(Clone, Debug)]
+pub struct StageProgress {
+    retrieved: ProgressBar,
+    augmented: ProgressBar,
+    written: ProgressBar,
+}
+
+impl StageProgress {
+    /// Returns stage progress bars added to the given `MultiProgress`.
+    ///
+    /// If `record_count` is `None`, each stage shows its count without a bar.
+    pub fn new(
+        multi_progress: &MultiProgress,
+        progress_chars: &str,
+        record_count: Option<u64>,
+        position: u64,
+    ) -> Self {
+        let template = match record_count {
+            Some(_) => "  {msg:>9} [{bar:40.cyan/blue}] {pos}/{len}",
+            None => "  {msg:>9} {pos}",
+        };
+        let style = ProgressStyle::default_bar()
+            .template(template)
+            .expect("Invalid stage progress bar template.")
+            .progress_chars(progress_chars);
+        let stage_bar = |name: &'static str| {
+            let bar = multi_progress.add(ProgressBar::with_draw_target(
+                record_count,
+                ProgressDrawTarget::hidden(),
+            ));
+            bar.set_style(style.clone());
+            bar.set_message(name);
+            bar.set_position(position);
+            bar
+        };
+
+        Self {
+            retrieved: stage_bar("retrieved"),
+            augmented: stage_bar("augmented"),
+            written: stage_bar("written"),
+        }
+    }
+
+    /// Returns stage progress that doesn't show anything.
+    pub fn hidden() -> Self {
+        Self {
+            retrieved: ProgressBar::hidden(),
+            augmented: ProgressBar::hidden(),
+            written: ProgressBar::hidden(),
+        }
+    }
+
+    /// Records that a record's information was retrieved.
+    pub fn retrieved(&self) {
+        self.retrieved.inc(1);
+    }
+
+    /// Records that a record was augmented.
+    pub fn augmented(&self) {
+        self.augmented.inc(1);
+    }
+
+    /// Records that a record was written.
+    pub fn written(&self) {
+        self.written.inc(1);
+    }
+
+    /// Records that a record was skipped, so no stage processes it.
+    pub fn skipped(&self) {
+        self.retrieved.inc(1);
+        self.augmented.inc(1);
+        self.written.inc(1);
+    }
+
+    /// Sets the number of records for the stages to process, e.g. when a new
+    /// input file is found with `--watch`.
+    pub fn set_length(&self, length: u64) {
+        self.retrieved.set_length(length);
+        self.augmented.set_length(length);
+        self.written.set_length(length);
+    }
+
+    /// Leaves the stage bars where they stopped.
+    pub fn finish(&self) {
+        self.retrieved.abandon();
+        self.augmented.abandon();
+        self.written.abandon();
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    convert::TryFrom,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Kinds of stage each record passes through, for timings and progress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StageKind {
+    /// Waiting to avoid exceeding the server's rate limit.
+    RateLimit,
+    /// Authenticating with the server.
+    Authenticate,
+    /// Retrieving the record's information.
+    Retrieve,
+    /// Adding the information to the record.
+    Augment,
+    /// Writing the populated record to the output.
+    Output,
+}
+
+impl StageKind {
+    /// All stages, in processing order.
+    pub const ALL: [StageKind; 5] = [
+        StageKind::RateLimit,
+        StageKind::Authenticate,
+        StageKind::Retrieve,
+        StageKind::Augment,
+        StageKind::Output,
+    ];
+
+    /// Returns the human readable name of this stage.
+    pub fn name(self) -> &'static str {
+        match self {
+            StageKind::RateLimit => "rate limit",
+            StageKind::Authenticate => "authenticate",
+            StageKind::Retrieve => "retrieve",
+            StageKind::Augment => "augment",
+            StageKind::Output => "output",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Accumulates time spent in each stage across all records.
+///
+/// This is shared between the processing tasks and the `Reporter`, so the
+/// accumulators are atomic.
+#[derive(Debug, Default)]
+pub struct StageTimings {
+    /// Total nanoseconds spent in each stage.
+    nanos: [AtomicU64; 5],
+    /// Number of times each stage was run.
+    counts: [AtomicU64; 5],
+}
+
+impl StageTimings {
+    /// Records time spent in a stage.
+    pub fn record(&self, stage: StageKind, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.nanos[stage.index()].fetch_add(nanos, Ordering::Relaxed);
+        self.counts[stage.index()].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the total time spent in a stage.
+    pub fn total(&self, stage: StageKind) -> Duration {
+        Duration::from_nanos(self.nanos[stage.index()].load(Ordering::Relaxed))
+    }
+
+    /// Returns the number of times a stage was run.
+    pub fn count(&self, stage: StageKind) -> u64 {
+        self.counts[stage.index()].load(Ordering::Relaxed)
+    }
+
+    /// Returns the average time spent in a stage, if it was run at all.
+    pub fn average(&self, stage: StageKind) -> Option<Duration> {
+        let count = self.count(stage);
+        if count > 0 {
+            Some(self.total(stage) / count as u32)
+        } else {
+            None
+        }
+    }
+}
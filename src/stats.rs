@@ -0,0 +1,327 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{self, Write as _},
+    path::PathBuf,
+};
+
+use chrono::{DateTime, NaiveDate};
+use clap::Args;
+
+use crate::{
+    output::{OutputLine, OutputLineReader, OutputRecord, RecordStatus},
+    report::SourceCounts,
+    Colours,
+};
+
+/// Prints statistics about an output file.
+#[derive(Debug, Args)]
+pub struct StatsOpt {
+    /// Output file written with `--output`.
+    ///
+    /// Files ending in `.gz` or `.zst` are decompressed.
+    output: PathBuf,
+    /// Number of most common errors to list.
+    #[arg(long, default_value = "10")]
+    max_error_rows: usize,
+}
+
+/// Number of records with each status.
+#[derive(Clone, Copy, Debug, Default)]
+struct StatusCounts {
+    success_count: usize,
+    success_partial_count: usize,
+    error_count: usize,
+}
+
+impl StatusCounts {
+    fn add(&mut self, status: RecordStatus) {
+        match status {
+            RecordStatus::Success => self.success_count += 1,
+            RecordStatus::SuccessPartial => self.success_partial_count += 1,
+            RecordStatus::Error => self.error_count += 1,
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.success_count + self.success_partial_count + self.error_count
+    }
+}
+
+/// Runs and records written on one day.
+#[derive(Debug, Default)]
+struct DayStats {
+    run_count: usize,
+    status_counts: StatusCounts,
+}
+
+/// Statistics gathered from the lines of an output file.
+#[derive(Debug, Default)]
+pub struct Stats {
+    run_count: usize,
+    /// IDs of the records, which appear more than once if a record was
+    /// written by several runs.
+    record_ids: HashSet<usize>,
+    status_counts: StatusCounts,
+    /// Number of failed records with each error message.
+    error_counts: BTreeMap<String, usize>,
+    /// Number of records each field was missing from, by field name.
+    missing_field_counts: BTreeMap<String, usize>,
+    /// Number of records each `--enrich` source succeeded and failed for.
+    source_counts: BTreeMap<String, SourceCounts>,
+    /// Runs and records by the day the run started, for runs whose start
+    /// time could be read.
+    days: BTreeMap<NaiveDate, DayStats>,
+}
+
+impl Stats {
+    /// Reads the output file and writes its statistics to stdout.
+    pub async fn print(stats_opt: &StatsOpt) -> io::Result<()> {
+        let stats = Self::read(stats_opt).await?;
+
+        let mut stdout = io::stdout().lock();
+        stats.write(&mut stdout, stats_opt.max_error_rows)?;
+        stdout.flush()
+    }
+
+    /// Reads every line of the output file.
+    async fn read(stats_opt: &StatsOpt) -> io::Result<Self> {
+        let mut stats = Self::default();
+        let mut output_lines = OutputLineReader::open(&stats_opt.output).await?;
+        let mut day = None;
+        while let Some(output_line) = output_lines.next_line().await? {
+            match output_line {
+                OutputLine::Header(run_metadata) => {
+                    stats.run_count += 1;
+                    day = DateTime::parse_from_rfc3339(&run_metadata.started_at)
+                        .ok()
+                        .map(|started_at| started_at.date_naive());
+                    if let Some(day) = day {
+                        stats.days.entry(day).or_default().run_count += 1;
+                    }
+                }
+                OutputLine::Record(output_record) => {
+                    if let Some(day) = day {
+                        stats
+                            .days
+                            .entry(day)
+                            .or_default()
+                            .status_counts
+                            .add(output_record.status);
+                    }
+                    stats.add(output_record);
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+
+    fn add(&mut self, output_record: OutputRecord) {
+        let OutputRecord {
+            record_id,
+            status,
+            error,
+            missing_fields,
+            sources,
+            ..
+        } = output_record;
+
+        self.record_ids.insert(record_id);
+        self.status_counts.add(status);
+        if let Some(error) = error {
+            *self.error_counts.entry(error).or_default() += 1;
+        }
+        missing_fields.into_iter().for_each(|field| {
+            *self.missing_field_counts.entry(field).or_default() += 1;
+        });
+        sources.into_iter().for_each(|(source, succeeded)| {
+            let source_counts = self.source_counts.entry(source).or_default();
+            if succeeded {
+                source_counts.succeeded_count += 1;
+            } else {
+                source_counts.failed_count += 1;
+            }
+        });
+    }
+
+    fn write(&self, out: &mut impl io::Write, max_error_rows: usize) -> io::Result<()> {
+        let record_count = self.status_counts.total();
+
+        writeln!(out, "{}", Colours::theme().report_title.apply("## Records"))?;
+        writeln!(out)?;
+        [
+            ("* Runs:", self.run_count),
+            ("* Records:", record_count),
+            ("* Unique records:", self.record_ids.len()),
+        ]
+        .iter()
+        .try_for_each(|(label, count)| {
+            writeln!(
+                out,
+                "{:<35} {:>7}",
+                Colours::theme().report_label.apply(*label),
+                count
+            )
+        })?;
+
+        writeln!(out)?;
+        writeln!(out, "{}", Colours::theme().report_title.apply("## Results"))?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{count:>7} | {share:>7} | {status}",
+            count = Colours::theme().report_label.apply("count"),
+            share = Colours::theme().report_label.apply("share"),
+            status = Colours::theme().report_label.apply("status")
+        )?;
+        writeln!(out, "------- | ------- | ---------------")?;
+        [
+            (
+                "success",
+                self.status_counts.success_count,
+                Colours::theme().report_item_success,
+            ),
+            (
+                "success_partial",
+                self.status_counts.success_partial_count,
+                Colours::theme().report_item_partial_success,
+            ),
+            (
+                "error",
+                self.status_counts.error_count,
+                Colours::theme().report_item_failure,
+            ),
+        ]
+        .iter()
+        .try_for_each(|(status, count, style)| {
+            writeln!(
+                out,
+                "{count:7} | {share:>6.1}% | {status}",
+                count = count,
+                share = Self::percent(*count, record_count),
+                status = style.apply(*status)
+            )
+        })?;
+
+        if !self.error_counts.is_empty() {
+            let mut error_counts = self.error_counts.iter().collect::<Vec<_>>();
+            error_counts.sort_by(|(error_a, count_a), (error_b, count_b)| {
+                count_b.cmp(count_a).then_with(|| error_a.cmp(error_b))
+            });
+
+            writeln!(out)?;
+            writeln!(out, "{}", Colours::theme().report_title.apply("## Errors"))?;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{count:>7} | {error}",
+                count = Colours::theme().report_label.apply("count"),
+                error = Colours::theme().report_label.apply("error")
+            )?;
+            writeln!(out, "------- | ------------------------------")?;
+            error_counts
+                .iter()
+                .take(max_error_rows)
+                .try_for_each(|(error, count)| {
+                    writeln!(
+                        out,
+                        "{count:7} | {error}",
+                        count = count,
+                        error = Colours::theme().report_error_message.apply(error)
+                    )
+                })?;
+            if error_counts.len() > max_error_rows {
+                writeln!(out, "… {} more errors", error_counts.len() - max_error_rows)?;
+            }
+        }
+
+        if !self.missing_field_counts.is_empty() {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{}",
+                Colours::theme().report_title.apply("## Missing Fields")
+            )?;
+            writeln!(out)?;
+            self.missing_field_counts
+                .iter()
+                .try_for_each(|(field, count)| {
+                    writeln!(
+                        out,
+                        "{:<35} {:>7}",
+                        Colours::theme().report_label.apply(format!("* {}:", field)),
+                        count
+                    )
+                })?;
+        }
+
+        if !self.source_counts.is_empty() {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{}",
+                Colours::theme().report_title.apply("## Enrichment")
+            )?;
+            writeln!(out)?;
+            self.source_counts
+                .iter()
+                .try_for_each(|(source, source_counts)| {
+                    writeln!(
+                        out,
+                        "{:<35} {:>7}",
+                        Colours::theme()
+                            .report_label
+                            .apply(format!("* {} (succeeded / failed):", source)),
+                        format!(
+                            "{} / {}",
+                            source_counts.succeeded_count, source_counts.failed_count
+                        )
+                    )
+                })?;
+        }
+
+        if !self.days.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "{}", Colours::theme().report_title.apply("## Per Day"))?;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{day:<10} | {runs:>5} | {records:>7} | {success:>7} | {partial:>7} | {error:>7}",
+                day = Colours::theme().report_label.apply("day"),
+                runs = Colours::theme().report_label.apply("runs"),
+                records = Colours::theme().report_label.apply("records"),
+                success = Colours::theme().report_label.apply("success"),
+                partial = Colours::theme().report_label.apply("partial"),
+                error = Colours::theme().report_label.apply("error")
+            )?;
+            writeln!(
+                out,
+                "---------- | ----- | ------- | ------- | ------- | -------"
+            )?;
+            self.days.iter().try_for_each(|(day, day_stats)| {
+                let status_counts = &day_stats.status_counts;
+                writeln!(
+                    out,
+                    "{day:<10} | {runs:5} | {records:7} | {success:7} | {partial:7} | {error:7}",
+                    day = day.to_string(),
+                    runs = day_stats.run_count,
+                    records = status_counts.total(),
+                    success = status_counts.success_count,
+                    partial = status_counts.success_partial_count,
+                    error = status_counts.error_count
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns `count` as a percentage of `total`, or 0 if the total is 0.
+    fn percent(count: usize, total: usize) -> f64 {
+        if total == 0 {
+            0.0
+        } else {
+            count as f64 / total as f64 * 100.0
+        }
+    }
+}
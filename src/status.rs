@@ -0,0 +1,77 @@
+use std::{future::Future, sync::Arc};
+
+use hyper::{header::CONTENT_TYPE, Body, Method, Request, Response, StatusCode};
+use indicatif::ProgressBar;
+use serde::Serialize;
+
+use crate::{HttpServer, Metrics, RunControl};
+
+/// Progress of the run, served as JSON on `--status-port`.
+#[derive(Debug, Serialize)]
+pub struct Status {
+    /// Number of records processed in this execution, including failures.
+    pub processed: u64,
+    /// Number of records that failed to process.
+    pub failed: u64,
+    /// Number of records that have some information missing.
+    pub partial: u64,
+    /// Estimated number of seconds until all records are processed, or `null`
+    /// if the number of records isn't known.
+    pub eta_seconds: Option<u64>,
+    /// Whether the run was interrupted.
+    pub interrupted: bool,
+    /// Whether taking in new records is paused.
+    pub paused: bool,
+}
+
+impl Status {
+    /// Returns the current status of the run.
+    pub fn new(metrics: &Metrics, progress_bar: &ProgressBar, run_control: &RunControl) -> Self {
+        let successful = metrics.records_processed("success");
+        let partial = metrics.records_processed("partial");
+        let failed = metrics.records_processed("error");
+
+        Self {
+            processed: successful + partial + failed,
+            failed,
+            partial,
+            eta_seconds: progress_bar.length().map(|_| progress_bar.eta().as_secs()),
+            interrupted: metrics.is_interrupted(),
+            paused: run_control.is_paused(),
+        }
+    }
+
+    /// Serves `/status` on `127.0.0.1` at the given port until the process exits.
+    pub fn serve(
+        metrics: Arc<Metrics>,
+        progress_bar: ProgressBar,
+        run_control: Arc<RunControl>,
+        port: u16,
+    ) -> hyper::Result<impl Future<Output = hyper::Result<()>>> {
+        HttpServer::serve(port, move |request| {
+            Self::respond(&metrics, &progress_bar, &run_control, request)
+        })
+    }
+
+    fn respond(
+        metrics: &Metrics,
+        progress_bar: &ProgressBar,
+        run_control: &RunControl,
+        request: &Request<Body>,
+    ) -> Response<Body> {
+        match (request.method(), request.uri().path()) {
+            (&Method::GET, "/status") => {
+                let status = Self::new(metrics, progress_bar, run_control);
+                Response::builder()
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&status).expect("Failed to serialize status."),
+                    ))
+            }
+            _ => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty()),
+        }
+        .expect("Failed to build status response.")
+    }
+}
@@ -0,0 +1,48 @@
+use futures::{stream, Stream};
+use tokio::io::{self, AsyncBufReadExt, BufReader, Lines, Stdin};
+
+use crate::{PropertyRecord, RecordFilter};
+
+/// Record IDs read from stdin, for `--stdin`.
+///
+/// Lines have the same format as `--only-ids`. Lines that aren't record IDs
+/// are logged and skipped, so one bad line doesn't stop the stream.
+#[derive(Debug)]
+pub struct StdinRecords {
+    lines: Lines<BufReader<Stdin>>,
+    /// Number of lines read, for error messages.
+    line_number: usize,
+}
+
+impl StdinRecords {
+    /// Returns the records read from stdin, as they are read.
+    ///
+    /// The stream ends when stdin is closed.
+    pub fn stream() -> impl Stream<Item = PropertyRecord> {
+        let stdin_records = Self {
+            lines: BufReader::new(io::stdin()).lines(),
+            line_number: 0,
+        };
+        stream::unfold(stdin_records, |mut stdin_records| async move {
+            loop {
+                let line = match stdin_records.lines.next_line().await {
+                    Ok(line) => line?,
+                    Err(e) => {
+                        tracing::error!("Failed to read records from stdin: {}", e);
+                        return None;
+                    }
+                };
+                stdin_records.line_number += 1;
+                match RecordFilter::parse_id_line(&line) {
+                    Some(Ok(id)) => return Some((PropertyRecord(id), stdin_records)),
+                    Some(Err(e)) => tracing::error!(
+                        line = stdin_records.line_number,
+                        "Skipping record from stdin: {}",
+                        e
+                    ),
+                    None => {}
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,178 @@
+use std::{collections::HashSet, time::SystemTime};
+
+use futures::{Stream, StreamExt};
+use sqlx::{any::AnyPoolOptions, AnyPool, Row};
+
+use crate::{PropertyInfoResult, PropertyRecord, RecordProgress, Report, RunMetadata};
+
+/// Statements that create the store's tables, if they don't exist.
+///
+/// These, and the other statements, work with both SQLite and PostgreSQL.
+const SCHEMA: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS runs (
+        run_id TEXT PRIMARY KEY NOT NULL,
+        version TEXT NOT NULL,
+        args TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        finished_at TEXT,
+        interrupted BOOLEAN
+    )",
+    "CREATE TABLE IF NOT EXISTS records (
+        record_id BIGINT PRIMARY KEY NOT NULL,
+        title_number TEXT NOT NULL,
+        status TEXT NOT NULL,
+        error TEXT,
+        attempts BIGINT,
+        duration_ms BIGINT,
+        run_id TEXT NOT NULL REFERENCES runs (run_id),
+        updated_at TEXT NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS records_status ON records (status)",
+];
+
+/// Database that keeps each record's status and result, and the metadata of
+/// each run, given with `--store`.
+///
+/// Records are `pending` until processed, then `succeeded`, `partial`, or
+/// `failed`, so a later run can `--resume` by skipping records that didn't
+/// fail. Each record has one row, which is upserted as it is processed.
+#[derive(Clone, Debug)]
+pub struct Store {
+    pool: AnyPool,
+    run_id: String,
+}
+
+impl Store {
+    /// Connects to the store, e.g. `sqlite:run.db` or
+    /// `postgres://user@host/db`, and creates its tables.
+    ///
+    /// SQLite database files are created if they don't exist.
+    pub async fn open(url: &str, run_metadata: &RunMetadata) -> Result<Self, sqlx::Error> {
+        sqlx::any::install_default_drivers();
+        let url = if url.starts_with("sqlite:") && !url.contains("mode=") {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            format!("{}{}mode=rwc", url, separator)
+        } else {
+            String::from(url)
+        };
+        let pool = AnyPoolOptions::new().connect(&url).await?;
+        for statement in SCHEMA {
+            sqlx::query(statement).execute(&pool).await?;
+        }
+
+        Ok(Self {
+            pool,
+            run_id: run_metadata.run_id.clone(),
+        })
+    }
+
+    /// Returns the IDs of records that were processed without failing by
+    /// earlier runs.
+    pub async fn completed_record_ids(&self) -> Result<HashSet<usize>, sqlx::Error> {
+        let rows =
+            sqlx::query("SELECT record_id FROM records WHERE status IN ('succeeded', 'partial')")
+                .fetch_all(&self.pool)
+                .await?;
+
+        rows.iter()
+            .map(|row| {
+                row.try_get::<i64, _>("record_id")
+                    .map(|record_id| record_id as usize)
+            })
+            .collect()
+    }
+
+    /// Records the start of this run, and marks the records it will process
+    /// as `pending`, in one transaction.
+    pub async fn start_run(
+        &self,
+        run_metadata: &RunMetadata,
+        records: impl Stream<Item = PropertyRecord>,
+    ) -> Result<(), sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query("INSERT INTO runs (run_id, version, args, started_at) VALUES ($1, $2, $3, $4)")
+            .bind(&run_metadata.run_id)
+            .bind(&run_metadata.version)
+            .bind(serde_json::to_string(&run_metadata.args).unwrap_or_default())
+            .bind(&run_metadata.started_at)
+            .execute(&mut *transaction)
+            .await?;
+
+        let updated_at = Self::now();
+        futures::pin_mut!(records);
+        while let Some(record) = records.next().await {
+            sqlx::query(
+                "INSERT INTO records (record_id, title_number, status, run_id, updated_at)
+                VALUES ($1, $2, 'pending', $3, $4)
+                ON CONFLICT (record_id) DO UPDATE SET
+                    status = excluded.status,
+                    error = NULL,
+                    attempts = NULL,
+                    duration_ms = NULL,
+                    run_id = excluded.run_id,
+                    updated_at = excluded.updated_at",
+            )
+            .bind(record.0 as i64)
+            .bind(record.title_number())
+            .bind(&self.run_id)
+            .bind(&updated_at)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await
+    }
+
+    /// Records the result of processing a record.
+    pub async fn record_processed(
+        &self,
+        record_progress: &RecordProgress,
+    ) -> Result<(), sqlx::Error> {
+        let (status, error) = match record_progress.info {
+            PropertyInfoResult::Success => ("succeeded", String::new()),
+            PropertyInfoResult::SuccessPartial(..) => ("partial", String::new()),
+            PropertyInfoResult::Error(ref error) => ("failed", error.to_string()),
+        };
+
+        // The error is bound as text even when there is none, because
+        // Postgres keeps the parameter types of the first `NULL` bound to a
+        // prepared statement.
+        sqlx::query(
+            "INSERT INTO records (record_id, title_number, status, error, attempts, duration_ms, run_id, updated_at)
+            VALUES ($1, $2, $3, NULLIF($4, ''), $5, $6, $7, $8)
+            ON CONFLICT (record_id) DO UPDATE SET
+                status = excluded.status,
+                error = excluded.error,
+                attempts = excluded.attempts,
+                duration_ms = excluded.duration_ms,
+                run_id = excluded.run_id,
+                updated_at = excluded.updated_at",
+        )
+        .bind(record_progress.record.0 as i64)
+        .bind(record_progress.record.title_number())
+        .bind(status)
+        .bind(error)
+        .bind(i64::from(record_progress.attempts))
+        .bind(record_progress.duration.as_millis() as i64)
+        .bind(&self.run_id)
+        .bind(Self::now())
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+    }
+
+    /// Records the end of this run.
+    pub async fn finish_run(&self, report: &Report) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE runs SET finished_at = $1, interrupted = $2 WHERE run_id = $3")
+            .bind(Self::now())
+            .bind(report.interrupted)
+            .bind(&self.run_id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    fn now() -> String {
+        humantime::format_rfc3339_seconds(SystemTime::now()).to_string()
+    }
+}
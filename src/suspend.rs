@@ -0,0 +1,72 @@
+use indicatif::ProgressBar;
+
+#[cfg(unix)]
+use crossterm::terminal;
+
+#[cfg(unix)]
+use crate::KeyboardControl;
+
+/// Suspends the process on Ctrl-Z, leaving the terminal usable while it is
+/// stopped, and redraws the progress bars once it continues.
+#[derive(Clone, Debug)]
+pub struct Suspend {
+    progress_bar: ProgressBar,
+}
+
+impl Suspend {
+    /// Returns a handle that suspends the process, clearing the progress bars
+    /// that `progress_bar` is drawn with.
+    pub fn new(progress_bar: ProgressBar) -> Self {
+        Self { progress_bar }
+    }
+
+    /// Suspends the process on `SIGTSTP`, e.g. Ctrl-Z when the terminal isn't
+    /// in raw mode.
+    ///
+    /// Handling `SIGTSTP` replaces the default of stopping the process
+    /// straight away, which would leave the progress bars on the screen.
+    pub async fn handle_sigtstp(self) {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let mut sigtstp = match signal(SignalKind::from_raw(libc::SIGTSTP)) {
+                Ok(sigtstp) => sigtstp,
+                Err(e) => {
+                    tracing::warn!("Failed to set SIGTSTP handler: {}", e);
+                    return;
+                }
+            };
+            while sigtstp.recv().await.is_some() {
+                self.suspend();
+            }
+        }
+    }
+
+    /// Clears the progress bars, restores the terminal, and stops the process
+    /// until it is continued, e.g. with `fg`. The progress bars are then
+    /// redrawn, and raw mode is enabled again if it was.
+    ///
+    /// Does nothing outside Unix, which has no job control.
+    pub fn suspend(&self) {
+        #[cfg(unix)]
+        self.progress_bar.suspend(|| {
+            let raw_mode = terminal::is_raw_mode_enabled().unwrap_or(false);
+            if raw_mode {
+                KeyboardControl::restore_terminal();
+            }
+
+            // `SIGSTOP` can't be handled, so this returns once the process
+            // receives `SIGCONT`.
+            unsafe {
+                libc::raise(libc::SIGSTOP);
+            }
+
+            if raw_mode {
+                if let Err(e) = terminal::enable_raw_mode() {
+                    tracing::warn!("Failed to enable raw mode: {}", e);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,225 @@
+use std::{collections::HashSet, fmt, future::Future};
+
+use futures::{future::LocalBoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
+
+/// Returns the order to run nodes in so that each node comes after the nodes
+/// it depends on.
+///
+/// Nodes are given as their name and the names of the nodes they depend on.
+/// Nodes that don't depend on each other keep the order they are given in.
+/// Returns the indices of the nodes, or an error if a node depends on a node
+/// that doesn't exist, a name is given more than once, or the dependencies
+/// form a cycle.
+pub fn dependency_order(nodes: &[(&'static str, Vec<&'static str>)]) -> Result<Vec<usize>, String> {
+    let mut names = HashSet::with_capacity(nodes.len());
+    if let Some((name, _)) = nodes.iter().find(|(name, _)| !names.insert(*name)) {
+        return Err(format!("`{}` is given more than once.", name));
+    }
+    if let Some((name, dependency)) = nodes.iter().find_map(|(name, depends_on)| {
+        depends_on
+            .iter()
+            .find(|dependency| !names.contains(*dependency))
+            .map(|dependency| (name, dependency))
+    }) {
+        return Err(format!(
+            "`{}` depends on `{}`, which doesn't exist.",
+            name, dependency
+        ));
+    }
+
+    let mut ordered = Vec::with_capacity(nodes.len());
+    let mut done = HashSet::with_capacity(nodes.len());
+    while ordered.len() < nodes.len() {
+        let next = nodes.iter().position(|(name, depends_on)| {
+            !done.contains(name)
+                && depends_on
+                    .iter()
+                    .all(|dependency| done.contains(dependency))
+        });
+        match next {
+            Some(index) => {
+                ordered.push(index);
+                done.insert(nodes[index].0);
+            }
+            None => {
+                let remaining = nodes
+                    .iter()
+                    .filter(|(name, _)| !done.contains(name))
+                    .map(|(name, _)| format!("`{}`", name))
+                    .collect::<Vec<_>>();
+                return Err(format!(
+                    "{} depend on each other in a cycle.",
+                    remaining.join(", ")
+                ));
+            }
+        }
+    }
+
+    Ok(ordered)
+}
+
+/// Runs tasks once the tasks they depend on have finished.
+///
+/// Tasks that don't depend on each other run concurrently, so independent
+/// startup work, such as connecting to the store and opening the output file,
+/// doesn't wait on each other. Tasks share their results through cells that
+/// their futures borrow, and a task's future isn't polled until its
+/// dependencies have finished.
+///
+/// The first task to fail stops the remaining tasks.
+pub struct TaskGraph<'a, E> {
+    tasks: Vec<Task<'a, E>>,
+}
+
+/// A task in a [`TaskGraph`].
+struct Task<'a, E> {
+    name: &'static str,
+    depends_on: Vec<&'static str>,
+    future: LocalBoxFuture<'a, Result<(), E>>,
+}
+
+impl<'a, E> TaskGraph<'a, E> {
+    /// Returns an empty task graph.
+    pub fn new() -> Self {
+        Self { tasks: Vec::new() }
+    }
+
+    /// Adds a task that starts once the tasks named in `depends_on` have
+    /// finished.
+    pub fn task<F>(mut self, name: &'static str, depends_on: &[&'static str], future: F) -> Self
+    where
+        F: Future<Output = Result<(), E>> + 'a,
+    {
+        self.tasks.push(Task {
+            name,
+            depends_on: depends_on.to_vec(),
+            future: future.boxed_local(),
+        });
+        self
+    }
+
+    /// Runs every task, each as soon as its dependencies have finished.
+    ///
+    /// Returns the error of the first task that fails, without waiting for
+    /// the tasks still running.
+    ///
+    /// # Panics
+    ///
+    /// Panics without running any task if the dependencies can't be ordered,
+    /// see [`dependency_order`].
+    pub async fn run(self) -> Result<(), E> {
+        let nodes = self
+            .tasks
+            .iter()
+            .map(|task| (task.name, task.depends_on.clone()))
+            .collect::<Vec<_>>();
+        if let Err(e) = dependency_order(&nodes) {
+            panic!("Failed to order tasks: {}", e);
+        }
+
+        let mut pending = self.tasks.into_iter().map(Some).collect::<Vec<_>>();
+        let mut done = HashSet::with_capacity(pending.len());
+        let mut running = FuturesUnordered::new();
+        loop {
+            pending
+                .iter_mut()
+                .filter(|task| {
+                    task.as_ref().is_some_and(|task| {
+                        task.depends_on
+                            .iter()
+                            .all(|dependency| done.contains(dependency))
+                    })
+                })
+                .filter_map(Option::take)
+                .for_each(|task| {
+                    let Task { name, future, .. } = task;
+                    tracing::debug!(task = name, "Task started.");
+                    running.push(future.map(move |result| (name, result)));
+                });
+
+            match running.next().await {
+                Some((name, Ok(()))) => {
+                    tracing::debug!(task = name, "Task finished.");
+                    done.insert(name);
+                }
+                Some((name, Err(e))) => {
+                    tracing::debug!(task = name, "Task failed.");
+                    return Err(e);
+                }
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<E> fmt::Debug for TaskGraph<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.tasks.iter().map(|task| (task.name, &task.depends_on)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dependency_order;
+
+    #[test]
+    fn dependency_order_puts_nodes_after_their_dependencies() {
+        let nodes = [
+            ("report", vec!["output", "store"]),
+            ("output", vec!["credentials"]),
+            ("credentials", vec![]),
+            ("store", vec![]),
+        ];
+
+        assert_eq!(Ok(vec![2, 1, 3, 0]), dependency_order(&nodes));
+    }
+
+    #[test]
+    fn dependency_order_returns_error_when_dependencies_form_cycle() {
+        let nodes = [
+            ("credentials", vec![]),
+            ("output", vec!["report"]),
+            ("report", vec!["output"]),
+        ];
+
+        assert_eq!(
+            Err(String::from(
+                "`output`, `report` depend on each other in a cycle."
+            )),
+            dependency_order(&nodes)
+        );
+    }
+
+    #[test]
+    fn dependency_order_returns_error_when_node_depends_on_itself() {
+        let nodes = [("output", vec!["output"])];
+
+        assert!(matches!(dependency_order(&nodes), Err(e) if e.contains("cycle")));
+    }
+
+    #[test]
+    fn dependency_order_returns_error_when_name_given_more_than_once() {
+        let nodes = [("output", vec![]), ("store", vec![]), ("output", vec![])];
+
+        assert_eq!(
+            Err(String::from("`output` is given more than once.")),
+            dependency_order(&nodes)
+        );
+    }
+
+    #[test]
+    fn dependency_order_returns_error_when_dependency_does_not_exist() {
+        let nodes = [("output", vec!["credentials"])];
+
+        assert_eq!(
+            Err(String::from(
+                "`output` depends on `credentials`, which doesn't exist."
+            )),
+            dependency_order(&nodes)
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::{env, io, str::FromStr};
+
+use crossterm::tty::IsTty;
+
+/// What the terminal that stderr is attached to can display.
+#[derive(Clone, Copy, Debug)]
+pub struct TerminalCapabilities {
+    /// Whether stderr is a terminal.
+    pub is_tty: bool,
+    /// Width of the terminal in columns, if known.
+    pub width: Option<u16>,
+    /// Number of colours the terminal supports.
+    pub color_depth: ColorDepth,
+    /// Whether the terminal can display non-ASCII characters.
+    pub unicode: bool,
+}
+
+impl TerminalCapabilities {
+    /// Width below which the compact progress bar template is used.
+    pub const WIDTH_NARROW: u16 = 100;
+
+    /// Detects the capabilities of the terminal that stderr is attached to.
+    ///
+    /// * Width is read from the terminal, falling back to `COLUMNS`.
+    /// * Colour depth is read from `COLORTERM` and `TERM`.
+    /// * Unicode support is read from `LC_ALL`, `LC_CTYPE`, and `LANG`, and
+    ///   is assumed on Windows.
+    pub fn detect() -> Self {
+        let is_tty = io::stderr().is_tty();
+        let width = crossterm::terminal::size()
+            .ok()
+            .map(|(columns, _rows)| columns)
+            .filter(|columns| *columns > 0)
+            .or_else(|| env::var("COLUMNS").ok()?.parse().ok());
+
+        Self {
+            is_tty,
+            width,
+            color_depth: ColorDepth::detect(),
+            unicode: Self::detect_unicode(),
+        }
+    }
+
+    /// Returns whether the terminal is narrower than [`Self::WIDTH_NARROW`].
+    pub fn is_narrow(&self) -> bool {
+        self.width
+            .map(|width| width < Self::WIDTH_NARROW)
+            .unwrap_or(false)
+    }
+
+    fn detect_unicode() -> bool {
+        if cfg!(windows) {
+            return true;
+        }
+
+        // The first of these that is set takes precedence, as with `setlocale`.
+        ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+            .map(|locale| {
+                let locale = locale.to_lowercase();
+                locale.contains("utf-8") || locale.contains("utf8")
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// Number of colours a terminal supports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    /// The 16 standard ANSI colours.
+    Ansi16,
+    /// The 256 colour palette.
+    Ansi256,
+    /// 24-bit RGB colours.
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Detects the colour depth from `COLORTERM` and `TERM`.
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+impl FromStr for ColorDepth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "16" => Ok(Self::Ansi16),
+            "256" => Ok(Self::Ansi256),
+            "truecolor" => Ok(Self::TrueColor),
+            _ => Err(format!("`{}` is not one of `16`, `256`, `truecolor`.", s)),
+        }
+    }
+}
+
+/// Whether a terminal has a dark or light background.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Background {
+    /// Dark background, e.g. black.
+    Dark,
+    /// Light background, e.g. white.
+    Light,
+}
+
+impl Background {
+    /// Time to wait for the terminal to report its background colour.
+    #[cfg(unix)]
+    const QUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+    /// Time to wait between reads of the terminal's response.
+    #[cfg(unix)]
+    const QUERY_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(5);
+
+    /// Detects the terminal background from `COLORFGBG`, or by asking the
+    /// terminal with an OSC 11 query.
+    ///
+    /// Returns `None` if the background is unknown.
+    pub fn detect() -> Option<Self> {
+        Self::from_colorfgbg().or_else(Self::query)
+    }
+
+    /// Reads the background from `COLORFGBG`, e.g. `15;0` or `0;default;15`.
+    fn from_colorfgbg() -> Option<Self> {
+        let colorfgbg = env::var("COLORFGBG").ok()?;
+        // The background is the last field, as an ANSI colour number.
+        match colorfgbg.rsplit(';').next()?.parse::<u8>().ok()? {
+            7 | 9..=15 => Some(Self::Light),
+            _ => Some(Self::Dark),
+        }
+    }
+
+    /// Asks the terminal for its background colour.
+    ///
+    /// This is only done when both stdin and stderr are terminals, so that
+    /// the response is not mixed into piped input.
+    #[cfg(unix)]
+    fn query() -> Option<Self> {
+        use std::{
+            fs::OpenOptions,
+            io::{Read, Write},
+            os::unix::fs::OpenOptionsExt,
+            thread,
+            time::Instant,
+        };
+
+        if !io::stdin().is_tty() || !io::stderr().is_tty() {
+            return None;
+        }
+        let mut tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/tty")
+            .ok()?;
+
+        // Raw mode stops the response from being echoed.
+        crossterm::terminal::enable_raw_mode().ok()?;
+        let response = (|| {
+            tty.write_all(b"\x1b]11;?\x07").ok()?;
+
+            let deadline = Instant::now() + Self::QUERY_TIMEOUT;
+            let mut response = Vec::new();
+            let mut buffer = [0u8; 64];
+            while Instant::now() < deadline {
+                match tty.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        response.extend_from_slice(&buffer[..n]);
+                        // Terminals end the response with either BEL or ST.
+                        if response.ends_with(b"\x07") || response.ends_with(b"\x1b\\") {
+                            break;
+                        }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(Self::QUERY_POLL_INTERVAL)
+                    }
+                    Err(_) => break,
+                }
+            }
+            Some(response)
+        })();
+        let _ = crossterm::terminal::disable_raw_mode();
+
+        Self::from_osc_11(&String::from_utf8_lossy(&response?))
+    }
+
+    #[cfg(not(unix))]
+    fn query() -> Option<Self> {
+        None
+    }
+
+    /// Parses an OSC 11 response, e.g. `\x1b]11;rgb:ffff/ffff/ffff\x07`.
+    fn from_osc_11(response: &str) -> Option<Self> {
+        let rgb = response.split("rgb:").nth(1)?;
+        // Each channel has 1 to 4 hex digits.
+        let mut channels = rgb
+            .split(&['/', '\x07', '\x1b'][..])
+            .take(3)
+            .map(|channel| {
+                if channel.is_empty() || channel.len() > 4 {
+                    return None;
+                }
+                let value = u32::from_str_radix(channel, 16).ok()?;
+                let max = (1u32 << (4 * channel.len())) - 1;
+                Some(f64::from(value) / f64::from(max))
+            });
+        let r = channels.next()??;
+        let g = channels.next()??;
+        let b = channels.next()??;
+
+        let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        if luminance > 0.5 {
+            Some(Self::Light)
+        } else {
+            Some(Self::Dark)
+        }
+    }
+}
+
+/// When to colour output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Colour output when stderr is a terminal and `NO_COLOR` is not set.
+    Auto,
+    /// Always colour output.
+    Always,
+    /// Never colour output.
+    Never,
+}
+
+impl ColorMode {
+    /// Returns whether output should be coloured.
+    ///
+    /// See <https://no-color.org>.
+    pub fn enabled(self, terminal: &TerminalCapabilities) -> bool {
+        match self {
+            Self::Auto => {
+                let no_color = env::var_os("NO_COLOR")
+                    .map(|no_color| !no_color.is_empty())
+                    .unwrap_or(false);
+                terminal.is_tty && !no_color
+            }
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(format!("`{}` is not one of `auto`, `always`, `never`.", s)),
+        }
+    }
+}
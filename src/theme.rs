@@ -0,0 +1,463 @@
+use std::{collections::BTreeMap, convert::TryFrom, fmt, str::FromStr};
+
+use crossterm::style::{Attribute, Attributes, Color, ContentStyle};
+use serde::Deserialize;
+
+use crate::{ColorDepth, StyleConfig};
+
+/// Built in themes, selected with `--theme` or `name` in the `[theme]`
+/// section of the config file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    /// Blue and green logo, with cyan titles.
+    Default,
+    /// Colours from the Solarized palette.
+    Solarized,
+    /// No colours, only bold, dim, and underlined text.
+    Monochrome,
+    /// Bright colours and bold text.
+    HighContrast,
+    /// Darker colours that are readable on a light background.
+    Light,
+}
+
+impl fmt::Display for ThemeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Default => "default",
+            Self::Solarized => "solarized",
+            Self::Monochrome => "monochrome",
+            Self::HighContrast => "high-contrast",
+            Self::Light => "light",
+        };
+        f.pad(name)
+    }
+}
+
+impl FromStr for ThemeName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            "solarized" => Ok(Self::Solarized),
+            "monochrome" => Ok(Self::Monochrome),
+            "high-contrast" => Ok(Self::HighContrast),
+            "light" => Ok(Self::Light),
+            _ => Err(format!(
+                "`{}` is not one of `default`, `solarized`, `monochrome`, `high-contrast`, `light`.",
+                s
+            )),
+        }
+    }
+}
+
+/// Styles for UI output on terminal.
+///
+/// Each field may be overridden in the `[theme.styles]` section of the config
+/// file, using the field name as the key.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// Logo left color.
+    pub logo_left: ContentStyle,
+    /// Logo right color.
+    pub logo_right: ContentStyle,
+    /// Styling for a report border.
+    pub report_border: ContentStyle,
+    /// Styling for a report section title.
+    pub report_title: ContentStyle,
+    /// Styling for a report error section title.
+    pub report_title_error: ContentStyle,
+    /// Styling for a report label.
+    pub report_label: ContentStyle,
+    /// Styling for a report item success.
+    pub report_item_success: ContentStyle,
+    /// Styling for a report item partial success.
+    pub report_item_partial_success: ContentStyle,
+    /// Styling for a report item failure.
+    pub report_item_failure: ContentStyle,
+    /// Styling for a report error item.
+    pub report_error_item: ContentStyle,
+    /// Styling for a report error message.
+    pub report_error_message: ContentStyle,
+    /// Styling for the latest error shown after the progress bar.
+    pub progress_error: ContentStyle,
+}
+
+impl Theme {
+    /// Returns the built in theme with the given name.
+    pub fn named(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Default => Self::default_theme(),
+            ThemeName::Solarized => Self::solarized(),
+            ThemeName::Monochrome => Self::monochrome(),
+            ThemeName::HighContrast => Self::high_contrast(),
+            ThemeName::Light => Self::light(),
+        }
+    }
+
+    /// Returns a theme without any styling, used when colours are disabled.
+    pub fn plain() -> Self {
+        let plain = ContentStyle::new();
+        Self {
+            logo_left: plain,
+            logo_right: plain,
+            report_border: plain,
+            report_title: plain,
+            report_title_error: plain,
+            report_label: plain,
+            report_item_success: plain,
+            report_item_partial_success: plain,
+            report_item_failure: plain,
+            report_error_item: plain,
+            report_error_message: plain,
+            progress_error: plain,
+        }
+    }
+
+    fn default_theme() -> Self {
+        let bold = Attributes::from(Attribute::Bold);
+        Self {
+            logo_left: style(Some(Color::Blue), bold),
+            logo_right: style(Some(Color::Green), bold),
+            report_border: style(Some(Color::Blue), bold),
+            report_title: style(Some(Color::Cyan), bold),
+            report_title_error: style(Some(Color::Red), bold),
+            report_label: style(None, bold),
+            report_item_success: style(Some(Color::Green), bold),
+            report_item_partial_success: style(
+                Some(Color::Rgb {
+                    r: 216,
+                    g: 216,
+                    b: 0,
+                }),
+                bold,
+            ),
+            report_item_failure: style(Some(Color::Red), bold),
+            report_error_item: style(None, Attributes::default()),
+            report_error_message: style(Some(Color::Yellow), Attributes::default()),
+            progress_error: style(None, Attributes::from(Attribute::Dim)),
+        }
+    }
+
+    fn solarized() -> Self {
+        let base01 = Color::Rgb {
+            r: 0x58,
+            g: 0x6e,
+            b: 0x75,
+        };
+        let yellow = Color::Rgb {
+            r: 0xb5,
+            g: 0x89,
+            b: 0x00,
+        };
+        let orange = Color::Rgb {
+            r: 0xcb,
+            g: 0x4b,
+            b: 0x16,
+        };
+        let red = Color::Rgb {
+            r: 0xdc,
+            g: 0x32,
+            b: 0x2f,
+        };
+        let blue = Color::Rgb {
+            r: 0x26,
+            g: 0x8b,
+            b: 0xd2,
+        };
+        let cyan = Color::Rgb {
+            r: 0x2a,
+            g: 0xa1,
+            b: 0x98,
+        };
+        let green = Color::Rgb {
+            r: 0x85,
+            g: 0x99,
+            b: 0x00,
+        };
+
+        let bold = Attributes::from(Attribute::Bold);
+        Self {
+            logo_left: style(Some(blue), bold),
+            logo_right: style(Some(green), bold),
+            report_border: style(Some(base01), bold),
+            report_title: style(Some(cyan), bold),
+            report_title_error: style(Some(red), bold),
+            report_label: style(None, bold),
+            report_item_success: style(Some(green), bold),
+            report_item_partial_success: style(Some(yellow), bold),
+            report_item_failure: style(Some(red), bold),
+            report_error_item: style(None, Attributes::default()),
+            report_error_message: style(Some(orange), Attributes::default()),
+            progress_error: style(Some(base01), Attributes::default()),
+        }
+    }
+
+    fn monochrome() -> Self {
+        let bold = Attributes::from(Attribute::Bold);
+        let dim = Attributes::from(Attribute::Dim);
+        let underlined = Attributes::from(Attribute::Underlined);
+        Self {
+            logo_left: style(None, bold),
+            logo_right: style(None, Attributes::default()),
+            report_border: style(None, dim),
+            report_title: style(None, bold),
+            report_title_error: style(None, bold | Attribute::Underlined),
+            report_label: style(None, bold),
+            report_item_success: style(None, bold),
+            report_item_partial_success: style(None, underlined),
+            report_item_failure: style(None, bold | Attribute::Underlined),
+            report_error_item: style(None, Attributes::default()),
+            report_error_message: style(None, Attributes::default()),
+            progress_error: style(None, dim),
+        }
+    }
+
+    fn high_contrast() -> Self {
+        let bold = Attributes::from(Attribute::Bold);
+        Self {
+            logo_left: style(Some(Color::Cyan), bold),
+            logo_right: style(Some(Color::Green), bold),
+            report_border: style(Some(Color::White), bold),
+            report_title: style(Some(Color::Cyan), bold | Attribute::Underlined),
+            report_title_error: style(Some(Color::Red), bold | Attribute::Underlined),
+            report_label: style(Some(Color::White), bold),
+            report_item_success: style(Some(Color::Green), bold),
+            report_item_partial_success: style(Some(Color::Yellow), bold),
+            report_item_failure: style(Some(Color::Red), bold),
+            report_error_item: style(Some(Color::White), Attributes::default()),
+            report_error_message: style(Some(Color::Yellow), bold),
+            progress_error: style(Some(Color::Yellow), Attributes::default()),
+        }
+    }
+
+    fn light() -> Self {
+        let bold = Attributes::from(Attribute::Bold);
+        // Dark yellow, as bright yellow is hard to read on white.
+        let amber = Color::Rgb {
+            r: 160,
+            g: 110,
+            b: 0,
+        };
+        Self {
+            logo_left: style(Some(Color::DarkBlue), bold),
+            logo_right: style(Some(Color::DarkGreen), bold),
+            report_border: style(Some(Color::DarkBlue), bold),
+            report_title: style(Some(Color::DarkCyan), bold),
+            report_title_error: style(Some(Color::DarkRed), bold),
+            report_label: style(None, bold),
+            report_item_success: style(Some(Color::DarkGreen), bold),
+            report_item_partial_success: style(Some(amber), bold),
+            report_item_failure: style(Some(Color::DarkRed), bold),
+            report_error_item: style(None, Attributes::default()),
+            report_error_message: style(Some(amber), Attributes::default()),
+            // Dim text is too faint on a light background.
+            progress_error: style(Some(Color::DarkGrey), Attributes::default()),
+        }
+    }
+
+    /// Replaces styles with the ones from the `[theme.styles]` section of the
+    /// config file.
+    pub fn with_overrides(
+        mut self,
+        styles: &BTreeMap<String, StyleConfig>,
+    ) -> Result<Self, String> {
+        for (name, style_config) in styles {
+            let content_style = self
+                .style_mut(name)
+                .ok_or_else(|| format!("`{}` is not a style name.", name))?;
+            *content_style = Self::content_style(style_config)
+                .map_err(|e| format!("Style `{}` is invalid: {}", name, e))?;
+        }
+        Ok(self)
+    }
+
+    /// Replaces colours that the terminal doesn't support with the nearest
+    /// supported colour.
+    pub fn fit(mut self, color_depth: ColorDepth) -> Self {
+        for content_style in self.styles_mut().iter_mut() {
+            content_style.foreground_color = content_style
+                .foreground_color
+                .map(|color| fit_color(color, color_depth));
+            content_style.background_color = content_style
+                .background_color
+                .map(|color| fit_color(color, color_depth));
+        }
+        self
+    }
+
+    fn styles_mut(&mut self) -> [&mut ContentStyle; 12] {
+        [
+            &mut self.logo_left,
+            &mut self.logo_right,
+            &mut self.report_border,
+            &mut self.report_title,
+            &mut self.report_title_error,
+            &mut self.report_label,
+            &mut self.report_item_success,
+            &mut self.report_item_partial_success,
+            &mut self.report_item_failure,
+            &mut self.report_error_item,
+            &mut self.report_error_message,
+            &mut self.progress_error,
+        ]
+    }
+
+    fn style_mut(&mut self, name: &str) -> Option<&mut ContentStyle> {
+        let content_style = match name {
+            "logo_left" => &mut self.logo_left,
+            "logo_right" => &mut self.logo_right,
+            "report_border" => &mut self.report_border,
+            "report_title" => &mut self.report_title,
+            "report_title_error" => &mut self.report_title_error,
+            "report_label" => &mut self.report_label,
+            "report_item_success" => &mut self.report_item_success,
+            "report_item_partial_success" => &mut self.report_item_partial_success,
+            "report_item_failure" => &mut self.report_item_failure,
+            "report_error_item" => &mut self.report_error_item,
+            "report_error_message" => &mut self.report_error_message,
+            "progress_error" => &mut self.progress_error,
+            _ => return None,
+        };
+        Some(content_style)
+    }
+
+    fn content_style(style_config: &StyleConfig) -> Result<ContentStyle, String> {
+        let mut attributes = Attributes::default();
+        [
+            (style_config.bold, Attribute::Bold),
+            (style_config.dim, Attribute::Dim),
+            (style_config.italic, Attribute::Italic),
+            (style_config.underlined, Attribute::Underlined),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .for_each(|(_, attribute)| attributes.set(*attribute));
+
+        Ok(ContentStyle {
+            foreground_color: style_config
+                .foreground
+                .as_deref()
+                .map(parse_color)
+                .transpose()?,
+            background_color: style_config
+                .background
+                .as_deref()
+                .map(parse_color)
+                .transpose()?,
+            attributes,
+        })
+    }
+}
+
+fn style(foreground_color: Option<Color>, attributes: Attributes) -> ContentStyle {
+    ContentStyle {
+        foreground_color,
+        background_color: None,
+        attributes,
+    }
+}
+
+/// Parses a colour name such as `dark_yellow`, an ANSI colour number from
+/// `0` to `255`, or a hex colour such as `#d8d800`.
+fn parse_color(s: &str) -> Result<Color, String> {
+    if let Some(hex) = s.strip_prefix('#') {
+        let channel = |index: usize| {
+            hex.get(index..index + 2)
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+        };
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb { r, g, b }),
+            _ => Err(format!("`{}` is not a hex colour such as `#d8d800`.", s)),
+        };
+    }
+    if let Ok(ansi_value) = s.parse::<u8>() {
+        return Ok(Color::AnsiValue(ansi_value));
+    }
+    Color::try_from(s).map_err(|()| {
+        format!(
+            "`{}` is not a colour name, ANSI colour number, or hex colour.",
+            s
+        )
+    })
+}
+
+/// The 16 ANSI colours, with their usual RGB values.
+const ANSI_16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (128, 0, 0)),
+    (Color::DarkGreen, (0, 128, 0)),
+    (Color::DarkYellow, (128, 128, 0)),
+    (Color::DarkBlue, (0, 0, 128)),
+    (Color::DarkMagenta, (128, 0, 128)),
+    (Color::DarkCyan, (0, 128, 128)),
+    (Color::Grey, (192, 192, 192)),
+    (Color::DarkGrey, (128, 128, 128)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Levels of each channel in the 256 colour palette's 6x6x6 colour cube.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Returns the nearest colour that fits in the given colour depth.
+fn fit_color(color: Color, color_depth: ColorDepth) -> Color {
+    match (color, color_depth) {
+        (_, ColorDepth::TrueColor) => color,
+        (Color::Rgb { r, g, b }, ColorDepth::Ansi256) => {
+            let level = |channel: u8| nearest_index(CUBE_LEVELS.iter().copied(), channel) as u8;
+            Color::AnsiValue(16 + 36 * level(r) + 6 * level(g) + level(b))
+        }
+        (Color::Rgb { r, g, b }, ColorDepth::Ansi16) => nearest_ansi_16((r, g, b)),
+        (Color::AnsiValue(ansi_value), ColorDepth::Ansi16) => {
+            nearest_ansi_16(ansi_256_rgb(ansi_value))
+        }
+        _ => color,
+    }
+}
+
+/// Returns the RGB value of a colour in the 256 colour palette.
+fn ansi_256_rgb(ansi_value: u8) -> (u8, u8, u8) {
+    match ansi_value {
+        0..=15 => ANSI_16[usize::from(ansi_value)].1,
+        16..=231 => {
+            let index = ansi_value - 16;
+            (
+                CUBE_LEVELS[usize::from(index / 36)],
+                CUBE_LEVELS[usize::from(index / 6 % 6)],
+                CUBE_LEVELS[usize::from(index % 6)],
+            )
+        }
+        232..=255 => {
+            let grey = 8 + 10 * (ansi_value - 232);
+            (grey, grey, grey)
+        }
+    }
+}
+
+fn nearest_ansi_16((r, g, b): (u8, u8, u8)) -> Color {
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+    ANSI_16
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::White)
+}
+
+fn nearest_index(levels: impl Iterator<Item = u8>, channel: u8) -> usize {
+    levels
+        .enumerate()
+        .min_by_key(|(_, level)| (i16::from(*level) - i16::from(channel)).abs())
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
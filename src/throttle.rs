@@ -0,0 +1,65 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{EventBus, RunEvent};
+
+/// Pauses taking in new records while the server is throttling requests, e.g.
+/// after a `429 Too Many Requests` response.
+///
+/// Every request would be throttled too, so records wait until the server
+/// allows requests again, instead of each backing off on its own.
+#[derive(Debug)]
+pub struct Throttle {
+    /// When the server allows requests again.
+    until: Mutex<Option<Instant>>,
+    /// Publishes each throttle, for the progress bar and report.
+    event_bus: EventBus,
+}
+
+impl Throttle {
+    /// Returns a throttle that isn't throttling.
+    pub fn new(event_bus: EventBus) -> Self {
+        Self {
+            until: Mutex::new(None),
+            event_bus,
+        }
+    }
+
+    /// Pauses taking in new records for `retry_after`, e.g. from the
+    /// response's `Retry-After` header.
+    ///
+    /// If already throttled for longer, the pause isn't shortened.
+    pub async fn throttle(&self, retry_after: Duration) {
+        let until = Instant::now() + retry_after;
+        {
+            let mut until_current = self.until.lock().expect("Throttle lock poisoned.");
+            if until_current.is_some_and(|until_current| until_current >= until) {
+                return;
+            }
+            *until_current = Some(until);
+        }
+
+        tracing::warn!(
+            "Throttled by server, pausing for {}.",
+            humantime::format_duration(retry_after)
+        );
+        self.event_bus
+            .publish(RunEvent::Throttled { retry_after })
+            .await;
+    }
+
+    /// Waits until the server allows requests again.
+    pub async fn wait(&self) {
+        loop {
+            let until = *self.until.lock().expect("Throttle lock poisoned.");
+            match until {
+                Some(until) if until > Instant::now() => {
+                    tokio::time::sleep_until(until.into()).await
+                }
+                _ => return,
+            }
+        }
+    }
+}
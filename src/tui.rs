@@ -0,0 +1,532 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Stderr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{
+        cursor,
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        execute, terminal,
+    },
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Sparkline},
+    Frame, Terminal,
+};
+use tokio::time;
+
+use crate::{
+    CircuitState, ConcurrencyLimit, EventReceiver, InFlight, InterruptReason, LookupResult,
+    ProgressMessage, Record, RecordStatus, RunControl, RunEvent, StageKind, StageTimings,
+};
+
+/// Recent log lines, shown on the dashboard instead of being written over it.
+#[derive(Clone, Debug, Default)]
+pub struct TuiLog {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl TuiLog {
+    /// Number of log lines kept.
+    const LINE_COUNT: usize = 50;
+
+    /// Adds a log line, dropping the oldest line if there are too many.
+    pub fn push(&self, line: String) {
+        let mut lines = self.lines.lock().expect("TUI log lock poisoned.");
+        if lines.len() == Self::LINE_COUNT {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Returns the most recent `count` lines, oldest first.
+    fn recent(&self, count: usize) -> Vec<String> {
+        let lines = self.lines.lock().expect("TUI log lock poisoned.");
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Full-screen dashboard shown with `--tui`, instead of the progress bars.
+///
+/// Shows the overall progress, each stage's throughput, the most recent
+/// errors and log lines, and handles the same keys as [`KeyboardControl`].
+///
+/// [`KeyboardControl`]: crate::KeyboardControl
+#[derive(Debug)]
+pub struct Tui {
+    run_control: Arc<RunControl>,
+    concurrency_limit: Arc<ConcurrencyLimit>,
+    progress_message: ProgressMessage,
+    stage_timings: Arc<StageTimings>,
+    in_flight: Arc<InFlight>,
+    log: TuiLog,
+}
+
+/// What the dashboard shows, updated from [`RunEvent`]s.
+#[derive(Debug)]
+struct TuiState {
+    start: Instant,
+    /// Total number of records, or `None` if it isn't known.
+    record_count: Option<usize>,
+    record_skipped_count: usize,
+    success_count: usize,
+    partial_count: usize,
+    failed_count: usize,
+    /// Title number and error of the most recent failures, newest last.
+    errors: VecDeque<(String, String)>,
+    /// When the server allows requests again, if it is throttling.
+    throttled_until: Option<Instant>,
+    circuit_state: CircuitState,
+    interrupted: Option<InterruptReason>,
+    /// Number of times each stage had run at the last sample.
+    stage_counts: [u64; StageKind::ALL.len()],
+    /// Number of records through each stage in each of the last samples,
+    /// oldest first.
+    stage_throughput: [VecDeque<u64>; StageKind::ALL.len()],
+}
+
+/// Restores the terminal when the dashboard or error browser stops,
+/// including on panic.
+#[derive(Debug)]
+pub struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if let Err(e) = terminal::disable_raw_mode() {
+            tracing::warn!("Failed to disable raw mode: {}", e);
+        }
+        if let Err(e) = execute!(io::stderr(), terminal::LeaveAlternateScreen, cursor::Show) {
+            tracing::warn!("Failed to leave the alternate screen: {}", e);
+        }
+    }
+}
+
+impl Tui {
+    /// How often the dashboard is redrawn, and key presses are read.
+    const DRAW_INTERVAL: Duration = Duration::from_millis(250);
+    /// How often each stage's throughput is sampled.
+    const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+    /// Number of throughput samples kept for each stage.
+    const SAMPLE_COUNT: usize = 120;
+    /// Number of recent errors kept.
+    const ERROR_COUNT: usize = 100;
+
+    /// Returns a dashboard for the run.
+    pub fn new(
+        run_control: Arc<RunControl>,
+        concurrency_limit: Arc<ConcurrencyLimit>,
+        progress_message: ProgressMessage,
+        stage_timings: Arc<StageTimings>,
+        in_flight: Arc<InFlight>,
+        log: TuiLog,
+    ) -> Self {
+        Self {
+            run_control,
+            concurrency_limit,
+            progress_message,
+            stage_timings,
+            in_flight,
+            log,
+        }
+    }
+
+    /// Shows the dashboard until the run finishes, as published on the
+    /// [`EventBus`], then restores the terminal so the report can be printed.
+    ///
+    /// [`EventBus`]: crate::EventBus
+    pub async fn run<R, I>(self, mut events: EventReceiver<R, I>)
+    where
+        R: Record,
+        I: LookupResult,
+    {
+        let (mut terminal, _terminal_guard) = match Self::enter() {
+            Ok(terminal) => terminal,
+            Err(e) => {
+                tracing::error!("Failed to start the dashboard: {}", e);
+                return;
+            }
+        };
+
+        let mut state = TuiState::new();
+        let mut draw_interval = time::interval(Self::DRAW_INTERVAL);
+        let mut sample_interval = time::interval(Self::SAMPLE_INTERVAL);
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(RunEvent::RunFinished(_)) | None => break,
+                    Some(event) => state.event(event),
+                },
+                _ = sample_interval.tick() => state.sample(&self.stage_timings),
+                _ = draw_interval.tick() => {
+                    self.read_keys();
+                    if let Err(e) = terminal.draw(|frame| self.draw(frame, &state)) {
+                        tracing::error!("Failed to draw the dashboard: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Switches to the alternate screen in raw mode, until the returned guard
+    /// is dropped.
+    pub fn enter() -> io::Result<(Terminal<CrosstermBackend<Stderr>>, TerminalGuard)> {
+        terminal::enable_raw_mode()?;
+        let terminal_guard = TerminalGuard;
+        execute!(io::stderr(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        let terminal = Terminal::new(CrosstermBackend::new(io::stderr()))?;
+
+        Ok((terminal, terminal_guard))
+    }
+
+    /// Handles the key presses since the last draw, without waiting.
+    fn read_keys(&self) {
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            let key_event = match event::read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => key_event,
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::warn!("Failed to read key press: {}", e);
+                    break;
+                }
+            };
+
+            match key_event {
+                KeyEvent {
+                    code: KeyCode::Char('p'),
+                    ..
+                } => self.run_control.pause(&self.progress_message),
+                KeyEvent {
+                    code: KeyCode::Char('r'),
+                    ..
+                } => self.run_control.resume(&self.progress_message),
+                KeyEvent {
+                    code: KeyCode::Char('q'),
+                    ..
+                } => self.run_control.stop(InterruptReason::Stop),
+                KeyEvent {
+                    code: KeyCode::Char('+'),
+                    ..
+                } => self.concurrency_limit.increase(),
+                KeyEvent {
+                    code: KeyCode::Char('-'),
+                    ..
+                } => self.concurrency_limit.decrease(),
+                // Raw mode stops Ctrl-C from sending `SIGINT`, so it is handled here.
+                KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers,
+                    ..
+                } if modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.run_control.stop(InterruptReason::CtrlC)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame<'_>, state: &TuiState) {
+        let [progress_area, status_area, throughput_area, errors_area, log_area, keys_area] =
+            Layout::vertical([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(7),
+                Constraint::Min(5),
+                Constraint::Length(8),
+                Constraint::Length(1),
+            ])
+            .areas(frame.area());
+
+        self.draw_progress(frame, progress_area, state);
+        self.draw_status(frame, status_area, state);
+        self.draw_throughput(frame, throughput_area, state);
+        Self::draw_errors(frame, errors_area, state);
+        self.draw_log(frame, log_area);
+        frame.render_widget(
+            Paragraph::new("p pause · r resume · q quit · + / - concurrency")
+                .style(Style::default().fg(Color::DarkGray)),
+            keys_area,
+        );
+    }
+
+    fn draw_progress(&self, frame: &mut Frame<'_>, area: Rect, state: &TuiState) {
+        let block = Block::default().borders(Borders::ALL).title(" Progress ");
+        let done_count = state.record_skipped_count + state.processed_count();
+        match state.record_count {
+            Some(record_count) => {
+                let ratio = if record_count == 0 {
+                    1.0
+                } else {
+                    (done_count as f64 / record_count as f64).min(1.0)
+                };
+                let gauge = Gauge::default()
+                    .block(block)
+                    .gauge_style(Style::default().fg(Color::Cyan))
+                    .ratio(ratio)
+                    .label(format!(
+                        "{}/{} ({:.0}%)",
+                        done_count,
+                        record_count,
+                        ratio * 100.0
+                    ));
+                frame.render_widget(gauge, area);
+            }
+            None => {
+                let paragraph =
+                    Paragraph::new(format!("{} processed", state.processed_count())).block(block);
+                frame.render_widget(paragraph, area);
+            }
+        }
+    }
+
+    fn draw_status(&self, frame: &mut Frame<'_>, area: Rect, state: &TuiState) {
+        let elapsed = state.start.elapsed();
+        let rate = state.processed_count() as f64 / elapsed.as_secs_f64().max(1.0);
+        let mut spans = vec![
+            Span::styled(
+                format!("✔ {} ", state.success_count),
+                Style::default().fg(Color::Green),
+            ),
+            Span::styled(
+                format!("◐ {} ", state.partial_count),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::styled(
+                format!("✖ {} ", state.failed_count),
+                Style::default().fg(Color::Red),
+            ),
+            Span::raw(format!(
+                "· {} · {:.1}/s · concurrency {} ",
+                humantime::format_duration(Duration::from_secs(elapsed.as_secs())),
+                rate,
+                self.concurrency_limit.limit()
+            )),
+        ];
+
+        let flag_style = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+        if self.run_control.is_paused() {
+            spans.push(Span::styled("PAUSED ", flag_style));
+        }
+        if state
+            .throttled_until
+            .is_some_and(|throttled_until| Instant::now() < throttled_until)
+        {
+            spans.push(Span::styled("THROTTLED ", flag_style));
+        }
+        match state.circuit_state {
+            CircuitState::Closed => {}
+            CircuitState::Open => spans.push(Span::styled("CIRCUIT OPEN ", flag_style)),
+            CircuitState::HalfOpen => spans.push(Span::styled("CIRCUIT HALF-OPEN ", flag_style)),
+        }
+        if let Some(reason) = state.interrupted {
+            spans.push(Span::styled(
+                format!(
+                    "INTERRUPTED ({}), finishing {} in-flight records…",
+                    reason,
+                    self.in_flight.count()
+                ),
+                flag_style,
+            ));
+        }
+
+        let paragraph = Paragraph::new(Line::from(spans))
+            .block(Block::default().borders(Borders::ALL).title(" Status "));
+        frame.render_widget(paragraph, area);
+    }
+
+    fn draw_throughput(&self, frame: &mut Frame<'_>, area: Rect, state: &TuiState) {
+        let areas = Layout::horizontal(
+            StageKind::ALL
+                .iter()
+                .map(|_| Constraint::Ratio(1, StageKind::ALL.len() as u32)),
+        )
+        .split(area);
+
+        StageKind::ALL
+            .iter()
+            .zip(state.stage_throughput.iter())
+            .zip(areas.iter())
+            .for_each(|((stage, throughput), area)| {
+                // Newest samples on the right, as many as fit.
+                let width = usize::from(area.width.saturating_sub(2));
+                let data = throughput
+                    .iter()
+                    .skip(throughput.len().saturating_sub(width))
+                    .copied()
+                    .collect::<Vec<_>>();
+                let latest = data.last().copied().unwrap_or(0);
+                let sparkline = Sparkline::default()
+                    .block(Block::default().borders(Borders::ALL).title(format!(
+                        " {} {}/s ",
+                        stage.name(),
+                        latest
+                    )))
+                    .style(Style::default().fg(Color::Cyan))
+                    .data(&data);
+                frame.render_widget(sparkline, *area);
+            });
+    }
+
+    fn draw_errors(frame: &mut Frame<'_>, area: Rect, state: &TuiState) {
+        let items = state
+            .errors
+            .iter()
+            .rev()
+            .map(|(title_number, error)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{} ", title_number),
+                        Style::default().fg(Color::Magenta),
+                    ),
+                    Span::styled(error.as_str(), Style::default().fg(Color::Red)),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Recent errors "),
+        );
+        frame.render_widget(list, area);
+    }
+
+    fn draw_log(&self, frame: &mut Frame<'_>, area: Rect) {
+        let items = self
+            .log
+            .recent(usize::from(area.height.saturating_sub(2)))
+            .into_iter()
+            .map(ListItem::new)
+            .collect::<Vec<_>>();
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title(" Log "));
+        frame.render_widget(list, area);
+    }
+}
+
+impl TuiState {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            record_count: None,
+            record_skipped_count: 0,
+            success_count: 0,
+            partial_count: 0,
+            failed_count: 0,
+            errors: VecDeque::new(),
+            throttled_until: None,
+            circuit_state: CircuitState::Closed,
+            interrupted: None,
+            stage_counts: [0; StageKind::ALL.len()],
+            stage_throughput: Default::default(),
+        }
+    }
+
+    /// Returns the number of records processed in this run.
+    fn processed_count(&self) -> usize {
+        self.success_count + self.partial_count + self.failed_count
+    }
+
+    fn event<R, I>(&mut self, event: RunEvent<R, I>)
+    where
+        R: Record,
+        I: LookupResult,
+    {
+        match event {
+            RunEvent::RunStarted {
+                record_count,
+                record_skipped_count,
+            } => {
+                self.record_count = record_count;
+                self.record_skipped_count = record_skipped_count;
+            }
+            RunEvent::RecordsDiscovered { record_count } => {
+                if let Some(total) = self.record_count.as_mut() {
+                    *total += record_count;
+                }
+            }
+            RunEvent::RecordRetrieved(record_progress) => match record_progress.info.status() {
+                RecordStatus::Success => self.success_count += 1,
+                RecordStatus::SuccessPartial => self.partial_count += 1,
+                RecordStatus::Error => {
+                    self.failed_count += 1;
+                    self.error(
+                        record_progress.record,
+                        record_progress.info.error().unwrap_or_default(),
+                    );
+                }
+            },
+            RunEvent::RecordDuplicate { .. } => self.record_skipped_count += 1,
+            RunEvent::Throttled { retry_after } => {
+                let throttled_until = Instant::now() + retry_after;
+                self.throttled_until = Some(
+                    self.throttled_until
+                        .map_or(throttled_until, |until| until.max(throttled_until)),
+                );
+            }
+            RunEvent::CircuitChanged(circuit_state) => self.circuit_state = circuit_state,
+            RunEvent::RecordFailed {
+                record,
+                stage,
+                error,
+            } => self.error(record, format!("{} failed: {}", stage.name(), error)),
+            RunEvent::RecordPanicked {
+                record,
+                stage,
+                message,
+                looked_up,
+            } => {
+                // Records that panicked after being looked up were already
+                // counted when they were retrieved.
+                if !looked_up {
+                    self.failed_count += 1;
+                }
+                self.error(
+                    record,
+                    format!("{} stage panicked: {}", stage.name(), message),
+                );
+            }
+            RunEvent::Interrupted(reason) => self.interrupted = Some(reason),
+            RunEvent::RecordWritten { .. }
+            | RunEvent::RecordsAbandoned { .. }
+            | RunEvent::ProcessingFinished
+            | RunEvent::RunFinished(_) => {}
+        }
+    }
+
+    /// Records a failure in the recent errors.
+    fn error(&mut self, record: impl Record, error: String) {
+        if self.errors.len() == Tui::ERROR_COUNT {
+            self.errors.pop_front();
+        }
+        // Only the first line, so each error takes one row.
+        let error = error.lines().next().unwrap_or_default().to_string();
+        self.errors.push_back((record.label(), error));
+    }
+
+    /// Records how many records passed through each stage since the last
+    /// sample.
+    fn sample(&mut self, stage_timings: &StageTimings) {
+        StageKind::ALL
+            .iter()
+            .zip(self.stage_counts.iter_mut())
+            .zip(self.stage_throughput.iter_mut())
+            .for_each(|((stage, stage_count), throughput)| {
+                let count = stage_timings.count(*stage);
+                if throughput.len() == Tui::SAMPLE_COUNT {
+                    throughput.pop_front();
+                }
+                throughput.push_back(count.saturating_sub(*stage_count));
+                *stage_count = count;
+            });
+    }
+}
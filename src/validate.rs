@@ -0,0 +1,88 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+    path::Path,
+};
+
+use crate::Colours;
+
+/// Results of checking a run's settings, without processing any records.
+///
+/// Every check is run, so that all problems are reported at once.
+#[derive(Debug, Default)]
+pub struct Validation {
+    /// Name of each check, and the problem it found, if any.
+    checks: Vec<(String, Result<(), String>)>,
+}
+
+impl Validation {
+    /// Records the result of a check.
+    pub fn check<E>(&mut self, name: impl Into<String>, result: Result<(), E>)
+    where
+        E: ToString,
+    {
+        self.checks
+            .push((name.into(), result.map_err(|e| e.to_string())));
+    }
+
+    /// Checks that a file can be written to, without truncating it.
+    ///
+    /// If the file doesn't exist, it is created and removed again.
+    pub fn check_writable(&mut self, name: &str, path: &Path) {
+        let result = if path.exists() {
+            OpenOptions::new().append(true).open(path).map(|_| ())
+        } else {
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .and_then(|_| fs::remove_file(path))
+        };
+        self.check(format!("{} `{}`", name, path.display()), result);
+    }
+
+    /// Returns whether every check passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// Writes the result of each check to stderr, followed by the number of
+    /// problems found.
+    pub fn print(&self) -> io::Result<()> {
+        let mut output = String::with_capacity(512);
+        self.checks.iter().for_each(|(name, result)| {
+            // Writing to a `String` doesn't fail.
+            let _ = match result {
+                Ok(()) => writeln!(
+                    &mut output,
+                    "{} {}",
+                    Colours::theme().report_item_success.apply("[ok]   "),
+                    name
+                ),
+                Err(problem) => writeln!(
+                    &mut output,
+                    "{} {}: {}",
+                    Colours::theme().report_item_failure.apply("[error]"),
+                    name,
+                    Colours::theme().report_error_message.apply(problem)
+                ),
+            };
+        });
+
+        let problem_count = self
+            .checks
+            .iter()
+            .filter(|(_, result)| result.is_err())
+            .count();
+        let _ = match problem_count {
+            0 => writeln!(&mut output, "\nNo problems found."),
+            1 => writeln!(&mut output, "\n1 problem found."),
+            _ => writeln!(&mut output, "\n{} problems found.", problem_count),
+        };
+
+        let mut stderr = io::stderr();
+        stderr.write_all(output.as_bytes())?;
+        stderr.flush()
+    }
+}
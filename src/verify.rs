@@ -0,0 +1,281 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write as _},
+    path::PathBuf,
+};
+
+use clap::Args;
+
+use crate::{
+    output::{OutputLine, OutputLineReader},
+    Colours, PropertyRecord, RecordFilter,
+};
+
+/// Checks that every input record appears exactly once in an output file.
+#[derive(Debug, Args)]
+pub struct VerifyOpt {
+    /// Record IDs that were processed, one per line, in the same format as
+    /// `--only-ids`, e.g. `records.csv`.
+    ///
+    /// Defaults to the records given with `--count`.
+    #[arg(long)]
+    input: Option<PathBuf>,
+    /// Output file written with `--output`.
+    ///
+    /// Files ending in `.gz` or `.zst` are decompressed.
+    #[arg(long)]
+    output: PathBuf,
+    /// Number of records or lines listed for each kind of problem.
+    #[arg(long, default_value = "10")]
+    max_rows: usize,
+}
+
+/// Differences between the records in the input and in an output file.
+#[derive(Debug, Default)]
+pub struct Verification {
+    input_count: usize,
+    output_count: usize,
+    /// Input records that aren't in the output.
+    records_missing: Vec<usize>,
+    /// Records in the output more than once, with how many times they are.
+    records_duplicate: BTreeMap<usize, usize>,
+    /// Records in the output that aren't in the input.
+    records_unexpected: Vec<usize>,
+    /// Lines that aren't valid output lines, by line number, with why.
+    lines_corrupt: Vec<(usize, String)>,
+}
+
+impl Verification {
+    /// Compares the output file with the input, and writes the differences to
+    /// stdout.
+    ///
+    /// Returns whether every input record is in the output exactly once.
+    pub async fn run(verify_opt: &VerifyOpt, record_count: usize) -> io::Result<bool> {
+        let input = match verify_opt.input.as_deref() {
+            Some(input) => RecordFilter::read_id_list(input)?
+                .into_iter()
+                .collect::<BTreeSet<_>>(),
+            None => (0..record_count).collect(),
+        };
+
+        let mut verification = Self {
+            input_count: input.len(),
+            ..Self::default()
+        };
+        let mut record_counts = BTreeMap::<usize, usize>::new();
+        let mut output_lines = OutputLineReader::open(&verify_opt.output).await?;
+        while let Some(output_line) = output_lines.try_next_line().await? {
+            match output_line {
+                Ok(OutputLine::Header(_)) => {}
+                Ok(OutputLine::Record(output_record)) => {
+                    let title_number = PropertyRecord(output_record.record_id).title_number();
+                    if output_record.title_number != title_number {
+                        verification.lines_corrupt.push((
+                            output_lines.line_number(),
+                            format!(
+                                "Title number `{}` doesn't match record {}, which is `{}`.",
+                                output_record.title_number, output_record.record_id, title_number
+                            ),
+                        ));
+                        continue;
+                    }
+                    verification.output_count += 1;
+                    *record_counts.entry(output_record.record_id).or_default() += 1;
+                }
+                Err(e) => verification
+                    .lines_corrupt
+                    .push((output_lines.line_number(), e.to_string())),
+            }
+        }
+
+        verification.records_missing = input
+            .iter()
+            .filter(|record_id| !record_counts.contains_key(record_id))
+            .copied()
+            .collect();
+        verification.records_unexpected = record_counts
+            .keys()
+            .filter(|record_id| !input.contains(record_id))
+            .copied()
+            .collect();
+        verification.records_duplicate = record_counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .collect();
+
+        let mut stdout = io::stdout().lock();
+        verification.write(&mut stdout, verify_opt.max_rows)?;
+        stdout.flush()?;
+
+        Ok(verification.matches())
+    }
+
+    /// Returns whether every input record is in the output exactly once, and
+    /// every line is valid.
+    pub fn matches(&self) -> bool {
+        self.records_missing.is_empty()
+            && self.records_duplicate.is_empty()
+            && self.records_unexpected.is_empty()
+            && self.lines_corrupt.is_empty()
+    }
+
+    fn write(&self, out: &mut impl io::Write, max_rows: usize) -> io::Result<()> {
+        writeln!(
+            out,
+            "{}",
+            Colours::theme().report_title.apply("## Verification")
+        )?;
+        writeln!(out)?;
+        [
+            ("* Input records:", self.input_count, false),
+            ("* Output records:", self.output_count, false),
+            ("* Missing records:", self.records_missing.len(), true),
+            ("* Duplicate records:", self.records_duplicate.len(), true),
+            ("* Unexpected records:", self.records_unexpected.len(), true),
+            ("* Corrupt lines:", self.lines_corrupt.len(), true),
+        ]
+        .iter()
+        .try_for_each(|(label, count, problem)| {
+            // Padded before styling, as styled content ignores the width.
+            let count_padded = format!("{:>7}", count);
+            let count = if *problem && *count > 0 {
+                Colours::theme().report_item_failure.apply(count_padded)
+            } else {
+                Colours::theme().report_label.apply(count_padded)
+            };
+            writeln!(
+                out,
+                "{:<35} {}",
+                Colours::theme().report_label.apply(*label),
+                count
+            )
+        })?;
+
+        Self::write_records(out, "## Missing Records", &self.records_missing, max_rows)?;
+        if !self.records_duplicate.is_empty() {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{}",
+                Colours::theme()
+                    .report_title_error
+                    .apply("## Duplicate Records")
+            )?;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{count:>5} | {title_number}",
+                count = Colours::theme().report_label.apply("count"),
+                title_number = Colours::theme().report_label.apply("title_number")
+            )?;
+            writeln!(out, "----- | -------------")?;
+            self.records_duplicate
+                .iter()
+                .take(max_rows)
+                .try_for_each(|(record_id, count)| {
+                    writeln!(
+                        out,
+                        "{count:5} | {title_number}",
+                        count = count,
+                        title_number = Colours::theme()
+                            .report_error_item
+                            .apply(PropertyRecord(*record_id).title_number())
+                    )
+                })?;
+            Self::write_rows_omitted(out, self.records_duplicate.len(), max_rows)?;
+        }
+        Self::write_records(
+            out,
+            "## Unexpected Records",
+            &self.records_unexpected,
+            max_rows,
+        )?;
+        if !self.lines_corrupt.is_empty() {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{}",
+                Colours::theme()
+                    .report_title_error
+                    .apply("## Corrupt Lines")
+            )?;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{line:>5} | {error}",
+                line = Colours::theme().report_label.apply("line"),
+                error = Colours::theme().report_label.apply("error")
+            )?;
+            writeln!(out, "----- | ------------------------------")?;
+            self.lines_corrupt
+                .iter()
+                .take(max_rows)
+                .try_for_each(|(line_number, error)| {
+                    writeln!(
+                        out,
+                        "{line:5} | {error}",
+                        line = line_number,
+                        error = Colours::theme().report_error_message.apply(error.as_str())
+                    )
+                })?;
+            Self::write_rows_omitted(out, self.lines_corrupt.len(), max_rows)?;
+        }
+
+        writeln!(out)?;
+        if self.matches() {
+            writeln!(
+                out,
+                "{}",
+                Colours::theme()
+                    .report_item_success
+                    .apply("Every input record is in the output exactly once.")
+            )
+        } else {
+            writeln!(
+                out,
+                "{}",
+                Colours::theme()
+                    .report_item_failure
+                    .apply("The output does not match the input.")
+            )
+        }
+    }
+
+    /// Writes a section listing the records' title numbers, if there are any.
+    fn write_records(
+        out: &mut impl io::Write,
+        title: &str,
+        record_ids: &[usize],
+        max_rows: usize,
+    ) -> io::Result<()> {
+        if record_ids.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(out)?;
+        writeln!(out, "{}", Colours::theme().report_title_error.apply(title))?;
+        writeln!(out)?;
+        record_ids.iter().take(max_rows).try_for_each(|record_id| {
+            writeln!(
+                out,
+                "* {}",
+                Colours::theme()
+                    .report_error_item
+                    .apply(PropertyRecord(*record_id).title_number())
+            )
+        })?;
+        Self::write_rows_omitted(out, record_ids.len(), max_rows)
+    }
+
+    /// Writes how many rows were left out of a list, if any were.
+    fn write_rows_omitted(
+        out: &mut impl io::Write,
+        row_count: usize,
+        max_rows: usize,
+    ) -> io::Result<()> {
+        if row_count > max_rows {
+            writeln!(out, "… (+{})", row_count - max_rows)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use serde_json::json;
+
+use crate::{history::RunStatus, EventReceiver, HttpOptions, Report, RunEvent};
+
+/// Shape of the JSON posted to the `--notify-webhook` URL.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebhookFormat {
+    /// The report, as written with `--report-out`.
+    Json,
+    /// A message for a Slack incoming webhook.
+    Slack,
+    /// A message card for a Microsoft Teams incoming webhook.
+    Teams,
+}
+
+impl fmt::Display for WebhookFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+            Self::Slack => write!(f, "slack"),
+            Self::Teams => write!(f, "teams"),
+        }
+    }
+}
+
+impl FromStr for WebhookFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "slack" => Ok(Self::Slack),
+            "teams" => Ok(Self::Teams),
+            _ => Err(format!("`{}` is not one of `json`, `slack`, `teams`.", s)),
+        }
+    }
+}
+
+/// Posts the report to a URL when the run finishes, including when it is
+/// interrupted.
+#[derive(Debug)]
+pub struct Webhook {
+    client: reqwest::Client,
+    url: String,
+    format: WebhookFormat,
+}
+
+impl Webhook {
+    /// Time to wait for the server to respond, so a slow server doesn't hold
+    /// up exiting.
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Returns a webhook that posts to `url` in the given format.
+    pub fn new(
+        url: String,
+        format: WebhookFormat,
+        http_options: &HttpOptions,
+    ) -> Result<Self, reqwest::Error> {
+        let client = http_options
+            .apply(reqwest::Client::builder().timeout(Self::TIMEOUT))?
+            .build()?;
+
+        Ok(Self {
+            client,
+            url,
+            format,
+        })
+    }
+
+    /// Posts the report once the run finishes, as published on the
+    /// [`EventBus`].
+    ///
+    /// [`EventBus`]: crate::EventBus
+    pub async fn notify(self, mut events: EventReceiver) {
+        while let Some(event) = events.recv().await {
+            if let RunEvent::RunFinished(report) = event {
+                if let Err(e) = self.run_finished(&report).await {
+                    tracing::error!(url = %self.url, "Failed to notify webhook: {}", e);
+                }
+                break;
+            }
+        }
+    }
+
+    /// Posts the report.
+    async fn run_finished(&self, report: &Report) -> Result<(), reqwest::Error> {
+        let payload = match self.format {
+            WebhookFormat::Json => match serde_json::to_value(report) {
+                Ok(payload) => payload,
+                Err(e) => {
+                    tracing::error!(url = %self.url, "Failed to serialize report: {}", e);
+                    return Ok(());
+                }
+            },
+            WebhookFormat::Slack => json!({ "text": report.message() }),
+            WebhookFormat::Teams => json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": format!("cli_async run {}", RunStatus::from_report(report)),
+                "themeColor": Self::theme_color(report),
+                "text": report.message(),
+            }),
+        };
+
+        self.client
+            .post(&self.url)
+            .json(&payload)
+            .send()
+            .await?
+            .error_for_status()?;
+        tracing::debug!(url = %self.url, "Notified webhook.");
+
+        Ok(())
+    }
+
+    /// Returns the colour of the message card's accent, by how the run
+    /// finished.
+    fn theme_color(report: &Report) -> &'static str {
+        match RunStatus::from_report(report) {
+            RunStatus::Completed => "2eb886",
+            RunStatus::CompletedWithErrors => "d00000",
+            RunStatus::Interrupted => "daa038",
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+use crate::{reporter::ProgressOptions, Record, StageKind};
+
+/// One progress bar per record being processed, shown beneath the overall bar
+/// with `--progress per-worker`.
+///
+/// Bars are reused once their record is written, so there are only as many bars
+/// as records processed at the same time.
+#[derive(Clone, Debug)]
+pub struct WorkerProgress {
+    inner: Option<Arc<Mutex<WorkerProgressInner>>>,
+}
+
+#[derive(Debug)]
+struct WorkerProgressInner {
+    multi_progress: MultiProgress,
+    /// Whether to only use ASCII characters for spinners.
+    ascii: bool,
+    bars: Vec<ProgressBar>,
+    /// Indices of bars whose record has been written.
+    idle: Vec<usize>,
+}
+
+impl WorkerProgress {
+    /// Returns worker progress that adds bars to the given `MultiProgress`.
+    pub fn new(multi_progress: MultiProgress, ascii: bool) -> Self {
+        Self {
+            inner: Some(Arc::new(Mutex::new(WorkerProgressInner {
+                multi_progress,
+                ascii,
+                bars: Vec::new(),
+                idle: Vec::new(),
+            }))),
+        }
+    }
+
+    /// Returns worker progress that doesn't show anything.
+    pub fn hidden() -> Self {
+        Self { inner: None }
+    }
+
+    /// Returns a bar to show the progress of the record.
+    pub fn start(&self, record: impl Record) -> WorkerBar {
+        let (index, bar) = match self.inner.as_ref() {
+            Some(inner) => {
+                let mut inner = inner.lock().expect("Worker progress lock poisoned.");
+                let index = match inner.idle.pop() {
+                    Some(index) => index,
+                    None => {
+                        let index = inner.bars.len();
+                        let bar = inner.multi_progress.add(ProgressBar::new_spinner());
+                        let style = ProgressStyle::default_spinner()
+                            .template(
+                                "  {spinner:.green} worker {prefix:>2}: {msg:<24} {elapsed:>4}",
+                            )
+                            .expect("Invalid worker progress bar template.");
+                        if inner.ascii {
+                            bar.set_style(style.tick_chars(ProgressOptions::TICK_CHARS_ASCII));
+                        } else {
+                            bar.set_style(style);
+                        }
+                        bar.set_prefix((index + 1).to_string());
+                        bar.enable_steady_tick(Duration::from_millis(100));
+                        inner.bars.push(bar);
+                        index
+                    }
+                };
+                let bar = inner.bars[index].clone();
+                bar.reset_elapsed();
+                (Some(index), bar)
+            }
+            None => (None, ProgressBar::hidden()),
+        };
+
+        WorkerBar {
+            worker_progress: self.clone(),
+            index,
+            bar,
+            title_number: record.label(),
+        }
+    }
+
+    /// Clears the worker bars.
+    pub fn finish(&self) {
+        if let Some(inner) = self.inner.as_ref() {
+            let inner = inner.lock().expect("Worker progress lock poisoned.");
+            inner.bars.iter().for_each(ProgressBar::finish_and_clear);
+        }
+    }
+}
+
+/// Progress bar for one record, which is freed for the next record when dropped.
+#[derive(Debug)]
+pub struct WorkerBar {
+    worker_progress: WorkerProgress,
+    index: Option<usize>,
+    bar: ProgressBar,
+    title_number: String,
+}
+
+impl WorkerBar {
+    /// Shows the stage the record is in.
+    pub fn stage(&self, stage: StageKind) {
+        self.bar
+            .set_message(format!("{} {}", self.title_number, stage.name()));
+    }
+}
+
+impl Drop for WorkerBar {
+    fn drop(&mut self) {
+        if let (Some(inner), Some(index)) = (self.worker_progress.inner.as_ref(), self.index) {
+            self.bar.set_message("idle");
+            inner
+                .lock()
+                .expect("Worker progress lock poisoned.")
+                .idle
+                .push(index);
+        }
+    }
+}